@@ -2,14 +2,17 @@ use alloc::vec;
 use alloc::vec::Vec;
 use core::iter::Iterator;
 use core::marker::PhantomData;
-use core::ops::Deref;
+use core::ops::{Deref, Range};
 use core::slice::Iter;
 
 use p3_air::{
     AirBuilder, AirBuilderWithPublicValues, ExtensionBuilder, PairBuilder, PermutationAirBuilder,
 };
+use p3_interaction::InteractionAirBuilder;
 use p3_matrix::Matrix;
 
+use crate::{PeriodicAirBuilder, RowSelector, SelectorAirBuilder};
+
 /// A subset of a matrix. The matrix will contain a subset of the elements of `self.inner`.
 pub struct SubMatrix<T, M>
 where
@@ -100,6 +103,9 @@ pub struct SubAirBuilder<'a, AB: AirBuilder> {
     inner: &'a mut AB,
     preprocessed_indices: Vec<usize>,
     main_indices: Vec<usize>,
+    permutation_indices: Option<Vec<usize>>,
+    public_values_range: Option<Range<usize>>,
+    periodic_range: Option<Range<usize>>,
 }
 
 impl<'a, AB: AirBuilder> SubAirBuilder<'a, AB> {
@@ -112,23 +118,36 @@ impl<'a, AB: AirBuilder> SubAirBuilder<'a, AB> {
             inner,
             preprocessed_indices,
             main_indices,
+            permutation_indices: None,
+            public_values_range: None,
+            periodic_range: None,
         }
     }
 
     pub fn new_preprocessed(inner: &'a mut AB, preprocessed_indices: Vec<usize>) -> Self {
-        Self {
-            inner,
-            preprocessed_indices,
-            main_indices: vec![],
-        }
+        Self::new(inner, preprocessed_indices, vec![])
     }
 
     pub fn new_main(inner: &'a mut AB, main_indices: Vec<usize>) -> Self {
-        Self {
-            inner,
-            preprocessed_indices: vec![],
-            main_indices,
-        }
+        Self::new(inner, vec![], main_indices)
+    }
+
+    /// Restricts the permutation matrix to the given columns. It is not restricted by default.
+    pub fn with_permutation_indices(mut self, permutation_indices: Vec<usize>) -> Self {
+        self.permutation_indices = Some(permutation_indices);
+        self
+    }
+
+    /// Restricts the public values to the given range. They are not restricted by default.
+    pub fn with_public_values_range(mut self, public_values_range: Range<usize>) -> Self {
+        self.public_values_range = Some(public_values_range);
+        self
+    }
+
+    /// Restricts the periodic values to the given range. They are not restricted by default.
+    pub fn with_periodic_range(mut self, periodic_range: Range<usize>) -> Self {
+        self.periodic_range = Some(periodic_range);
+        self
     }
 }
 
@@ -162,7 +181,7 @@ impl<'a, AB: AirBuilder> AirBuilder for SubAirBuilder<'a, AB> {
 
 impl<'a, AB: PairBuilder> PairBuilder for SubAirBuilder<'a, AB> {
     fn preprocessed(&self) -> Self::M {
-        let matrix = self.inner.main();
+        let matrix = self.inner.preprocessed();
         SubMatrix::new(matrix, self.preprocessed_indices.clone())
     }
 }
@@ -171,7 +190,11 @@ impl<'a, AB: AirBuilderWithPublicValues> AirBuilderWithPublicValues for SubAirBu
     type PublicVar = AB::PublicVar;
 
     fn public_values(&self) -> &[Self::PublicVar] {
-        self.inner.public_values()
+        let public_values = self.inner.public_values();
+        match &self.public_values_range {
+            Some(range) => &public_values[range.clone()],
+            None => public_values,
+        }
     }
 }
 
@@ -189,15 +212,50 @@ impl<'a, AB: ExtensionBuilder> ExtensionBuilder for SubAirBuilder<'a, AB> {
 }
 
 impl<'a, AB: PermutationAirBuilder> PermutationAirBuilder for SubAirBuilder<'a, AB> {
-    type MP = AB::MP;
+    type MP = SubMatrix<Self::VarEF, AB::MP>;
 
     type RandomVar = AB::RandomVar;
 
     fn permutation(&self) -> Self::MP {
-        self.inner.permutation()
+        let matrix = self.inner.permutation();
+        let indices = self
+            .permutation_indices
+            .clone()
+            .unwrap_or_else(|| (0..matrix.width()).collect());
+        SubMatrix::new(matrix, indices)
     }
 
     fn permutation_randomness(&self) -> &[Self::RandomVar] {
         self.inner.permutation_randomness()
     }
 }
+
+impl<'a, AB: InteractionAirBuilder> InteractionAirBuilder for SubAirBuilder<'a, AB> {
+    /// Panics if the permutation matrix is restricted, since the parent's cumulative sum is not
+    /// the sub-RAP's own.
+    fn cumulative_sum(&self) -> Self::VarEF {
+        assert!(
+            self.permutation_indices.is_none(),
+            "a sub-RAP with a restricted permutation matrix has no cumulative sum of its own"
+        );
+        self.inner.cumulative_sum()
+    }
+}
+
+impl<'a, AB: PeriodicAirBuilder> PeriodicAirBuilder for SubAirBuilder<'a, AB> {
+    type PeriodicVar = AB::PeriodicVar;
+
+    fn periodic_values(&self) -> &[Self::PeriodicVar] {
+        let periodic_values = self.inner.periodic_values();
+        match &self.periodic_range {
+            Some(range) => &periodic_values[range.clone()],
+            None => periodic_values,
+        }
+    }
+}
+
+impl<'a, AB: SelectorAirBuilder> SelectorAirBuilder for SubAirBuilder<'a, AB> {
+    fn row_selector(&self, selector: RowSelector) -> Self::Expr {
+        self.inner.row_selector(selector)
+    }
+}
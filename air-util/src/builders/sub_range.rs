@@ -6,8 +6,11 @@ use core::ops::{Deref, Range};
 use p3_air::{
     AirBuilder, AirBuilderWithPublicValues, ExtensionBuilder, PairBuilder, PermutationAirBuilder,
 };
+use p3_interaction::InteractionAirBuilder;
 use p3_matrix::Matrix;
 
+use crate::{PeriodicAirBuilder, RowSelector, SelectorAirBuilder};
+
 /// A submatrix of a matrix. The matrix will contain a subset of the columns of `self.inner`.
 pub struct SubMatrixRange<T, M>
 where
@@ -72,6 +75,9 @@ pub struct SubRangeAirBuilder<'a, AB: AirBuilder> {
     inner: &'a mut AB,
     main_range: Range<usize>,
     preprocessed_range: Range<usize>,
+    permutation_range: Option<Range<usize>>,
+    public_values_range: Option<Range<usize>>,
+    periodic_range: Option<Range<usize>>,
 }
 
 impl<'a, AB: AirBuilder> SubRangeAirBuilder<'a, AB> {
@@ -84,6 +90,9 @@ impl<'a, AB: AirBuilder> SubRangeAirBuilder<'a, AB> {
             inner,
             preprocessed_range,
             main_range,
+            permutation_range: None,
+            public_values_range: None,
+            periodic_range: None,
         }
     }
 
@@ -94,6 +103,24 @@ impl<'a, AB: AirBuilder> SubRangeAirBuilder<'a, AB> {
     pub fn new_preprocessed(inner: &'a mut AB, preprocessed_range: Range<usize>) -> Self {
         Self::new(inner, preprocessed_range, 0..0)
     }
+
+    /// Restricts the permutation matrix to the given columns. It is not restricted by default.
+    pub fn with_permutation_range(mut self, permutation_range: Range<usize>) -> Self {
+        self.permutation_range = Some(permutation_range);
+        self
+    }
+
+    /// Restricts the public values to the given range. They are not restricted by default.
+    pub fn with_public_values_range(mut self, public_values_range: Range<usize>) -> Self {
+        self.public_values_range = Some(public_values_range);
+        self
+    }
+
+    /// Restricts the periodic values to the given range. They are not restricted by default.
+    pub fn with_periodic_range(mut self, periodic_range: Range<usize>) -> Self {
+        self.periodic_range = Some(periodic_range);
+        self
+    }
 }
 
 impl<'a, AB: AirBuilder> AirBuilder for SubRangeAirBuilder<'a, AB> {
@@ -126,7 +153,7 @@ impl<'a, AB: AirBuilder> AirBuilder for SubRangeAirBuilder<'a, AB> {
 
 impl<'a, AB: PairBuilder> PairBuilder for SubRangeAirBuilder<'a, AB> {
     fn preprocessed(&self) -> Self::M {
-        let matrix = self.inner.main();
+        let matrix = self.inner.preprocessed();
         SubMatrixRange::new(matrix, self.preprocessed_range.clone())
     }
 }
@@ -135,7 +162,11 @@ impl<'a, AB: AirBuilderWithPublicValues> AirBuilderWithPublicValues for SubRange
     type PublicVar = AB::PublicVar;
 
     fn public_values(&self) -> &[Self::PublicVar] {
-        self.inner.public_values()
+        let public_values = self.inner.public_values();
+        match &self.public_values_range {
+            Some(range) => &public_values[range.clone()],
+            None => public_values,
+        }
     }
 }
 
@@ -153,15 +184,47 @@ impl<'a, AB: ExtensionBuilder> ExtensionBuilder for SubRangeAirBuilder<'a, AB> {
 }
 
 impl<'a, AB: PermutationAirBuilder> PermutationAirBuilder for SubRangeAirBuilder<'a, AB> {
-    type MP = AB::MP;
+    type MP = SubMatrixRange<Self::VarEF, AB::MP>;
 
     type RandomVar = AB::RandomVar;
 
     fn permutation(&self) -> Self::MP {
-        self.inner.permutation()
+        let matrix = self.inner.permutation();
+        let range = self.permutation_range.clone().unwrap_or(0..matrix.width());
+        SubMatrixRange::new(matrix, range)
     }
 
     fn permutation_randomness(&self) -> &[Self::RandomVar] {
         self.inner.permutation_randomness()
     }
 }
+
+impl<'a, AB: InteractionAirBuilder> InteractionAirBuilder for SubRangeAirBuilder<'a, AB> {
+    /// Panics if the permutation matrix is restricted, since the parent's cumulative sum is not
+    /// the sub-RAP's own.
+    fn cumulative_sum(&self) -> Self::VarEF {
+        assert!(
+            self.permutation_range.is_none(),
+            "a sub-RAP with a restricted permutation matrix has no cumulative sum of its own"
+        );
+        self.inner.cumulative_sum()
+    }
+}
+
+impl<'a, AB: PeriodicAirBuilder> PeriodicAirBuilder for SubRangeAirBuilder<'a, AB> {
+    type PeriodicVar = AB::PeriodicVar;
+
+    fn periodic_values(&self) -> &[Self::PeriodicVar] {
+        let periodic_values = self.inner.periodic_values();
+        match &self.periodic_range {
+            Some(range) => &periodic_values[range.clone()],
+            None => periodic_values,
+        }
+    }
+}
+
+impl<'a, AB: SelectorAirBuilder> SelectorAirBuilder for SubRangeAirBuilder<'a, AB> {
+    fn row_selector(&self, selector: RowSelector) -> Self::Expr {
+        self.inner.row_selector(selector)
+    }
+}
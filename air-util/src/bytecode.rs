@@ -0,0 +1,436 @@
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use p3_field::Field;
+use p3_interaction::NUM_PERM_CHALLENGES;
+use p3_uni_stark::{Entry, SymbolicExpression, SymbolicVariable};
+
+/// A value read by a constraint program from the row being evaluated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Input {
+    Preprocessed { offset: usize, index: usize },
+    Main { offset: usize, index: usize },
+    Permutation { offset: usize, index: usize },
+    Public(usize),
+    Periodic(usize),
+    RowSelector(usize),
+    PermChallenge(usize),
+    CumulativeSum,
+    IsFirstRow,
+    IsLastRow,
+    IsTransition,
+}
+
+impl Input {
+    pub fn is_ext(&self) -> bool {
+        matches!(
+            self,
+            Input::Permutation { .. } | Input::PermChallenge(_) | Input::CumulativeSum
+        )
+    }
+}
+
+/// A register of a constraint program. Base registers hold base field values and extension
+/// registers hold extension field values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Register {
+    Base(usize),
+    Ext(usize),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Instruction<F> {
+    Load {
+        dst: Register,
+        input: Input,
+    },
+    Constant {
+        dst: usize,
+        value: F,
+    },
+    Add {
+        dst: Register,
+        x: Register,
+        y: Register,
+    },
+    Sub {
+        dst: Register,
+        x: Register,
+        y: Register,
+    },
+    Mul {
+        dst: Register,
+        x: Register,
+        y: Register,
+    },
+    Neg {
+        dst: Register,
+        x: Register,
+    },
+    /// Folds the register into the accumulator as the next constraint.
+    AssertZero {
+        x: Register,
+    },
+}
+
+/// The constraints of an AIR lowered to a straight-line program. Common subexpressions are only
+/// computed once, constant subexpressions are folded, and registers are reused once their values
+/// are no longer needed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConstraintProgram<F> {
+    pub instructions: Vec<Instruction<F>>,
+    pub num_base_registers: usize,
+    pub num_ext_registers: usize,
+    pub num_constraints: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Node {
+    Input(Input),
+    Constant(usize),
+    Add(usize, usize),
+    Sub(usize, usize),
+    Mul(usize, usize),
+    Neg(usize),
+}
+
+impl Node {
+    fn operands(&self) -> Vec<usize> {
+        match *self {
+            Node::Input(_) | Node::Constant(_) => vec![],
+            Node::Add(x, y) | Node::Sub(x, y) | Node::Mul(x, y) => vec![x, y],
+            Node::Neg(x) => vec![x],
+        }
+    }
+}
+
+impl<F: Field> ConstraintProgram<F> {
+    /// Compiles constraints recorded by the `SymbolicAirBuilder`. Preprocessed variables past
+    /// `preprocessed_width` are the periodic columns, followed by the row selectors.
+    pub fn compile(
+        constraints: &[SymbolicExpression<F>],
+        preprocessed_width: usize,
+        num_periodic_columns: usize,
+    ) -> Self {
+        let mut dag = Dag {
+            preprocessed_width,
+            num_periodic_columns,
+            nodes: vec![],
+            is_ext: vec![],
+            constants: vec![],
+            node_ids: BTreeMap::new(),
+            visited: BTreeMap::new(),
+        };
+        let roots = constraints
+            .iter()
+            .map(|constraint| dag.visit(constraint))
+            .collect::<Vec<_>>();
+        dag.lower(&roots)
+    }
+}
+
+/// The constraints as a DAG of hash-consed nodes. Operands always have smaller ids than the nodes
+/// using them.
+struct Dag<F> {
+    preprocessed_width: usize,
+    num_periodic_columns: usize,
+    nodes: Vec<Node>,
+    is_ext: Vec<bool>,
+    constants: Vec<F>,
+    node_ids: BTreeMap<Node, usize>,
+    /// The node of each visited expression, keyed by its address. Expressions share subtrees
+    /// through `Rc`, so this avoids walking a shared subtree more than once.
+    visited: BTreeMap<usize, usize>,
+}
+
+enum Step {
+    Compute(usize),
+    AssertZero(usize),
+}
+
+impl<F: Field> Dag<F> {
+    fn visit(&mut self, expr: &SymbolicExpression<F>) -> usize {
+        let address = expr as *const SymbolicExpression<F> as usize;
+        if let Some(&id) = self.visited.get(&address) {
+            return id;
+        }
+        let id = match expr {
+            SymbolicExpression::Variable(var) => {
+                let input = self.decode(var);
+                self.input(input)
+            }
+            SymbolicExpression::IsFirstRow => self.input(Input::IsFirstRow),
+            SymbolicExpression::IsLastRow => self.input(Input::IsLastRow),
+            SymbolicExpression::IsTransition => self.input(Input::IsTransition),
+            SymbolicExpression::Constant(value) => self.constant(*value),
+            SymbolicExpression::Add { x, y, .. } => {
+                let (x, y) = (self.visit(x), self.visit(y));
+                self.add(x, y)
+            }
+            SymbolicExpression::Sub { x, y, .. } => {
+                let (x, y) = (self.visit(x), self.visit(y));
+                self.sub(x, y)
+            }
+            SymbolicExpression::Mul { x, y, .. } => {
+                let (x, y) = (self.visit(x), self.visit(y));
+                self.mul(x, y)
+            }
+            SymbolicExpression::Neg { x, .. } => {
+                let x = self.visit(x);
+                self.neg(x)
+            }
+        };
+        self.visited.insert(address, id);
+        id
+    }
+
+    fn decode(&self, var: &SymbolicVariable<F>) -> Input {
+        let index = var.index;
+        match var.entry {
+            Entry::Preprocessed { offset } => {
+                if index < self.preprocessed_width {
+                    Input::Preprocessed { offset, index }
+                } else if index < self.preprocessed_width + self.num_periodic_columns {
+                    Input::Periodic(index - self.preprocessed_width)
+                } else {
+                    Input::RowSelector(index - self.preprocessed_width - self.num_periodic_columns)
+                }
+            }
+            Entry::Main { offset } => Input::Main { offset, index },
+            Entry::Permutation { offset } => Input::Permutation { offset, index },
+            Entry::Public => Input::Public(index),
+            Entry::Challenge => {
+                if index < NUM_PERM_CHALLENGES {
+                    Input::PermChallenge(index)
+                } else {
+                    Input::CumulativeSum
+                }
+            }
+        }
+    }
+
+    fn intern(&mut self, node: Node, is_ext: bool) -> usize {
+        if let Some(&id) = self.node_ids.get(&node) {
+            return id;
+        }
+        let id = self.nodes.len();
+        self.nodes.push(node);
+        self.is_ext.push(is_ext);
+        self.node_ids.insert(node, id);
+        id
+    }
+
+    fn input(&mut self, input: Input) -> usize {
+        self.intern(Node::Input(input), input.is_ext())
+    }
+
+    fn constant(&mut self, value: F) -> usize {
+        let index = match self.constants.iter().position(|&c| c == value) {
+            Some(index) => index,
+            None => {
+                self.constants.push(value);
+                self.constants.len() - 1
+            }
+        };
+        self.intern(Node::Constant(index), false)
+    }
+
+    fn constant_value(&self, id: usize) -> Option<F> {
+        match self.nodes[id] {
+            Node::Constant(index) => Some(self.constants[index]),
+            _ => None,
+        }
+    }
+
+    fn is_ext_operation(&self, x: usize, y: usize) -> bool {
+        self.is_ext[x] || self.is_ext[y]
+    }
+
+    fn add(&mut self, x: usize, y: usize) -> usize {
+        match (self.constant_value(x), self.constant_value(y)) {
+            (Some(a), Some(b)) => self.constant(a + b),
+            (Some(a), _) if a.is_zero() => y,
+            (_, Some(b)) if b.is_zero() => x,
+            _ => {
+                let is_ext = self.is_ext_operation(x, y);
+                self.intern(Node::Add(x.min(y), x.max(y)), is_ext)
+            }
+        }
+    }
+
+    fn sub(&mut self, x: usize, y: usize) -> usize {
+        if x == y {
+            return self.constant(F::zero());
+        }
+        match (self.constant_value(x), self.constant_value(y)) {
+            (Some(a), Some(b)) => self.constant(a - b),
+            (Some(a), _) if a.is_zero() => self.neg(y),
+            (_, Some(b)) if b.is_zero() => x,
+            _ => {
+                let is_ext = self.is_ext_operation(x, y);
+                self.intern(Node::Sub(x, y), is_ext)
+            }
+        }
+    }
+
+    fn mul(&mut self, x: usize, y: usize) -> usize {
+        match (self.constant_value(x), self.constant_value(y)) {
+            (Some(a), Some(b)) => self.constant(a * b),
+            (Some(a), _) if a.is_zero() => x,
+            (_, Some(b)) if b.is_zero() => y,
+            (Some(a), _) if a.is_one() => y,
+            (_, Some(b)) if b.is_one() => x,
+            _ => {
+                let is_ext = self.is_ext_operation(x, y);
+                self.intern(Node::Mul(x.min(y), x.max(y)), is_ext)
+            }
+        }
+    }
+
+    fn neg(&mut self, x: usize) -> usize {
+        if let Some(a) = self.constant_value(x) {
+            return self.constant(-a);
+        }
+        if let Node::Neg(inner) = self.nodes[x] {
+            return inner;
+        }
+        let is_ext = self.is_ext[x];
+        self.intern(Node::Neg(x), is_ext)
+    }
+
+    /// Schedules the nodes the constraints depend on, and assigns them registers.
+    fn lower(&self, roots: &[usize]) -> ConstraintProgram<F> {
+        let mut live = vec![false; self.nodes.len()];
+        for &root in roots {
+            live[root] = true;
+        }
+        for id in (0..self.nodes.len()).rev() {
+            if live[id] {
+                for operand in self.nodes[id].operands() {
+                    live[operand] = true;
+                }
+            }
+        }
+
+        // Nodes are computed in id order, and each constraint is asserted as soon as it and all
+        // the constraints before it have been computed.
+        let mut steps = vec![];
+        let mut next_constraint = 0;
+        for id in (0..self.nodes.len()).filter(|&id| live[id]) {
+            steps.push(Step::Compute(id));
+            while next_constraint < roots.len() && roots[next_constraint] <= id {
+                steps.push(Step::AssertZero(next_constraint));
+                next_constraint += 1;
+            }
+        }
+
+        let mut last_use = vec![0; self.nodes.len()];
+        for (position, step) in steps.iter().enumerate() {
+            match *step {
+                Step::Compute(id) => {
+                    for operand in self.nodes[id].operands() {
+                        last_use[operand] = position;
+                    }
+                }
+                Step::AssertZero(constraint) => last_use[roots[constraint]] = position,
+            }
+        }
+
+        let mut registers = vec![Register::Base(0); self.nodes.len()];
+        let mut allocator = RegisterAllocator::default();
+        let mut instructions = Vec::with_capacity(steps.len());
+        for (position, step) in steps.iter().enumerate() {
+            match *step {
+                Step::Compute(id) => {
+                    let node = self.nodes[id];
+                    let mut operands = node.operands();
+                    operands.dedup();
+                    // Operands are read before the result is written, so the result can reuse the
+                    // register of an operand which is no longer needed.
+                    for &operand in operands.iter() {
+                        if last_use[operand] == position {
+                            allocator.free(registers[operand]);
+                        }
+                    }
+                    let dst = allocator.allocate(self.is_ext[id]);
+                    registers[id] = dst;
+                    instructions.push(match node {
+                        Node::Input(input) => Instruction::Load { dst, input },
+                        Node::Constant(index) => Instruction::Constant {
+                            dst: match dst {
+                                Register::Base(dst) => dst,
+                                Register::Ext(_) => unreachable!("constants are base field values"),
+                            },
+                            value: self.constants[index],
+                        },
+                        Node::Add(x, y) => Instruction::Add {
+                            dst,
+                            x: registers[x],
+                            y: registers[y],
+                        },
+                        Node::Sub(x, y) => Instruction::Sub {
+                            dst,
+                            x: registers[x],
+                            y: registers[y],
+                        },
+                        Node::Mul(x, y) => Instruction::Mul {
+                            dst,
+                            x: registers[x],
+                            y: registers[y],
+                        },
+                        Node::Neg(x) => Instruction::Neg {
+                            dst,
+                            x: registers[x],
+                        },
+                    });
+                }
+                Step::AssertZero(constraint) => {
+                    let root = roots[constraint];
+                    instructions.push(Instruction::AssertZero { x: registers[root] });
+                    if last_use[root] == position {
+                        allocator.free(registers[root]);
+                    }
+                }
+            }
+        }
+
+        ConstraintProgram {
+            instructions,
+            num_base_registers: allocator.num_base,
+            num_ext_registers: allocator.num_ext,
+            num_constraints: roots.len(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct RegisterAllocator {
+    free_base: Vec<usize>,
+    free_ext: Vec<usize>,
+    num_base: usize,
+    num_ext: usize,
+}
+
+impl RegisterAllocator {
+    fn allocate(&mut self, is_ext: bool) -> Register {
+        if is_ext {
+            Register::Ext(self.free_ext.pop().unwrap_or_else(|| {
+                self.num_ext += 1;
+                self.num_ext - 1
+            }))
+        } else {
+            Register::Base(self.free_base.pop().unwrap_or_else(|| {
+                self.num_base += 1;
+                self.num_base - 1
+            }))
+        }
+    }
+
+    fn free(&mut self, register: Register) {
+        match register {
+            Register::Base(index) => self.free_base.push(index),
+            Register::Ext(index) => self.free_ext.push(index),
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec;
+use alloc::vec::Vec;
+
+use p3_air::{PairCol, VirtualPairCol};
+use p3_field::Field;
+use p3_interaction::Rap;
+use p3_uni_stark::{Entry, SymbolicExpression};
+
+use crate::folders::rap::SymbolicAirBuilder;
+use crate::folders::EntriesLog;
+use crate::util::TraceEntry;
+use crate::PeriodicAir;
+
+/// Why a main column may be under-constrained.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ColumnIssue {
+    /// The column is not referenced by any constraint or interaction.
+    Unreferenced,
+    /// The column is only referenced by constraints of degree 1 in the main columns, and is never
+    /// range checked.
+    OnlyLinear,
+}
+
+/// A main column that may be under-constrained.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct UnderconstrainedColumn {
+    pub col: usize,
+    pub issue: ColumnIssue,
+}
+
+/// Finds the main columns of `air` that are not referenced by any of its constraints or
+/// interactions, or that are only referenced by constraints of degree 1 in the main columns and
+/// are not a field of an interaction on one of the `range_check_buses`. The permutation
+/// constraints are not counted.
+pub fn find_underconstrained_columns<F, A>(
+    air: &A,
+    num_public_values: usize,
+    range_check_buses: &[usize],
+) -> Vec<UnderconstrainedColumn>
+where
+    F: Field,
+    A: Rap<SymbolicAirBuilder<F>> + PeriodicAir<F>,
+{
+    let width = air.width();
+    let mut builder = SymbolicAirBuilder::new(
+        air.preprocessed_width(),
+        width,
+        0,
+        num_public_values,
+        air.periodic_columns().len(),
+    );
+    air.eval(&mut builder);
+
+    // The highest degree in the main columns of a constraint referencing each column. Row
+    // selectors, preprocessed columns and periodic values are fixed, so they don't add to it.
+    let mut max_degrees = BTreeMap::new();
+    for constraint in builder.constraints() {
+        let degree = main_degree(&constraint, &mut BTreeMap::new());
+        let mut columns = BTreeSet::new();
+        let mut visited = BTreeSet::new();
+        collect_main_columns(&constraint, &mut columns, &mut visited);
+        for col in columns {
+            let max_degree = max_degrees.entry(col).or_insert(0);
+            *max_degree = degree.max(*max_degree);
+        }
+    }
+
+    let mut in_interaction = vec![false; width];
+    let mut range_checked = vec![false; width];
+    for (interaction, _) in air.all_interactions() {
+        let is_range_check = range_check_buses.contains(&interaction.argument_index);
+        for col in main_columns(&interaction.count) {
+            in_interaction[col] = true;
+        }
+        for field in interaction.fields.iter() {
+            for col in main_columns(field) {
+                in_interaction[col] = true;
+                range_checked[col] |= is_range_check;
+            }
+        }
+    }
+
+    (0..width)
+        .filter_map(|col| {
+            let issue = match max_degrees.get(&col) {
+                None if !in_interaction[col] => ColumnIssue::Unreferenced,
+                Some(&degree) if degree <= 1 && !range_checked[col] => ColumnIssue::OnlyLinear,
+                _ => return None,
+            };
+            Some(UnderconstrainedColumn { col, issue })
+        })
+        .collect()
+}
+
+/// Finds the main columns without a constrained entry in `entries`, as tracked over a concrete
+/// trace of width `main_width`.
+pub fn find_unconstrained_columns(
+    entries: &EntriesLog<TraceEntry>,
+    main_width: usize,
+) -> Vec<usize> {
+    let mut constrained = vec![false; main_width];
+    for entry in entries.constrained.iter() {
+        if let TraceEntry::Main { col, .. } = *entry {
+            constrained[col] = true;
+        }
+    }
+    (0..main_width).filter(|&col| !constrained[col]).collect()
+}
+
+fn main_columns<F: Field>(column: &VirtualPairCol<F>) -> impl Iterator<Item = usize> + '_ {
+    column
+        .column_weights
+        .iter()
+        .filter_map(|(col, _)| match col {
+            PairCol::Main(index) => Some(*index),
+            PairCol::Preprocessed(_) => None,
+        })
+}
+
+/// The degree of `expr` in the main columns. Subtrees are shared through `Rc`, so the degrees of
+/// visited subtrees are keyed by their address.
+fn main_degree<F: Field>(
+    expr: &SymbolicExpression<F>,
+    degrees: &mut BTreeMap<usize, usize>,
+) -> usize {
+    let address = expr as *const SymbolicExpression<F> as usize;
+    if let Some(&degree) = degrees.get(&address) {
+        return degree;
+    }
+    let degree = match expr {
+        SymbolicExpression::Variable(var) => match var.entry {
+            Entry::Main { .. } => 1,
+            _ => 0,
+        },
+        SymbolicExpression::IsFirstRow
+        | SymbolicExpression::IsLastRow
+        | SymbolicExpression::IsTransition
+        | SymbolicExpression::Constant(_) => 0,
+        SymbolicExpression::Add { x, y, .. } | SymbolicExpression::Sub { x, y, .. } => {
+            main_degree(x, degrees).max(main_degree(y, degrees))
+        }
+        SymbolicExpression::Mul { x, y, .. } => main_degree(x, degrees) + main_degree(y, degrees),
+        SymbolicExpression::Neg { x, .. } => main_degree(x, degrees),
+    };
+    degrees.insert(address, degree);
+    degree
+}
+
+/// Collects the main columns referenced by `expr`. Subtrees are shared through `Rc`, so visited
+/// subtrees are keyed by their address.
+fn collect_main_columns<F: Field>(
+    expr: &SymbolicExpression<F>,
+    columns: &mut BTreeSet<usize>,
+    visited: &mut BTreeSet<usize>,
+) {
+    let address = expr as *const SymbolicExpression<F> as usize;
+    if !visited.insert(address) {
+        return;
+    }
+    match expr {
+        SymbolicExpression::Variable(var) => {
+            if let Entry::Main { .. } = var.entry {
+                columns.insert(var.index);
+            }
+        }
+        SymbolicExpression::IsFirstRow
+        | SymbolicExpression::IsLastRow
+        | SymbolicExpression::IsTransition
+        | SymbolicExpression::Constant(_) => {}
+        SymbolicExpression::Add { x, y, .. }
+        | SymbolicExpression::Sub { x, y, .. }
+        | SymbolicExpression::Mul { x, y, .. } => {
+            collect_main_columns(x, columns, visited);
+            collect_main_columns(y, columns, visited);
+        }
+        SymbolicExpression::Neg { x, .. } => collect_main_columns(x, columns, visited),
+    }
+}
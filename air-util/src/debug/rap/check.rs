@@ -11,6 +11,7 @@ use p3_matrix::Matrix;
 use p3_maybe_rayon::prelude::IntoParallelIterator;
 
 use crate::folders::rap::DebugConstraintBuilder;
+use crate::{periodic_values_on_row, PeriodicAir};
 
 /// Check that all constraints vanish on the subgroup.
 pub fn check_constraints<F, EF, A>(
@@ -24,7 +25,7 @@ pub fn check_constraints<F, EF, A>(
 ) where
     F: Field,
     EF: ExtensionField<F>,
-    A: for<'a> Rap<DebugConstraintBuilder<'a, F, EF>>,
+    A: for<'a> Rap<DebugConstraintBuilder<'a, F, EF>> + PeriodicAir<F>,
 {
     let height = match (main.as_ref(), preprocessed.as_ref()) {
         (Some(main), Some(preprocessed)) => core::cmp::max(main.height(), preprocessed.height()),
@@ -37,6 +38,8 @@ pub fn check_constraints<F, EF, A>(
         assert_eq!(perm.height(), height);
     }
 
+    let periodic_columns = air.periodic_columns();
+
     // Check that constraints are satisfied.
     (0..height).into_par_iter().for_each(|i| {
         let i_next = (i + 1) % height;
@@ -58,9 +61,11 @@ pub fn check_constraints<F, EF, A>(
             .as_ref()
             .map(|perm| (perm.row_slice(i).to_vec(), perm.row_slice(i_next).to_vec()))
             .unwrap_or((vec![], vec![]));
+        let periodic_values = periodic_values_on_row(&periodic_columns, i);
 
         let mut builder = DebugConstraintBuilder {
             row_index: i,
+            height,
             preprocessed: VerticalPair::new(
                 RowMajorMatrixView::new_row(preprocessed_local.as_slice()),
                 RowMajorMatrixView::new_row(preprocessed_next.as_slice()),
@@ -75,6 +80,7 @@ pub fn check_constraints<F, EF, A>(
             ),
             perm_challenges,
             public_values,
+            periodic_values: periodic_values.as_slice(),
             cumulative_sum: cumulative_sum.unwrap_or_default(),
             is_first_row: F::zero(),
             is_last_row: F::zero(),
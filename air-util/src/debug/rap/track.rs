@@ -17,6 +17,7 @@ use crate::{
     folders::rap::{DebugConstraintBuilder, TrackingConstraintBuilder},
     util::{TraceEntry, TrackedFieldVariable},
 };
+use crate::{periodic_values_on_row, PeriodicAir};
 
 pub fn track_constraints<F, EF, A>(
     air: &A,
@@ -30,7 +31,7 @@ pub fn track_constraints<F, EF, A>(
 where
     F: Field,
     EF: ExtensionField<F>,
-    A: for<'a> Rap<TrackingConstraintBuilder<'a, F, EF>>,
+    A: for<'a> Rap<TrackingConstraintBuilder<'a, F, EF>> + PeriodicAir<F>,
 {
     let height = match (main.as_ref(), preprocessed.as_ref()) {
         (Some(main), Some(preprocessed)) => core::cmp::max(main.height(), preprocessed.height()),
@@ -42,6 +43,8 @@ where
         assert_eq!(perm.height(), height);
     }
 
+    let periodic_columns = air.periodic_columns();
+
     let mut entries = EntriesLog::<TraceEntry>::default();
     (0..height).into_par_iter().for_each(|i| {
         let i_next = (i + 1) % height;
@@ -134,10 +137,16 @@ where
             .enumerate()
             .map(|(j, x)| TrackedFieldVariable::new(*x, TraceEntry::Public { index: j }))
             .collect::<Vec<_>>();
+        let periodic_values = periodic_values_on_row(&periodic_columns, i)
+            .into_iter()
+            .map(TrackedFieldVariable::new_untracked)
+            .collect::<Vec<_>>();
         let perm_challenges = perm_challenges.map(|x| TrackedFieldVariable::new_untracked(x));
         let cumulative_sum = cumulative_sum.map(|x| TrackedFieldVariable::new_untracked(x));
 
         let mut builder = TrackingConstraintBuilder {
+            row_index: i,
+            height,
             entries: EntriesLog::default(),
             preprocessed: VerticalPair::new(
                 RowMajorMatrixView::new_row(preprocessed_local.as_slice()),
@@ -152,6 +161,7 @@ where
                 RowMajorMatrixView::new_row(&*permutation_next),
             ),
             public_values: public_values.as_slice(),
+            periodic_values: periodic_values.as_slice(),
             perm_challenges,
             cumulative_sum: cumulative_sum.unwrap_or_default(),
             is_first_row: F::zero(),
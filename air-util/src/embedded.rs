@@ -0,0 +1,167 @@
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::Field;
+use p3_interaction::{BaseInteractionAir, Interaction, InteractionAir};
+use p3_matrix::dense::RowMajorMatrix;
+
+use crate::builders::SubRangeAirBuilder;
+use crate::PeriodicAir;
+
+/// A child chip embedded in a range of a parent chip's preprocessed and main columns. Its
+/// constraints are evaluated through a `SubRangeAirBuilder`, and its interactions are remapped to
+/// the parent's columns through `receives_from_indices` and `sends_from_indices`, which the child
+/// should implement. The child sees all of the parent's periodic values unless they are restricted
+/// with `with_periodic_range`.
+#[derive(Clone, Debug)]
+pub struct Embedded<Child> {
+    pub child: Child,
+    pub preprocessed_range: Range<usize>,
+    pub main_range: Range<usize>,
+    pub periodic_range: Option<Range<usize>>,
+}
+
+impl<Child> Embedded<Child> {
+    pub const fn new(
+        child: Child,
+        preprocessed_range: Range<usize>,
+        main_range: Range<usize>,
+    ) -> Self {
+        Self {
+            child,
+            preprocessed_range,
+            main_range,
+            periodic_range: None,
+        }
+    }
+
+    pub const fn new_main(child: Child, main_range: Range<usize>) -> Self {
+        Self::new(child, 0..0, main_range)
+    }
+
+    /// Restricts the periodic values the child sees to the given range of the parent's.
+    pub fn with_periodic_range(mut self, periodic_range: Range<usize>) -> Self {
+        self.periodic_range = Some(periodic_range);
+        self
+    }
+
+    /// The parent's column indices of the child's columns.
+    fn indices(&self) -> (Vec<usize>, Vec<usize>) {
+        (
+            self.preprocessed_range.clone().collect(),
+            self.main_range.clone().collect(),
+        )
+    }
+}
+
+impl<F, Child: BaseAir<F>> BaseAir<F> for Embedded<Child> {
+    fn width(&self) -> usize {
+        self.main_range.len()
+    }
+
+    fn preprocessed_trace(&self) -> Option<RowMajorMatrix<F>> {
+        self.child.preprocessed_trace()
+    }
+}
+
+impl<AB, Child> Air<AB> for Embedded<Child>
+where
+    AB: AirBuilder,
+    Child: BaseAir<AB::F> + for<'a> Air<SubRangeAirBuilder<'a, AB>>,
+{
+    fn eval(&self, builder: &mut AB) {
+        let mut sub_builder = SubRangeAirBuilder::new(
+            builder,
+            self.preprocessed_range.clone(),
+            self.main_range.clone(),
+        );
+        if let Some(periodic_range) = &self.periodic_range {
+            sub_builder = sub_builder.with_periodic_range(periodic_range.clone());
+        }
+        self.child.eval(&mut sub_builder);
+    }
+}
+
+impl<F: Field, Child: BaseInteractionAir<F>> BaseInteractionAir<F> for Embedded<Child> {
+    fn receives_from_indices(
+        &self,
+        preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        self.child.receives_from_indices(
+            &preprocessed_indices[self.preprocessed_range.clone()],
+            &main_indices[self.main_range.clone()],
+        )
+    }
+
+    fn sends_from_indices(
+        &self,
+        preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        self.child.sends_from_indices(
+            &preprocessed_indices[self.preprocessed_range.clone()],
+            &main_indices[self.main_range.clone()],
+        )
+    }
+}
+
+impl<F: Field, Child: BaseInteractionAir<F>> InteractionAir<F> for Embedded<Child> {
+    fn receives(&self) -> Vec<Interaction<F>> {
+        let (preprocessed_indices, main_indices) = self.indices();
+        self.child
+            .receives_from_indices(&preprocessed_indices, &main_indices)
+    }
+
+    fn sends(&self) -> Vec<Interaction<F>> {
+        let (preprocessed_indices, main_indices) = self.indices();
+        self.child
+            .sends_from_indices(&preprocessed_indices, &main_indices)
+    }
+}
+
+impl<F: Field, Child: PeriodicAir<F>> PeriodicAir<F> for Embedded<Child> {
+    fn periodic_columns(&self) -> Vec<Vec<F>> {
+        self.child.periodic_columns()
+    }
+}
+
+#[cfg(feature = "air-logger")]
+impl<Child: crate::AirLogger> crate::AirLogger for Embedded<Child> {
+    fn preprocessed_headers(&self) -> Vec<alloc::string::String> {
+        self.child.preprocessed_headers()
+    }
+
+    fn main_headers(&self) -> Vec<alloc::string::String> {
+        self.child.main_headers()
+    }
+
+    #[cfg(feature = "schema")]
+    fn preprocessed_headers_and_types(
+        &self,
+    ) -> Vec<(alloc::string::String, alloc::string::String, Range<usize>)> {
+        offset_ranges(
+            self.child.preprocessed_headers_and_types(),
+            self.preprocessed_range.start,
+        )
+    }
+
+    #[cfg(feature = "schema")]
+    fn main_headers_and_types(
+        &self,
+    ) -> Vec<(alloc::string::String, alloc::string::String, Range<usize>)> {
+        offset_ranges(self.child.main_headers_and_types(), self.main_range.start)
+    }
+}
+
+#[cfg(feature = "schema")]
+fn offset_ranges(
+    headers: Vec<(alloc::string::String, alloc::string::String, Range<usize>)>,
+    offset: usize,
+) -> Vec<(alloc::string::String, alloc::string::String, Range<usize>)> {
+    headers
+        .into_iter()
+        .map(|(name, ty, range)| (name, ty, range.start + offset..range.end + offset))
+        .collect()
+}
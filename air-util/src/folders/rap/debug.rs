@@ -5,16 +5,19 @@ use p3_field::{ExtensionField, Field};
 use p3_interaction::{InteractionAirBuilder, NUM_PERM_CHALLENGES};
 
 use crate::folders::ViewPair;
+use crate::{PeriodicAirBuilder, RowSelector, SelectorAirBuilder};
 
 /// An `AirBuilder` which asserts that each constraint is zero, allowing any failed constraints to
 /// be detected early.
 pub struct DebugConstraintBuilder<'a, F: Field, EF: ExtensionField<F>> {
     pub row_index: usize,
+    pub height: usize,
     pub preprocessed: ViewPair<'a, F>,
     pub main: ViewPair<'a, F>,
     pub permutation: ViewPair<'a, EF>,
     pub perm_challenges: [EF; NUM_PERM_CHALLENGES],
     pub public_values: &'a [F],
+    pub periodic_values: &'a [F],
     pub cumulative_sum: EF,
     pub is_first_row: F,
     pub is_last_row: F,
@@ -136,3 +139,17 @@ impl<'a, F: Field, EF: ExtensionField<F>> InteractionAirBuilder
         self.cumulative_sum
     }
 }
+
+impl<'a, F: Field, EF: ExtensionField<F>> PeriodicAirBuilder for DebugConstraintBuilder<'a, F, EF> {
+    type PeriodicVar = F;
+
+    fn periodic_values(&self) -> &[Self::PeriodicVar] {
+        self.periodic_values
+    }
+}
+
+impl<'a, F: Field, EF: ExtensionField<F>> SelectorAirBuilder for DebugConstraintBuilder<'a, F, EF> {
+    fn row_selector(&self, selector: RowSelector) -> Self::Expr {
+        F::from_bool(selector.is_selected(self.row_index, self.height))
+    }
+}
@@ -1,24 +1,142 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
 use p3_air::{
     AirBuilder, AirBuilderWithPublicValues, ExtensionBuilder, PairBuilder, PermutationAirBuilder,
 };
+use p3_field::{AbstractExtensionField, AbstractField};
 use p3_interaction::{InteractionAirBuilder, NUM_PERM_CHALLENGES};
+use p3_matrix::Matrix;
 use p3_uni_stark::{PackedChallenge, PackedVal, StarkGenericConfig, Val};
 
 use crate::folders::ViewPair;
+use crate::{
+    ConstraintProgram, Input, Instruction, PeriodicAirBuilder, Register, RowSelector,
+    SelectorAirBuilder,
+};
 
 /// A folder for prover constraints.
+///
+/// The `i`th of `n` constraints is weighted by `alpha^(n - 1 - i)`, which matches folding the
+/// constraints in order with `acc = acc * alpha + constraint`. Base field constraints are
+/// accumulated coefficient-wise against the decomposed alpha powers, so that they only need base
+/// field arithmetic, and extension field constraints are accumulated separately.
 pub struct ProverConstraintFolder<'a, SC: StarkGenericConfig> {
     pub preprocessed: ViewPair<'a, PackedVal<SC>>,
     pub main: ViewPair<'a, PackedVal<SC>>,
     pub perm: ViewPair<'a, PackedChallenge<SC>>,
     pub perm_challenges: [PackedChallenge<SC>; NUM_PERM_CHALLENGES],
     pub public_values: &'a [Val<SC>],
+    pub periodic_values: &'a [PackedVal<SC>],
+    pub row_selectors: &'a [RowSelector],
+    pub row_selector_values: &'a [PackedVal<SC>],
     pub cumulative_sum: PackedChallenge<SC>,
     pub is_first_row: PackedVal<SC>,
     pub is_last_row: PackedVal<SC>,
     pub is_transition: PackedVal<SC>,
-    pub alpha: PackedChallenge<SC>,
-    pub accumulator: PackedChallenge<SC>,
+    /// `alpha^(n - 1 - i)` for each constraint `i`.
+    pub alpha_powers: &'a [SC::Challenge],
+    /// The base field coefficients of `alpha_powers`, indexed by coefficient and then constraint.
+    pub decomposed_alpha_powers: &'a [Vec<Val<SC>>],
+    pub base_accumulators: Vec<PackedVal<SC>>,
+    pub ext_accumulator: PackedChallenge<SC>,
+    pub constraint_index: usize,
+}
+
+impl<'a, SC: StarkGenericConfig> ProverConstraintFolder<'a, SC> {
+    /// Returns the random linear combination of all constraints evaluated so far.
+    pub fn accumulator(&self) -> PackedChallenge<SC> {
+        PackedChallenge::<SC>::from_base_fn(|i| self.base_accumulators[i]) + self.ext_accumulator
+    }
+
+    /// Evaluates a compiled constraint program, folding its constraints like `Rap::eval_all`
+    /// would.
+    pub fn eval_program(&mut self, program: &ConstraintProgram<Val<SC>>) {
+        let mut base = vec![PackedVal::<SC>::zero(); program.num_base_registers];
+        let mut ext = vec![PackedChallenge::<SC>::zero(); program.num_ext_registers];
+        for instruction in program.instructions.iter() {
+            match *instruction {
+                Instruction::Load { dst, input } => match dst {
+                    Register::Base(dst) => base[dst] = self.load_base(input),
+                    Register::Ext(dst) => ext[dst] = self.load_ext(input),
+                },
+                Instruction::Constant { dst, value } => base[dst] = PackedVal::<SC>::from_f(value),
+                Instruction::Add { dst, x, y } => match (dst, x, y) {
+                    (Register::Base(dst), Register::Base(x), Register::Base(y)) => {
+                        base[dst] = base[x] + base[y]
+                    }
+                    (Register::Ext(dst), Register::Ext(x), Register::Ext(y)) => {
+                        ext[dst] = ext[x] + ext[y]
+                    }
+                    (Register::Ext(dst), Register::Ext(x), Register::Base(y))
+                    | (Register::Ext(dst), Register::Base(y), Register::Ext(x)) => {
+                        ext[dst] = ext[x] + base[y]
+                    }
+                    _ => unreachable!("the result of an addition has the widest operand type"),
+                },
+                Instruction::Sub { dst, x, y } => match (dst, x, y) {
+                    (Register::Base(dst), Register::Base(x), Register::Base(y)) => {
+                        base[dst] = base[x] - base[y]
+                    }
+                    (Register::Ext(dst), Register::Ext(x), Register::Ext(y)) => {
+                        ext[dst] = ext[x] - ext[y]
+                    }
+                    (Register::Ext(dst), Register::Ext(x), Register::Base(y)) => {
+                        ext[dst] = ext[x] - base[y]
+                    }
+                    (Register::Ext(dst), Register::Base(x), Register::Ext(y)) => {
+                        ext[dst] = PackedChallenge::<SC>::from_base(base[x]) - ext[y]
+                    }
+                    _ => unreachable!("the result of a subtraction has the widest operand type"),
+                },
+                Instruction::Mul { dst, x, y } => match (dst, x, y) {
+                    (Register::Base(dst), Register::Base(x), Register::Base(y)) => {
+                        base[dst] = base[x] * base[y]
+                    }
+                    (Register::Ext(dst), Register::Ext(x), Register::Ext(y)) => {
+                        ext[dst] = ext[x] * ext[y]
+                    }
+                    (Register::Ext(dst), Register::Ext(x), Register::Base(y))
+                    | (Register::Ext(dst), Register::Base(y), Register::Ext(x)) => {
+                        ext[dst] = ext[x] * base[y]
+                    }
+                    _ => unreachable!("the result of a multiplication has the widest operand type"),
+                },
+                Instruction::Neg { dst, x } => match (dst, x) {
+                    (Register::Base(dst), Register::Base(x)) => base[dst] = -base[x],
+                    (Register::Ext(dst), Register::Ext(x)) => ext[dst] = -ext[x],
+                    _ => unreachable!("negation preserves the operand type"),
+                },
+                Instruction::AssertZero { x } => match x {
+                    Register::Base(x) => self.assert_zero(base[x]),
+                    Register::Ext(x) => self.assert_zero_ext(ext[x]),
+                },
+            }
+        }
+    }
+
+    fn load_base(&self, input: Input) -> PackedVal<SC> {
+        match input {
+            Input::Preprocessed { offset, index } => self.preprocessed.get(offset, index),
+            Input::Main { offset, index } => self.main.get(offset, index),
+            Input::Public(index) => PackedVal::<SC>::from_f(self.public_values[index]),
+            Input::Periodic(index) => self.periodic_values[index],
+            Input::RowSelector(index) => self.row_selector_values[index],
+            Input::IsFirstRow => self.is_first_row,
+            Input::IsLastRow => self.is_last_row,
+            Input::IsTransition => self.is_transition,
+            _ => unreachable!("{:?} is an extension field input", input),
+        }
+    }
+
+    fn load_ext(&self, input: Input) -> PackedChallenge<SC> {
+        match input {
+            Input::Permutation { offset, index } => self.perm.get(offset, index),
+            Input::PermChallenge(index) => self.perm_challenges[index],
+            Input::CumulativeSum => self.cumulative_sum,
+            _ => unreachable!("{:?} is a base field input", input),
+        }
+    }
 }
 
 impl<'a, SC> AirBuilder for ProverConstraintFolder<'a, SC>
@@ -52,8 +170,14 @@ where
 
     fn assert_zero<I: Into<Self::Expr>>(&mut self, x: I) {
         let x: PackedVal<SC> = x.into();
-        self.accumulator *= self.alpha;
-        self.accumulator += x;
+        for (acc, alpha_powers) in self
+            .base_accumulators
+            .iter_mut()
+            .zip(self.decomposed_alpha_powers)
+        {
+            *acc += x * alpha_powers[self.constraint_index];
+        }
+        self.constraint_index += 1;
     }
 }
 
@@ -79,8 +203,9 @@ where
         I: Into<Self::ExprEF>,
     {
         let x: PackedChallenge<SC> = x.into();
-        self.accumulator *= self.alpha;
-        self.accumulator += x;
+        let alpha_power = PackedChallenge::<SC>::from_f(self.alpha_powers[self.constraint_index]);
+        self.ext_accumulator += x * alpha_power;
+        self.constraint_index += 1;
     }
 }
 
@@ -114,3 +239,22 @@ impl<'a, SC: StarkGenericConfig> InteractionAirBuilder for ProverConstraintFolde
         self.cumulative_sum
     }
 }
+
+impl<'a, SC: StarkGenericConfig> PeriodicAirBuilder for ProverConstraintFolder<'a, SC> {
+    type PeriodicVar = PackedVal<SC>;
+
+    fn periodic_values(&self) -> &[Self::PeriodicVar] {
+        self.periodic_values
+    }
+}
+
+impl<'a, SC: StarkGenericConfig> SelectorAirBuilder for ProverConstraintFolder<'a, SC> {
+    fn row_selector(&self, selector: RowSelector) -> Self::Expr {
+        let index = self
+            .row_selectors
+            .iter()
+            .position(|s| *s == selector)
+            .expect("row selector should be precomputed on the quotient domain");
+        self.row_selector_values[index]
+    }
+}
@@ -1,5 +1,6 @@
 use alloc::vec;
 use alloc::vec::Vec;
+use core::cell::RefCell;
 
 use p3_air::{
     AirBuilder, AirBuilderWithPublicValues, ExtensionBuilder, PairBuilder, PermutationAirBuilder,
@@ -7,8 +8,11 @@ use p3_air::{
 use p3_field::Field;
 use p3_interaction::{InteractionAirBuilder, NUM_PERM_CHALLENGES};
 use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
 use p3_uni_stark::{Entry, SymbolicExpression, SymbolicVariable};
 
+use crate::{ConstraintProgram, PeriodicAirBuilder, RowSelector, SelectorAirBuilder};
+
 /// An `AirBuilder` for evaluating constraints symbolically, and recording them for later use.
 #[derive(Debug)]
 pub struct SymbolicAirBuilder<F: Field> {
@@ -16,8 +20,11 @@ pub struct SymbolicAirBuilder<F: Field> {
     main: RowMajorMatrix<SymbolicVariable<F>>,
     permutation: RowMajorMatrix<SymbolicVariable<F>>,
     public_values: Vec<SymbolicVariable<F>>,
+    periodic_values: Vec<SymbolicVariable<F>>,
     perm_challenges: [SymbolicVariable<F>; NUM_PERM_CHALLENGES],
     cumulative_sum: SymbolicVariable<F>,
+    row_selector_offset: usize,
+    row_selectors: RefCell<Vec<RowSelector>>,
     constraints: Vec<SymbolicExpression<F>>,
 }
 
@@ -27,6 +34,7 @@ impl<F: Field> SymbolicAirBuilder<F> {
         main_width: usize,
         permutation_width: usize,
         num_public_values: usize,
+        num_periodic_columns: usize,
     ) -> Self {
         let prep_values = [0, 1]
             .into_iter()
@@ -46,27 +54,41 @@ impl<F: Field> SymbolicAirBuilder<F> {
             .into_iter()
             .flat_map(|offset| {
                 (0..permutation_width)
-                    .map(move |index| SymbolicVariable::new(Entry::Main { offset }, index))
+                    .map(move |index| SymbolicVariable::new(Entry::Permutation { offset }, index))
             })
             .collect();
         let public_values = (0..num_public_values)
             .map(move |index| SymbolicVariable::new(Entry::Public, index))
             .collect();
+        // Periodic columns are interpolated over the trace domain, so they have the same degree as
+        // a preprocessed column. They are recorded as preprocessed variables past the end of the
+        // preprocessed trace.
+        let periodic_values = (0..num_periodic_columns)
+            .map(move |index| {
+                SymbolicVariable::new(
+                    Entry::Preprocessed { offset: 0 },
+                    preprocessed_width + index,
+                )
+            })
+            .collect();
 
         let perm_challenges = (0..NUM_PERM_CHALLENGES)
             .map(move |index| SymbolicVariable::new(Entry::Challenge, index))
             .collect::<Vec<_>>()
             .try_into()
             .unwrap();
-        // TODO: This should be a symbolic variable
-        let cumulative_sum = SymbolicVariable::new(Entry::Challenge, 0);
+        // The cumulative sum is recorded as the challenge after the permutation challenges.
+        let cumulative_sum = SymbolicVariable::new(Entry::Challenge, NUM_PERM_CHALLENGES);
         Self {
             preprocessed: RowMajorMatrix::new(prep_values, preprocessed_width),
             main: RowMajorMatrix::new(main_values, main_width),
             permutation: RowMajorMatrix::new(perm_values, permutation_width),
             public_values,
+            periodic_values,
             perm_challenges,
             cumulative_sum,
+            row_selector_offset: preprocessed_width + num_periodic_columns,
+            row_selectors: RefCell::new(vec![]),
             constraints: vec![],
         }
     }
@@ -74,6 +96,20 @@ impl<F: Field> SymbolicAirBuilder<F> {
     pub fn constraints(self) -> Vec<SymbolicExpression<F>> {
         self.constraints
     }
+
+    /// Compiles the recorded constraints into a `ConstraintProgram`.
+    pub fn constraint_program(&self) -> ConstraintProgram<F> {
+        ConstraintProgram::compile(
+            &self.constraints,
+            self.preprocessed.width(),
+            self.periodic_values.len(),
+        )
+    }
+
+    /// The row selectors requested while evaluating the constraints, in order of first use.
+    pub fn row_selectors(&self) -> Vec<RowSelector> {
+        self.row_selectors.borrow().clone()
+    }
 }
 
 impl<F: Field> AirBuilder for SymbolicAirBuilder<F> {
@@ -152,3 +188,31 @@ impl<F: Field> InteractionAirBuilder for SymbolicAirBuilder<F> {
         self.cumulative_sum
     }
 }
+
+impl<F: Field> PeriodicAirBuilder for SymbolicAirBuilder<F> {
+    type PeriodicVar = SymbolicVariable<F>;
+
+    fn periodic_values(&self) -> &[Self::PeriodicVar] {
+        &self.periodic_values
+    }
+}
+
+impl<F: Field> SelectorAirBuilder for SymbolicAirBuilder<F> {
+    fn row_selector(&self, selector: RowSelector) -> Self::Expr {
+        // Like periodic columns, row selectors have the degree of a preprocessed column and are
+        // recorded as preprocessed variables, after the periodic columns.
+        let mut row_selectors = self.row_selectors.borrow_mut();
+        let index = match row_selectors.iter().position(|s| *s == selector) {
+            Some(index) => index,
+            None => {
+                row_selectors.push(selector);
+                row_selectors.len() - 1
+            }
+        };
+        SymbolicVariable::new(
+            Entry::Preprocessed { offset: 0 },
+            self.row_selector_offset + index,
+        )
+        .into()
+    }
+}
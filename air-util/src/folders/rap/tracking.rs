@@ -8,6 +8,7 @@ use crate::folders::{EntriesLog, ViewPair};
 use crate::util::{
     TraceEntry, TrackedExtensionFieldExpression, TrackedFieldExpression, TrackedFieldVariable,
 };
+use crate::{PeriodicAirBuilder, RowSelector, SelectorAirBuilder};
 
 // TODO: Remove permutations?
 pub struct TrackingConstraintBuilder<'a, F, EF>
@@ -15,12 +16,15 @@ where
     F: Field,
     EF: ExtensionField<F>,
 {
+    pub row_index: usize,
+    pub height: usize,
     pub entries: EntriesLog<TraceEntry>,
     pub preprocessed: ViewPair<'a, TrackedFieldVariable<F, TraceEntry>>,
     pub main: ViewPair<'a, TrackedFieldVariable<F, TraceEntry>>,
     pub permutation: ViewPair<'a, TrackedFieldVariable<EF, TraceEntry>>,
     pub perm_challenges: [TrackedFieldVariable<EF, TraceEntry>; NUM_PERM_CHALLENGES],
     pub public_values: &'a [TrackedFieldVariable<F, TraceEntry>],
+    pub periodic_values: &'a [TrackedFieldVariable<F, TraceEntry>],
     pub cumulative_sum: TrackedFieldVariable<EF, TraceEntry>,
     pub is_first_row: F,
     pub is_last_row: F,
@@ -135,3 +139,25 @@ where
         self.cumulative_sum
     }
 }
+
+impl<'a, F, EF> PeriodicAirBuilder for TrackingConstraintBuilder<'a, F, EF>
+where
+    F: Field,
+    EF: ExtensionField<F>,
+{
+    type PeriodicVar = TrackedFieldVariable<F, TraceEntry>;
+
+    fn periodic_values(&self) -> &[Self::PeriodicVar] {
+        self.periodic_values
+    }
+}
+
+impl<'a, F, EF> SelectorAirBuilder for TrackingConstraintBuilder<'a, F, EF>
+where
+    F: Field,
+    EF: ExtensionField<F>,
+{
+    fn row_selector(&self, selector: RowSelector) -> Self::Expr {
+        F::from_bool(selector.is_selected(self.row_index, self.height)).into()
+    }
+}
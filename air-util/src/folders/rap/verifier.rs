@@ -6,6 +6,7 @@ use p3_interaction::{InteractionAirBuilder, NUM_PERM_CHALLENGES};
 use p3_uni_stark::{StarkGenericConfig, Val};
 
 use crate::folders::ViewPair;
+use crate::{PeriodicAirBuilder, RowSelector, SelectorAirBuilder};
 
 pub struct VerifierConstraintFolder<'a, SC: StarkGenericConfig> {
     pub preprocessed: ViewPair<'a, SC::Challenge>,
@@ -13,6 +14,9 @@ pub struct VerifierConstraintFolder<'a, SC: StarkGenericConfig> {
     pub perm: ViewPair<'a, SC::Challenge>,
     pub perm_challenges: [SC::Challenge; NUM_PERM_CHALLENGES],
     pub public_values: &'a [Val<SC>],
+    pub periodic_values: &'a [SC::Challenge],
+    pub row_selectors: &'a [RowSelector],
+    pub row_selector_values: &'a [SC::Challenge],
     pub cumulative_sum: SC::Challenge,
     pub is_first_row: SC::Challenge,
     pub is_last_row: SC::Challenge,
@@ -111,3 +115,22 @@ impl<'a, SC: StarkGenericConfig> InteractionAirBuilder for VerifierConstraintFol
         self.cumulative_sum
     }
 }
+
+impl<'a, SC: StarkGenericConfig> PeriodicAirBuilder for VerifierConstraintFolder<'a, SC> {
+    type PeriodicVar = SC::Challenge;
+
+    fn periodic_values(&self) -> &[Self::PeriodicVar] {
+        self.periodic_values
+    }
+}
+
+impl<'a, SC: StarkGenericConfig> SelectorAirBuilder for VerifierConstraintFolder<'a, SC> {
+    fn row_selector(&self, selector: RowSelector) -> Self::Expr {
+        let index = self
+            .row_selectors
+            .iter()
+            .position(|s| *s == selector)
+            .expect("row selector should be evaluated at zeta");
+        self.row_selector_values[index]
+    }
+}
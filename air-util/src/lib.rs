@@ -6,12 +6,24 @@ extern crate alloc;
 #[cfg(feature = "air-logger")]
 mod air_logger;
 pub mod builders;
+mod bytecode;
+mod coverage;
 pub mod debug;
+mod embedded;
 pub mod folders;
+mod periodic;
 pub mod proof;
 mod quotient;
+mod selectors;
+mod trace_builder;
 pub mod util;
 
 #[cfg(feature = "air-logger")]
 pub use air_logger::*;
+pub use bytecode::*;
+pub use coverage::*;
+pub use embedded::*;
+pub use periodic::*;
 pub use quotient::*;
+pub use selectors::*;
+pub use trace_builder::*;
@@ -0,0 +1,27 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use p3_air::AirBuilder;
+use p3_field::Field;
+
+/// An AIR with periodic columns. A periodic column is a short table of values that repeats down
+/// the trace. It is never committed: the prover and the verifier both interpolate it.
+pub trait PeriodicAir<F: Field> {
+    /// The value table of each periodic column. Every table length must be a power of two no
+    /// larger than the trace height.
+    fn periodic_columns(&self) -> Vec<Vec<F>> {
+        vec![]
+    }
+}
+
+/// A builder which exposes the values of the periodic columns on the current row.
+pub trait PeriodicAirBuilder: AirBuilder {
+    type PeriodicVar: Into<Self::Expr> + Copy;
+
+    fn periodic_values(&self) -> &[Self::PeriodicVar];
+}
+
+/// Returns the value of each periodic column on the given row of the trace.
+pub fn periodic_values_on_row<F: Field>(columns: &[Vec<F>], row: usize) -> Vec<F> {
+    columns.iter().map(|col| col[row % col.len()]).collect()
+}
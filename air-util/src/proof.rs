@@ -4,7 +4,8 @@ use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Commitments<Com> {
-    pub main: Option<Com>,
+    /// One commitment per main commitment group.
+    pub main: Vec<Option<Com>>,
     pub permutation: Option<Com>,
     pub quotient_chunks: Option<Com>,
 }
@@ -7,12 +7,13 @@ use p3_util::log2_ceil_usize;
 use tracing::instrument;
 
 use crate::folders::rap::SymbolicAirBuilder;
+use crate::{ConstraintProgram, PeriodicAir, RowSelector};
 
 #[instrument(name = "infer log of constraint degree", skip_all)]
 pub fn get_quotient_degree<F, A>(air: &A, num_public_values: usize) -> usize
 where
     F: Field,
-    A: Rap<SymbolicAirBuilder<F>>,
+    A: Rap<SymbolicAirBuilder<F>> + PeriodicAir<F>,
 {
     // We pad to at least degree 2, since a quotient argument doesn't make sense with smaller degrees.
     let constraint_degree = get_max_constraint_degree(air, num_public_values).max(2);
@@ -28,7 +29,7 @@ where
 fn get_max_constraint_degree<F, A>(air: &A, num_public_values: usize) -> usize
 where
     F: Field,
-    A: Rap<SymbolicAirBuilder<F>>,
+    A: Rap<SymbolicAirBuilder<F>> + PeriodicAir<F>,
 {
     get_symbolic_constraints(air, num_public_values)
         .iter()
@@ -37,18 +38,58 @@ where
         .unwrap_or(0)
 }
 
+/// Returns the number of constraints of `air`, including its permutation constraints.
+#[instrument(name = "count constraints", skip_all, level = "debug")]
+pub fn get_num_constraints<F, A>(air: &A, num_public_values: usize) -> usize
+where
+    F: Field,
+    A: Rap<SymbolicAirBuilder<F>> + PeriodicAir<F>,
+{
+    get_symbolic_constraints(air, num_public_values).len()
+}
+
 #[instrument(name = "evaluate constraints symbolically", skip_all, level = "debug")]
 fn get_symbolic_constraints<F, A>(air: &A, num_public_values: usize) -> Vec<SymbolicExpression<F>>
 where
     F: Field,
-    A: Rap<SymbolicAirBuilder<F>>,
+    A: Rap<SymbolicAirBuilder<F>> + PeriodicAir<F>,
+{
+    eval_symbolically(air, num_public_values).constraints()
+}
+
+/// Returns the row selectors used by the constraints of `air`, in the order the constraint
+/// folders expect their values.
+#[instrument(name = "collect row selectors", skip_all, level = "debug")]
+pub fn get_row_selectors<F, A>(air: &A, num_public_values: usize) -> Vec<RowSelector>
+where
+    F: Field,
+    A: Rap<SymbolicAirBuilder<F>> + PeriodicAir<F>,
+{
+    eval_symbolically(air, num_public_values).row_selectors()
+}
+
+/// Evaluates the constraints of `air` symbolically and compiles them into a `ConstraintProgram`.
+#[instrument(name = "compile constraints", skip_all, level = "debug")]
+pub fn get_constraint_program<F, A>(air: &A, num_public_values: usize) -> ConstraintProgram<F>
+where
+    F: Field,
+    A: Rap<SymbolicAirBuilder<F>> + PeriodicAir<F>,
+{
+    eval_symbolically(air, num_public_values).constraint_program()
+}
+
+fn eval_symbolically<F, A>(air: &A, num_public_values: usize) -> SymbolicAirBuilder<F>
+where
+    F: Field,
+    A: Rap<SymbolicAirBuilder<F>> + PeriodicAir<F>,
 {
     let mut builder = SymbolicAirBuilder::new(
         air.preprocessed_width(),
         air.width(),
         air.permutation_width().unwrap_or_default(),
         num_public_values,
+        air.periodic_columns().len(),
     );
     air.eval_all(&mut builder);
-    builder.constraints()
+    builder
 }
@@ -0,0 +1,76 @@
+use core::iter::StepBy;
+use core::ops::Range;
+
+use p3_air::{AirBuilder, FilteredAirBuilder};
+
+/// A set of rows of the trace, beyond the first/last/transition rows every builder exposes.
+/// Rows outside the trace are never selected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RowSelector {
+    /// A single row.
+    Row(usize),
+    /// A contiguous range of rows.
+    Rows(Range<usize>),
+    /// Rows `offset, offset + period, offset + 2 * period, ...`. The period must be a power of
+    /// two no larger than the trace height, and the offset smaller than the period.
+    EveryNthRow { period: usize, offset: usize },
+}
+
+impl RowSelector {
+    /// Returns the indices of the selected rows of a trace with the given height.
+    pub fn rows(&self, height: usize) -> StepBy<Range<usize>> {
+        match self {
+            RowSelector::Row(row) => ((*row).min(height)..(*row + 1).min(height)).step_by(1),
+            RowSelector::Rows(rows) => (rows.start.min(height)..rows.end.min(height)).step_by(1),
+            RowSelector::EveryNthRow { period, offset } => {
+                ((*offset).min(height)..height).step_by(*period)
+            }
+        }
+    }
+
+    pub fn is_selected(&self, row: usize, height: usize) -> bool {
+        if row >= height {
+            return false;
+        }
+        match self {
+            RowSelector::Row(selected) => row == *selected,
+            RowSelector::Rows(rows) => rows.contains(&row),
+            RowSelector::EveryNthRow { period, offset } => {
+                row >= *offset && (row - offset) % period == 0
+            }
+        }
+    }
+}
+
+/// A builder which exposes selectors for arbitrary sets of rows.
+pub trait SelectorAirBuilder: AirBuilder {
+    /// Returns an expression which is one on the selected rows and zero elsewhere.
+    fn row_selector(&self, selector: RowSelector) -> Self::Expr;
+
+    fn is_row(&self, row: usize) -> Self::Expr {
+        self.row_selector(RowSelector::Row(row))
+    }
+
+    fn is_in_rows(&self, rows: Range<usize>) -> Self::Expr {
+        self.row_selector(RowSelector::Rows(rows))
+    }
+
+    fn is_every_nth_row(&self, period: usize, offset: usize) -> Self::Expr {
+        self.row_selector(RowSelector::EveryNthRow { period, offset })
+    }
+
+    fn when_row(&mut self, row: usize) -> FilteredAirBuilder<Self> {
+        let condition = self.is_row(row);
+        self.when(condition)
+    }
+
+    fn when_rows(&mut self, rows: Range<usize>) -> FilteredAirBuilder<Self> {
+        let condition = self.is_in_rows(rows);
+        self.when(condition)
+    }
+
+    fn when_every_nth_row(&mut self, period: usize, offset: usize) -> FilteredAirBuilder<Self> {
+        let condition = self.is_every_nth_row(period, offset);
+        self.when(condition)
+    }
+}
@@ -0,0 +1,100 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::borrow::BorrowMut;
+use core::marker::PhantomData;
+
+use p3_field::Field;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_maybe_rayon::prelude::{IndexedParallelIterator, ParallelIterator, ParallelSliceMut};
+
+/// A struct of trace columns, implemented by `#[derive(Columnar)]`.
+pub trait Columnar {
+    fn num_cols() -> usize;
+}
+
+/// Builds a main trace one typed row at a time.
+pub struct TraceBuilder<F, Cols> {
+    values: Vec<F>,
+    _marker: PhantomData<Cols>,
+}
+
+impl<F, Cols> TraceBuilder<F, Cols>
+where
+    F: Field,
+    Cols: Columnar,
+    [F]: BorrowMut<Cols>,
+{
+    pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+
+    pub fn with_capacity(height: usize) -> Self {
+        Self {
+            values: Vec::with_capacity(height * Cols::num_cols()),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Fills `height` rows in parallel, passing each row's index to `fill`.
+    pub fn from_rows<Fill>(height: usize, fill: Fill) -> Self
+    where
+        Fill: Fn(usize, &mut Cols) + Sync,
+    {
+        let width = Cols::num_cols();
+        let mut values = vec![F::zero(); height * width];
+        values
+            .par_chunks_mut(width)
+            .enumerate()
+            .for_each(|(i, row)| fill(i, row.borrow_mut()));
+        Self {
+            values,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn height(&self) -> usize {
+        self.values.len() / Cols::num_cols()
+    }
+
+    /// Appends a zeroed row and fills it with `fill`.
+    pub fn push_row(&mut self, fill: impl FnOnce(&mut Cols)) {
+        let width = Cols::num_cols();
+        let start = self.values.len();
+        self.values.resize(start + width, F::zero());
+        fill(self.values[start..].borrow_mut());
+    }
+
+    /// Builds the trace, padded to the next power of two height with `padding_row`.
+    pub fn build_padded(self, padding_row: &[F]) -> RowMajorMatrix<F> {
+        let mut trace = self.build();
+        pad_to_power_of_two(&mut trace, padding_row);
+        trace
+    }
+
+    pub fn build(self) -> RowMajorMatrix<F> {
+        RowMajorMatrix::new(self.values, Cols::num_cols())
+    }
+}
+
+impl<F, Cols> Default for TraceBuilder<F, Cols>
+where
+    F: Field,
+    Cols: Columnar,
+    [F]: BorrowMut<Cols>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Pads the trace to the next power of two height by repeating `padding_row`. Empty traces are
+/// padded to a single row.
+pub fn pad_to_power_of_two<F: Clone>(trace: &mut RowMajorMatrix<F>, padding_row: &[F]) {
+    assert_eq!(padding_row.len(), trace.width, "Padding row width mismatch");
+    let height = trace.values.len() / trace.width;
+    let padded_height = height.next_power_of_two();
+    trace.values.reserve((padded_height - height) * trace.width);
+    for _ in height..padded_height {
+        trace.values.extend_from_slice(padding_row);
+    }
+}
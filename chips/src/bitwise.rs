@@ -0,0 +1,154 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::{self, Display, Formatter};
+
+use p3_air::{Air, AirBuilder, BaseAir, VirtualPairCol};
+#[cfg(feature = "air-logger")]
+use p3_air_util::AirLogger;
+use p3_air_util::PeriodicAir;
+use p3_derive::Columnar;
+use p3_field::Field;
+use p3_interaction::{BaseInteractionAir, Interaction, InteractionAir, InteractionAirBuilder, Rap};
+use p3_machine::chip::Chip;
+use p3_matrix::dense::RowMajorMatrix;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BitwiseOp {
+    Xor,
+    And,
+    Or,
+}
+
+impl BitwiseOp {
+    pub const fn apply(&self, a: u8, b: u8) -> u8 {
+        match self {
+            BitwiseOp::Xor => a ^ b,
+            BitwiseOp::And => a & b,
+            BitwiseOp::Or => a | b,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Columnar, Default)]
+pub struct BitwisePreprocessedCols<T> {
+    pub a: T,
+    pub b: T,
+    pub result: T,
+}
+
+#[repr(C)]
+#[derive(Columnar, Default)]
+pub struct BitwiseCols<T> {
+    pub multiplicity: T,
+}
+
+/// A table of `op` applied to every pair of bytes. It receives `(a, b, result)` lookups on `bus`,
+/// and its main trace is the multiplicity of each pair.
+#[derive(Clone, Debug)]
+pub struct BitwiseChip {
+    pub op: BitwiseOp,
+    pub bus: usize,
+}
+
+/// Looks up `result = a op b` in the bitwise table on `bus`.
+pub fn bitwise<F: Field>(
+    bus: usize,
+    a: VirtualPairCol<F>,
+    b: VirtualPairCol<F>,
+    result: VirtualPairCol<F>,
+    count: VirtualPairCol<F>,
+) -> Interaction<F> {
+    Interaction {
+        fields: vec![a, b, result],
+        count,
+        argument_index: bus,
+    }
+}
+
+impl Display for BitwiseChip {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}8Chip", self.op)
+    }
+}
+
+impl<F: Field> BaseAir<F> for BitwiseChip {
+    fn width(&self) -> usize {
+        BitwiseCols::<F>::num_cols()
+    }
+
+    fn preprocessed_trace(&self) -> Option<RowMajorMatrix<F>> {
+        let values = (0..=u8::MAX)
+            .flat_map(|a| {
+                (0..=u8::MAX).flat_map(move |b| {
+                    [a, b, self.op.apply(a, b)].map(|value| F::from_canonical_u8(value))
+                })
+            })
+            .collect();
+        Some(RowMajorMatrix::new(
+            values,
+            BitwisePreprocessedCols::<F>::num_cols(),
+        ))
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for BitwiseChip {
+    fn eval(&self, _builder: &mut AB) {}
+}
+
+impl<F: Field> BaseInteractionAir<F> for BitwiseChip {
+    fn receives_from_indices(
+        &self,
+        preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        let preprocessed = BitwisePreprocessedCols::from_slice(preprocessed_indices);
+        let main = BitwiseCols::from_slice(main_indices);
+        vec![bitwise(
+            self.bus,
+            VirtualPairCol::single_preprocessed(preprocessed.a),
+            VirtualPairCol::single_preprocessed(preprocessed.b),
+            VirtualPairCol::single_preprocessed(preprocessed.result),
+            VirtualPairCol::single_main(main.multiplicity),
+        )]
+    }
+}
+
+impl<F: Field> InteractionAir<F> for BitwiseChip {
+    fn receives(&self) -> Vec<Interaction<F>> {
+        let preprocessed_col_map = BitwisePreprocessedCols::<F>::col_map();
+        let col_map = BitwiseCols::<F>::col_map();
+        self.receives_from_indices(preprocessed_col_map.as_slice(), col_map.as_slice())
+    }
+}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for BitwiseChip {
+    fn preprocessed_width(&self) -> usize {
+        BitwisePreprocessedCols::<AB::F>::num_cols()
+    }
+}
+
+impl<F: Field> PeriodicAir<F> for BitwiseChip {}
+
+#[cfg(feature = "air-logger")]
+impl AirLogger for BitwiseChip {
+    fn preprocessed_headers(&self) -> Vec<String> {
+        BitwisePreprocessedCols::<usize>::headers()
+    }
+
+    fn main_headers(&self) -> Vec<String> {
+        BitwiseCols::<usize>::headers()
+    }
+
+    #[cfg(feature = "schema")]
+    fn preprocessed_headers_and_types(&self) -> Vec<(String, String, core::ops::Range<usize>)> {
+        BitwisePreprocessedCols::<usize>::headers_and_types()
+    }
+
+    #[cfg(feature = "schema")]
+    fn main_headers_and_types(&self) -> Vec<(String, String, core::ops::Range<usize>)> {
+        BitwiseCols::<usize>::headers_and_types()
+    }
+}
+
+impl Chip for BitwiseChip {}
@@ -0,0 +1,49 @@
+use core::borrow::Borrow;
+
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_derive::Columnar;
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+
+#[repr(C)]
+#[derive(Columnar, Default, Clone, Copy)]
+pub struct IsEqualCols<T> {
+    pub a: T,
+    pub b: T,
+    pub inverse: T,
+    pub is_equal: T,
+}
+
+impl<F: Field> IsEqualCols<F> {
+    pub fn populate(&mut self, a: F, b: F) {
+        self.a = a;
+        self.b = b;
+        self.inverse = (a - b).try_inverse().unwrap_or_default();
+        self.is_equal = F::from_bool(a == b);
+    }
+}
+
+/// Constrains `is_equal` to be one if `a` equals `b`, and zero otherwise.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IsEqualAir;
+
+impl<F> BaseAir<F> for IsEqualAir {
+    fn width(&self) -> usize {
+        IsEqualCols::<F>::num_cols()
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for IsEqualAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let local: &IsEqualCols<AB::Var> = (*local).borrow();
+
+        let diff = local.a - local.b;
+        builder.assert_eq(
+            local.is_equal,
+            AB::Expr::one() - diff.clone() * local.inverse,
+        );
+        builder.assert_zero(diff * local.is_equal);
+    }
+}
@@ -0,0 +1,44 @@
+use core::borrow::Borrow;
+
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_derive::Columnar;
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+
+#[repr(C)]
+#[derive(Columnar, Default, Clone, Copy)]
+pub struct IsZeroCols<T> {
+    pub x: T,
+    pub inverse: T,
+    pub is_zero: T,
+}
+
+impl<F: Field> IsZeroCols<F> {
+    pub fn populate(&mut self, x: F) {
+        self.x = x;
+        self.inverse = x.try_inverse().unwrap_or_default();
+        self.is_zero = F::from_bool(x.is_zero());
+    }
+}
+
+/// Constrains `is_zero` to be one if `x` is zero, and zero otherwise. Embed it with a
+/// `SubRangeAirBuilder` over the range of an `IsZeroCols` in a larger trace.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IsZeroAir;
+
+impl<F> BaseAir<F> for IsZeroAir {
+    fn width(&self) -> usize {
+        IsZeroCols::<F>::num_cols()
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for IsZeroAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let local: &IsZeroCols<AB::Var> = (*local).borrow();
+
+        builder.assert_eq(local.is_zero, AB::Expr::one() - local.x * local.inverse);
+        builder.assert_zero(local.x * local.is_zero);
+    }
+}
@@ -0,0 +1,73 @@
+use core::borrow::Borrow;
+
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_derive::Columnar;
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+
+#[repr(C)]
+#[derive(Columnar, Clone, Copy)]
+pub struct LessThanCols<T, const N: usize> {
+    pub a: T,
+    pub b: T,
+    pub lt: T,
+    /// The bits of `a - b + lt * 2^N`.
+    pub diff_bits: [T; N],
+}
+
+impl<T: Default + Copy, const N: usize> Default for LessThanCols<T, N> {
+    fn default() -> Self {
+        Self {
+            a: T::default(),
+            b: T::default(),
+            lt: T::default(),
+            diff_bits: [T::default(); N],
+        }
+    }
+}
+
+impl<F: Field, const N: usize> LessThanCols<F, N> {
+    /// Fills in the columns for `a < b`, where both are `N`-bit values.
+    pub fn populate(&mut self, a: u32, b: u32) {
+        assert!(N < 30, "Comparisons should be narrower than the field");
+        debug_assert!(a < 1 << N && b < 1 << N);
+        let lt = a < b;
+        let diff = (a + ((lt as u32) << N)) - b;
+        self.a = F::from_canonical_u32(a);
+        self.b = F::from_canonical_u32(b);
+        self.lt = F::from_bool(lt);
+        for (i, bit) in self.diff_bits.iter_mut().enumerate() {
+            *bit = F::from_bool((diff >> i) & 1 == 1);
+        }
+    }
+}
+
+/// Constrains `lt` to be one if `a < b`, and zero otherwise, where `a` and `b` are `N`-bit
+/// values that are range checked elsewhere.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LessThanAir<const N: usize>;
+
+impl<F, const N: usize> BaseAir<F> for LessThanAir<N> {
+    fn width(&self) -> usize {
+        LessThanCols::<F, N>::num_cols()
+    }
+}
+
+impl<AB: AirBuilder, const N: usize> Air<AB> for LessThanAir<N> {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let local: &LessThanCols<AB::Var, N> = (*local).borrow();
+
+        builder.assert_bool(local.lt);
+        let mut diff = AB::Expr::zero();
+        for (i, &bit) in local.diff_bits.iter().enumerate() {
+            builder.assert_bool(bit);
+            diff += bit * AB::Expr::from_canonical_u32(1 << i);
+        }
+        builder.assert_eq(
+            local.a - local.b + local.lt * AB::Expr::from_canonical_u32(1 << N),
+            diff,
+        );
+    }
+}
@@ -0,0 +1,17 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+mod bitwise;
+mod is_equal;
+mod is_zero;
+mod less_than;
+mod memory;
+mod range;
+
+pub use bitwise::*;
+pub use is_equal::*;
+pub use is_zero::*;
+pub use less_than::*;
+pub use memory::*;
+pub use range::*;
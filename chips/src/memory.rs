@@ -0,0 +1,388 @@
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::fmt::{self, Display, Formatter};
+
+use p3_air::{Air, AirBuilder, BaseAir, VirtualPairCol};
+#[cfg(feature = "air-logger")]
+use p3_air_util::AirLogger;
+use p3_air_util::{pad_to_power_of_two, PeriodicAir, TraceBuilder};
+use p3_derive::Columnar;
+use p3_field::{AbstractField, Field};
+use p3_interaction::{BaseInteractionAir, Interaction, InteractionAir, InteractionAirBuilder, Rap};
+use p3_machine::chip::Chip;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+
+use crate::range_check;
+
+#[repr(C)]
+#[derive(Columnar, Default)]
+pub struct MemoryCols<T> {
+    pub addr: T,
+    pub value: T,
+    pub timestamp: T,
+    pub is_write: T,
+    /// Whether the row holds the initial value of its address, with a zero timestamp.
+    pub is_first: T,
+    /// Whether the row is an access received on the memory bus.
+    pub is_access: T,
+    /// Whether the row holds the final value of its address.
+    pub is_last: T,
+    /// `addr - prev_addr - 1` on the first row of an address, and `timestamp - prev_timestamp - 1`
+    /// on an access. It is range checked to keep the rows sorted.
+    pub diff: T,
+}
+
+#[repr(C)]
+#[derive(Columnar, Default)]
+pub struct MemoryImageCols<T> {
+    pub addr: T,
+    pub value: T,
+    pub is_real: T,
+}
+
+/// A memory access, as sent to the memory bus by `memory_access`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryAccess {
+    pub addr: u32,
+    pub value: u32,
+    pub timestamp: u32,
+    pub is_write: bool,
+}
+
+/// An offline memory argument. It receives `(addr, value, timestamp, is_write)` accesses on `bus`,
+/// with its rows sorted by address and then timestamp, so that every read returns the previous
+/// value of its address. The first and last value of each address are sent as `(addr, value)` to
+/// the memory images on `initial_bus` and `final_bus`, and the differences between consecutive
+/// addresses and timestamps must fit in the range check table on `range_bus`.
+#[derive(Clone, Debug)]
+pub struct MemoryChip {
+    pub bus: usize,
+    pub initial_bus: usize,
+    pub final_bus: usize,
+    pub range_bus: usize,
+}
+
+/// A fixed memory image. It receives `(addr, value)` on `bus` once for every address.
+#[derive(Clone, Debug)]
+pub struct MemoryImageChip {
+    pub image: BTreeMap<u32, u32>,
+    pub bus: usize,
+}
+
+/// Sends an access to the memory chip on `bus`.
+pub fn memory_access<F: Field>(
+    bus: usize,
+    addr: VirtualPairCol<F>,
+    value: VirtualPairCol<F>,
+    timestamp: VirtualPairCol<F>,
+    is_write: VirtualPairCol<F>,
+    count: VirtualPairCol<F>,
+) -> Interaction<F> {
+    Interaction {
+        fields: vec![addr, value, timestamp, is_write],
+        count,
+        argument_index: bus,
+    }
+}
+
+/// Builds the memory trace from `initial_image` and an access log in any order, and returns it with
+/// the final memory image.
+///
+/// Panics if the accesses to an address do not have distinct timestamps of at least 1, or if a
+/// read does not return the value last written to its address.
+pub fn generate_memory_trace<F: Field>(
+    initial_image: &BTreeMap<u32, u32>,
+    accesses: &[MemoryAccess],
+) -> (RowMajorMatrix<F>, BTreeMap<u32, u32>) {
+    let mut accesses_by_addr: BTreeMap<u32, Vec<MemoryAccess>> = BTreeMap::new();
+    for access in accesses {
+        assert!(
+            initial_image.contains_key(&access.addr),
+            "Address {} should be in the initial image",
+            access.addr
+        );
+        accesses_by_addr
+            .entry(access.addr)
+            .or_default()
+            .push(*access);
+    }
+
+    let mut builder =
+        TraceBuilder::<F, MemoryCols<F>>::with_capacity(initial_image.len() + accesses.len());
+    let mut final_image = BTreeMap::new();
+    let mut prev_addr = None;
+    for (&addr, &initial_value) in initial_image {
+        let addr_accesses = accesses_by_addr.entry(addr).or_default();
+        addr_accesses.sort_by_key(|access| access.timestamp);
+
+        builder.push_row(|row| {
+            row.addr = F::from_canonical_u32(addr);
+            row.value = F::from_canonical_u32(initial_value);
+            row.is_first = F::one();
+            row.is_last = F::from_bool(addr_accesses.is_empty());
+            row.diff = prev_addr.map_or(F::zero(), |prev: u32| {
+                F::from_canonical_u32(addr - prev - 1)
+            });
+        });
+
+        let mut value = initial_value;
+        let mut prev_timestamp = 0;
+        for (i, access) in addr_accesses.iter().enumerate() {
+            assert!(
+                access.timestamp > prev_timestamp,
+                "Accesses to address {addr} should have distinct timestamps of at least 1, found {} \
+                 after {prev_timestamp}",
+                access.timestamp
+            );
+            assert!(
+                access.is_write || access.value == value,
+                "Read of address {addr} at timestamp {} should return {value}",
+                access.timestamp
+            );
+            builder.push_row(|row| {
+                row.addr = F::from_canonical_u32(addr);
+                row.value = F::from_canonical_u32(access.value);
+                row.timestamp = F::from_canonical_u32(access.timestamp);
+                row.is_write = F::from_bool(access.is_write);
+                row.is_access = F::one();
+                row.is_last = F::from_bool(i == addr_accesses.len() - 1);
+                row.diff = F::from_canonical_u32(access.timestamp - prev_timestamp - 1);
+            });
+            value = access.value;
+            prev_timestamp = access.timestamp;
+        }
+
+        final_image.insert(addr, value);
+        prev_addr = Some(addr);
+    }
+
+    (builder.build(), final_image)
+}
+
+impl Display for MemoryChip {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "MemoryChip")
+    }
+}
+
+impl<F: Field> BaseAir<F> for MemoryChip {
+    fn width(&self) -> usize {
+        MemoryCols::<F>::num_cols()
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for MemoryChip {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let next = main.row_slice(1);
+        let local: &MemoryCols<AB::Var> = (*local).borrow();
+        let next: &MemoryCols<AB::Var> = (*next).borrow();
+
+        let local_is_real = local.is_first + local.is_access;
+        let next_is_real = next.is_first + next.is_access;
+
+        builder.assert_bool(local.is_write);
+        builder.assert_bool(local.is_first);
+        builder.assert_bool(local.is_access);
+        builder.assert_bool(local.is_last);
+        builder.assert_bool(local_is_real.clone());
+        builder.assert_zero(local.is_write * (AB::Expr::one() - local.is_access));
+        builder.assert_zero(local.is_first * local.timestamp);
+
+        // The trace starts with the first row of an address, and ends its last address.
+        builder.when_first_row().assert_zero(local.is_access);
+        builder.when_first_row().assert_zero(local.diff);
+        builder
+            .when_last_row()
+            .assert_eq(local.is_last, local_is_real.clone());
+
+        let mut when_transition = builder.when_transition();
+        // Padding rows are only at the end.
+        when_transition
+            .assert_zero(next_is_real.clone() * (AB::Expr::one() - local_is_real.clone()));
+        // An access belongs to the address of the previous row.
+        when_transition.assert_zero(next.is_access * (next.addr - local.addr));
+        when_transition.assert_eq(
+            next.diff,
+            next.is_first * (next.addr - local.addr - AB::Expr::one())
+                + next.is_access * (next.timestamp - local.timestamp - AB::Expr::one()),
+        );
+        // A read returns the previous value of its address.
+        when_transition.assert_zero(
+            next.is_access * (AB::Expr::one() - next.is_write) * (next.value - local.value),
+        );
+        when_transition.assert_eq(
+            local.is_last,
+            local_is_real * (AB::Expr::one() - next.is_access),
+        );
+    }
+}
+
+impl<F: Field> BaseInteractionAir<F> for MemoryChip {
+    fn receives_from_indices(
+        &self,
+        _preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        let col_map = MemoryCols::from_slice(main_indices);
+        vec![memory_access(
+            self.bus,
+            VirtualPairCol::single_main(col_map.addr),
+            VirtualPairCol::single_main(col_map.value),
+            VirtualPairCol::single_main(col_map.timestamp),
+            VirtualPairCol::single_main(col_map.is_write),
+            VirtualPairCol::single_main(col_map.is_access),
+        )]
+    }
+
+    fn sends_from_indices(
+        &self,
+        _preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        let col_map = MemoryCols::from_slice(main_indices);
+        let is_real = VirtualPairCol::sum_main(vec![col_map.is_first, col_map.is_access]);
+        let image = |bus, count| Interaction {
+            fields: vec![
+                VirtualPairCol::single_main(col_map.addr),
+                VirtualPairCol::single_main(col_map.value),
+            ],
+            count,
+            argument_index: bus,
+        };
+        vec![
+            image(
+                self.initial_bus,
+                VirtualPairCol::single_main(col_map.is_first),
+            ),
+            image(self.final_bus, VirtualPairCol::single_main(col_map.is_last)),
+            range_check(
+                self.range_bus,
+                VirtualPairCol::single_main(col_map.diff),
+                is_real,
+            ),
+        ]
+    }
+}
+
+impl<F: Field> InteractionAir<F> for MemoryChip {
+    fn receives(&self) -> Vec<Interaction<F>> {
+        let col_map = MemoryCols::<F>::col_map();
+        self.receives_from_main_indices(col_map.as_slice())
+    }
+
+    fn sends(&self) -> Vec<Interaction<F>> {
+        let col_map = MemoryCols::<F>::col_map();
+        self.sends_from_main_indices(col_map.as_slice())
+    }
+}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for MemoryChip {}
+
+impl<F: Field> PeriodicAir<F> for MemoryChip {}
+
+#[cfg(feature = "air-logger")]
+impl AirLogger for MemoryChip {
+    fn main_headers(&self) -> Vec<String> {
+        MemoryCols::<usize>::headers()
+    }
+
+    #[cfg(feature = "schema")]
+    fn main_headers_and_types(&self) -> Vec<(String, String, core::ops::Range<usize>)> {
+        MemoryCols::<usize>::headers_and_types()
+    }
+}
+
+impl Chip for MemoryChip {
+    fn padding_row<F: Field>(&self) -> Option<Vec<F>> {
+        Some(vec![F::zero(); MemoryCols::<F>::num_cols()])
+    }
+}
+
+impl Display for MemoryImageChip {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "MemoryImage{}Chip", self.bus)
+    }
+}
+
+impl<F: Field> BaseAir<F> for MemoryImageChip {
+    fn width(&self) -> usize {
+        0
+    }
+
+    fn preprocessed_trace(&self) -> Option<RowMajorMatrix<F>> {
+        let values = self
+            .image
+            .iter()
+            .flat_map(|(&addr, &value)| [addr, value, 1].map(|value| F::from_canonical_u32(value)))
+            .collect();
+        let num_cols = MemoryImageCols::<F>::num_cols();
+        let mut trace = RowMajorMatrix::new(values, num_cols);
+        pad_to_power_of_two(&mut trace, &vec![F::zero(); num_cols]);
+        Some(trace)
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for MemoryImageChip {
+    fn eval(&self, _builder: &mut AB) {}
+}
+
+impl<F: Field> BaseInteractionAir<F> for MemoryImageChip {
+    fn receives_from_indices(
+        &self,
+        preprocessed_indices: &[usize],
+        _main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        let preprocessed = MemoryImageCols::from_slice(preprocessed_indices);
+        vec![Interaction {
+            fields: vec![
+                VirtualPairCol::single_preprocessed(preprocessed.addr),
+                VirtualPairCol::single_preprocessed(preprocessed.value),
+            ],
+            count: VirtualPairCol::single_preprocessed(preprocessed.is_real),
+            argument_index: self.bus,
+        }]
+    }
+}
+
+impl<F: Field> InteractionAir<F> for MemoryImageChip {
+    fn receives(&self) -> Vec<Interaction<F>> {
+        let preprocessed_col_map = MemoryImageCols::<F>::col_map();
+        self.receives_from_preprocessed_indices(preprocessed_col_map.as_slice())
+    }
+}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for MemoryImageChip {
+    fn preprocessed_width(&self) -> usize {
+        MemoryImageCols::<AB::F>::num_cols()
+    }
+}
+
+impl<F: Field> PeriodicAir<F> for MemoryImageChip {}
+
+#[cfg(feature = "air-logger")]
+impl AirLogger for MemoryImageChip {
+    fn preprocessed_headers(&self) -> Vec<String> {
+        MemoryImageCols::<usize>::headers()
+    }
+
+    fn main_headers(&self) -> Vec<String> {
+        vec![]
+    }
+
+    #[cfg(feature = "schema")]
+    fn preprocessed_headers_and_types(&self) -> Vec<(String, String, core::ops::Range<usize>)> {
+        MemoryImageCols::<usize>::headers_and_types()
+    }
+
+    #[cfg(feature = "schema")]
+    fn main_headers_and_types(&self) -> Vec<(String, String, core::ops::Range<usize>)> {
+        vec![]
+    }
+}
+
+impl Chip for MemoryImageChip {}
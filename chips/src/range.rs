@@ -0,0 +1,135 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::{self, Display, Formatter};
+
+use p3_air::{Air, AirBuilder, BaseAir, VirtualPairCol};
+#[cfg(feature = "air-logger")]
+use p3_air_util::AirLogger;
+use p3_air_util::PeriodicAir;
+use p3_derive::Columnar;
+use p3_field::Field;
+use p3_interaction::{BaseInteractionAir, Interaction, InteractionAir, InteractionAirBuilder, Rap};
+use p3_machine::chip::Chip;
+use p3_matrix::dense::RowMajorMatrix;
+
+#[repr(C)]
+#[derive(Columnar, Default)]
+pub struct RangeCheckPreprocessedCols<T> {
+    pub value: T,
+}
+
+#[repr(C)]
+#[derive(Columnar, Default)]
+pub struct RangeCheckCols<T> {
+    pub multiplicity: T,
+}
+
+/// A table of every `bits`-bit value. It receives range checks on `bus`, and its main trace is
+/// the multiplicity of each value.
+#[derive(Clone, Debug)]
+pub struct RangeCheckChip {
+    pub bits: usize,
+    pub bus: usize,
+}
+
+impl RangeCheckChip {
+    pub const fn u8(bus: usize) -> Self {
+        Self { bits: 8, bus }
+    }
+
+    pub const fn u16(bus: usize) -> Self {
+        Self { bits: 16, bus }
+    }
+}
+
+/// Sends `value` to the range check table on `bus`.
+pub fn range_check<F: Field>(
+    bus: usize,
+    value: VirtualPairCol<F>,
+    count: VirtualPairCol<F>,
+) -> Interaction<F> {
+    Interaction {
+        fields: vec![value],
+        count,
+        argument_index: bus,
+    }
+}
+
+impl Display for RangeCheckChip {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "RangeCheck{}Chip", self.bits)
+    }
+}
+
+impl<F: Field> BaseAir<F> for RangeCheckChip {
+    fn width(&self) -> usize {
+        RangeCheckCols::<F>::num_cols()
+    }
+
+    fn preprocessed_trace(&self) -> Option<RowMajorMatrix<F>> {
+        let values = (0..1 << self.bits).map(F::from_canonical_usize).collect();
+        Some(RowMajorMatrix::new(
+            values,
+            RangeCheckPreprocessedCols::<F>::num_cols(),
+        ))
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for RangeCheckChip {
+    fn eval(&self, _builder: &mut AB) {}
+}
+
+impl<F: Field> BaseInteractionAir<F> for RangeCheckChip {
+    fn receives_from_indices(
+        &self,
+        preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        let preprocessed = RangeCheckPreprocessedCols::from_slice(preprocessed_indices);
+        let main = RangeCheckCols::from_slice(main_indices);
+        vec![range_check(
+            self.bus,
+            VirtualPairCol::single_preprocessed(preprocessed.value),
+            VirtualPairCol::single_main(main.multiplicity),
+        )]
+    }
+}
+
+impl<F: Field> InteractionAir<F> for RangeCheckChip {
+    fn receives(&self) -> Vec<Interaction<F>> {
+        let preprocessed_col_map = RangeCheckPreprocessedCols::<F>::col_map();
+        let col_map = RangeCheckCols::<F>::col_map();
+        self.receives_from_indices(preprocessed_col_map.as_slice(), col_map.as_slice())
+    }
+}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for RangeCheckChip {
+    fn preprocessed_width(&self) -> usize {
+        RangeCheckPreprocessedCols::<AB::F>::num_cols()
+    }
+}
+
+impl<F: Field> PeriodicAir<F> for RangeCheckChip {}
+
+#[cfg(feature = "air-logger")]
+impl AirLogger for RangeCheckChip {
+    fn preprocessed_headers(&self) -> Vec<String> {
+        RangeCheckPreprocessedCols::<usize>::headers()
+    }
+
+    fn main_headers(&self) -> Vec<String> {
+        RangeCheckCols::<usize>::headers()
+    }
+
+    #[cfg(feature = "schema")]
+    fn preprocessed_headers_and_types(&self) -> Vec<(String, String, core::ops::Range<usize>)> {
+        RangeCheckPreprocessedCols::<usize>::headers_and_types()
+    }
+
+    #[cfg(feature = "schema")]
+    fn main_headers_and_types(&self) -> Vec<(String, String, core::ops::Range<usize>)> {
+        RangeCheckCols::<usize>::headers_and_types()
+    }
+}
+
+impl Chip for RangeCheckChip {}
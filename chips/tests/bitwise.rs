@@ -0,0 +1,179 @@
+extern crate alloc;
+
+mod common;
+
+use core::fmt::{self, Display, Formatter};
+use std::collections::BTreeMap;
+
+use p3_air::{Air, AirBuilder, BaseAir, VirtualPairCol};
+use p3_air_util::PeriodicAir;
+use p3_chips::{bitwise, BitwiseChip, BitwiseOp};
+use p3_derive::EnumDispatch;
+use p3_field::{AbstractField, Field};
+use p3_interaction::{BaseInteractionAir, Interaction, InteractionAir, InteractionAirBuilder, Rap};
+use p3_machine::chip::{Chip, ChipId};
+use p3_machine::error::LookupError;
+use p3_machine::lookup::generate_multiplicities;
+use p3_machine::machine::Machine;
+use p3_machine::options::ProverOptions;
+use p3_matrix::dense::RowMajorMatrix;
+
+use common::{default_config, TestBus, Val};
+
+/// Sends `(a, b, result)` from its first three columns to the bitwise table, once per row.
+#[derive(Clone, Debug)]
+struct SenderChip;
+
+impl Display for SenderChip {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "SenderChip")
+    }
+}
+
+impl<F: Field> BaseAir<F> for SenderChip {
+    fn width(&self) -> usize {
+        3
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for SenderChip {
+    fn eval(&self, _builder: &mut AB) {}
+}
+
+impl<F: Field> BaseInteractionAir<F> for SenderChip {
+    fn sends_from_indices(
+        &self,
+        _preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        vec![bitwise(
+            TestBus::Bitwise as usize,
+            VirtualPairCol::single_main(main_indices[0]),
+            VirtualPairCol::single_main(main_indices[1]),
+            VirtualPairCol::single_main(main_indices[2]),
+            VirtualPairCol::constant(F::one()),
+        )]
+    }
+}
+
+impl<F: Field> InteractionAir<F> for SenderChip {
+    fn sends(&self) -> Vec<Interaction<F>> {
+        self.sends_from_indices(&[], &[0, 1, 2])
+    }
+}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for SenderChip {}
+
+impl<F: Field> PeriodicAir<F> for SenderChip {}
+
+impl Chip for SenderChip {}
+
+#[derive(Clone, Debug, EnumDispatch)]
+enum BitwiseTestChip {
+    Bitwise(BitwiseChip),
+    Sender(SenderChip),
+}
+
+struct BitwiseTestMachine {
+    op: BitwiseOp,
+}
+
+impl BitwiseTestMachine {
+    fn table(&self) -> BitwiseTestChip {
+        BitwiseTestChip::Bitwise(BitwiseChip {
+            op: self.op,
+            bus: TestBus::Bitwise as usize,
+        })
+    }
+}
+
+impl Machine for BitwiseTestMachine {
+    type Chip = BitwiseTestChip;
+
+    type Bus = TestBus;
+
+    fn chips(&self) -> Vec<Self::Chip> {
+        vec![self.table(), BitwiseTestChip::Sender(SenderChip)]
+    }
+}
+
+const INPUTS: [(u8, u8); 4] = [(0x0f, 0xf0), (0xff, 0x3c), (0x12, 0x34), (0x0f, 0xf0)];
+
+fn sender_trace(rows: &[(u8, u8, u8)]) -> BTreeMap<ChipId, RowMajorMatrix<Val>> {
+    let values = rows
+        .iter()
+        .flat_map(|&(a, b, result)| [a, b, result].map(Val::from_canonical_u8))
+        .collect();
+    let sender = BitwiseTestChip::Sender(SenderChip);
+    BTreeMap::from([(sender.id(), RowMajorMatrix::new(values, 3))])
+}
+
+fn traces_for(machine: &BitwiseTestMachine) -> BTreeMap<ChipId, RowMajorMatrix<Val>> {
+    let rows: Vec<_> = INPUTS
+        .iter()
+        .map(|&(a, b)| (a, b, machine.op.apply(a, b)))
+        .collect();
+    let mut traces = sender_trace(&rows);
+    let table = machine.table();
+    let multiplicities = generate_multiplicities(
+        &table,
+        TestBus::Bitwise as usize,
+        &machine.chips_by_id(),
+        &traces,
+    )
+    .unwrap();
+    assert_eq!(multiplicities[0x0ff0], Val::two());
+    traces.insert(table.id(), RowMajorMatrix::new_col(multiplicities));
+    traces
+}
+
+#[test]
+fn test_bitwise_ops() {
+    assert_eq!(BitwiseOp::Xor.apply(0x0f, 0xff), 0xf0);
+    assert_eq!(BitwiseOp::And.apply(0x0f, 0xff), 0x0f);
+    assert_eq!(BitwiseOp::Or.apply(0x0f, 0xf0), 0xff);
+}
+
+#[test]
+fn test_multiplicities_for_all_ops() {
+    for op in [BitwiseOp::Xor, BitwiseOp::And, BitwiseOp::Or] {
+        let traces = traces_for(&BitwiseTestMachine { op });
+        assert_eq!(traces.len(), 2);
+    }
+}
+
+#[test]
+fn test_xor_prove_and_verify() {
+    let machine = BitwiseTestMachine { op: BitwiseOp::Xor };
+    let traces = traces_for(&machine);
+
+    let (config, challenger) = default_config();
+    let (pk, vk) = machine.setup(&config);
+    let proof = machine.prove(
+        &config,
+        &mut challenger.clone(),
+        &pk,
+        traces,
+        &[],
+        &ProverOptions::default(),
+    );
+    machine
+        .verify(&config, &mut challenger.clone(), &vk, &proof, &[])
+        .unwrap();
+}
+
+#[test]
+fn test_wrong_result_not_in_table() {
+    let machine = BitwiseTestMachine { op: BitwiseOp::And };
+    let traces = sender_trace(&[(0x0f, 0xf0, 0x00), (0x0f, 0xf0, 0xff)]);
+    let result = generate_multiplicities(
+        &machine.table(),
+        TestBus::Bitwise as usize,
+        &machine.chips_by_id(),
+        &traces,
+    );
+    assert!(matches!(
+        result,
+        Err(LookupError::MessageNotInTable { row: 1, .. })
+    ));
+}
@@ -0,0 +1,173 @@
+extern crate alloc;
+
+mod common;
+
+use core::fmt::{self, Display, Formatter};
+use std::collections::BTreeMap;
+
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_air_util::builders::SubRangeAirBuilder;
+use p3_air_util::debug::air::check_constraints;
+use p3_air_util::{PeriodicAir, TraceBuilder};
+use p3_chips::{IsEqualAir, IsEqualCols, IsZeroAir, IsZeroCols, LessThanAir, LessThanCols};
+use p3_derive::Columnar;
+use p3_field::{AbstractField, Field};
+use p3_interaction::{BaseInteractionAir, InteractionAir, InteractionAirBuilder, Rap};
+use p3_machine::chip::Chip;
+use p3_machine::machine::Machine;
+use p3_machine::options::ProverOptions;
+use p3_matrix::dense::RowMajorMatrix;
+
+use common::{default_config, TestBus, Val};
+
+const BITS: usize = 8;
+
+#[repr(C)]
+#[derive(Columnar)]
+struct GadgetCols<T> {
+    is_zero: IsZeroCols<T>,
+    is_equal: IsEqualCols<T>,
+    less_than: LessThanCols<T, BITS>,
+}
+
+/// A chip that evaluates each gadget over its own range of columns.
+#[derive(Clone, Debug)]
+struct GadgetChip;
+
+impl Display for GadgetChip {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "GadgetChip")
+    }
+}
+
+impl<F: Field> BaseAir<F> for GadgetChip {
+    fn width(&self) -> usize {
+        GadgetCols::<F>::num_cols()
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for GadgetChip {
+    fn eval(&self, builder: &mut AB) {
+        let col_map = GadgetCols::<AB::Var>::col_map();
+        IsZeroAir.eval(&mut SubRangeAirBuilder::new_main(
+            builder,
+            col_map.is_zero.as_range(),
+        ));
+        IsEqualAir.eval(&mut SubRangeAirBuilder::new_main(
+            builder,
+            col_map.is_equal.as_range(),
+        ));
+        LessThanAir::<BITS>.eval(&mut SubRangeAirBuilder::new_main(
+            builder,
+            col_map.less_than.as_range(),
+        ));
+    }
+}
+
+impl<F: Field> BaseInteractionAir<F> for GadgetChip {}
+
+impl<F: Field> InteractionAir<F> for GadgetChip {}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for GadgetChip {}
+
+impl<F: Field> PeriodicAir<F> for GadgetChip {}
+
+impl Chip for GadgetChip {}
+
+struct GadgetMachine;
+
+impl Machine for GadgetMachine {
+    type Chip = GadgetChip;
+
+    type Bus = TestBus;
+
+    fn chips(&self) -> Vec<Self::Chip> {
+        vec![GadgetChip]
+    }
+}
+
+fn gadget_row(i: usize, row: &mut GadgetCols<Val>) {
+    let a = (i * 37 % 256) as u32;
+    let b = (i * 11 % 5) as u32;
+    row.is_zero.populate(Val::from_canonical_u32(b));
+    row.is_equal
+        .populate(Val::from_canonical_u32(a % 4), Val::from_canonical_u32(b));
+    row.less_than.populate(a, b);
+}
+
+#[test]
+fn test_populate() {
+    let mut is_zero = IsZeroCols::<Val>::default();
+    is_zero.populate(Val::zero());
+    assert_eq!(is_zero.is_zero, Val::one());
+    is_zero.populate(Val::two());
+    assert_eq!(is_zero.is_zero, Val::zero());
+    assert_eq!(is_zero.x * is_zero.inverse, Val::one());
+
+    let mut is_equal = IsEqualCols::<Val>::default();
+    is_equal.populate(Val::two(), Val::two());
+    assert_eq!(is_equal.is_equal, Val::one());
+
+    let mut less_than = LessThanCols::<Val, BITS>::default();
+    less_than.populate(3, 200);
+    assert_eq!(less_than.lt, Val::one());
+    less_than.populate(200, 3);
+    assert_eq!(less_than.lt, Val::zero());
+    less_than.populate(7, 7);
+    assert_eq!(less_than.lt, Val::zero());
+}
+
+#[test]
+fn test_gadget_chip_prove_and_verify() {
+    let machine = GadgetMachine;
+    let traces = BTreeMap::from([(
+        GadgetChip.id(),
+        TraceBuilder::<Val, GadgetCols<Val>>::from_rows(16, gadget_row).build(),
+    )]);
+
+    let (config, challenger) = default_config();
+    let (pk, vk) = machine.setup(&config);
+    let proof = machine.prove(
+        &config,
+        &mut challenger.clone(),
+        &pk,
+        traces,
+        &[],
+        &ProverOptions::default(),
+    );
+    machine
+        .verify(&config, &mut challenger.clone(), &vk, &proof, &[])
+        .unwrap();
+}
+
+#[test]
+#[should_panic(expected = "values didn't match on row 0")]
+fn test_forged_is_zero_rejected() {
+    let mut cols = IsZeroCols::<Val>::default();
+    cols.populate(Val::two());
+    cols.is_zero = Val::one();
+    let trace = RowMajorMatrix::new(vec![cols.x, cols.inverse, cols.is_zero], 3);
+    check_constraints(&IsZeroAir, &None, &Some(trace.as_view()), &[]);
+}
+
+#[test]
+#[should_panic(expected = "values didn't match on row 0")]
+fn test_forged_is_equal_rejected() {
+    let mut cols = IsEqualCols::<Val>::default();
+    cols.populate(Val::two(), Val::from_canonical_u32(3));
+    cols.is_equal = Val::one();
+    let trace = RowMajorMatrix::new(vec![cols.a, cols.b, cols.inverse, cols.is_equal], 4);
+    check_constraints(&IsEqualAir, &None, &Some(trace.as_view()), &[]);
+}
+
+#[test]
+#[should_panic(expected = "values didn't match on row 0")]
+fn test_forged_less_than_rejected() {
+    let mut cols = LessThanCols::<Val, BITS>::default();
+    cols.populate(3, 200);
+    cols.lt = Val::zero();
+    let mut values = vec![cols.a, cols.b, cols.lt];
+    values.extend(cols.diff_bits);
+    let trace = RowMajorMatrix::new(values, LessThanCols::<Val, BITS>::num_cols());
+    check_constraints(&LessThanAir::<BITS>, &None, &Some(trace.as_view()), &[]);
+}
@@ -0,0 +1,342 @@
+extern crate alloc;
+
+mod common;
+
+use core::borrow::Borrow;
+use core::fmt::{self, Display, Formatter};
+use std::collections::BTreeMap;
+
+use p3_air::{Air, AirBuilder, BaseAir, VirtualPairCol};
+use p3_air_util::debug::rap::check_cumulative_sums;
+use p3_air_util::{PeriodicAir, TraceBuilder};
+use p3_chips::{
+    generate_memory_trace, memory_access, MemoryAccess, MemoryChip, MemoryImageChip, RangeCheckChip,
+};
+use p3_derive::{Columnar, EnumDispatch};
+use p3_field::{AbstractField, Field};
+use p3_interaction::{
+    generate_permutation_trace, BaseInteractionAir, Interaction, InteractionAir,
+    InteractionAirBuilder, Rap,
+};
+use p3_machine::chip::{Chip, ChipId};
+use p3_machine::error::VerificationError;
+use p3_machine::lookup::generate_multiplicities;
+use p3_machine::machine::Machine;
+use p3_machine::options::ProverOptions;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+
+use common::{default_config, Challenge, TestBus, Val};
+
+#[repr(C)]
+#[derive(Columnar, Default)]
+struct CpuCols<T> {
+    addr: T,
+    value: T,
+    timestamp: T,
+    is_write: T,
+    is_real: T,
+}
+
+/// Sends one memory access per real row.
+#[derive(Clone, Debug)]
+struct CpuChip;
+
+impl Display for CpuChip {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "CpuChip")
+    }
+}
+
+impl<F: Field> BaseAir<F> for CpuChip {
+    fn width(&self) -> usize {
+        CpuCols::<F>::num_cols()
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for CpuChip {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let local: &CpuCols<AB::Var> = (*local).borrow();
+        builder.assert_bool(local.is_real);
+    }
+}
+
+impl<F: Field> BaseInteractionAir<F> for CpuChip {
+    fn sends_from_indices(
+        &self,
+        _preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        let col_map = CpuCols::from_slice(main_indices);
+        vec![memory_access(
+            TestBus::Memory as usize,
+            VirtualPairCol::single_main(col_map.addr),
+            VirtualPairCol::single_main(col_map.value),
+            VirtualPairCol::single_main(col_map.timestamp),
+            VirtualPairCol::single_main(col_map.is_write),
+            VirtualPairCol::single_main(col_map.is_real),
+        )]
+    }
+}
+
+impl<F: Field> InteractionAir<F> for CpuChip {
+    fn sends(&self) -> Vec<Interaction<F>> {
+        let col_map = CpuCols::<F>::col_map();
+        self.sends_from_main_indices(col_map.as_slice())
+    }
+}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for CpuChip {}
+
+impl<F: Field> PeriodicAir<F> for CpuChip {}
+
+impl Chip for CpuChip {
+    fn padding_row<F: Field>(&self) -> Option<Vec<F>> {
+        Some(vec![F::zero(); CpuCols::<F>::num_cols()])
+    }
+}
+
+#[derive(Clone, Debug, EnumDispatch)]
+enum MemoryTestChip {
+    Memory(MemoryChip),
+    InitialImage(MemoryImageChip),
+    FinalImage(MemoryImageChip),
+    Range(RangeCheckChip),
+    Cpu(CpuChip),
+}
+
+fn memory_chip() -> MemoryChip {
+    MemoryChip {
+        bus: TestBus::Memory as usize,
+        initial_bus: TestBus::InitialMemory as usize,
+        final_bus: TestBus::FinalMemory as usize,
+        range_bus: TestBus::Range as usize,
+    }
+}
+
+struct MemoryMachine {
+    initial_image: BTreeMap<u32, u32>,
+    final_image: BTreeMap<u32, u32>,
+}
+
+impl MemoryMachine {
+    fn range_table(&self) -> MemoryTestChip {
+        MemoryTestChip::Range(RangeCheckChip::u8(TestBus::Range as usize))
+    }
+}
+
+impl Machine for MemoryMachine {
+    type Chip = MemoryTestChip;
+
+    type Bus = TestBus;
+
+    fn chips(&self) -> Vec<Self::Chip> {
+        vec![
+            MemoryTestChip::Memory(memory_chip()),
+            MemoryTestChip::InitialImage(MemoryImageChip {
+                image: self.initial_image.clone(),
+                bus: TestBus::InitialMemory as usize,
+            }),
+            MemoryTestChip::FinalImage(MemoryImageChip {
+                image: self.final_image.clone(),
+                bus: TestBus::FinalMemory as usize,
+            }),
+            self.range_table(),
+            MemoryTestChip::Cpu(CpuChip),
+        ]
+    }
+}
+
+fn initial_image() -> BTreeMap<u32, u32> {
+    BTreeMap::from([(0, 5), (1, 7), (3, 0), (4, 11)])
+}
+
+fn accesses() -> Vec<MemoryAccess> {
+    let access = |addr, value, timestamp, is_write| MemoryAccess {
+        addr,
+        value,
+        timestamp,
+        is_write,
+    };
+    vec![
+        access(0, 9, 1, true),
+        access(1, 7, 2, false),
+        access(0, 9, 3, false),
+        access(3, 2, 4, true),
+        access(3, 2, 5, false),
+        access(1, 1, 6, true),
+    ]
+}
+
+/// Builds the machine for the access log, and its main traces with a read value in the CPU trace
+/// replaced by `tampered_read`.
+fn machine_and_traces(
+    tampered_read: Option<u32>,
+) -> (MemoryMachine, BTreeMap<ChipId, RowMajorMatrix<Val>>) {
+    let accesses = accesses();
+    let initial_image = initial_image();
+    let (memory_trace, final_image) = generate_memory_trace(&initial_image, &accesses);
+    let machine = MemoryMachine {
+        initial_image,
+        final_image,
+    };
+
+    let cpu_trace = TraceBuilder::<Val, CpuCols<Val>>::from_rows(accesses.len(), |i, row| {
+        let access = accesses[i];
+        let value = match tampered_read {
+            Some(value) if i == 1 => value,
+            _ => access.value,
+        };
+        row.addr = Val::from_canonical_u32(access.addr);
+        row.value = Val::from_canonical_u32(value);
+        row.timestamp = Val::from_canonical_u32(access.timestamp);
+        row.is_write = Val::from_bool(access.is_write);
+        row.is_real = Val::one();
+    })
+    .build();
+
+    let mut traces = BTreeMap::from([
+        (MemoryTestChip::Memory(memory_chip()).id(), memory_trace),
+        (MemoryTestChip::Cpu(CpuChip).id(), cpu_trace),
+    ]);
+    let range_table = machine.range_table();
+    let multiplicities = generate_multiplicities(
+        &range_table,
+        TestBus::Range as usize,
+        &machine.chips_by_id(),
+        &traces,
+    )
+    .unwrap();
+    traces.insert(range_table.id(), RowMajorMatrix::new_col(multiplicities));
+    (machine, traces)
+}
+
+fn check_buses(machine: &MemoryMachine, traces: &BTreeMap<ChipId, RowMajorMatrix<Val>>) {
+    let chips = machine.chips();
+    let preprocessed = chips
+        .iter()
+        .map(BaseAir::<Val>::preprocessed_trace)
+        .collect::<Vec<_>>();
+    let main = chips
+        .iter()
+        .map(|chip| traces.get(&chip.id()).cloned())
+        .collect::<Vec<_>>();
+    let preprocessed = preprocessed
+        .iter()
+        .map(|trace| trace.as_ref().map(|trace| trace.as_view()))
+        .collect::<Vec<_>>();
+    let main = main
+        .iter()
+        .map(|trace| trace.as_ref().map(|trace| trace.as_view()))
+        .collect::<Vec<_>>();
+    let perm_challenges = [
+        Challenge::from_canonical_u32(7),
+        Challenge::from_canonical_u32(13),
+    ];
+    let permutation = chips
+        .iter()
+        .zip(preprocessed.iter().zip(main.iter()))
+        .map(|(chip, (preprocessed, main))| {
+            generate_permutation_trace(
+                preprocessed,
+                main,
+                &InteractionAir::<Val>::all_interactions(chip),
+                perm_challenges,
+            )
+        })
+        .collect::<Vec<_>>();
+    let permutation = permutation
+        .iter()
+        .map(|trace| trace.as_ref().map(|trace| trace.as_view()))
+        .collect::<Vec<_>>();
+
+    check_cumulative_sums::<_, _, _, TestBus>(&chips, &preprocessed, &main, &permutation);
+}
+
+#[test]
+fn test_final_image() {
+    let (memory_trace, final_image) = generate_memory_trace::<Val>(&initial_image(), &accesses());
+    assert_eq!(
+        final_image,
+        BTreeMap::from([(0, 9), (1, 1), (3, 2), (4, 11)])
+    );
+    assert_eq!(
+        memory_trace.height(),
+        initial_image().len() + accesses().len()
+    );
+}
+
+#[test]
+#[should_panic(expected = "Read of address 1 at timestamp 2 should return 7")]
+fn test_wrong_read_in_log_rejected() {
+    let mut accesses = accesses();
+    accesses[1].value = 8;
+    generate_memory_trace::<Val>(&initial_image(), &accesses);
+}
+
+#[test]
+#[should_panic(expected = "Accesses to address 0 should have distinct timestamps of at least 1")]
+fn test_repeated_timestamp_rejected() {
+    let mut accesses = accesses();
+    accesses[2].timestamp = 1;
+    generate_memory_trace::<Val>(&initial_image(), &accesses);
+}
+
+#[test]
+#[should_panic(expected = "Accesses to address 4 should have distinct timestamps of at least 1")]
+fn test_zero_timestamp_rejected() {
+    let mut accesses = accesses();
+    accesses.push(MemoryAccess {
+        addr: 4,
+        value: 11,
+        timestamp: 0,
+        is_write: false,
+    });
+    generate_memory_trace::<Val>(&initial_image(), &accesses);
+}
+
+#[test]
+fn test_memory_buses_balance() {
+    let (machine, traces) = machine_and_traces(None);
+    check_buses(&machine, &traces);
+}
+
+#[test]
+#[should_panic(expected = "Memory bus cumulative sum is not zero")]
+fn test_tampered_read_unbalances_buses() {
+    let (machine, traces) = machine_and_traces(Some(8));
+    check_buses(&machine, &traces);
+}
+
+fn prove_and_verify(
+    tampered_read: Option<u32>,
+    options: &ProverOptions,
+) -> Result<(), VerificationError> {
+    let (machine, traces) = machine_and_traces(tampered_read);
+    let (config, challenger) = default_config();
+    let (pk, vk) = machine.setup(&config);
+    let proof = machine.prove(&config, &mut challenger.clone(), &pk, traces, &[], options);
+    machine.verify(&config, &mut challenger.clone(), &vk, &proof, &[])
+}
+
+#[test]
+fn test_memory_prove_and_verify() {
+    prove_and_verify(None, &ProverOptions::default()).unwrap();
+}
+
+#[test]
+fn test_tampered_read_rejected() {
+    // The prover's own check of the cumulative sums is skipped, so that the proof reaches the
+    // verifier.
+    let options = ProverOptions {
+        check_constraints: false,
+        ..Default::default()
+    };
+    let result = prove_and_verify(Some(8), &options);
+    assert!(matches!(
+        result,
+        Err(VerificationError::NonZeroCumulativeSum)
+    ));
+}
@@ -0,0 +1,259 @@
+extern crate alloc;
+
+mod common;
+
+use core::fmt::{self, Display, Formatter};
+use std::collections::BTreeMap;
+
+use p3_air::{Air, AirBuilder, BaseAir, VirtualPairCol};
+use p3_air_util::util::TraceEntry;
+use p3_air_util::{PeriodicAir, TraceBuilder};
+use p3_chips::{range_check, IsZeroAir, IsZeroCols, RangeCheckChip};
+use p3_derive::EnumDispatch;
+use p3_field::{AbstractField, Field};
+use p3_interaction::{BaseInteractionAir, Interaction, InteractionAir, InteractionAirBuilder, Rap};
+use p3_machine::chip::{Chip, ChipId};
+use p3_machine::lookup::generate_multiplicities;
+use p3_machine::machine::Machine;
+use p3_machine::mutation::{find_surviving_mutations, MutationOptions, MutationStrategy};
+use p3_matrix::dense::RowMajorMatrix;
+
+use common::{Challenge, TestBus, Val};
+
+/// The `IsZeroAir` over the whole trace.
+#[derive(Clone, Debug)]
+struct IsZeroChip;
+
+impl Display for IsZeroChip {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "IsZeroChip")
+    }
+}
+
+impl<F: Field> BaseAir<F> for IsZeroChip {
+    fn width(&self) -> usize {
+        IsZeroCols::<F>::num_cols()
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for IsZeroChip {
+    fn eval(&self, builder: &mut AB) {
+        IsZeroAir.eval(builder);
+    }
+}
+
+impl<F: Field> BaseInteractionAir<F> for IsZeroChip {}
+
+impl<F: Field> InteractionAir<F> for IsZeroChip {}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for IsZeroChip {}
+
+impl<F: Field> PeriodicAir<F> for IsZeroChip {}
+
+impl Chip for IsZeroChip {}
+
+struct IsZeroMachine;
+
+impl Machine for IsZeroMachine {
+    type Chip = IsZeroChip;
+
+    type Bus = TestBus;
+
+    fn chips(&self) -> Vec<Self::Chip> {
+        vec![IsZeroChip]
+    }
+}
+
+fn is_zero_traces() -> BTreeMap<ChipId, RowMajorMatrix<Val>> {
+    let xs = [0, 1, 2, 5];
+    let trace = TraceBuilder::<Val, IsZeroCols<Val>>::from_rows(xs.len(), |i, row| {
+        row.populate(Val::from_canonical_u32(xs[i]))
+    })
+    .build();
+    BTreeMap::from([(IsZeroChip.id(), trace)])
+}
+
+/// Sends its first column to the range check table with its second column as the count.
+#[derive(Clone, Debug)]
+struct SenderChip;
+
+impl Display for SenderChip {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "SenderChip")
+    }
+}
+
+impl<F: Field> BaseAir<F> for SenderChip {
+    fn width(&self) -> usize {
+        2
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for SenderChip {
+    fn eval(&self, _builder: &mut AB) {}
+}
+
+impl<F: Field> BaseInteractionAir<F> for SenderChip {
+    fn sends_from_indices(
+        &self,
+        _preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        vec![range_check(
+            TestBus::Range as usize,
+            VirtualPairCol::single_main(main_indices[0]),
+            VirtualPairCol::single_main(main_indices[1]),
+        )]
+    }
+}
+
+impl<F: Field> InteractionAir<F> for SenderChip {
+    fn sends(&self) -> Vec<Interaction<F>> {
+        self.sends_from_indices(&[], &[0, 1])
+    }
+}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for SenderChip {}
+
+impl<F: Field> PeriodicAir<F> for SenderChip {}
+
+impl Chip for SenderChip {}
+
+#[derive(Clone, Debug, EnumDispatch)]
+enum RangeTestChip {
+    Range(RangeCheckChip),
+    Sender(SenderChip),
+}
+
+struct RangeTestMachine;
+
+impl Machine for RangeTestMachine {
+    type Chip = RangeTestChip;
+
+    type Bus = TestBus;
+
+    fn chips(&self) -> Vec<Self::Chip> {
+        vec![
+            RangeTestChip::Range(RangeCheckChip::u8(TestBus::Range as usize)),
+            RangeTestChip::Sender(SenderChip),
+        ]
+    }
+}
+
+fn range_traces() -> BTreeMap<ChipId, RowMajorMatrix<Val>> {
+    let values = [(3, 1), (255, 2), (17, 1), (3, 1)]
+        .into_iter()
+        .flat_map(|(value, count)| [value, count].map(Val::from_canonical_u32))
+        .collect();
+    let mut traces = BTreeMap::from([(
+        RangeTestChip::Sender(SenderChip).id(),
+        RowMajorMatrix::new(values, 2),
+    )]);
+    let machine = RangeTestMachine;
+    let table = &machine.chips_by_id()[0];
+    let multiplicities = generate_multiplicities(
+        table,
+        TestBus::Range as usize,
+        &machine.chips_by_id(),
+        &traces,
+    )
+    .unwrap();
+    traces.insert(table.id(), RowMajorMatrix::new_col(multiplicities));
+    traces
+}
+
+#[test]
+fn test_only_free_inverse_survives() {
+    let mutations = find_surviving_mutations::<_, Val, Challenge>(
+        &IsZeroMachine,
+        &IsZeroChip.id(),
+        &is_zero_traces(),
+        &[],
+        &MutationOptions::default(),
+    );
+    // The inverse of zero is unconstrained, so it can be set to anything but zero. Every other
+    // cell is determined by `x`, and changing `x` breaks the constraints.
+    let inverse = IsZeroCols::<usize>::col_map().inverse;
+    assert_eq!(mutations.len(), 2);
+    for mutation in mutations {
+        assert_eq!(
+            mutation.entry,
+            TraceEntry::Main {
+                row: 0,
+                col: inverse
+            }
+        );
+        assert_ne!(mutation.value, Val::zero());
+    }
+}
+
+#[test]
+fn test_random_mutations() {
+    let options = MutationOptions {
+        strategy: MutationStrategy::Random { num_mutations: 64 },
+        seed: 7,
+        ..Default::default()
+    };
+    let inverse = IsZeroCols::<usize>::col_map().inverse;
+    let mutations = find_surviving_mutations::<_, Val, Challenge>(
+        &IsZeroMachine,
+        &IsZeroChip.id(),
+        &is_zero_traces(),
+        &[],
+        &options,
+    );
+    assert!(mutations.iter().all(|mutation| mutation.entry
+        == TraceEntry::Main {
+            row: 0,
+            col: inverse
+        }));
+}
+
+#[test]
+fn test_bus_kills_mutations() {
+    // The sender has no constraints, but every change to a value or count unbalances the bus.
+    let mutations = find_surviving_mutations::<_, Val, Challenge>(
+        &RangeTestMachine,
+        &RangeTestChip::Sender(SenderChip).id(),
+        &range_traces(),
+        &[],
+        &MutationOptions::default(),
+    );
+    assert!(mutations.is_empty(), "{mutations:?}");
+}
+
+#[test]
+fn test_unused_table_rows_survive() {
+    // Changing the value of a table row that is never looked up keeps the bus balanced.
+    let options = MutationOptions {
+        strategy: MutationStrategy::Random { num_mutations: 16 },
+        mutate_preprocessed: true,
+        seed: 3,
+    };
+    let mutations = find_surviving_mutations::<_, Val, Challenge>(
+        &RangeTestMachine,
+        &RangeTestChip::Range(RangeCheckChip::u8(TestBus::Range as usize)).id(),
+        &range_traces(),
+        &[],
+        &options,
+    );
+    let used_rows = [3, 17, 255];
+    assert!(mutations.iter().all(|mutation| match mutation.entry {
+        TraceEntry::Preprocessed { row, .. } => !used_rows.contains(&row),
+        _ => false,
+    }));
+}
+
+#[test]
+#[should_panic]
+fn test_invalid_witness_rejected() {
+    let mut traces = is_zero_traces();
+    traces.get_mut(&IsZeroChip.id()).unwrap().values[0] = Val::one();
+    find_surviving_mutations::<_, Val, Challenge>(
+        &IsZeroMachine,
+        &IsZeroChip.id(),
+        &traces,
+        &[],
+        &MutationOptions::default(),
+    );
+}
@@ -0,0 +1,234 @@
+extern crate alloc;
+
+mod common;
+
+use core::fmt::{self, Display, Formatter};
+use std::collections::BTreeMap;
+
+use p3_air::{Air, AirBuilder, BaseAir, VirtualPairCol};
+use p3_air_util::PeriodicAir;
+use p3_chips::{range_check, RangeCheckChip};
+use p3_derive::EnumDispatch;
+use p3_field::{AbstractField, Field};
+use p3_interaction::{BaseInteractionAir, Interaction, InteractionAir, InteractionAirBuilder, Rap};
+use p3_machine::chip::{Chip, ChipId};
+use p3_machine::error::LookupError;
+use p3_machine::lookup::generate_multiplicities;
+use p3_machine::machine::{Instance, Machine};
+use p3_machine::options::ProverOptions;
+use p3_matrix::dense::RowMajorMatrix;
+
+use common::{default_config, TestBus, Val};
+
+/// Sends its first column to the range check table with its second column as the count.
+#[derive(Clone, Debug)]
+struct SenderChip;
+
+impl Display for SenderChip {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "SenderChip")
+    }
+}
+
+impl<F: Field> BaseAir<F> for SenderChip {
+    fn width(&self) -> usize {
+        2
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for SenderChip {
+    fn eval(&self, _builder: &mut AB) {}
+}
+
+impl<F: Field> BaseInteractionAir<F> for SenderChip {
+    fn sends_from_indices(
+        &self,
+        _preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        vec![range_check(
+            TestBus::Range as usize,
+            VirtualPairCol::single_main(main_indices[0]),
+            VirtualPairCol::single_main(main_indices[1]),
+        )]
+    }
+}
+
+impl<F: Field> InteractionAir<F> for SenderChip {
+    fn sends(&self) -> Vec<Interaction<F>> {
+        self.sends_from_indices(&[], &[0, 1])
+    }
+}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for SenderChip {}
+
+impl<F: Field> PeriodicAir<F> for SenderChip {}
+
+impl Chip for SenderChip {}
+
+#[derive(Clone, Debug, EnumDispatch)]
+enum RangeTestChip {
+    Range(RangeCheckChip),
+    Sender(SenderChip),
+}
+
+struct RangeTestMachine {
+    table: RangeCheckChip,
+}
+
+impl Machine for RangeTestMachine {
+    type Chip = RangeTestChip;
+
+    type Bus = TestBus;
+
+    fn chips(&self) -> Vec<Self::Chip> {
+        vec![
+            RangeTestChip::Range(self.table.clone()),
+            RangeTestChip::Sender(SenderChip),
+        ]
+    }
+}
+
+fn sender_trace(rows: &[(u32, u32)]) -> BTreeMap<ChipId, RowMajorMatrix<Val>> {
+    let values = rows
+        .iter()
+        .flat_map(|&(value, count)| [value, count].map(Val::from_canonical_u32))
+        .collect();
+    let sender = RangeTestChip::Sender(SenderChip);
+    BTreeMap::from([(sender.id(), RowMajorMatrix::new(values, 2))])
+}
+
+/// Adds the table's multiplicities to `traces`.
+fn with_multiplicities(
+    machine: &RangeTestMachine,
+    mut traces: BTreeMap<ChipId, RowMajorMatrix<Val>>,
+) -> BTreeMap<ChipId, RowMajorMatrix<Val>> {
+    let table = RangeTestChip::Range(machine.table.clone());
+    let multiplicities = generate_multiplicities(
+        &table,
+        TestBus::Range as usize,
+        &machine.chips_by_id(),
+        &traces,
+    )
+    .unwrap();
+    traces.insert(table.id(), RowMajorMatrix::new_col(multiplicities));
+    traces
+}
+
+/// Adds the table's multiplicities to `traces`, then proves and verifies them.
+fn prove_and_verify(machine: &RangeTestMachine, traces: BTreeMap<ChipId, RowMajorMatrix<Val>>) {
+    let traces = with_multiplicities(machine, traces);
+
+    let (config, challenger) = default_config();
+    let (pk, vk) = machine.setup(&config);
+    let proof = machine.prove(
+        &config,
+        &mut challenger.clone(),
+        &pk,
+        traces,
+        &[],
+        &ProverOptions::default(),
+    );
+    machine
+        .verify(&config, &mut challenger.clone(), &vk, &proof, &[])
+        .unwrap();
+}
+
+#[test]
+fn test_u8_range_check() {
+    let machine = RangeTestMachine {
+        table: RangeCheckChip::u8(TestBus::Range as usize),
+    };
+    let traces = sender_trace(&[(0, 1), (255, 2), (17, 1), (255, 1)]);
+    let table = RangeTestChip::Range(machine.table.clone());
+    let multiplicities = generate_multiplicities(
+        &table,
+        TestBus::Range as usize,
+        &machine.chips_by_id(),
+        &traces,
+    )
+    .unwrap();
+    assert_eq!(multiplicities.len(), 256);
+    assert_eq!(multiplicities[255], Val::from_canonical_u32(3));
+
+    prove_and_verify(&machine, traces);
+}
+
+#[test]
+fn test_u16_range_check() {
+    let machine = RangeTestMachine {
+        table: RangeCheckChip::u16(TestBus::Range as usize),
+    };
+    let traces = sender_trace(&[(256, 1), (65535, 1), (256, 4), (0, 2)]);
+    let table = RangeTestChip::Range(machine.table.clone());
+    let multiplicities = generate_multiplicities(
+        &table,
+        TestBus::Range as usize,
+        &machine.chips_by_id(),
+        &traces,
+    )
+    .unwrap();
+    assert_eq!(multiplicities.len(), 1 << 16);
+    assert_eq!(multiplicities[256], Val::from_canonical_u32(5));
+    assert_eq!(multiplicities[65535], Val::one());
+
+    prove_and_verify(&machine, traces);
+}
+
+#[test]
+fn test_u8_range_check_batch() {
+    // Every instance reads the same committed table
+    let machine = RangeTestMachine {
+        table: RangeCheckChip::u8(TestBus::Range as usize),
+    };
+    let instances = [
+        vec![(0, 1), (255, 2)],
+        vec![(17, 1), (3, 1), (3, 2), (200, 1)],
+        vec![(128, 4)],
+    ]
+    .iter()
+    .map(|rows| Instance {
+        main_traces: with_multiplicities(&machine, sender_trace(rows)),
+        public_values: vec![],
+    })
+    .collect::<Vec<_>>();
+    let num_instances = instances.len();
+
+    let (config, challenger) = default_config();
+    let (pk, vk) = machine.setup(&config);
+    let proof = machine.prove_batch(
+        &config,
+        &mut challenger.clone(),
+        &pk,
+        instances,
+        &ProverOptions::default(),
+    );
+    machine
+        .verify_batch(
+            &config,
+            &mut challenger.clone(),
+            &vk,
+            &proof,
+            &vec![vec![]; num_instances],
+        )
+        .unwrap();
+}
+
+#[test]
+fn test_value_out_of_range() {
+    let machine = RangeTestMachine {
+        table: RangeCheckChip::u8(TestBus::Range as usize),
+    };
+    let traces = sender_trace(&[(3, 1), (256, 1)]);
+    let table = RangeTestChip::Range(machine.table.clone());
+    let result = generate_multiplicities(
+        &table,
+        TestBus::Range as usize,
+        &machine.chips_by_id(),
+        &traces,
+    );
+    assert!(matches!(
+        result,
+        Err(LookupError::MessageNotInTable { row: 1, .. })
+    ));
+}
@@ -0,0 +1,325 @@
+extern crate alloc;
+
+mod common;
+
+use core::fmt::{self, Display, Formatter};
+use std::collections::BTreeMap;
+use std::fs;
+
+use p3_air::{Air, AirBuilder, BaseAir, VirtualPairCol};
+use p3_air_util::PeriodicAir;
+use p3_chips::{range_check, RangeCheckChip};
+use p3_derive::EnumDispatch;
+use p3_field::{AbstractExtensionField, AbstractField, Field};
+use p3_interaction::{BaseInteractionAir, Interaction, InteractionAir, InteractionAirBuilder, Rap};
+use p3_machine::chip::{Chip, ChipId};
+use p3_machine::error::SnapshotError;
+use p3_machine::lookup::generate_multiplicities;
+use p3_machine::machine::Machine;
+use p3_machine::options::{ProverOptions, TraceDump, TraceFormat};
+use p3_machine::snapshot::{constraints_hash, ChipTraceSnapshot, TraceSnapshot};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+
+use common::{Challenge, TestBus, Val};
+
+/// Sends its first column to the range check table with its second column as the count, which
+/// is constrained to be boolean.
+#[derive(Clone, Debug)]
+struct SenderChip;
+
+impl Display for SenderChip {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "SenderChip")
+    }
+}
+
+impl<F: Field> BaseAir<F> for SenderChip {
+    fn width(&self) -> usize {
+        2
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for SenderChip {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        builder.assert_bool(local[1]);
+    }
+}
+
+impl<F: Field> BaseInteractionAir<F> for SenderChip {
+    fn sends_from_indices(
+        &self,
+        _preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        vec![range_check(
+            TestBus::Range as usize,
+            VirtualPairCol::single_main(main_indices[0]),
+            VirtualPairCol::single_main(main_indices[1]),
+        )]
+    }
+}
+
+impl<F: Field> InteractionAir<F> for SenderChip {
+    fn sends(&self) -> Vec<Interaction<F>> {
+        self.sends_from_indices(&[], &[0, 1])
+    }
+}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for SenderChip {}
+
+impl<F: Field> PeriodicAir<F> for SenderChip {}
+
+impl Chip for SenderChip {}
+
+#[derive(Clone, Debug, EnumDispatch)]
+enum SnapshotTestChip {
+    Range(RangeCheckChip),
+    Sender(SenderChip),
+}
+
+struct SnapshotTestMachine {
+    table: RangeCheckChip,
+}
+
+impl Machine for SnapshotTestMachine {
+    type Chip = SnapshotTestChip;
+
+    type Bus = TestBus;
+
+    fn chips(&self) -> Vec<Self::Chip> {
+        vec![
+            SnapshotTestChip::Range(self.table.clone()),
+            SnapshotTestChip::Sender(SenderChip),
+        ]
+    }
+}
+
+fn machine() -> SnapshotTestMachine {
+    SnapshotTestMachine {
+        table: RangeCheckChip::u8(TestBus::Range as usize),
+    }
+}
+
+fn traces(
+    machine: &SnapshotTestMachine,
+    rows: [(u32, u32); 4],
+) -> BTreeMap<ChipId, RowMajorMatrix<Val>> {
+    let values = rows
+        .into_iter()
+        .flat_map(|(value, count)| [value, count].map(Val::from_canonical_u32))
+        .collect();
+    let mut traces = BTreeMap::from([(SenderChip.id(), RowMajorMatrix::new(values, 2))]);
+
+    let table = SnapshotTestChip::Range(machine.table.clone());
+    let multiplicities = generate_multiplicities(
+        &table,
+        TestBus::Range as usize,
+        &machine.chips_by_id(),
+        &traces,
+    )
+    .unwrap();
+    traces.insert(table.id(), RowMajorMatrix::new_col(multiplicities));
+    traces
+}
+
+/// A snapshot of valid traces, taken without proving them.
+fn snapshot(machine: &SnapshotTestMachine) -> TraceSnapshot<Val, Challenge> {
+    let mut traces = traces(machine, [(5, 1), (7, 1), (9, 0), (255, 1)]);
+    let chip_traces = machine
+        .chips()
+        .into_iter()
+        .map(|chip| ChipTraceSnapshot {
+            chip: chip.id(),
+            instance: 0,
+            preprocessed: BaseAir::<Val>::preprocessed_trace(&chip),
+            main: traces.remove(&chip.id()),
+        })
+        .collect();
+    let challenge = |coefficients: [u32; 4]| {
+        <Challenge as AbstractExtensionField<Val>>::from_base_slice(
+            &coefficients.map(Val::from_canonical_u32),
+        )
+    };
+    TraceSnapshot {
+        constraints_hash: constraints_hash::<_, Val>(machine, &[1]),
+        chip_traces,
+        public_values: vec![vec![Val::from_canonical_u32(3)]],
+        perm_challenges: [challenge([1, 2, 3, 4]), challenge([5, 6, 7, 8])],
+    }
+}
+
+/// Proves `traces` with the constraints checked and the traces dumped as `dump` requests,
+/// returning whether proving panicked.
+fn prove_with_dump(
+    machine: &SnapshotTestMachine,
+    traces: BTreeMap<ChipId, RowMajorMatrix<Val>>,
+    dump: TraceDump,
+) -> bool {
+    use std::panic::{self, AssertUnwindSafe};
+
+    use common::default_config;
+
+    let (config, challenger) = default_config();
+    let (pk, _) = machine.setup(&config);
+    let options = ProverOptions {
+        check_constraints: true,
+        dump: Some(dump),
+        ..Default::default()
+    };
+    panic::catch_unwind(AssertUnwindSafe(|| {
+        machine.prove(
+            &config,
+            &mut challenger.clone(),
+            &pk,
+            traces,
+            &[Val::from_canonical_u32(3)],
+            &options,
+        )
+    }))
+    .is_err()
+}
+
+fn temp_path(name: &str) -> String {
+    let path = std::env::temp_dir().join(format!("{name}-{}.snapshot", std::process::id()));
+    path.to_str().unwrap().into()
+}
+
+#[test]
+fn test_snapshot_round_trip() {
+    let machine = machine();
+    let snapshot = snapshot(&machine);
+    let path = temp_path("round-trip");
+    snapshot.write_to_file(&path).unwrap();
+    let read = TraceSnapshot::<Val, Challenge>::read_from_file(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(read.constraints_hash, snapshot.constraints_hash);
+    assert_eq!(read.public_values, snapshot.public_values);
+    assert_eq!(read.perm_challenges, snapshot.perm_challenges);
+    assert_eq!(read.chip_traces.len(), snapshot.chip_traces.len());
+    for (read, chip_trace) in read.chip_traces.iter().zip(&snapshot.chip_traces) {
+        assert_eq!(read.chip, chip_trace.chip);
+        assert_eq!(read.instance, chip_trace.instance);
+        for (read, trace) in [
+            (&read.preprocessed, &chip_trace.preprocessed),
+            (&read.main, &chip_trace.main),
+        ] {
+            assert_eq!(
+                read.as_ref().map(|trace| (trace.width, &trace.values)),
+                trace.as_ref().map(|trace| (trace.width, &trace.values))
+            );
+        }
+    }
+
+    read.check_constraints(&machine).unwrap();
+}
+
+#[test]
+fn test_changed_constraints_rejected() {
+    let machine = machine();
+    let mut snapshot = snapshot(&machine);
+    snapshot.constraints_hash ^= 1;
+    assert!(matches!(
+        snapshot.check_constraints(&machine),
+        Err(SnapshotError::ConstraintsMismatch)
+    ));
+}
+
+#[test]
+fn test_malformed_snapshot_rejected() {
+    let path = temp_path("malformed");
+    fs::write(&path, "p3-trace-snapshot 1\nconstraints xyz\n").unwrap();
+    let result = TraceSnapshot::<Val, Challenge>::read_from_file(&path);
+    fs::remove_file(&path).unwrap();
+    assert!(matches!(result, Err(SnapshotError::Malformed { line: 2 })));
+}
+
+#[test]
+fn test_prove_dumps_chosen_chips() {
+    let path = temp_path("chosen-chips");
+    let machine = machine();
+    let traces = traces(&machine, [(5, 1), (7, 1), (9, 0), (255, 1)]);
+    let sender_main = traces[&SenderChip.id()].values.clone();
+    let dump = TraceDump {
+        chips: Some(vec![SenderChip.id()]),
+        ..TraceDump::new(path.clone(), TraceFormat::Snapshot)
+    };
+    assert!(!prove_with_dump(&machine, traces, dump));
+
+    let snapshot = TraceSnapshot::<Val, Challenge>::read_from_file(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(snapshot.chip_traces.len(), 1);
+    assert_eq!(snapshot.chip_traces[0].chip, SenderChip.id());
+    assert_eq!(
+        snapshot.chip_traces[0].main.as_ref().unwrap().values,
+        sender_main
+    );
+    assert_eq!(
+        snapshot.public_values,
+        vec![vec![Val::from_canonical_u32(3)]]
+    );
+    // The constraints are replayed without the range table, whose bus does not balance alone
+    let entries = snapshot.track_constraints(&machine).unwrap();
+    assert!(entries[0].failing.is_empty());
+}
+
+#[test]
+fn test_passing_prove_dumps_nothing_on_failure_only() {
+    let path = temp_path("passing-prove");
+    let machine = machine();
+    let traces = traces(&machine, [(5, 1), (7, 1), (9, 0), (255, 1)]);
+    let dump = TraceDump {
+        only_on_failure: true,
+        ..TraceDump::new(path.clone(), TraceFormat::Snapshot)
+    };
+    assert!(!prove_with_dump(&machine, traces, dump));
+    assert!(!std::path::Path::new(&path).exists());
+}
+
+#[test]
+fn test_failing_prove_writes_snapshot() {
+    use std::panic::{self, AssertUnwindSafe};
+
+    use p3_air_util::util::TraceEntry;
+
+    let path = temp_path("failing-prove");
+    let machine = machine();
+    let traces = traces(&machine, [(5, 1), (7, 2), (9, 0), (255, 1)]);
+    let sender_main = traces[&SenderChip.id()].values.clone();
+    let dump = TraceDump {
+        only_on_failure: true,
+        ..TraceDump::new(path.clone(), TraceFormat::Snapshot)
+    };
+    assert!(prove_with_dump(&machine, traces, dump));
+
+    let snapshot = TraceSnapshot::<Val, Challenge>::read_from_file(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    let ids = snapshot
+        .chip_traces
+        .iter()
+        .map(|chip_trace| chip_trace.chip.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(ids, ["RangeCheck8Chip", "SenderChip"]);
+    assert_eq!(
+        snapshot.public_values,
+        vec![vec![Val::from_canonical_u32(3)]]
+    );
+    assert_eq!(
+        snapshot.chip_traces[1].main.as_ref().unwrap().values,
+        sender_main
+    );
+
+    // The failure is replayed from the snapshot
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        snapshot.check_constraints(&machine).unwrap()
+    }));
+    assert!(result.is_err());
+    let entries = snapshot.track_constraints(&machine).unwrap();
+    assert!(entries[0].failing.is_empty());
+    assert!(entries[1]
+        .failing
+        .contains(&TraceEntry::Main { row: 1, col: 1 }));
+}
@@ -0,0 +1,190 @@
+extern crate alloc;
+
+mod common;
+
+use core::fmt::{self, Display, Formatter};
+use std::collections::BTreeMap;
+
+use p3_air::{Air, AirBuilder, BaseAir, VirtualPairCol};
+use p3_air_util::PeriodicAir;
+use p3_chips::{range_check, RangeCheckChip};
+use p3_derive::EnumDispatch;
+use p3_field::{AbstractField, Field};
+use p3_interaction::{BaseInteractionAir, Interaction, InteractionAir, InteractionAirBuilder, Rap};
+use p3_machine::chip::{Chip, ChipId};
+use p3_machine::lookup::generate_multiplicities;
+use p3_machine::machine::Machine;
+use p3_machine::options::ProverOptions;
+use p3_machine::proof::MachineProof;
+use p3_machine::tamper::{check_tampered_proofs, tamperings};
+use p3_matrix::dense::RowMajorMatrix;
+
+use common::{default_config, MyConfig, TestBus, Val};
+
+/// Sends its first column to the range check table with its second column as the count.
+#[derive(Clone, Debug)]
+struct SenderChip;
+
+impl Display for SenderChip {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "SenderChip")
+    }
+}
+
+impl<F: Field> BaseAir<F> for SenderChip {
+    fn width(&self) -> usize {
+        2
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for SenderChip {
+    fn eval(&self, _builder: &mut AB) {}
+}
+
+impl<F: Field> BaseInteractionAir<F> for SenderChip {
+    fn sends_from_indices(
+        &self,
+        _preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        vec![range_check(
+            TestBus::Range as usize,
+            VirtualPairCol::single_main(main_indices[0]),
+            VirtualPairCol::single_main(main_indices[1]),
+        )]
+    }
+}
+
+impl<F: Field> InteractionAir<F> for SenderChip {
+    fn sends(&self) -> Vec<Interaction<F>> {
+        self.sends_from_indices(&[], &[0, 1])
+    }
+}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for SenderChip {}
+
+impl<F: Field> PeriodicAir<F> for SenderChip {}
+
+impl Chip for SenderChip {}
+
+#[derive(Clone, Debug, EnumDispatch)]
+enum RangeTestChip {
+    Range(RangeCheckChip),
+    Sender(SenderChip),
+}
+
+struct RangeTestMachine {
+    table: RangeCheckChip,
+}
+
+impl Machine for RangeTestMachine {
+    type Chip = RangeTestChip;
+
+    type Bus = TestBus;
+
+    fn chips(&self) -> Vec<Self::Chip> {
+        vec![
+            RangeTestChip::Range(self.table.clone()),
+            RangeTestChip::Sender(SenderChip),
+        ]
+    }
+}
+
+fn machine() -> RangeTestMachine {
+    RangeTestMachine {
+        table: RangeCheckChip::u8(TestBus::Range as usize),
+    }
+}
+
+fn traces(machine: &RangeTestMachine) -> BTreeMap<ChipId, RowMajorMatrix<Val>> {
+    let values = [(0, 1), (255, 2), (17, 1), (255, 1)]
+        .into_iter()
+        .flat_map(|(value, count)| [value, count].map(Val::from_canonical_u32))
+        .collect();
+    let sender = RangeTestChip::Sender(SenderChip);
+    let mut traces = BTreeMap::from([(sender.id(), RowMajorMatrix::new(values, 2))]);
+
+    let table = RangeTestChip::Range(machine.table.clone());
+    let multiplicities = generate_multiplicities(
+        &table,
+        TestBus::Range as usize,
+        &machine.chips_by_id(),
+        &traces,
+    )
+    .unwrap();
+    traces.insert(table.id(), RowMajorMatrix::new_col(multiplicities));
+    traces
+}
+
+#[test]
+fn test_tampered_proofs_rejected() {
+    let machine = machine();
+    let (config, challenger) = default_config();
+    let (pk, vk) = machine.setup(&config);
+    let proof = machine.prove(
+        &config,
+        &mut challenger.clone(),
+        &pk,
+        traces(&machine),
+        &[],
+        &ProverOptions::default(),
+    );
+    check_tampered_proofs(&machine, &config, &challenger, &vk, &proof, &[]);
+}
+
+#[test]
+fn test_tamperings_cover_every_chip() {
+    let machine = machine();
+    let (config, challenger) = default_config();
+    let (pk, _) = machine.setup(&config);
+    let proof: MachineProof<MyConfig> = machine.prove(
+        &config,
+        &mut challenger.clone(),
+        &pk,
+        traces(&machine),
+        &[],
+        &ProverOptions::default(),
+    );
+    let descriptions = tamperings(&proof)
+        .into_iter()
+        .map(|tampering| tampering.description)
+        .collect::<Vec<_>>();
+
+    for chip in machine.chips() {
+        for tampering in [
+            "flipped a local main value",
+            "flipped a local permutation value",
+            "truncated the quotient chunks",
+            "altered the cumulative sum",
+        ] {
+            let description = format!("{}[0]: {tampering}", chip.id());
+            assert!(descriptions.contains(&description), "{description}");
+        }
+    }
+    let (range, sender) = ("RangeCheck8Chip", "SenderChip");
+    for description in [
+        format!("{range}[0]: flipped a local preprocessed value"),
+        format!("swapped the proofs of {range} and {sender}"),
+        "dropped the permutation commitment".into(),
+    ] {
+        assert!(descriptions.contains(&description), "{description}");
+    }
+}
+
+#[test]
+#[should_panic(expected = "The untampered proof should verify")]
+fn test_invalid_proof_panics() {
+    let machine = machine();
+    let (config, challenger) = default_config();
+    let (pk, vk) = machine.setup(&config);
+    let mut proof = machine.prove(
+        &config,
+        &mut challenger.clone(),
+        &pk,
+        traces(&machine),
+        &[],
+        &ProverOptions::default(),
+    );
+    proof.chip_proofs.clear();
+    check_tampered_proofs(&machine, &config, &challenger, &vk, &proof, &[]);
+}
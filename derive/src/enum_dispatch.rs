@@ -81,6 +81,14 @@ pub fn generate_trait_impls(
             }
         }
 
+        impl<F: p3_field::Field> p3_air_util::PeriodicAir<F> for #name {
+            fn periodic_columns(&self) -> alloc::vec::Vec<alloc::vec::Vec<F>> {
+                match self {
+                    #(#name::#variant_names(chip) => <#variant_field_types as p3_air_util::PeriodicAir<F>>::periodic_columns(chip),)*
+                }
+            }
+        }
+
         #[cfg(feature = "air-logger")]
         impl p3_air_util::AirLogger for #name {
             fn preprocessed_headers(&self) -> alloc::vec::Vec<String> {
@@ -110,6 +118,33 @@ pub fn generate_trait_impls(
             }
         }
 
-        impl p3_machine::chip::Chip for #name {}
+        impl p3_machine::chip::Chip for #name {
+            fn padding_row<F: p3_field::Field>(&self) -> Option<alloc::vec::Vec<F>> {
+                match self {
+                    #(#name::#variant_names(chip) => <#variant_field_types as p3_machine::chip::Chip>::padding_row(chip),)*
+                }
+            }
+        }
+
+        impl<F: p3_field::Field, R> p3_machine::generation::TraceGenerator<F, R> for #name
+        where
+            #(#variant_field_types: p3_machine::generation::TraceGenerator<F, R>,)*
+        {
+            fn dependencies(&self) -> alloc::vec::Vec<p3_machine::chip::ChipId> {
+                match self {
+                    #(#name::#variant_names(chip) => <#variant_field_types as p3_machine::generation::TraceGenerator<F, R>>::dependencies(chip),)*
+                }
+            }
+
+            fn generate_trace(
+                &self,
+                record: &R,
+                traces: &alloc::collections::BTreeMap<p3_machine::chip::ChipId, p3_matrix::dense::RowMajorMatrix<F>>,
+            ) -> Option<p3_matrix::dense::RowMajorMatrix<F>> {
+                match self {
+                    #(#name::#variant_names(chip) => <#variant_field_types as p3_machine::generation::TraceGenerator<F, R>>::generate_trace(chip, record, traces),)*
+                }
+            }
+        }
     }
 }
@@ -164,6 +164,12 @@ pub fn columnar_derive(input: TokenStream) -> TokenStream {
             }
         }
 
+        impl #impl_generics p3_air_util::Columnar for #name #type_generics #where_clause {
+            fn num_cols() -> usize {
+                Self::num_cols()
+            }
+        }
+
         impl #impl_generics core::borrow::Borrow<#name #type_generics> for [#type_generic] #where_clause {
             fn borrow(&self) -> &#name #type_generics {
                 debug_assert_eq!(self.len(), core::mem::size_of::<#name<u8 #(, #non_first_generics_idents)*>>());
@@ -1,18 +1,24 @@
 use alloc::vec;
 use alloc::vec::Vec;
-use core::borrow::Borrow;
 
 use p3_field::{ExtensionField, Field};
 use p3_matrix::{
     dense::{RowMajorMatrix, RowMajorMatrixView},
     Matrix,
 };
+use p3_maybe_rayon::prelude::{
+    IndexedParallelIterator, IntoParallelIterator, ParallelIterator, ParallelSlice,
+    ParallelSliceMut,
+};
 
 use crate::interaction::{Interaction, InteractionType};
 use crate::util::{batch_multiplicative_inverse_allowing_zero, generate_rlc_elements, reduce_row};
 
 pub const NUM_PERM_CHALLENGES: usize = 2;
 
+const BATCH_INVERSE_CHUNK_SIZE: usize = 1 << 12;
+const PREFIX_SUM_CHUNK_SIZE: usize = 1 << 12;
+
 pub fn generate_permutation_trace<F: Field, EF: ExtensionField<F>>(
     preprocessed: &Option<RowMajorMatrixView<F>>,
     main: &Option<RowMajorMatrixView<F>>,
@@ -42,86 +48,105 @@ pub fn generate_permutation_trace<F: Field, EF: ExtensionField<F>>(
     // Note: We can optimize this by combining several reciprocal columns into one (the
     // number is subject to a target constraint degree).
     let perm_width = interactions.len() + 1;
-    let mut perm_values = Vec::with_capacity(height * perm_width);
-
-    for n in 0..height {
-        let preprocessed_row = preprocessed
-            .as_ref()
-            .map(|preprocessed| {
-                let row = preprocessed.row_slice(n);
-                let row: &[_] = (*row).borrow();
-                row.to_vec()
-            })
-            .unwrap_or_default();
-        let main_row = main
-            .as_ref()
-            .map(|main| {
-                let row = main.row_slice(n);
-                let row: &[_] = (*row).borrow();
-                row.to_vec()
-            })
-            .unwrap_or_default();
-
-        let mut row = vec![EF::zero(); perm_width];
-        for (m, (interaction, _)) in interactions.iter().enumerate() {
-            let alpha_m = alphas[interaction.argument_index];
-            row[m] = reduce_row(
-                preprocessed_row.as_slice(),
-                main_row.as_slice(),
-                &interaction.fields,
-                alpha_m,
-                betas.clone(),
-            );
-        }
-        perm_values.extend(row);
-    }
+    let mut perm_values = vec![EF::zero(); height * perm_width];
+    perm_values
+        .par_chunks_mut(perm_width)
+        .enumerate()
+        .for_each(|(n, row)| {
+            let (preprocessed_row, main_row) = rows(preprocessed, main, n);
+            for (m, (interaction, _)) in interactions.iter().enumerate() {
+                let alpha_m = alphas[interaction.argument_index];
+                row[m] = reduce_row(
+                    preprocessed_row,
+                    main_row,
+                    &interaction.fields,
+                    alpha_m,
+                    betas.clone(),
+                );
+            }
+        });
     // TODO: Switch to batch_multiplicative_inverse (not allowing zero)?
     // Zero should be vanishingly unlikely if properly randomized?
-    let perm_values = batch_multiplicative_inverse_allowing_zero(perm_values);
+    perm_values
+        .par_chunks_mut(BATCH_INVERSE_CHUNK_SIZE)
+        .for_each(|chunk| {
+            let inverses = batch_multiplicative_inverse_allowing_zero(chunk.to_vec());
+            chunk.copy_from_slice(&inverses);
+        });
     let mut perm = RowMajorMatrix::new(perm_values, perm_width);
 
     // Compute the running sum column
-    let mut phi = vec![EF::zero(); perm.height()];
-    for (n, perm_row) in perm.rows().enumerate() {
-        let preprocessed_row = preprocessed
-            .as_ref()
-            .map(|preprocessed| {
-                let row = preprocessed.row_slice(n);
-                let row: &[_] = (*row).borrow();
-                row.to_vec()
-            })
-            .unwrap_or_default();
-        let main_row = main
-            .as_ref()
-            .map(|main| {
-                let row = main.row_slice(n);
-                let row: &[_] = (*row).borrow();
-                row.to_vec()
-            })
-            .unwrap_or_default();
-        let perm_row: Vec<_> = perm_row.collect();
-
-        if n > 0 {
-            phi[n] = phi[n - 1];
-        }
-        for (m, (interaction, interaction_type)) in interactions.iter().enumerate() {
-            let mult = interaction
-                .count
-                .apply::<F, F>(preprocessed_row.as_slice(), main_row.as_slice());
-            match interaction_type {
-                InteractionType::Send => {
-                    phi[n] += perm_row[m] * mult;
-                }
-                InteractionType::Receive => {
-                    phi[n] -= perm_row[m] * mult;
+    let mut phi = perm
+        .values
+        .par_chunks(perm_width)
+        .enumerate()
+        .map(|(n, perm_row)| {
+            let (preprocessed_row, main_row) = rows(preprocessed, main, n);
+            let mut delta = EF::zero();
+            for ((interaction, interaction_type), &perm_value) in interactions.iter().zip(perm_row)
+            {
+                let mult = interaction.count.apply::<F, F>(preprocessed_row, main_row);
+                match interaction_type {
+                    InteractionType::Send => {
+                        delta += perm_value * mult;
+                    }
+                    InteractionType::Receive => {
+                        delta -= perm_value * mult;
+                    }
                 }
             }
-        }
-    }
+            delta
+        })
+        .collect::<Vec<_>>();
+    parallel_prefix_sum(&mut phi);
 
-    for (n, row) in perm.as_view_mut().rows_mut().enumerate() {
-        *row.last_mut().unwrap() = phi[n];
-    }
+    perm.values
+        .par_chunks_mut(perm_width)
+        .zip(phi.into_par_iter())
+        .for_each(|(row, phi)| *row.last_mut().unwrap() = phi);
 
     Some(perm)
 }
+
+/// Returns the preprocessed and main rows at index `n`, or empty rows for missing traces.
+fn rows<'a, F: Field>(
+    preprocessed: &'a Option<RowMajorMatrixView<'a, F>>,
+    main: &'a Option<RowMajorMatrixView<'a, F>>,
+    n: usize,
+) -> (&'a [F], &'a [F]) {
+    let row = |mat: &'a Option<RowMajorMatrixView<'a, F>>| {
+        mat.as_ref()
+            .map(|mat| &mat.values[n * mat.width..(n + 1) * mat.width])
+            .unwrap_or_default()
+    };
+    (row(preprocessed), row(main))
+}
+
+/// Replaces each value with the sum of itself and all previous values.
+fn parallel_prefix_sum<EF: Field>(values: &mut [EF]) {
+    // Sum each chunk locally, then offset each chunk by the total of the chunks before it.
+    values
+        .par_chunks_mut(PREFIX_SUM_CHUNK_SIZE)
+        .for_each(|chunk| {
+            for i in 1..chunk.len() {
+                chunk[i] = chunk[i - 1] + chunk[i];
+            }
+        });
+    let offsets = values
+        .chunks(PREFIX_SUM_CHUNK_SIZE)
+        .scan(EF::zero(), |total, chunk| {
+            let offset = *total;
+            *total += *chunk.last().unwrap();
+            Some(offset)
+        })
+        .collect::<Vec<_>>();
+    values
+        .par_chunks_mut(PREFIX_SUM_CHUNK_SIZE)
+        .zip(offsets.into_par_iter())
+        .skip(1)
+        .for_each(|(chunk, offset)| {
+            for value in chunk.iter_mut() {
+                *value += offset;
+            }
+        });
+}
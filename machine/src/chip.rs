@@ -1,10 +1,66 @@
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
 use core::fmt::{Debug, Display};
 
+use p3_field::Field;
+
 #[cfg(feature = "air-logger")]
 use p3_air_util::AirLogger;
 
+/// A stable identifier of a chip, unique within its machine.
+pub type ChipId = String;
+
+/// The ids of `chips`, in order. Chips sharing an id, such as two chips with the same name, are
+/// told apart by their position among the chips sharing it, as `{id}#0`, `{id}#1` and so on.
+pub fn unique_ids<C: Chip>(chips: &[C]) -> Vec<ChipId> {
+    let ids: Vec<ChipId> = chips.iter().map(Chip::id).collect();
+    let mut counts = BTreeMap::<&str, usize>::new();
+    for id in ids.iter() {
+        *counts.entry(id).or_default() += 1;
+    }
+    let mut seen = BTreeMap::<&str, usize>::new();
+    ids.iter()
+        .map(|id| {
+            if counts[id.as_str()] == 1 {
+                return id.clone();
+            }
+            let index = seen.entry(id).or_default();
+            *index += 1;
+            format!("{id}#{}", *index - 1)
+        })
+        .collect()
+}
+
 #[cfg(not(feature = "air-logger"))]
-pub trait Chip: Clone + Debug + Display {}
+pub trait Chip: Clone + Debug + Display {
+    /// The chip's identifier. Traces, keys and proofs are keyed by it, and chips are laid out in
+    /// the order of their ids. Defaults to the chip's name. Chips of a machine sharing an id are
+    /// told apart by `unique_ids`.
+    fn id(&self) -> ChipId {
+        self.to_string()
+    }
+
+    /// The row the chip's main trace is padded with up to a power of two height before it is
+    /// committed to. Defaults to no padding.
+    fn padding_row<F: Field>(&self) -> Option<Vec<F>> {
+        None
+    }
+}
 
 #[cfg(feature = "air-logger")]
-pub trait Chip: Clone + Debug + Display + AirLogger {}
+pub trait Chip: Clone + Debug + Display + AirLogger {
+    /// The chip's identifier. Traces, keys and proofs are keyed by it, and chips are laid out in
+    /// the order of their ids. Defaults to the chip's name. Chips of a machine sharing an id are
+    /// told apart by `unique_ids`.
+    fn id(&self) -> ChipId {
+        self.to_string()
+    }
+
+    /// The row the chip's main trace is padded with up to a power of two height before it is
+    /// committed to. Defaults to no padding.
+    fn padding_row<F: Field>(&self) -> Option<Vec<F>> {
+        None
+    }
+}
@@ -0,0 +1,154 @@
+use alloc::vec::Vec;
+
+use p3_air_util::folders::rap::{
+    DebugConstraintBuilder, ProverConstraintFolder, SymbolicAirBuilder, TrackingConstraintBuilder,
+    VerifierConstraintFolder,
+};
+use p3_air_util::PeriodicAir;
+use p3_field::{PrimeField32, TwoAdicField};
+use p3_interaction::Rap;
+use p3_uni_stark::{Domain, StarkGenericConfig, Val};
+use tracing::instrument;
+
+use crate::{
+    error::VerificationError,
+    machine::{Instance, Machine},
+    options::ProverOptions,
+    proof::{PcsProverData, ProvingKey, SegmentProof, VerifyingKey},
+};
+
+/// The main traces and public values of one segment of an execution.
+pub type Segment<F> = Instance<F>;
+
+/// A machine whose executions are proven as a chain of segments, each with its own proof.
+///
+/// The public values of a segment start with a digest of the state the segment starts from,
+/// followed by a digest of the state it ends in. The machine's constraints must bind both digests
+/// to the segment's traces.
+pub trait ContinuationMachine: Machine {
+    /// The number of public values in a state digest.
+    fn state_digest_len(&self) -> usize;
+
+    /// The digest of the state a segment starts from.
+    fn initial_state<'a, F>(&self, public_values: &'a [F]) -> &'a [F] {
+        &public_values[..self.state_digest_len()]
+    }
+
+    /// The digest of the state a segment ends in.
+    fn final_state<'a, F>(&self, public_values: &'a [F]) -> &'a [F] {
+        &public_values[self.state_digest_len()..2 * self.state_digest_len()]
+    }
+
+    /// Proves each segment in turn, each from a fresh copy of `challenger`. Segments are consumed
+    /// one at a time, so only one segment's traces need to be held in memory.
+    #[instrument(skip_all)]
+    fn prove_segments<'a, SC, I>(
+        &self,
+        config: &'a SC,
+        challenger: &SC::Challenger,
+        pk: &'a ProvingKey<SC>,
+        segments: I,
+        options: &ProverOptions,
+    ) -> Vec<SegmentProof<SC>>
+    where
+        SC: StarkGenericConfig,
+        SC::Challenger: Clone,
+        I: IntoIterator<Item = Segment<Val<SC>>>,
+        Self::Chip: for<'b> Rap<ProverConstraintFolder<'b, SC>>
+            + for<'b> Rap<VerifierConstraintFolder<'b, SC>>
+            + for<'b> Rap<SymbolicAirBuilder<Val<SC>>>
+            + for<'b> Rap<DebugConstraintBuilder<'b, Val<SC>, SC::Challenge>>
+            + for<'b> Rap<TrackingConstraintBuilder<'b, Val<SC>, SC::Challenge>>
+            + PeriodicAir<Val<SC>>
+            + Send
+            + Sync,
+        SC::Pcs: Sync,
+        PcsProverData<SC>: Sync,
+        Domain<SC>: Send + Sync,
+        Val<SC>: PrimeField32,
+    {
+        let mut proofs: Vec<SegmentProof<SC>> = Vec::new();
+        for (i, segment) in segments.into_iter().enumerate() {
+            assert!(
+                segment.public_values.len() >= 2 * self.state_digest_len(),
+                "Segment {i} is missing its state digests"
+            );
+            if let Some(previous) = proofs.last() {
+                assert_eq!(
+                    self.final_state(&previous.public_values),
+                    self.initial_state(&segment.public_values),
+                    "Segment {i} does not start from the final state of the previous segment"
+                );
+            }
+
+            let proof = tracing::info_span!("prove segment", index = i).in_scope(|| {
+                self.prove(
+                    config,
+                    &mut challenger.clone(),
+                    pk,
+                    segment.main_traces,
+                    &segment.public_values,
+                    options,
+                )
+            });
+            proofs.push(SegmentProof {
+                proof,
+                public_values: segment.public_values,
+            });
+        }
+        proofs
+    }
+
+    /// Verifies every segment proof, and that the segments chain from `initial_state` to
+    /// `final_state`.
+    #[instrument(skip_all)]
+    fn verify_segments<'a, SC>(
+        &self,
+        config: &'a SC,
+        challenger: &SC::Challenger,
+        vk: &'a VerifyingKey<SC>,
+        proofs: &[SegmentProof<SC>],
+        initial_state: &[Val<SC>],
+        final_state: &[Val<SC>],
+    ) -> Result<(), VerificationError>
+    where
+        SC: StarkGenericConfig,
+        SC::Challenger: Clone,
+        Val<SC>: PrimeField32 + TwoAdicField,
+        Self::Chip: for<'b> Rap<VerifierConstraintFolder<'b, SC>>
+            + for<'b> Rap<SymbolicAirBuilder<Val<SC>>>
+            + PeriodicAir<Val<SC>>,
+    {
+        if proofs.is_empty()
+            || proofs
+                .iter()
+                .any(|proof| proof.public_values.len() < 2 * self.state_digest_len())
+        {
+            return Err(VerificationError::InvalidProofShape);
+        }
+
+        let first = &proofs[0].public_values;
+        let last = &proofs[proofs.len() - 1].public_values;
+        if self.initial_state(first) != initial_state || self.final_state(last) != final_state {
+            return Err(VerificationError::InvalidSegmentBoundary);
+        }
+        for pair in proofs.windows(2) {
+            if self.final_state(&pair[0].public_values)
+                != self.initial_state(&pair[1].public_values)
+            {
+                return Err(VerificationError::InvalidSegmentBoundary);
+            }
+        }
+
+        for proof in proofs {
+            self.verify(
+                config,
+                &mut challenger.clone(),
+                vk,
+                &proof.proof,
+                &proof.public_values,
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,89 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use std::error::Error;
+use std::panic::{self, AssertUnwindSafe};
+
+use p3_air_util::folders::rap::{
+    DebugConstraintBuilder, SymbolicAirBuilder, TrackingConstraintBuilder,
+};
+use p3_air_util::PeriodicAir;
+use p3_field::PrimeField32;
+use p3_interaction::{Rap, NUM_PERM_CHALLENGES};
+use p3_uni_stark::{StarkGenericConfig, Val};
+
+use crate::machine::Machine;
+use crate::options::{TraceDump, TraceFormat};
+use crate::snapshot::snapshot_trace;
+use crate::trace::{MachineTrace, MachineTraceChecker};
+
+/// Writes the traces of `trace` as `dump` requests.
+pub(crate) fn dump_traces<M, SC>(
+    machine: &M,
+    trace: &MachineTrace<SC, M::Chip>,
+    public_values: &[Vec<Val<SC>>],
+    perm_challenges: [SC::Challenge; NUM_PERM_CHALLENGES],
+    dump: &TraceDump,
+) -> Result<(), Box<dyn Error>>
+where
+    M: Machine + ?Sized,
+    SC: StarkGenericConfig,
+    Val<SC>: PrimeField32,
+    M::Chip: for<'a> Rap<DebugConstraintBuilder<'a, Val<SC>, SC::Challenge>>
+        + for<'a> Rap<TrackingConstraintBuilder<'a, Val<SC>, SC::Challenge>>
+        + Rap<SymbolicAirBuilder<Val<SC>>>
+        + PeriodicAir<Val<SC>>,
+{
+    match dump.format {
+        #[cfg(feature = "air-logger")]
+        TraceFormat::Xlsx => {
+            use crate::trace::MachineTraceDebugger;
+
+            trace.write_traces_to_file(
+                &dump.path,
+                perm_challenges,
+                public_values,
+                dump.chips.as_deref(),
+            )
+        }
+        TraceFormat::Snapshot => {
+            let mut snapshot = snapshot_trace(machine, trace, public_values, perm_challenges);
+            if let Some(chips) = &dump.chips {
+                snapshot
+                    .chip_traces
+                    .retain(|chip_trace| chips.contains(&chip_trace.chip));
+            }
+            snapshot.write_to_file(&dump.path)?;
+            Ok(())
+        }
+    }
+}
+
+/// Checks the constraints of `trace` like `MachineTraceChecker::check_constraints`, dumping the
+/// traces as `dump` requests before panicking when they fail.
+pub(crate) fn check_constraints_or_dump<M, SC>(
+    machine: &M,
+    trace: &MachineTrace<SC, M::Chip>,
+    public_values: &[Vec<Val<SC>>],
+    perm_challenges: [SC::Challenge; NUM_PERM_CHALLENGES],
+    dump: &TraceDump,
+) where
+    M: Machine + ?Sized,
+    SC: StarkGenericConfig,
+    Val<SC>: PrimeField32,
+    M::Chip: for<'a> Rap<DebugConstraintBuilder<'a, Val<SC>, SC::Challenge>>
+        + for<'a> Rap<TrackingConstraintBuilder<'a, Val<SC>, SC::Challenge>>
+        + Rap<SymbolicAirBuilder<Val<SC>>>
+        + PeriodicAir<Val<SC>>,
+{
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        trace.check_constraints::<M::Bus>(perm_challenges, public_values)
+    }));
+    if let Err(payload) = result {
+        let path = &dump.path;
+        match dump_traces(machine, trace, public_values, perm_challenges, dump) {
+            Ok(()) => tracing::error!("Dumped the failing traces to {path}"),
+            Err(err) => tracing::error!("Failed to dump the failing traces to {path}: {err}"),
+        }
+        panic::resume_unwind(payload);
+    }
+}
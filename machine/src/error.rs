@@ -1,10 +1,70 @@
-#[derive(Debug)]
+use alloc::vec::Vec;
+
+use crate::chip::ChipId;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum VerificationError {
     InvalidProofShape,
+    /// The verifying key or proof does not have an entry for exactly the machine's chips.
+    InvalidChipIds,
     /// An error occurred while verifying the claimed openings.
     InvalidOpeningArgument,
     /// Out-of-domain evaluation mismatch, i.e. `constraints(zeta)` did not match
     /// `quotient(zeta) Z_H(zeta)`.
     OodEvaluationMismatch,
     NonZeroCumulativeSum,
+    /// A segment does not start from the state the previous segment ended in, or the segments do
+    /// not run from the claimed initial state to the claimed final state.
+    InvalidSegmentBoundary,
+}
+
+/// An error computing the multiplicities of a lookup table.
+#[derive(Debug)]
+pub enum LookupError<F> {
+    /// The chip has no preprocessed trace, does not receive on the bus, or its receive reads
+    /// columns outside its preprocessed trace.
+    InvalidTable(ChipId),
+    /// A chip sends a message on the bus that is not a row of the table.
+    MessageNotInTable {
+        chip: ChipId,
+        row: usize,
+        message: Vec<F>,
+    },
+}
+
+/// An error reading a trace snapshot, or replaying it against a machine.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(std::io::Error),
+    /// The line, counted from one, does not follow the snapshot format.
+    Malformed {
+        line: usize,
+    },
+    /// The machine's constraints differ from those the snapshot was taken with.
+    ConstraintsMismatch,
+    /// The snapshot has a trace of a chip the machine does not have.
+    UnknownChip(ChipId),
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for SnapshotError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+#[cfg(feature = "std")]
+impl core::fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{err}"),
+            Self::Malformed { line } => write!(f, "malformed snapshot at line {line}"),
+            Self::ConstraintsMismatch => write!(f, "the machine's constraints have changed"),
+            Self::UnknownChip(id) => write!(f, "the machine has no chip {id}"),
+        }
+    }
 }
+
+#[cfg(feature = "std")]
+impl std::error::Error for SnapshotError {}
@@ -0,0 +1,76 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec::Vec;
+
+use p3_field::Field;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_maybe_rayon::prelude::{IntoParallelIterator, ParallelIterator};
+
+use crate::chip::{Chip, ChipId};
+
+/// A chip that generates its main trace from a shared execution record.
+pub trait TraceGenerator<F: Field, R> {
+    /// The ids of the chips whose traces must be generated before this chip's.
+    fn dependencies(&self) -> Vec<ChipId> {
+        Vec::new()
+    }
+
+    /// Generates the chip's main trace, or `None` if it has none. `traces` holds the traces
+    /// generated so far, which include those of every dependency.
+    fn generate_trace(
+        &self,
+        record: &R,
+        traces: &BTreeMap<ChipId, RowMajorMatrix<F>>,
+    ) -> Option<RowMajorMatrix<F>>;
+}
+
+/// Generates the main trace of every chip from `record`, keyed by chip id. Chips are generated
+/// in layers: each layer holds the chips whose dependencies are all in earlier layers, and the
+/// chips of a layer are generated in parallel.
+pub fn generate_traces<F, R, C>(
+    chips: &BTreeMap<ChipId, C>,
+    record: &R,
+) -> BTreeMap<ChipId, RowMajorMatrix<F>>
+where
+    F: Field,
+    R: Sync,
+    C: Chip + TraceGenerator<F, R> + Sync,
+{
+    let mut pending = chips
+        .iter()
+        .map(|(id, chip)| {
+            let dependencies = chip.dependencies();
+            for dependency in dependencies.iter() {
+                assert!(
+                    chips.contains_key(dependency),
+                    "Chip {id} depends on unknown chip {dependency}"
+                );
+            }
+            (id, chip, dependencies)
+        })
+        .collect::<Vec<_>>();
+
+    let mut traces = BTreeMap::new();
+    let mut generated = BTreeSet::new();
+    while !pending.is_empty() {
+        let (ready, rest): (Vec<_>, Vec<_>) =
+            pending.into_iter().partition(|(_, _, dependencies)| {
+                dependencies
+                    .iter()
+                    .all(|dependency| generated.contains(dependency))
+            });
+        assert!(!ready.is_empty(), "Chip dependencies should not be cyclic");
+
+        let layer: Vec<_> = ready
+            .into_par_iter()
+            .map(|(id, chip, _)| (id.clone(), chip.generate_trace(record, &traces)))
+            .collect();
+        for (id, trace) in layer {
+            if let Some(trace) = trace {
+                traces.insert(id.clone(), trace);
+            }
+            generated.insert(id);
+        }
+        pending = rest;
+    }
+    traces
+}
@@ -4,9 +4,29 @@
 extern crate alloc;
 
 pub mod chip;
+pub mod continuation;
+#[cfg(feature = "std")]
+mod dump;
 pub mod error;
+pub mod generation;
+pub mod lookup;
 pub mod machine;
+pub mod memory;
+pub mod minimize;
+pub mod multiplexer;
+#[cfg(feature = "std")]
+pub mod mutation;
+pub mod options;
+pub mod periodic;
 pub mod proof;
 pub mod quotient;
+pub mod selectors;
+pub mod shard;
+#[cfg(feature = "std")]
+mod silent;
+#[cfg(feature = "std")]
+pub mod snapshot;
+#[cfg(feature = "std")]
+pub mod tamper;
 pub mod trace;
 pub mod verify;
@@ -0,0 +1,104 @@
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use p3_air::{BaseAir, PairCol};
+use p3_field::PrimeField32;
+use p3_interaction::InteractionAir;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+
+use crate::chip::{Chip, ChipId};
+use crate::error::LookupError;
+
+/// Computes the multiplicity column of a lookup table chip: how many times each row of the table
+/// is looked up by the sends of `chips` on bus `argument_index`.
+///
+/// The table's rows are keyed by the fields of its receive on the bus, which may only read its
+/// preprocessed trace. The sends are evaluated on each chip's preprocessed trace and on its main
+/// trace in `main_traces`, and are weighted by their counts. Chips without a main trace are
+/// skipped.
+pub fn generate_multiplicities<F, C>(
+    table: &C,
+    argument_index: usize,
+    chips: &BTreeMap<ChipId, C>,
+    main_traces: &BTreeMap<ChipId, RowMajorMatrix<F>>,
+) -> Result<Vec<F>, LookupError<F>>
+where
+    F: PrimeField32,
+    C: Chip + BaseAir<F> + InteractionAir<F>,
+{
+    let receive = table
+        .receives()
+        .into_iter()
+        .find(|interaction| interaction.argument_index == argument_index);
+    let (Some(receive), Some(table_trace)) = (receive, table.preprocessed_trace()) else {
+        return Err(LookupError::InvalidTable(table.id()));
+    };
+    let reads_preprocessed_only = receive.fields.iter().all(|field| {
+        field
+            .column_weights
+            .iter()
+            .all(|(col, _)| matches!(col, PairCol::Preprocessed(k) if *k < table_trace.width()))
+    });
+    if !reads_preprocessed_only {
+        return Err(LookupError::InvalidTable(table.id()));
+    }
+
+    let mut table_rows = BTreeMap::new();
+    for (row, values) in table_trace.values.chunks(table_trace.width()).enumerate() {
+        let message = receive
+            .fields
+            .iter()
+            .map(|field| field.apply::<F, F>(values, &[]).as_canonical_u32())
+            .collect::<Vec<_>>();
+        table_rows.entry(message).or_insert(row);
+    }
+
+    let mut multiplicities = vec![F::zero(); table_trace.height()];
+    for (id, chip) in chips {
+        let sends = chip
+            .sends()
+            .into_iter()
+            .filter(|interaction| interaction.argument_index == argument_index)
+            .collect::<Vec<_>>();
+        let Some(main) = main_traces.get(id) else {
+            continue;
+        };
+        if sends.is_empty() {
+            continue;
+        }
+
+        let preprocessed = chip.preprocessed_trace();
+        for n in 0..main.height() {
+            let preprocessed_row = preprocessed
+                .as_ref()
+                .map(|preprocessed| {
+                    &preprocessed.values[n * preprocessed.width..][..preprocessed.width]
+                })
+                .unwrap_or_default();
+            let main_row = &main.values[n * main.width..][..main.width];
+            for send in sends.iter() {
+                let count = send.count.apply::<F, F>(preprocessed_row, main_row);
+                if count.is_zero() {
+                    continue;
+                }
+                let message = send
+                    .fields
+                    .iter()
+                    .map(|field| field.apply::<F, F>(preprocessed_row, main_row))
+                    .collect::<Vec<_>>();
+                let key = message.iter().map(F::as_canonical_u32).collect::<Vec<_>>();
+                let Some(&row) = table_rows.get(&key) else {
+                    return Err(LookupError::MessageNotInTable {
+                        chip: id.clone(),
+                        row: n,
+                        message,
+                    });
+                };
+                multiplicities[row] += count;
+            }
+        }
+    }
+    Ok(multiplicities)
+}
@@ -1,41 +1,61 @@
+use alloc::collections::BTreeMap;
+#[cfg(feature = "air-logger")]
+use alloc::string::String;
+use alloc::vec;
 use alloc::vec::Vec;
 
 use itertools::Itertools;
 use p3_challenger::{CanObserve, FieldChallenger};
 use p3_commit::{Pcs, PolynomialSpace};
-#[cfg(feature = "schema")]
-use p3_field::Field;
-use p3_field::PrimeField32;
+use p3_field::{AbstractField, Field, PrimeField32, TwoAdicField};
 use p3_matrix::dense::RowMajorMatrix;
-use p3_uni_stark::{StarkGenericConfig, Val};
+use p3_uni_stark::{Domain, StarkGenericConfig, Val};
+use p3_util::log2_strict_usize;
 use tracing::instrument;
 
 use p3_air_util::folders::rap::{
     DebugConstraintBuilder, ProverConstraintFolder, SymbolicAirBuilder, TrackingConstraintBuilder,
     VerifierConstraintFolder,
 };
-use p3_air_util::proof::Commitments;
+use p3_air_util::proof::{Commitments, InteractionAirProof};
+#[cfg(feature = "air-logger")]
+use p3_air_util::{find_underconstrained_columns, AirLogger, ColumnIssue};
+use p3_air_util::{get_quotient_degree, pad_to_power_of_two, PeriodicAir};
 #[cfg(feature = "schema")]
 use p3_interaction::InteractionAir;
 use p3_interaction::{Bus, Rap, NUM_PERM_CHALLENGES};
 
-#[cfg(debug_assertions)]
+#[cfg(feature = "std")]
+use crate::dump::{check_constraints_or_dump, dump_traces};
 use crate::trace::MachineTraceChecker;
 #[cfg(feature = "air-logger")]
 use crate::trace::MachineTraceDebugger;
 use crate::{
-    chip::Chip,
+    chip::{unique_ids, Chip, ChipId},
     error::VerificationError,
+    generation::{generate_traces, TraceGenerator},
+    memory::stage,
+    options::ProverOptions,
     proof::{
-        MachineProof, ProverPreprocessedData, ProvingKey, VerifierPreprocessedData, VerifyingKey,
+        BatchMachineProof, Com, MachineProof, PcsProof, PcsProverData, ProverPreprocessedData,
+        ProvingKey, VerifierPreprocessedData, VerifyingKey,
     },
+    shard::{num_shards, repeat_per_shard, shard_trace},
     trace::{
-        MachineTrace, MachineTraceBuilder, MachineTraceCommiter, MachineTraceConstraintVerifier,
-        MachineTraceLoader, MachineTraceOpener, MachineTraceOpening, MachineTraceOpeningBuilder,
-        MachineTraceOpeningLoader, MachineTraceOpeningVerifier,
+        num_groups, MachineTrace, MachineTraceBuilder, MachineTraceCommiter,
+        MachineTraceConstraintVerifier, MachineTraceLoader, MachineTraceOpener,
+        MachineTraceOpening, MachineTraceOpeningBuilder, MachineTraceOpeningLoader,
+        MachineTraceOpeningVerifier,
     },
 };
 
+/// The main traces and public values of one instance of a machine.
+pub struct Instance<F> {
+    /// The main trace of each chip that has one, keyed by chip id.
+    pub main_traces: BTreeMap<ChipId, RowMajorMatrix<F>>,
+    pub public_values: Vec<F>,
+}
+
 pub trait Machine {
     type Chip: Chip;
 
@@ -43,16 +63,67 @@ pub trait Machine {
 
     fn chips(&self) -> Vec<Self::Chip>;
 
+    /// The id of each chip, in the order of `chips`. Chips sharing an id are told apart as in
+    /// `unique_ids`.
+    fn chip_ids(&self) -> Vec<ChipId> {
+        unique_ids(&self.chips())
+    }
+
+    /// The chips keyed by their ids. Traces, keys and proofs lay chips out in this order, so it
+    /// does not depend on the order of `chips`.
+    fn chips_by_id(&self) -> BTreeMap<ChipId, Self::Chip> {
+        let chips = self.chips();
+        let num_chips = chips.len();
+        let chips: BTreeMap<_, _> = self.chip_ids().into_iter().zip_eq(chips).collect();
+        assert_eq!(chips.len(), num_chips, "Chip ids should be unique");
+        chips
+    }
+
+    /// The main commitment group of each chip, keyed by chip id. The main traces of each group
+    /// are committed to separately, so that only one group's LDEs are computed at a time.
+    /// Defaults to a single group.
+    fn main_commitment_groups(&self) -> BTreeMap<ChipId, usize> {
+        self.chip_ids().into_iter().map(|id| (id, 0)).collect()
+    }
+
+    /// The maximum height of a main trace. Taller main traces are split into shards of this
+    /// height, each proven as a separate instance of the chip. The chip's constraints must hold
+    /// on every shard independently, and chips with preprocessed traces cannot be sharded.
+    /// Defaults to no limit.
+    fn max_shard_height(&self) -> Option<usize> {
+        None
+    }
+
+    /// The log of the blowup factor of the PCS the machine is proven with. The verifier rejects
+    /// trace heights whose low-degree extension would not fit in the field's two-adic subgroup.
+    /// Defaults to 1.
+    fn log_blowup(&self) -> usize {
+        1
+    }
+
     fn setup<'a, SC>(&self, config: &'a SC) -> (ProvingKey<SC>, VerifyingKey<SC>)
     where
         SC: StarkGenericConfig,
         Self::Chip: for<'b> Rap<ProverConstraintFolder<'b, SC>>
             + for<'b> Rap<VerifierConstraintFolder<'b, SC>>
             + for<'b> Rap<SymbolicAirBuilder<Val<SC>>>
-            + for<'b> Rap<DebugConstraintBuilder<'b, Val<SC>, SC::Challenge>>,
+            + for<'b> Rap<DebugConstraintBuilder<'b, Val<SC>, SC::Challenge>>
+            + PeriodicAir<Val<SC>>
+            + Send
+            + Sync,
+        SC::Pcs: Sync,
+        PcsProverData<SC>: Sync,
+        Domain<SC>: Send + Sync,
     {
         let pcs = config.pcs();
-        let chips = self.chips();
+        let chips = self.chips_by_id().into_iter().collect_vec();
+        let main_commitment_groups = self.main_commitment_groups();
+        assert!(
+            main_commitment_groups
+                .keys()
+                .eq(chips.iter().map(|(id, _)| id)),
+            "Main commitment groups should be given for exactly the machine's chips"
+        );
         let mut trace: MachineTrace<SC, _> = MachineTraceBuilder::new(chips.as_slice());
 
         // 1. Generate and commit to preprocessed traces
@@ -68,14 +139,13 @@ pub trait Machine {
                     .map(|preprocessed| preprocessed.trace.value.clone())
             })
             .collect();
-        let indexed_degrees: Vec<(usize, usize)> = trace
+        let degrees: BTreeMap<ChipId, usize> = trace
             .iter()
-            .enumerate()
-            .flat_map(|(i, chip_trace)| {
+            .flat_map(|chip_trace| {
                 chip_trace
                     .preprocessed
                     .as_ref()
-                    .map(|trace| (i, trace.trace.domain.size()))
+                    .map(|trace| (chip_trace.id.clone(), trace.trace.domain.size()))
             })
             .collect();
 
@@ -90,7 +160,7 @@ pub trait Machine {
 
             Some(VerifierPreprocessedData {
                 commitment: commit,
-                degrees: indexed_degrees,
+                degrees,
             })
         } else {
             None
@@ -98,9 +168,11 @@ pub trait Machine {
 
         let vk = VerifyingKey {
             preprocessed: verifier_data,
+            main_commitment_groups: main_commitment_groups.clone(),
         };
         let pk = ProvingKey {
             preprocessed: prover_data,
+            main_commitment_groups,
         };
 
         (pk, vk)
@@ -111,9 +183,10 @@ pub trait Machine {
         config: &'a SC,
         challenger: &mut SC::Challenger,
         pk: &'a ProvingKey<SC>,
-        main_traces: Vec<Option<RowMajorMatrix<Val<SC>>>>,
+        main_traces: BTreeMap<ChipId, RowMajorMatrix<Val<SC>>>,
         // TODO: Change to 2d vector?
         public_values: &'a [Val<SC>],
+        options: &ProverOptions,
     ) -> MachineProof<SC>
     where
         SC: StarkGenericConfig,
@@ -122,32 +195,210 @@ pub trait Machine {
             + for<'b> Rap<SymbolicAirBuilder<Val<SC>>>
             + for<'b> Rap<DebugConstraintBuilder<'b, Val<SC>, SC::Challenge>>
             // TODO: Put behind air-logger feature
-            + for<'b> Rap<TrackingConstraintBuilder<'b, Val<SC>, SC::Challenge>>,
+            + for<'b> Rap<TrackingConstraintBuilder<'b, Val<SC>, SC::Challenge>>
+            + PeriodicAir<Val<SC>>
+            + Send
+            + Sync,
+        SC::Pcs: Sync,
+        PcsProverData<SC>: Sync,
+        Domain<SC>: Send + Sync,
+        Val<SC>: PrimeField32,
+    {
+        let instance = Instance {
+            main_traces,
+            public_values: public_values.to_vec(),
+        };
+        let BatchMachineProof {
+            commitments,
+            opening_proof,
+            mut chip_proofs,
+        } = self.prove_batch(config, challenger, pk, vec![instance], options);
+
+        MachineProof {
+            commitments,
+            opening_proof,
+            chip_proofs: chip_proofs.pop().unwrap(),
+        }
+    }
+
+    /// Generates the main traces of the chips from an execution record, in dependency order.
+    fn generate_traces<F, R>(&self, record: &R) -> BTreeMap<ChipId, RowMajorMatrix<F>>
+    where
+        F: Field,
+        R: Sync,
+        Self::Chip: TraceGenerator<F, R> + Sync,
+    {
+        generate_traces(&self.chips_by_id(), record)
+    }
+
+    /// Generates the main traces from an execution record and proves them.
+    fn prove_record<'a, SC, R>(
+        &self,
+        config: &'a SC,
+        challenger: &mut SC::Challenger,
+        pk: &'a ProvingKey<SC>,
+        record: &R,
+        public_values: &'a [Val<SC>],
+        options: &ProverOptions,
+    ) -> MachineProof<SC>
+    where
+        SC: StarkGenericConfig,
+        Self::Chip: for<'b> Rap<ProverConstraintFolder<'b, SC>>
+            + for<'b> Rap<VerifierConstraintFolder<'b, SC>>
+            + for<'b> Rap<SymbolicAirBuilder<Val<SC>>>
+            + for<'b> Rap<DebugConstraintBuilder<'b, Val<SC>, SC::Challenge>>
+            // TODO: Put behind air-logger feature
+            + for<'b> Rap<TrackingConstraintBuilder<'b, Val<SC>, SC::Challenge>>
+            + PeriodicAir<Val<SC>>
+            + TraceGenerator<Val<SC>, R>
+            + Send
+            + Sync,
+        SC::Pcs: Sync,
+        PcsProverData<SC>: Sync,
+        Domain<SC>: Send + Sync,
+        Val<SC>: PrimeField32,
+        R: Sync,
+    {
+        let main_traces = stage!("generate main traces", self.generate_traces(record));
+        self.prove(config, challenger, pk, main_traces, public_values, options)
+    }
+
+    /// Proves several independent instances of the machine together. The instances share their
+    /// commitments and are opened at a single point, but the buses of each instance must balance
+    /// on their own.
+    fn prove_batch<'a, SC>(
+        &self,
+        config: &'a SC,
+        challenger: &mut SC::Challenger,
+        pk: &'a ProvingKey<SC>,
+        instances: Vec<Instance<Val<SC>>>,
+        options: &ProverOptions,
+    ) -> BatchMachineProof<SC>
+    where
+        SC: StarkGenericConfig,
+        Self::Chip: for<'b> Rap<ProverConstraintFolder<'b, SC>>
+            + for<'b> Rap<VerifierConstraintFolder<'b, SC>>
+            + for<'b> Rap<SymbolicAirBuilder<Val<SC>>>
+            + for<'b> Rap<DebugConstraintBuilder<'b, Val<SC>, SC::Challenge>>
+            // TODO: Put behind air-logger feature
+            + for<'b> Rap<TrackingConstraintBuilder<'b, Val<SC>, SC::Challenge>>
+            + PeriodicAir<Val<SC>>
+            + Send
+            + Sync,
+        SC::Pcs: Sync,
+        PcsProverData<SC>: Sync,
+        Domain<SC>: Send + Sync,
         Val<SC>: PrimeField32,
     {
         // TODO: Use fixed size array instead of Vecs
-        let chips = self.chips();
-        assert_eq!(main_traces.len(), chips.len(), "Length mismatch");
+        let chips = self.chips_by_id().into_iter().collect_vec();
+        assert!(
+            pk.main_commitment_groups
+                .keys()
+                .eq(chips.iter().map(|(id, _)| id)),
+            "Proving key does not match the machine's chips"
+        );
+        let chip_main_commitment_groups = pk.main_commitment_groups.values().copied().collect_vec();
+        assert!(
+            !instances.is_empty(),
+            "At least one instance should be proven"
+        );
 
         let pcs = config.pcs();
 
-        // 1. Observe public values
-        challenger.observe_slice(public_values);
+        let max_shard_height = self.max_shard_height();
+        if let Some(max_shard_height) = max_shard_height {
+            assert!(
+                max_shard_height.is_power_of_two(),
+                "Maximum shard height should be a power of two"
+            );
+        }
 
-        let mut trace: MachineTrace<SC, _> = MachineTraceBuilder::new(&chips);
+        // 1. Observe public values and shard counts
+        challenger.observe(Val::<SC>::from_canonical_usize(instances.len()));
+
+        let mut public_values = vec![];
+        let mut shard_counts = vec![];
+        let mut instance_chips = vec![];
+        let mut chip_instances = vec![];
+        let mut main_traces = vec![];
+        let mut main_commitment_groups = vec![];
+        for (index, mut instance) in instances.into_iter().enumerate() {
+            let instance_main_traces = chips
+                .iter()
+                .map(|(id, chip)| {
+                    let mut main_trace = instance.main_traces.remove(id)?;
+                    if let Some(padding_row) = chip.padding_row() {
+                        pad_to_power_of_two(&mut main_trace, &padding_row);
+                    }
+                    Some(main_trace)
+                })
+                .collect_vec();
+            assert!(
+                instance.main_traces.is_empty(),
+                "Main traces should only be given for the machine's chips"
+            );
+            let counts = instance_main_traces
+                .iter()
+                .map(|main_trace| num_shards(main_trace, max_shard_height))
+                .collect_vec();
+
+            challenger.observe_slice(&instance.public_values);
+            observe_shard_counts::<SC>(challenger, &counts);
+
+            instance_chips.extend(repeat_per_shard(&chips, &counts));
+            chip_instances.extend(repeat_per_shard(&vec![index; chips.len()], &counts));
+            assert!(
+                pk.preprocessed
+                    .traces
+                    .iter()
+                    .zip_eq(counts.iter())
+                    .all(|(preprocessed_trace, &count)| count == 1 || preprocessed_trace.is_none()),
+                "Chips with preprocessed traces cannot be sharded"
+            );
+            main_traces.extend(
+                instance_main_traces
+                    .into_iter()
+                    .zip_eq(counts.iter())
+                    .flat_map(|(main_trace, &count)| shard_trace(main_trace, count)),
+            );
+            main_commitment_groups.extend(repeat_per_shard(&chip_main_commitment_groups, &counts));
+
+            public_values.push(instance.public_values);
+            shard_counts.push(counts);
+        }
+
+        let mut trace: MachineTrace<SC, _> = MachineTraceBuilder::new(&instance_chips);
+        for (chip_trace, &instance) in trace.iter_mut().zip_eq(chip_instances.iter()) {
+            chip_trace.instance = instance;
+        }
 
         // 2. Observe preprocessed commitment
-        tracing::info_span!("load preprocessed traces")
-            .in_scope(|| trace.load_preprocessed(pcs, pk.preprocessed.traces.as_slice()));
+        let preprocessed_traces = chips
+            .iter()
+            .zip_eq(pk.preprocessed.traces.iter())
+            .filter_map(|((id, _), preprocessed_trace)| {
+                Some((id.clone(), preprocessed_trace.clone()?))
+            })
+            .collect();
+        stage!(
+            "load preprocessed traces",
+            trace.load_preprocessed(pcs, preprocessed_traces)
+        );
         if let Some(commit) = &pk.preprocessed.commitment {
             challenger.observe(commit.clone());
         }
 
         // 3. Generate and commit to main trace
-        tracing::info_span!("load main traces").in_scope(|| trace.load_main(pcs, main_traces));
-        let (main_commit, main_data) =
-            tracing::info_span!("commit to main traces").in_scope(|| trace.commit_main(pcs));
-        if let Some(main_commit) = &main_commit {
+        stage!(
+            "load main traces",
+            trace.load_main(pcs, main_traces, &main_commitment_groups)
+        );
+        let (main_commits, main_data): (Vec<_>, Vec<_>) =
+            stage!("commit to main traces", trace.commit_main(pcs))
+                .into_iter()
+                .unzip();
+        for main_commit in main_commits.iter().flatten() {
             challenger.observe(main_commit.clone());
         }
 
@@ -159,27 +410,74 @@ pub trait Machine {
             .unwrap();
 
         // 5. Generate and commit to permutation trace
-        tracing::info_span!("generate permutation traces")
-            .in_scope(|| trace.generate_permutation(pcs, perm_challenges));
-        let (permutation_commit, permutation_data) =
-            tracing::info_span!("commit to permutation traces")
-                .in_scope(|| trace.commit_permutation(pcs));
-        if let Some(permutation_commit) = &permutation_commit {
-            challenger.observe(permutation_commit.clone());
-        }
-        let alpha: SC::Challenge = challenger.sample_ext_element();
+        stage!(
+            "generate permutation traces",
+            trace.generate_permutation(pcs, perm_challenges)
+        );
 
         #[cfg(feature = "air-logger")]
-        let _ = tracing::info_span!("writing traces to file")
-            .in_scope(|| trace.write_traces_to_file("trace.xlsx", perm_challenges));
+        if options.find_unconstrained_columns {
+            tracing::info_span!("finding unconstrained columns").in_scope(|| {
+                let columns = trace.find_unconstrained_columns(perm_challenges, &public_values);
+                for (chip_trace, columns) in trace.iter().zip_eq(columns) {
+                    if !columns.is_empty() {
+                        tracing::warn!(
+                            "{} has unconstrained columns: {}",
+                            chip_trace.chip,
+                            columns.join(", ")
+                        );
+                    }
+                }
+            });
+        }
+
+        // The traces are dumped before they are checked, unless only failing traces are dumped
+        #[cfg(feature = "std")]
+        if let Some(dump) = options.dump.as_ref().filter(|dump| !dump.only_on_failure) {
+            tracing::info_span!("dumping traces").in_scope(|| {
+                dump_traces(self, &trace, &public_values, perm_challenges, dump).unwrap_or_else(
+                    |err| panic!("Failed to dump the traces to {}: {err}", dump.path),
+                )
+            });
+        }
 
         // Verify constraints
-        #[cfg(debug_assertions)]
-        tracing::info_span!("checking constraints")
-            .in_scope(|| trace.check_constraints::<Self::Bus>(perm_challenges, &[]));
+        if options.check_constraints {
+            tracing::info_span!("checking constraints").in_scope(|| {
+                #[cfg(feature = "std")]
+                if let Some(dump) = options.dump.as_ref().filter(|dump| dump.only_on_failure) {
+                    return check_constraints_or_dump(
+                        self,
+                        &trace,
+                        &public_values,
+                        perm_challenges,
+                        dump,
+                    );
+                }
+                trace.check_constraints::<Self::Bus>(perm_challenges, &public_values)
+            });
+        }
+
+        // The host traces are not read again once the permutation traces have been generated and
+        // checked, so they can be dropped before committing to the rest.
+        #[cfg(feature = "low-memory")]
+        stage!(
+            "release preprocessed and main traces",
+            trace.release_preprocessed_and_main()
+        );
+
+        let (permutation_commit, permutation_data) = stage!(
+            "commit to permutation traces",
+            trace.commit_permutation(pcs)
+        );
+        if let Some(permutation_commit) = &permutation_commit {
+            challenger.observe(permutation_commit.clone());
+        }
+        let alpha: SC::Challenge = challenger.sample_ext_element();
 
         // 6. Generate and commit to quotient traces
-        tracing::info_span!("generate quotient trace").in_scope(|| {
+        stage!(
+            "generate quotient trace",
             trace.generate_quotient(
                 pcs,
                 &pk.preprocessed.data,
@@ -187,18 +485,19 @@ pub trait Machine {
                 &permutation_data,
                 perm_challenges,
                 alpha,
-                public_values,
+                &public_values,
+                options.use_bytecode,
             )
-        });
+        );
         // TODO: Panic if this is None
-        let (quotient_commit, quotient_data) = tracing::info_span!("commit to quotient chunks")
-            .in_scope(|| trace.commit_quotient(pcs));
+        let (quotient_commit, quotient_data) =
+            stage!("commit to quotient chunks", trace.commit_quotient(pcs));
         if let Some(quotient_commit) = &quotient_commit {
             challenger.observe(quotient_commit.clone());
         }
 
         let commitments = Commitments {
-            main: main_commit,
+            main: main_commits,
             permutation: permutation_commit,
             quotient_chunks: quotient_commit,
         };
@@ -223,9 +522,20 @@ pub trait Machine {
             &quotient_data,
         );
 
-        let chip_proofs = trace.generate_proofs(opening_values);
+        let mut chip_proofs = trace.generate_proofs(opening_values).into_iter();
+        let chip_proofs = shard_counts
+            .iter()
+            .map(|counts| {
+                chips
+                    .iter()
+                    .map(|(id, _)| id)
+                    .zip_eq(counts.iter())
+                    .map(|(id, &count)| (id.clone(), chip_proofs.by_ref().take(count).collect()))
+                    .collect()
+            })
+            .collect();
 
-        MachineProof {
+        BatchMachineProof {
             commitments,
             opening_proof,
             chip_proofs,
@@ -243,76 +553,93 @@ pub trait Machine {
     ) -> Result<(), VerificationError>
     where
         SC: StarkGenericConfig,
-        Val<SC>: PrimeField32,
+        Val<SC>: PrimeField32 + TwoAdicField,
         Self::Chip: for<'b> Rap<VerifierConstraintFolder<'b, SC>>
-            + for<'b> Rap<SymbolicAirBuilder<Val<SC>>>,
+            + for<'b> Rap<SymbolicAirBuilder<Val<SC>>>
+            + PeriodicAir<Val<SC>>,
     {
-        let chips = self.chips();
-        let pcs = config.pcs();
-
-        let mut trace: MachineTraceOpening<SC, _> = MachineTraceOpeningBuilder::new(&chips);
-
         let MachineProof {
             commitments,
             opening_proof,
             chip_proofs,
         } = proof;
+        verify_instances(
+            self,
+            config,
+            challenger,
+            vk,
+            commitments,
+            opening_proof,
+            core::slice::from_ref(chip_proofs),
+            &[public_values.to_vec()],
+        )
+    }
 
-        let mut preprocessed_degrees = (0..trace.len()).map(|_| 0usize).collect_vec();
-        if let Some(preprocessed) = &vk.preprocessed {
-            for (i, degree) in preprocessed.degrees.iter() {
-                preprocessed_degrees[*i] = *degree;
-            }
-        }
-        // TODO: Avoid clone
-        trace.load_openings(pcs, chip_proofs.clone(), preprocessed_degrees);
-
-        // Verify proof shape
-        trace.verify_shapes()?;
-
-        // Observe commitments
-        if let Some(preprocessed) = &vk.preprocessed {
-            challenger.observe(preprocessed.commitment.clone());
-        }
-        if let Some(main) = &commitments.main {
-            challenger.observe(main.clone());
-        }
-        let perm_challenges: [SC::Challenge; NUM_PERM_CHALLENGES] = (0..NUM_PERM_CHALLENGES)
-            .map(|_| challenger.sample_ext_element::<SC::Challenge>())
-            .collect_vec()
-            .try_into()
-            .unwrap();
-        if let Some(permutation) = &commitments.permutation {
-            challenger.observe(permutation.clone());
-        }
-        let alpha = challenger.sample_ext_element::<SC::Challenge>();
-        if let Some(quotient_chunks) = &commitments.quotient_chunks {
-            challenger.observe(quotient_chunks.clone());
-        }
-
-        let zeta: SC::Challenge = challenger.sample_ext_element();
-
-        // TODO: Remove clone
-        let rounds = trace.generate_rounds(
-            zeta,
-            &vk.preprocessed
-                .as_ref()
-                .map(|preprocessed| preprocessed.commitment.clone()),
-            &commitments.main,
-            &commitments.permutation,
-            &commitments.quotient_chunks,
-        );
-
-        pcs.verify(rounds, opening_proof, challenger)
-            .map_err(|_| VerificationError::InvalidOpeningArgument)?;
-
-        // Verify constraints at zeta
-        trace.verify_constraints(zeta, alpha, perm_challenges, public_values)?;
-
-        // Verify cumulative sum adds to zero
-        trace.verify_cumulative_sums()?;
+    /// Verifies a batch of instances proven with `prove_batch`, given the public values of each.
+    #[instrument(skip_all)]
+    fn verify_batch<'a, SC>(
+        &self,
+        config: &'a SC,
+        challenger: &'a mut SC::Challenger,
+        vk: &'a VerifyingKey<SC>,
+        proof: &BatchMachineProof<SC>,
+        public_values: &[Vec<Val<SC>>],
+    ) -> Result<(), VerificationError>
+    where
+        SC: StarkGenericConfig,
+        Val<SC>: PrimeField32 + TwoAdicField,
+        Self::Chip: for<'b> Rap<VerifierConstraintFolder<'b, SC>>
+            + for<'b> Rap<SymbolicAirBuilder<Val<SC>>>
+            + PeriodicAir<Val<SC>>,
+    {
+        let BatchMachineProof {
+            commitments,
+            opening_proof,
+            chip_proofs,
+        } = proof;
+        verify_instances(
+            self,
+            config,
+            challenger,
+            vk,
+            commitments,
+            opening_proof,
+            chip_proofs,
+            public_values,
+        )
+    }
 
-        Ok(())
+    /// Finds the main columns of each chip that may be under-constrained, by their headers. Only
+    /// chips with such columns are returned. See `find_underconstrained_columns` in `p3-air-util`
+    /// for what is reported.
+    #[cfg(feature = "air-logger")]
+    fn find_underconstrained_columns<F>(
+        &self,
+        num_public_values: usize,
+        range_check_buses: &[usize],
+    ) -> BTreeMap<ChipId, Vec<(String, ColumnIssue)>>
+    where
+        F: Field,
+        Self::Chip: Rap<SymbolicAirBuilder<F>> + PeriodicAir<F>,
+    {
+        self.chips_by_id()
+            .into_iter()
+            .filter_map(|(id, chip)| {
+                let headers = chip.main_headers();
+                let columns =
+                    find_underconstrained_columns(&chip, num_public_values, range_check_buses)
+                        .into_iter()
+                        .map(|column| {
+                            let header = headers
+                                .get(column.col)
+                                .cloned()
+                                .unwrap_or_else(|| alloc::format!("main[{}]", column.col));
+                            (header, column.issue)
+                        })
+                        .collect_vec();
+                (!columns.is_empty()).then_some((id, columns))
+            })
+            .collect()
     }
 
     #[cfg(feature = "schema")]
@@ -327,7 +654,6 @@ pub trait Machine {
         use alloc::vec::Vec;
         use core::iter::once;
         use p3_air::PairCol;
-        use p3_air_util::AirLogger;
         use p3_interaction::InteractionType;
         use std::fs::File;
         use std::io::{BufWriter, Write};
@@ -426,3 +752,235 @@ pub trait Machine {
         }
     }
 }
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn verify_instances<M, SC>(
+    machine: &M,
+    config: &SC,
+    challenger: &mut SC::Challenger,
+    vk: &VerifyingKey<SC>,
+    commitments: &Commitments<Com<SC>>,
+    opening_proof: &PcsProof<SC>,
+    chip_proofs: &[BTreeMap<ChipId, Vec<Option<InteractionAirProof<SC::Challenge>>>>],
+    public_values: &[Vec<Val<SC>>],
+) -> Result<(), VerificationError>
+where
+    M: Machine + ?Sized,
+    SC: StarkGenericConfig,
+    Val<SC>: PrimeField32 + TwoAdicField,
+    M::Chip: for<'b> Rap<VerifierConstraintFolder<'b, SC>>
+        + for<'b> Rap<SymbolicAirBuilder<Val<SC>>>
+        + PeriodicAir<Val<SC>>,
+{
+    let chips = machine.chips_by_id();
+    let pcs = config.pcs();
+    let max_shard_height = machine.max_shard_height();
+
+    if public_values.is_empty() || chip_proofs.len() != public_values.len() {
+        return Err(VerificationError::InvalidProofShape);
+    }
+    // The key and every instance must have an entry for exactly the machine's chips
+    if !vk.main_commitment_groups.keys().eq(chips.keys())
+        || vk.preprocessed.as_ref().is_some_and(|preprocessed| {
+            preprocessed
+                .degrees
+                .keys()
+                .any(|id| !chips.contains_key(id))
+        })
+        || chip_proofs
+            .iter()
+            .any(|instance_chip_proofs| !instance_chip_proofs.keys().eq(chips.keys()))
+    {
+        return Err(VerificationError::InvalidChipIds);
+    }
+
+    let degrees = chips
+        .keys()
+        .map(|id| {
+            vk.preprocessed
+                .as_ref()
+                .and_then(|preprocessed| preprocessed.degrees.get(id).copied())
+                .unwrap_or(0)
+        })
+        .collect_vec();
+    let chip_main_commitment_groups = vk.main_commitment_groups.values().copied().collect_vec();
+    let chips = chips.into_iter().collect_vec();
+
+    let mut shard_counts = vec![];
+    let mut instance_chips = vec![];
+    let mut chip_instances = vec![];
+    let mut preprocessed_degrees = vec![];
+    let mut main_commitment_groups = vec![];
+    for (instance, instance_chip_proofs) in chip_proofs.iter().enumerate() {
+        let counts = instance_chip_proofs.values().map(Vec::len).collect_vec();
+        if counts.iter().any(|&count| count == 0)
+            || counts
+                .iter()
+                .zip_eq(degrees.iter())
+                .any(|(&count, &degree)| count > 1 && degree != 0)
+        {
+            return Err(VerificationError::InvalidProofShape);
+        }
+        // Every chip proof has a power of two degree, small enough for its quotient domain and
+        // low-degree extension to fit in the field's two-adic subgroup, and opens a preprocessed
+        // trace exactly when the chip has one
+        let num_public_values = public_values[instance].len();
+        if instance_chip_proofs
+            .values()
+            .zip_eq(chips.iter().zip_eq(degrees.iter()))
+            .any(|(shard_proofs, ((_, chip), &degree))| {
+                let log_quotient_degree =
+                    log2_strict_usize(get_quotient_degree::<Val<SC>, _>(chip, num_public_values));
+                let max_log_degree = Val::<SC>::TWO_ADICITY
+                    .saturating_sub(log_quotient_degree.max(machine.log_blowup()));
+                shard_proofs.iter().flatten().any(|chip_proof| {
+                    !chip_proof.degree.is_power_of_two()
+                        || log2_strict_usize(chip_proof.degree) > max_log_degree
+                        || chip_proof.opened_values.preprocessed.is_some() != (degree != 0)
+                })
+            })
+        {
+            return Err(VerificationError::InvalidProofShape);
+        }
+
+        // Every shard of a sharded chip has the maximum shard height
+        for shard_proofs in instance_chip_proofs
+            .values()
+            .filter(|proofs| proofs.len() > 1)
+        {
+            for chip_proof in shard_proofs {
+                let degree = chip_proof.as_ref().map(|chip_proof| chip_proof.degree);
+                if max_shard_height.is_none() || degree != max_shard_height {
+                    return Err(VerificationError::InvalidProofShape);
+                }
+            }
+        }
+
+        instance_chips.extend(repeat_per_shard(&chips, &counts));
+        chip_instances.extend(repeat_per_shard(&vec![instance; chips.len()], &counts));
+        preprocessed_degrees.extend(repeat_per_shard(&degrees, &counts));
+        main_commitment_groups.extend(repeat_per_shard(&chip_main_commitment_groups, &counts));
+        shard_counts.push(counts);
+    }
+
+    let mut trace: MachineTraceOpening<SC, _> = MachineTraceOpeningBuilder::new(&instance_chips);
+    for (chip_trace, &instance) in trace.iter_mut().zip_eq(chip_instances.iter()) {
+        chip_trace.instance = instance;
+    }
+
+    let num_public_values = public_values.iter().map(Vec::len).collect_vec();
+    // TODO: Avoid clone
+    trace.load_openings(
+        pcs,
+        chip_proofs
+            .iter()
+            .flat_map(|instance_chip_proofs| instance_chip_proofs.values().flatten().cloned())
+            .collect(),
+        preprocessed_degrees,
+        &main_commitment_groups,
+        &num_public_values,
+    );
+
+    // Verify proof shape
+    trace.verify_shapes(&num_public_values)?;
+    // Each main commitment group has a commitment exactly when one of its chips has a main trace
+    if commitments.main.len() != num_groups(&chip_main_commitment_groups) {
+        return Err(VerificationError::InvalidProofShape);
+    }
+    for (group, commitment) in commitments.main.iter().enumerate() {
+        let has_main = trace
+            .iter()
+            .any(|chip_trace| chip_trace.main_group == group && chip_trace.main.is_some());
+        if has_main != commitment.is_some() {
+            return Err(VerificationError::InvalidProofShape);
+        }
+    }
+    // The permutation and quotient commitments are present exactly when some chip opens them
+    let has_permutation = trace
+        .iter()
+        .any(|chip_trace| chip_trace.permutation.is_some());
+    let has_quotient_chunks = trace
+        .iter()
+        .any(|chip_trace| chip_trace.quotient_chunks.is_some());
+    if has_permutation != commitments.permutation.is_some()
+        || has_quotient_chunks != commitments.quotient_chunks.is_some()
+    {
+        return Err(VerificationError::InvalidProofShape);
+    }
+    // Only the first instance's preprocessed openings are checked against the commitment, so the
+    // other instances must claim the same values
+    let preprocessed_openings = |instance| {
+        trace
+            .iter()
+            .filter(|chip_trace| chip_trace.instance == instance)
+            .filter_map(|chip_trace| chip_trace.preprocessed.as_ref())
+            .map(|preprocessed| (&preprocessed.values.local, &preprocessed.values.next))
+            .collect_vec()
+    };
+    let first_preprocessed_openings = preprocessed_openings(0);
+    if (1..public_values.len())
+        .any(|instance| preprocessed_openings(instance) != first_preprocessed_openings)
+    {
+        return Err(VerificationError::InvalidProofShape);
+    }
+
+    // Observe public values and shard counts
+    challenger.observe(Val::<SC>::from_canonical_usize(public_values.len()));
+    for (instance_public_values, counts) in public_values.iter().zip_eq(shard_counts.iter()) {
+        challenger.observe_slice(instance_public_values);
+        observe_shard_counts::<SC>(challenger, counts);
+    }
+
+    // Observe commitments
+    if let Some(preprocessed) = &vk.preprocessed {
+        challenger.observe(preprocessed.commitment.clone());
+    }
+    for main in commitments.main.iter().flatten() {
+        challenger.observe(main.clone());
+    }
+    let perm_challenges: [SC::Challenge; NUM_PERM_CHALLENGES] = (0..NUM_PERM_CHALLENGES)
+        .map(|_| challenger.sample_ext_element::<SC::Challenge>())
+        .collect_vec()
+        .try_into()
+        .unwrap();
+    if let Some(permutation) = &commitments.permutation {
+        challenger.observe(permutation.clone());
+    }
+    let alpha = challenger.sample_ext_element::<SC::Challenge>();
+    if let Some(quotient_chunks) = &commitments.quotient_chunks {
+        challenger.observe(quotient_chunks.clone());
+    }
+
+    let zeta: SC::Challenge = challenger.sample_ext_element();
+
+    // TODO: Remove clone
+    let rounds = trace.generate_rounds(
+        zeta,
+        &vk.preprocessed
+            .as_ref()
+            .map(|preprocessed| preprocessed.commitment.clone()),
+        &commitments.main,
+        &commitments.permutation,
+        &commitments.quotient_chunks,
+    );
+
+    pcs.verify(rounds, opening_proof, challenger)
+        .map_err(|_| VerificationError::InvalidOpeningArgument)?;
+
+    // Verify constraints at zeta
+    trace.verify_constraints(zeta, alpha, perm_challenges, public_values)?;
+
+    // Verify cumulative sums add to zero in each instance
+    trace.verify_cumulative_sums()?;
+
+    Ok(())
+}
+
+fn observe_shard_counts<SC: StarkGenericConfig>(
+    challenger: &mut SC::Challenger,
+    shard_counts: &[usize],
+) {
+    for &count in shard_counts {
+        challenger.observe(Val::<SC>::from_canonical_usize(count));
+    }
+}
@@ -0,0 +1,63 @@
+/// Runs `$body` in an info span named `$name`. In low-memory mode, the resident set size before
+/// and after the stage, and its peak over the stage, are reported once it finishes.
+macro_rules! stage {
+    ($name:literal, $body:expr) => {
+        tracing::info_span!($name).in_scope(|| $crate::memory::measure($name, || $body))
+    };
+}
+
+pub(crate) use stage;
+
+pub(crate) fn measure<T>(stage: &str, f: impl FnOnce() -> T) -> T {
+    #[cfg(feature = "low-memory")]
+    let before = MemoryUsage::current();
+    #[cfg(feature = "low-memory")]
+    reset_peak_rss();
+    let result = f();
+    #[cfg(feature = "low-memory")]
+    if let (Some(before), Some(usage)) = (before, MemoryUsage::current()) {
+        tracing::info!(
+            "{stage}: rss {} KiB -> {} KiB, peak rss {} KiB",
+            before.rss_kib,
+            usage.rss_kib,
+            usage.peak_rss_kib
+        );
+    }
+    #[cfg(not(feature = "low-memory"))]
+    let _ = stage;
+    result
+}
+
+/// Memory usage of the current process, as reported by `/proc/self/status`.
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryUsage {
+    pub rss_kib: u64,
+    /// The peak resident set size since the process started, or since the last call to
+    /// `reset_peak_rss`.
+    pub peak_rss_kib: u64,
+}
+
+#[cfg(feature = "std")]
+impl MemoryUsage {
+    /// Returns `None` on platforms without procfs.
+    pub fn current() -> Option<Self> {
+        let status = std::fs::read_to_string("/proc/self/status").ok()?;
+        let field = |name: &str| {
+            status
+                .lines()
+                .find_map(|line| line.strip_prefix(name))
+                .and_then(|value| value.trim().trim_end_matches("kB").trim().parse().ok())
+        };
+        Some(Self {
+            rss_kib: field("VmRSS:")?,
+            peak_rss_kib: field("VmHWM:")?,
+        })
+    }
+}
+
+/// Resets the peak resident set size to the current one. Does nothing on platforms without procfs.
+#[cfg(feature = "std")]
+pub fn reset_peak_rss() {
+    let _ = std::fs::write("/proc/self/clear_refs", "5");
+}
@@ -0,0 +1,281 @@
+#[cfg(feature = "air-logger")]
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec::Vec;
+#[cfg(feature = "air-logger")]
+use core::error::Error;
+
+use itertools::Itertools;
+use p3_air::BaseAir;
+use p3_air_util::debug::rap::track_constraints;
+use p3_air_util::folders::rap::TrackingConstraintBuilder;
+use p3_air_util::folders::EntriesLog;
+use p3_air_util::util::{ColumnEntry, TraceEntry};
+use p3_air_util::{pad_to_power_of_two, PeriodicAir};
+use p3_field::{ExtensionField, Field};
+use p3_interaction::{generate_permutation_trace, InteractionAir, Rap, NUM_PERM_CHALLENGES};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+
+use crate::chip::{Chip, ChipId};
+use crate::machine::Machine;
+
+/// A failing witness with rows removed from its main traces.
+#[derive(Clone, Debug)]
+pub struct MinimizedWitness<F> {
+    /// The unpadded main traces. Chips whose main traces were removed entirely have none.
+    pub main_traces: BTreeMap<ChipId, RowMajorMatrix<F>>,
+    /// The rows of the original main traces that were kept.
+    pub rows: BTreeMap<ChipId, Vec<usize>>,
+}
+
+/// Removes rows from the unpadded `main_traces` of `machine` while the `target` chip fails the
+/// same constraints, given by the failing entries of `report` as tracked by `track_constraints` on
+/// the padded traces. The other chips' traces are removed entirely, or shrunk, while they keep
+/// failing the same constraints as before. Failures are compared by column, so the rows they
+/// occur in may change.
+///
+/// Traces are padded with their chip's padding row after removing rows, and traces of chips
+/// without one are only shrunk to contiguous windows of half their rows. Rows whose removal makes
+/// a transition fail differently are kept. Main traces of chips with a preprocessed trace are not
+/// shrunk, as their height is tied to it. The permutation traces are regenerated with
+/// `perm_challenges`, so buses may no longer balance in the minimized witness.
+pub fn minimize_failing_trace<M, F, EF>(
+    machine: &M,
+    target: &ChipId,
+    main_traces: &BTreeMap<ChipId, RowMajorMatrix<F>>,
+    report: &EntriesLog<TraceEntry>,
+    public_values: &[F],
+    perm_challenges: [EF; NUM_PERM_CHALLENGES],
+) -> MinimizedWitness<F>
+where
+    M: Machine,
+    M::Chip: for<'a> Rap<TrackingConstraintBuilder<'a, F, EF>> + PeriodicAir<F>,
+    F: Field,
+    EF: ExtensionField<F>,
+{
+    let chips = machine.chips_by_id();
+    assert!(
+        main_traces.contains_key(target) && chips.contains_key(target),
+        "The target should be one of the machine's chips, with a main trace"
+    );
+    let reported = failing_columns(report);
+    assert!(
+        !reported.is_empty(),
+        "The report should have failing entries"
+    );
+
+    let mut minimized = MinimizedWitness {
+        main_traces: BTreeMap::new(),
+        rows: BTreeMap::new(),
+    };
+    for (id, chip) in chips.iter() {
+        let Some(main_trace) = main_traces.get(id) else {
+            continue;
+        };
+        let preprocessed = BaseAir::<F>::preprocessed_trace(chip);
+        let padding_row = chip.padding_row::<F>();
+        let failure = |rows: Option<&[usize]>| {
+            let main = rows.map(|rows| select_rows(main_trace, rows, padding_row.as_deref()));
+            let entries = track(
+                chip,
+                preprocessed.as_ref(),
+                main.as_ref(),
+                public_values,
+                perm_challenges,
+            );
+            failing_columns(&entries)
+        };
+
+        let all_rows = (0..main_trace.height()).collect_vec();
+        let original = failure(Some(&all_rows));
+        if id == target {
+            assert_eq!(original, reported, "The target should fail as reported");
+        }
+        if preprocessed.is_none() && failure(None) == original {
+            continue;
+        }
+        let rows = if preprocessed.is_none() {
+            minimize_rows(all_rows, padding_row.is_some(), |rows| {
+                failure(Some(rows)) == original
+            })
+        } else {
+            all_rows
+        };
+        minimized
+            .main_traces
+            .insert(id.clone(), select_rows(main_trace, &rows, None));
+        minimized.rows.insert(id.clone(), rows);
+    }
+    minimized
+}
+
+#[cfg(feature = "air-logger")]
+impl<F: p3_field::PrimeField32> MinimizedWitness<F> {
+    /// Writes the padded traces of the chips with a trace to an xlsx file, with a worksheet per
+    /// chip, highlighting the failing entries of the constraints and interactions.
+    pub fn write_to_file<M, EF>(
+        &self,
+        machine: &M,
+        public_values: &[F],
+        perm_challenges: [EF; NUM_PERM_CHALLENGES],
+        path: &str,
+    ) -> Result<(), Box<dyn Error>>
+    where
+        M: Machine,
+        M::Chip: for<'a> Rap<TrackingConstraintBuilder<'a, F, EF>>
+            + for<'a> Rap<p3_air_util::folders::rap::DebugConstraintBuilder<'a, F, EF>>
+            + PeriodicAir<F>,
+        EF: ExtensionField<F>,
+    {
+        use p3_air_util::debug::rap::track_interactions;
+
+        use crate::trace::{write_workbook, WorksheetTrace};
+
+        let (ids, chips): (Vec<_>, Vec<_>) = machine.chips_by_id().into_iter().unzip();
+        let preprocessed = chips.iter().map(BaseAir::preprocessed_trace).collect_vec();
+        let main = chips
+            .iter()
+            .zip(ids.iter())
+            .map(|(chip, id)| {
+                let mut main_trace = self.main_traces.get(id)?.clone();
+                if let Some(padding_row) = chip.padding_row() {
+                    pad_to_power_of_two(&mut main_trace, &padding_row);
+                }
+                Some(main_trace)
+            })
+            .collect_vec();
+        let preprocessed_views = preprocessed
+            .iter()
+            .map(|trace| trace.as_ref().map(RowMajorMatrix::as_view))
+            .collect_vec();
+        let main_views = main
+            .iter()
+            .map(|trace| trace.as_ref().map(RowMajorMatrix::as_view))
+            .collect_vec();
+        let interaction_entries =
+            track_interactions::<F, EF, _>(&chips, &preprocessed_views, &main_views);
+
+        let traces = chips
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| preprocessed[i].is_some() || main[i].is_some())
+            .map(|(i, chip)| {
+                let mut entries = track(
+                    chip,
+                    preprocessed[i].as_ref(),
+                    main[i].as_ref(),
+                    public_values,
+                    perm_challenges,
+                );
+                entries.extend(&interaction_entries[i]);
+                WorksheetTrace {
+                    name: ids[i].clone(),
+                    chip,
+                    preprocessed: preprocessed[i].as_ref().map(RowMajorMatrix::as_view),
+                    main: main[i].as_ref().map(RowMajorMatrix::as_view),
+                    entries,
+                }
+            });
+        write_workbook::<_, EF, _>(path, traces)
+    }
+}
+
+/// Tracks the constraints of `chip`, with its permutation trace generated from `perm_challenges`.
+fn track<F, EF, C>(
+    chip: &C,
+    preprocessed: Option<&RowMajorMatrix<F>>,
+    main: Option<&RowMajorMatrix<F>>,
+    public_values: &[F],
+    perm_challenges: [EF; NUM_PERM_CHALLENGES],
+) -> EntriesLog<TraceEntry>
+where
+    F: Field,
+    EF: ExtensionField<F>,
+    C: for<'a> Rap<TrackingConstraintBuilder<'a, F, EF>> + PeriodicAir<F>,
+{
+    let preprocessed = preprocessed.map(RowMajorMatrix::as_view);
+    let main = main.map(RowMajorMatrix::as_view);
+    let permutation = generate_permutation_trace(
+        &preprocessed,
+        &main,
+        &InteractionAir::<F>::all_interactions(chip),
+        perm_challenges,
+    );
+    let cumulative_sum = permutation
+        .as_ref()
+        .map(|perm| *perm.row_slice(perm.height() - 1).last().unwrap());
+    track_constraints(
+        chip,
+        &preprocessed,
+        &main,
+        &permutation.as_ref().map(RowMajorMatrix::as_view),
+        perm_challenges,
+        cumulative_sum,
+        public_values,
+    )
+}
+
+fn failing_columns(entries: &EntriesLog<TraceEntry>) -> BTreeSet<ColumnEntry> {
+    entries.failing.iter().map(|&entry| entry.into()).collect()
+}
+
+/// The given rows of `trace`, padded with `padding_row` if there is one.
+fn select_rows<F: Clone + Send + Sync>(
+    trace: &RowMajorMatrix<F>,
+    rows: &[usize],
+    padding_row: Option<&[F]>,
+) -> RowMajorMatrix<F> {
+    let width = trace.width();
+    let values = rows
+        .iter()
+        .flat_map(|&row| trace.values[row * width..(row + 1) * width].iter().cloned())
+        .collect();
+    let mut selected = RowMajorMatrix::new(values, width);
+    if let Some(padding_row) = padding_row {
+        pad_to_power_of_two(&mut selected, padding_row);
+    }
+    selected
+}
+
+/// Removes rows while `fails` holds for the remaining ones, which are never empty. Chunks of rows
+/// are removed, halving their size when no chunk can be removed. Without padding, the number of
+/// rows stays a power of two, so windows of half the rows are kept instead.
+fn minimize_rows(
+    mut rows: Vec<usize>,
+    can_pad: bool,
+    fails: impl Fn(&[usize]) -> bool,
+) -> Vec<usize> {
+    if can_pad {
+        let mut chunk_size = (rows.len() / 2).max(1);
+        loop {
+            let mut start = 0;
+            while start < rows.len() {
+                let end = (start + chunk_size).min(rows.len());
+                let kept = [&rows[..start], &rows[end..]].concat();
+                if !kept.is_empty() && fails(&kept) {
+                    rows = kept;
+                } else {
+                    start = end;
+                }
+            }
+            if chunk_size == 1 {
+                break;
+            }
+            chunk_size /= 2;
+        }
+    } else {
+        while rows.len() > 1 {
+            let half = rows.len() / 2;
+            let step = (half / 2).max(1);
+            let Some(start) = (0..=rows.len() - half)
+                .step_by(step)
+                .find(|&start| fails(&rows[start..start + half]))
+            else {
+                break;
+            };
+            rows = rows[start..start + half].to_vec();
+        }
+    }
+    rows
+}
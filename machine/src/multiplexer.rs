@@ -0,0 +1,358 @@
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::{self, Display, Formatter};
+use core::ops::Range;
+
+use itertools::Itertools;
+use p3_air::{Air, AirBuilder, BaseAir, FilteredAirBuilder, VirtualPairCol};
+use p3_air_util::builders::SubRangeAirBuilder;
+use p3_air_util::PeriodicAir;
+use p3_field::{AbstractField, Field};
+use p3_interaction::{BaseInteractionAir, Interaction, InteractionAir, InteractionAirBuilder, Rap};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+
+use crate::chip::{Chip, ChipId};
+use crate::generation::TraceGenerator;
+
+/// Several chips sharing one trace. Each row belongs to at most one sub-chip, marked by its
+/// selector column, and the sub-chips' columns overlap after the selectors. Every interaction of a
+/// sub-chip gets its own count column, constrained to the selector times the sub-chip's count.
+///
+/// Rows of different sub-chips are interleaved, so the sub-chips must be `LocalAir`s. Sub-chips
+/// must have no preprocessed or periodic columns, and should implement `receives_from_indices` and
+/// `sends_from_indices`.
+#[derive(Clone, Debug)]
+pub struct MultiplexedChip<C> {
+    chips: Vec<C>,
+    widths: Vec<usize>,
+    num_interactions: Vec<usize>,
+}
+
+/// A chip whose constraints only involve the local row, never the next one. `MultiplexedChip`
+/// interleaves the rows of its sub-chips, so it only accepts chips implementing this promise.
+pub trait LocalAir {}
+
+/// The columns of a multiplexed trace.
+struct Layout {
+    selectors: Range<usize>,
+    shared: Range<usize>,
+    /// The count columns of each sub-chip's receives and then sends.
+    counts: Vec<Range<usize>>,
+    width: usize,
+}
+
+impl<C> MultiplexedChip<C> {
+    /// Multiplexes `chips`. The field is only used to count their interactions. Panics if a chip
+    /// has a preprocessed trace or periodic columns.
+    pub fn new<F: Field>(chips: Vec<C>) -> Self
+    where
+        C: BaseAir<F> + BaseInteractionAir<F> + PeriodicAir<F> + LocalAir,
+    {
+        assert!(!chips.is_empty(), "A multiplexed chip needs a sub-chip");
+        for (i, chip) in chips.iter().enumerate() {
+            assert!(
+                chip.preprocessed_trace().is_none(),
+                "Sub-chip {i} should have no preprocessed trace"
+            );
+            assert!(
+                chip.periodic_columns().is_empty(),
+                "Sub-chip {i} should have no periodic columns"
+            );
+        }
+        let widths = chips.iter().map(|chip| chip.width()).collect();
+        let num_interactions = chips
+            .iter()
+            .map(|chip| {
+                let (receives, sends) = local_interactions::<F, C>(chip);
+                receives.len() + sends.len()
+            })
+            .collect();
+        Self {
+            chips,
+            widths,
+            num_interactions,
+        }
+    }
+
+    pub fn chips(&self) -> &[C] {
+        &self.chips
+    }
+
+    fn layout(&self) -> Layout {
+        let selectors = 0..self.chips.len();
+        let shared_width = *self.widths.iter().max().unwrap();
+        let shared = selectors.end..selectors.end + shared_width;
+
+        let mut offset = shared.end;
+        let counts = self
+            .num_interactions
+            .iter()
+            .map(|&num_interactions| {
+                let range = offset..offset + num_interactions;
+                offset = range.end;
+                range
+            })
+            .collect();
+
+        Layout {
+            selectors,
+            shared,
+            counts,
+            width: offset,
+        }
+    }
+
+    /// Merges the sub-chips' main traces, given in the order of `chips`, into one trace. Sub-chips
+    /// without a trace have no rows.
+    pub fn merge_traces<F: Field>(&self, traces: &[Option<RowMajorMatrix<F>>]) -> RowMajorMatrix<F>
+    where
+        C: BaseAir<F> + BaseInteractionAir<F>,
+    {
+        assert_eq!(
+            traces.len(),
+            self.chips.len(),
+            "Expected a trace per sub-chip"
+        );
+        let layout = self.layout();
+
+        let mut values = vec![];
+        for (i, (chip, trace)) in self.chips.iter().zip_eq(traces.iter()).enumerate() {
+            let Some(trace) = trace else {
+                continue;
+            };
+            assert_eq!(trace.width(), chip.width(), "Sub-chip trace width mismatch");
+            let (receives, sends) = local_interactions::<F, C>(chip);
+            for row in trace.rows() {
+                let row = row.collect_vec();
+                let mut merged = vec![F::zero(); layout.width];
+                merged[layout.selectors.start + i] = F::one();
+                merged[layout.shared.start..layout.shared.start + row.len()].copy_from_slice(&row);
+                for (column, interaction) in
+                    layout.counts[i].clone().zip(receives.iter().chain(&sends))
+                {
+                    merged[column] = interaction.count.apply::<F, F>(&[], &row);
+                }
+                values.extend(merged);
+            }
+        }
+        RowMajorMatrix::new(values, layout.width)
+    }
+
+    /// Remaps a sub-chip's interactions to the given indices of the multiplexed trace, with their
+    /// counts replaced by the sub-chip's count columns.
+    fn remap<F: Field>(
+        &self,
+        layout: &Layout,
+        index: usize,
+        main_indices: &[usize],
+    ) -> (Vec<Interaction<F>>, Vec<Interaction<F>>)
+    where
+        C: BaseAir<F> + BaseInteractionAir<F>,
+    {
+        let chip = &self.chips[index];
+        let shared_indices =
+            &main_indices[layout.shared.start..layout.shared.start + self.widths[index]];
+        let receives = chip.receives_from_indices(&[], shared_indices);
+        let sends = chip.sends_from_indices(&[], shared_indices);
+
+        let mut counts = layout.counts[index]
+            .clone()
+            .map(|column| VirtualPairCol::single_main(main_indices[column]));
+        let mut gate = |interactions: Vec<Interaction<F>>| {
+            interactions
+                .into_iter()
+                .map(|interaction| Interaction {
+                    count: counts.next().unwrap(),
+                    ..interaction
+                })
+                .collect_vec()
+        };
+        let receives = gate(receives);
+        let sends = gate(sends);
+        (receives, sends)
+    }
+}
+
+/// A sub-chip's interactions over its own columns.
+fn local_interactions<F: Field, C: BaseAir<F> + BaseInteractionAir<F>>(
+    chip: &C,
+) -> (Vec<Interaction<F>>, Vec<Interaction<F>>) {
+    let indices = (0..chip.width()).collect_vec();
+    (
+        chip.receives_from_indices(&[], &indices),
+        chip.sends_from_indices(&[], &indices),
+    )
+}
+
+impl<C: Display> Display for MultiplexedChip<C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Multiplexed({})", self.chips.iter().join(", "))
+    }
+}
+
+impl<F: Field, C: BaseAir<F> + BaseInteractionAir<F>> BaseAir<F> for MultiplexedChip<C> {
+    fn width(&self) -> usize {
+        self.layout().width
+    }
+}
+
+impl<AB, C> Air<AB> for MultiplexedChip<C>
+where
+    AB: AirBuilder,
+    C: BaseAir<AB::F>
+        + BaseInteractionAir<AB::F>
+        + for<'a, 'b> Air<SubRangeAirBuilder<'a, FilteredAirBuilder<'b, AB>>>,
+{
+    fn eval(&self, builder: &mut AB) {
+        let layout = self.layout();
+        let main = builder.main();
+        let local = main.row_slice(0).to_vec();
+
+        let selectors = &local[layout.selectors.clone()];
+        let mut is_real = AB::Expr::zero();
+        for &selector in selectors {
+            builder.assert_bool(selector);
+            is_real += selector.into();
+        }
+        builder.assert_bool(is_real);
+
+        for (i, chip) in self.chips.iter().enumerate() {
+            let selector = selectors[i];
+            let shared = layout.shared.start..layout.shared.start + self.widths[i];
+
+            let (receives, sends) = local_interactions::<AB::F, C>(chip);
+            for (column, interaction) in layout.counts[i].clone().zip(receives.iter().chain(&sends))
+            {
+                let count = interaction
+                    .count
+                    .apply::<AB::Expr, AB::Var>(&[], &local[shared.clone()]);
+                builder.assert_eq(local[column], selector * count);
+            }
+
+            chip.eval(&mut SubRangeAirBuilder::new_main(
+                &mut builder.when(selector),
+                shared,
+            ));
+        }
+    }
+}
+
+impl<F: Field, C: BaseAir<F> + BaseInteractionAir<F>> BaseInteractionAir<F> for MultiplexedChip<C> {
+    fn receives_from_indices(
+        &self,
+        _preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        let layout = self.layout();
+        (0..self.chips.len())
+            .flat_map(|i| self.remap(&layout, i, main_indices).0)
+            .collect()
+    }
+
+    fn sends_from_indices(
+        &self,
+        _preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        let layout = self.layout();
+        (0..self.chips.len())
+            .flat_map(|i| self.remap(&layout, i, main_indices).1)
+            .collect()
+    }
+}
+
+impl<F: Field, C: BaseAir<F> + BaseInteractionAir<F>> InteractionAir<F> for MultiplexedChip<C> {
+    fn receives(&self) -> Vec<Interaction<F>> {
+        let indices = (0..self.width()).collect_vec();
+        self.receives_from_main_indices(&indices)
+    }
+
+    fn sends(&self) -> Vec<Interaction<F>> {
+        let indices = (0..self.width()).collect_vec();
+        self.sends_from_main_indices(&indices)
+    }
+}
+
+impl<AB, C> Rap<AB> for MultiplexedChip<C>
+where
+    AB: InteractionAirBuilder,
+    C: BaseAir<AB::F>
+        + BaseInteractionAir<AB::F>
+        + for<'a, 'b> Air<SubRangeAirBuilder<'a, FilteredAirBuilder<'b, AB>>>,
+{
+}
+
+impl<F: Field, C> PeriodicAir<F> for MultiplexedChip<C> {}
+
+#[cfg(feature = "air-logger")]
+impl<C: p3_air_util::AirLogger + Display> p3_air_util::AirLogger for MultiplexedChip<C> {
+    fn main_headers(&self) -> Vec<alloc::string::String> {
+        let layout = self.layout();
+        let chip_headers = self
+            .chips
+            .iter()
+            .map(|chip| chip.main_headers())
+            .collect_vec();
+
+        let selectors = self.chips.iter().map(|chip| alloc::format!("is_{chip}"));
+        let shared = layout.shared.clone().map(|column| {
+            chip_headers
+                .iter()
+                .filter_map(|headers| headers.get(column - layout.shared.start))
+                .join("|")
+        });
+        let counts = self
+            .chips
+            .iter()
+            .zip_eq(layout.counts.iter())
+            .flat_map(|(chip, counts)| {
+                (0..counts.len()).map(move |k| alloc::format!("{chip}.count[{k}]"))
+            });
+        selectors.chain(shared).chain(counts).collect()
+    }
+
+    #[cfg(feature = "schema")]
+    fn main_headers_and_types(
+        &self,
+    ) -> Vec<(alloc::string::String, alloc::string::String, Range<usize>)> {
+        self.main_headers()
+            .into_iter()
+            .enumerate()
+            .map(|(i, header)| (header, "Field".into(), i..i + 1))
+            .collect()
+    }
+}
+
+impl<C: Chip> Chip for MultiplexedChip<C> {
+    fn padding_row<F: Field>(&self) -> Option<Vec<F>> {
+        Some(vec![F::zero(); self.layout().width])
+    }
+}
+
+impl<F: Field, R, C> TraceGenerator<F, R> for MultiplexedChip<C>
+where
+    C: BaseAir<F> + BaseInteractionAir<F> + TraceGenerator<F, R>,
+{
+    fn dependencies(&self) -> Vec<ChipId> {
+        self.chips
+            .iter()
+            .flat_map(|chip| chip.dependencies())
+            .unique()
+            .collect()
+    }
+
+    fn generate_trace(
+        &self,
+        record: &R,
+        traces: &BTreeMap<ChipId, RowMajorMatrix<F>>,
+    ) -> Option<RowMajorMatrix<F>> {
+        let chip_traces = self
+            .chips
+            .iter()
+            .map(|chip| chip.generate_trace(record, traces))
+            .collect_vec();
+        Some(self.merge_traces(&chip_traces))
+    }
+}
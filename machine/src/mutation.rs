@@ -0,0 +1,291 @@
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use itertools::Itertools;
+use p3_air::BaseAir;
+use p3_air_util::debug::rap::{check_constraints, check_cumulative_sums};
+use p3_air_util::folders::rap::DebugConstraintBuilder;
+use p3_air_util::util::TraceEntry;
+use p3_air_util::{pad_to_power_of_two, PeriodicAir};
+use p3_field::{AbstractField, ExtensionField, Field};
+use p3_interaction::{generate_permutation_trace, Bus, InteractionAir, Rap, NUM_PERM_CHALLENGES};
+use p3_matrix::dense::{RowMajorMatrix, RowMajorMatrixView};
+use p3_matrix::Matrix;
+use p3_maybe_rayon::prelude::{IntoParallelIterator, ParallelIterator};
+use rand::distributions::{Distribution, Standard};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::chip::{Chip, ChipId};
+use crate::machine::Machine;
+use crate::silent::catch_unwind_silently;
+
+/// Which cells of a trace are mutated, and to what.
+#[derive(Clone, Copy, Debug)]
+pub enum MutationStrategy {
+    /// Every cell is set to zero, to one, and to its successor and predecessor, whichever of them
+    /// differ from its value.
+    Exhaustive,
+    /// `num_mutations` random cells are set to random values.
+    Random { num_mutations: usize },
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct MutationOptions {
+    pub strategy: MutationStrategy,
+    /// Whether the cells of the preprocessed trace are mutated too.
+    pub mutate_preprocessed: bool,
+    /// Seeds the random mutations and the permutation challenges.
+    pub seed: u64,
+}
+
+impl Default for MutationOptions {
+    fn default() -> Self {
+        Self {
+            strategy: MutationStrategy::Exhaustive,
+            mutate_preprocessed: false,
+            seed: 0,
+        }
+    }
+}
+
+/// A mutation of a single cell of a chip's trace.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mutation<F> {
+    pub chip: ChipId,
+    /// The mutated cell, either a `Preprocessed` or a `Main` entry.
+    pub entry: TraceEntry,
+    /// The header of the mutated column.
+    pub header: String,
+    pub value: F,
+}
+
+/// Mutates single cells of the traces of the `target` chip of `machine`, and returns the mutations
+/// that still satisfy the constraints of every chip and balance every bus. Surviving mutations are
+/// evidence that the chip is under-constrained.
+///
+/// The main traces are padded like the prover pads them, and should satisfy the constraints before
+/// they are mutated. The mutated witnesses are checked in parallel with `check_constraints` and
+/// `check_cumulative_sums`, and a failing check is caught as a panic.
+pub fn find_surviving_mutations<M, F, EF>(
+    machine: &M,
+    target: &ChipId,
+    main_traces: &BTreeMap<ChipId, RowMajorMatrix<F>>,
+    public_values: &[F],
+    options: &MutationOptions,
+) -> Vec<Mutation<F>>
+where
+    M: Machine,
+    M::Chip: for<'a> Rap<DebugConstraintBuilder<'a, F, EF>> + PeriodicAir<F> + Sync,
+    F: Field,
+    EF: ExtensionField<F>,
+    Standard: Distribution<F> + Distribution<EF>,
+{
+    let chips = machine.chips_by_id();
+    let target_index = chips
+        .keys()
+        .position(|id| id == target)
+        .expect("The target should be one of the machine's chips");
+    let (ids, chips): (Vec<_>, Vec<_>) = chips.into_iter().unzip();
+
+    let preprocessed = chips.iter().map(BaseAir::preprocessed_trace).collect_vec();
+    let main = chips
+        .iter()
+        .zip(ids.iter())
+        .map(|(chip, id)| {
+            let mut main_trace = main_traces.get(id)?.clone();
+            if let Some(padding_row) = chip.padding_row() {
+                pad_to_power_of_two(&mut main_trace, &padding_row);
+            }
+            Some(main_trace)
+        })
+        .collect_vec();
+    let trace_of = |entry: TraceEntry| match entry {
+        TraceEntry::Preprocessed { row, col } => (preprocessed[target_index].as_ref(), row, col),
+        TraceEntry::Main { row, col } => (main[target_index].as_ref(), row, col),
+        _ => unreachable!("Only preprocessed and main cells are mutated"),
+    };
+
+    let mut rng = StdRng::seed_from_u64(options.seed);
+    let perm_challenges: [EF; NUM_PERM_CHALLENGES] = core::array::from_fn(|_| rng.gen());
+
+    let check = |mutation: Option<(TraceEntry, F)>| {
+        let mutated = mutation.map(|(entry, value)| {
+            let (trace, row, col) = trace_of(entry);
+            let mut trace = trace.unwrap().clone();
+            let width = trace.width();
+            trace.values[row * width + col] = value;
+            (entry, trace)
+        });
+        let mut preprocessed_views = preprocessed
+            .iter()
+            .map(|trace| trace.as_ref().map(RowMajorMatrix::as_view))
+            .collect_vec();
+        let mut main_views = main
+            .iter()
+            .map(|trace| trace.as_ref().map(RowMajorMatrix::as_view))
+            .collect_vec();
+        match &mutated {
+            Some((TraceEntry::Preprocessed { .. }, trace)) => {
+                preprocessed_views[target_index] = Some(trace.as_view());
+            }
+            Some((_, trace)) => main_views[target_index] = Some(trace.as_view()),
+            None => {}
+        }
+        check_witness::<F, EF, M::Chip, M::Bus>(
+            &chips,
+            &preprocessed_views,
+            &main_views,
+            public_values,
+            perm_challenges,
+        );
+    };
+
+    // The unmutated witness is checked outside of `catch_unwind_silently`, so that its failure is
+    // reported.
+    check(None);
+
+    let mut cells = cells_of(main[target_index].as_ref(), |row, col| TraceEntry::Main {
+        row,
+        col,
+    });
+    if options.mutate_preprocessed {
+        cells.extend(cells_of(preprocessed[target_index].as_ref(), |row, col| {
+            TraceEntry::Preprocessed { row, col }
+        }));
+    }
+    let value_of = |entry: TraceEntry| {
+        let (trace, row, col) = trace_of(entry);
+        trace.unwrap().get(row, col)
+    };
+    let mutations = match options.strategy {
+        MutationStrategy::Exhaustive => cells
+            .into_iter()
+            .flat_map(|entry| {
+                let value = value_of(entry);
+                let mut values = vec![];
+                for mutated in [F::zero(), F::one(), value + F::one(), value - F::one()] {
+                    if mutated != value && !values.contains(&mutated) {
+                        values.push(mutated);
+                    }
+                }
+                values.into_iter().map(move |mutated| (entry, mutated))
+            })
+            .collect_vec(),
+        MutationStrategy::Random { num_mutations } if !cells.is_empty() => (0..num_mutations)
+            .filter_map(|_| {
+                let entry = cells[rng.gen_range(0..cells.len())];
+                let value: F = rng.gen();
+                (value != value_of(entry)).then_some((entry, value))
+            })
+            .collect_vec(),
+        MutationStrategy::Random { .. } => vec![],
+    };
+
+    // Failing checks panic, so their messages are silenced while the mutations are checked.
+    let surviving: Vec<_> = mutations
+        .into_par_iter()
+        .filter(|&mutation| catch_unwind_silently(|| check(Some(mutation))).is_ok())
+        .collect();
+
+    let chip = &chips[target_index];
+    surviving
+        .into_iter()
+        .map(|(entry, value)| Mutation {
+            chip: target.clone(),
+            entry,
+            header: header(chip, entry),
+            value,
+        })
+        .collect()
+}
+
+/// Checks the constraints of every chip and that every bus balances, panicking otherwise.
+fn check_witness<F, EF, C, B>(
+    chips: &[C],
+    preprocessed: &[Option<RowMajorMatrixView<F>>],
+    main: &[Option<RowMajorMatrixView<F>>],
+    public_values: &[F],
+    perm_challenges: [EF; NUM_PERM_CHALLENGES],
+) where
+    F: Field,
+    EF: ExtensionField<F>,
+    C: for<'a> Rap<DebugConstraintBuilder<'a, F, EF>> + PeriodicAir<F>,
+    B: Bus,
+{
+    let permutation = chips
+        .iter()
+        .zip_eq(preprocessed.iter().zip_eq(main.iter()))
+        .map(|(chip, (preprocessed, main))| {
+            generate_permutation_trace(
+                preprocessed,
+                main,
+                &InteractionAir::<F>::all_interactions(chip),
+                perm_challenges,
+            )
+        })
+        .collect_vec();
+    let permutation = permutation
+        .iter()
+        .map(|trace| trace.as_ref().map(RowMajorMatrix::as_view))
+        .collect_vec();
+
+    for (i, chip) in chips.iter().enumerate() {
+        let cumulative_sum = permutation[i]
+            .as_ref()
+            .map(|perm| *perm.row_slice(perm.height() - 1).last().unwrap());
+        check_constraints(
+            chip,
+            &preprocessed[i],
+            &main[i],
+            &permutation[i],
+            perm_challenges,
+            cumulative_sum,
+            public_values,
+        );
+    }
+    check_cumulative_sums::<F, EF, C, B>(chips, preprocessed, main, &permutation);
+}
+
+/// The cells of `trace`, in row-major order.
+fn cells_of<F, T>(trace: Option<&RowMajorMatrix<F>>, entry: impl Fn(usize, usize) -> T) -> Vec<T>
+where
+    F: Clone + Send + Sync,
+{
+    let Some(trace) = trace else {
+        return vec![];
+    };
+    (0..trace.height())
+        .cartesian_product(0..trace.width())
+        .map(|(row, col)| entry(row, col))
+        .collect()
+}
+
+#[cfg(feature = "air-logger")]
+fn header<C: Chip>(chip: &C, entry: TraceEntry) -> String {
+    let (headers, col) = match entry {
+        TraceEntry::Preprocessed { col, .. } => (chip.preprocessed_headers(), col),
+        TraceEntry::Main { col, .. } => (chip.main_headers(), col),
+        _ => unreachable!("Only preprocessed and main cells are mutated"),
+    };
+    headers
+        .get(col)
+        .cloned()
+        .unwrap_or_else(|| fallback_header(entry))
+}
+
+#[cfg(not(feature = "air-logger"))]
+fn header<C: Chip>(_chip: &C, entry: TraceEntry) -> String {
+    fallback_header(entry)
+}
+
+fn fallback_header(entry: TraceEntry) -> String {
+    match entry {
+        TraceEntry::Preprocessed { col, .. } => format!("preprocessed[{col}]"),
+        TraceEntry::Main { col, .. } => format!("main[{col}]"),
+        _ => unreachable!("Only preprocessed and main cells are mutated"),
+    }
+}
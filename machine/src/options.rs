@@ -0,0 +1,78 @@
+#[cfg(feature = "std")]
+use alloc::string::String;
+#[cfg(feature = "std")]
+use alloc::vec::Vec;
+
+#[cfg(feature = "std")]
+use crate::chip::ChipId;
+
+/// How the prover evaluates and checks the traces.
+#[derive(Clone, Debug)]
+pub struct ProverOptions {
+    /// Whether the constraints of every chip, and that the buses of each instance balance, are
+    /// checked before committing to the permutation traces. Failing checks panic. Defaults to
+    /// whether debug assertions are enabled.
+    pub check_constraints: bool,
+    /// Whether the quotients are evaluated by compiling each chip's constraints into a program
+    /// once, instead of running the chip's `eval` on every packed row. The results are the same.
+    /// Defaults to whether the `bytecode` feature is enabled.
+    pub use_bytecode: bool,
+    /// Whether the main columns that are not constrained in any row are reported as warnings.
+    /// Defaults to false.
+    #[cfg(feature = "air-logger")]
+    pub find_unconstrained_columns: bool,
+    /// Where and when the traces are dumped. Defaults to never.
+    #[cfg(feature = "std")]
+    pub dump: Option<TraceDump>,
+}
+
+impl Default for ProverOptions {
+    fn default() -> Self {
+        Self {
+            check_constraints: cfg!(debug_assertions),
+            use_bytecode: cfg!(feature = "bytecode"),
+            #[cfg(feature = "air-logger")]
+            find_unconstrained_columns: false,
+            #[cfg(feature = "std")]
+            dump: None,
+        }
+    }
+}
+
+/// A dump of the traces the prover checks, written once the permutation traces are generated.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug)]
+pub struct TraceDump {
+    pub path: String,
+    pub format: TraceFormat,
+    /// The chips whose traces are dumped. Defaults to every chip.
+    pub chips: Option<Vec<ChipId>>,
+    /// Whether the traces are only dumped when the constraint checks fail, in which case nothing
+    /// is dumped unless `check_constraints` is set.
+    pub only_on_failure: bool,
+}
+
+#[cfg(feature = "std")]
+impl TraceDump {
+    /// A dump of every chip's traces to `path`, whether or not the checks fail.
+    pub fn new(path: impl Into<String>, format: TraceFormat) -> Self {
+        Self {
+            path: path.into(),
+            format,
+            chips: None,
+            only_on_failure: false,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraceFormat {
+    /// An xlsx workbook with a worksheet per chip trace, highlighting the failing entries of the
+    /// constraints and interactions.
+    #[cfg(feature = "air-logger")]
+    Xlsx,
+    /// A `TraceSnapshot`, from which the checks can be replayed. Snapshots of some of the chips
+    /// can only replay their constraints, as the buses do not balance without the other chips.
+    Snapshot,
+}
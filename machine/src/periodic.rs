@@ -0,0 +1,114 @@
+use alloc::vec::Vec;
+
+use itertools::Itertools;
+use p3_commit::PolynomialSpace;
+use p3_field::{batch_multiplicative_inverse, AbstractField, ExtensionField, Field};
+use p3_util::log2_strict_usize;
+
+/// Evaluates each periodic column at `point`, where the columns repeat down a trace over
+/// `trace_domain`.
+pub fn periodic_values_at_point<D, EF>(
+    columns: &[Vec<D::Val>],
+    trace_domain: D,
+    point: EF,
+) -> Vec<EF>
+where
+    D: PolynomialSpace,
+    EF: ExtensionField<D::Val>,
+{
+    let unshifted_point = point * trace_domain.first_point().inverse();
+    columns
+        .iter()
+        .map(|column| {
+            let (period_generator, log_repetitions) = period_parameters(column, trace_domain);
+            let y = unshifted_point.exp_power_of_2(log_repetitions);
+            eval_periodic_column(column, period_generator, y)
+        })
+        .collect()
+}
+
+/// Evaluates each periodic column over `coset`. Since the columns repeat, only one period of the
+/// evaluations is returned; the value at the `i`th point of `coset` is at `i % len`.
+///
+/// This costs `O(period^2 * blowup)` field operations per column, so periodic columns are meant
+/// to be short.
+pub fn periodic_values_on_coset<D>(
+    columns: &[Vec<D::Val>],
+    trace_domain: D,
+    coset: D,
+) -> Vec<Vec<D::Val>>
+where
+    D: PolynomialSpace,
+{
+    let coset_size = coset.size();
+    let coset_generator = coset
+        .next_point(D::Val::one())
+        .expect("coset should have a generator");
+    let unshifted_start = coset.first_point() * trace_domain.first_point().inverse();
+    columns
+        .iter()
+        .map(|column| {
+            let (period_generator, log_repetitions) = period_parameters(column, trace_domain);
+            let start = unshifted_start.exp_power_of_2(log_repetitions);
+            let step = coset_generator.exp_power_of_2(log_repetitions);
+            let num_values = (coset_size >> log_repetitions).max(1);
+            step.shifted_powers(start)
+                .take(num_values)
+                .map(|y| eval_periodic_column(column, period_generator, y))
+                .collect()
+        })
+        .collect()
+}
+
+/// Returns the generator of the subgroup a periodic column is interpolated over, and the log of
+/// the number of times the column repeats down the trace.
+fn period_parameters<D: PolynomialSpace>(column: &[D::Val], trace_domain: D) -> (D::Val, usize) {
+    let period = column.len();
+    let trace_size = trace_domain.size();
+    assert!(
+        period.is_power_of_two() && period <= trace_size,
+        "periodic column length {} must be a power of two no larger than the trace height {}",
+        period,
+        trace_size,
+    );
+    let log_repetitions = log2_strict_usize(trace_size / period);
+    let trace_generator = trace_domain
+        .next_point(D::Val::one())
+        .expect("trace domain should have a generator");
+    (
+        trace_generator.exp_power_of_2(log_repetitions),
+        log_repetitions,
+    )
+}
+
+/// Evaluates the polynomial taking `values[j]` at `generator^j` at the point `y`.
+fn eval_periodic_column<F, EF>(values: &[F], generator: F, y: EF) -> EF
+where
+    F: Field,
+    EF: ExtensionField<F>,
+{
+    let period = values.len();
+    let points = generator.powers().take(period).collect_vec();
+    let zerofier = y.exp_power_of_2(log2_strict_usize(period)) - EF::one();
+    if zerofier.is_zero() {
+        let index = points
+            .iter()
+            .position(|&point| y == EF::from_base(point))
+            .expect("point should be in the subgroup");
+        return EF::from_base(values[index]);
+    }
+
+    // L_j(y) = g^j (y^p - 1) / (p (y - g^j))
+    let denominators = points
+        .iter()
+        .map(|&point| y - EF::from_base(point))
+        .collect_vec();
+    let inverses = batch_multiplicative_inverse(&denominators);
+    let sum = values
+        .iter()
+        .zip(points.iter())
+        .zip(inverses)
+        .map(|((&value, &point), inverse)| inverse * (value * point))
+        .sum::<EF>();
+    sum * zerofier * F::from_canonical_usize(period).inverse()
+}
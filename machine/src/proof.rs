@@ -1,3 +1,4 @@
+use alloc::collections::BTreeMap;
 use alloc::vec::Vec;
 
 use p3_commit::Pcs;
@@ -8,6 +9,8 @@ use serde::{Deserialize, Serialize};
 
 use p3_air_util::proof::{Commitments, InteractionAirProof};
 
+use crate::chip::ChipId;
+
 pub type Com<SC> = <<SC as StarkGenericConfig>::Pcs as Pcs<
     <SC as StarkGenericConfig>::Challenge,
     <SC as StarkGenericConfig>::Challenger,
@@ -27,10 +30,30 @@ pub type PcsProverData<SC> = <<SC as StarkGenericConfig>::Pcs as Pcs<
 pub struct MachineProof<SC: StarkGenericConfig> {
     pub commitments: Commitments<Com<SC>>,
     pub opening_proof: PcsProof<SC>,
-    pub chip_proofs: Vec<Option<InteractionAirProof<SC::Challenge>>>,
+    /// The proofs of each chip's shards.
+    pub chip_proofs: BTreeMap<ChipId, Vec<Option<InteractionAirProof<SC::Challenge>>>>,
+}
+
+/// A proof of several instances of a machine, sharing commitments and a single opening proof.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(bound = "SC::Challenge: Serialize + DeserializeOwned")]
+pub struct BatchMachineProof<SC: StarkGenericConfig> {
+    pub commitments: Commitments<Com<SC>>,
+    pub opening_proof: PcsProof<SC>,
+    /// The proofs of each chip's shards, for each instance.
+    pub chip_proofs: Vec<BTreeMap<ChipId, Vec<Option<InteractionAirProof<SC::Challenge>>>>>,
+}
+
+/// The proof of one segment of a continuation, with the public values it was proven against.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(bound = "SC::Challenge: Serialize + DeserializeOwned")]
+pub struct SegmentProof<SC: StarkGenericConfig> {
+    pub proof: MachineProof<SC>,
+    pub public_values: Vec<Val<SC>>,
 }
 
 pub struct ProverPreprocessedData<SC: StarkGenericConfig> {
+    /// The preprocessed trace of each chip, in the order of the chip ids.
     pub traces: Vec<Option<RowMajorMatrix<Val<SC>>>>,
     pub data: Option<PcsProverData<SC>>,
     pub commitment: Option<Com<SC>>,
@@ -39,15 +62,19 @@ pub struct ProverPreprocessedData<SC: StarkGenericConfig> {
 #[derive(Serialize, Deserialize)]
 pub struct VerifierPreprocessedData<SC: StarkGenericConfig> {
     pub commitment: Com<SC>,
-    // Index, degree
-    pub degrees: Vec<(usize, usize)>,
+    /// The degree of each chip with a preprocessed trace.
+    pub degrees: BTreeMap<ChipId, usize>,
 }
 
 pub struct ProvingKey<SC: StarkGenericConfig> {
     pub preprocessed: ProverPreprocessedData<SC>,
+    /// The main commitment group of each chip.
+    pub main_commitment_groups: BTreeMap<ChipId, usize>,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct VerifyingKey<SC: StarkGenericConfig> {
     pub preprocessed: Option<VerifierPreprocessedData<SC>>,
+    /// The main commitment group of each chip.
+    pub main_commitment_groups: BTreeMap<ChipId, usize>,
 }
@@ -1,3 +1,4 @@
+use alloc::vec;
 use alloc::vec::Vec;
 
 use itertools::Itertools;
@@ -9,19 +10,24 @@ use p3_maybe_rayon::prelude::{IntoParallelIterator, ParIterExt};
 use p3_uni_stark::{Domain, PackedChallenge, PackedVal, StarkGenericConfig, Val};
 use p3_util::log2_strict_usize;
 
-use p3_air_util::folders::rap::ProverConstraintFolder;
+use p3_air_util::{folders::rap::ProverConstraintFolder, ConstraintProgram, RowSelector};
 
 pub fn quotient_values<SC, A, Mat>(
     air: &A,
     trace_domain: Domain<SC>,
     quotient_domain: Domain<SC>,
-    preprocessed_trace_on_quotient_domain: Mat,
-    main_trace_on_quotient_domain: Mat,
-    perm_trace_on_quotient_domain: Mat,
+    preprocessed_trace_on_quotient_domain: Option<Mat>,
+    main_trace_on_quotient_domain: Option<Mat>,
+    perm_trace_on_quotient_domain: Option<Mat>,
+    periodic_values_on_quotient_domain: &[Vec<Val<SC>>],
+    row_selectors: &[RowSelector],
+    row_selectors_on_quotient_domain: &[Vec<Val<SC>>],
     perm_challenges: [PackedChallenge<SC>; NUM_PERM_CHALLENGES],
-    alpha: PackedChallenge<SC>,
+    alpha: SC::Challenge,
+    num_constraints: usize,
     cumulative_sum: PackedChallenge<SC>,
     public_values: &[Val<SC>],
+    program: Option<&ConstraintProgram<Val<SC>>>,
 ) -> Vec<SC::Challenge>
 where
     SC: StarkGenericConfig,
@@ -29,12 +35,22 @@ where
     Mat: Matrix<Val<SC>> + Sync,
 {
     let quotient_size = quotient_domain.size();
-    let perm_width = perm_trace_on_quotient_domain.width();
     let mut sels = trace_domain.selectors_on_coset(quotient_domain);
 
     let qdb = log2_strict_usize(quotient_domain.size()) - log2_strict_usize(trace_domain.size());
     let next_step = 1 << qdb;
 
+    let mut alpha_powers = alpha.powers().take(num_constraints).collect_vec();
+    alpha_powers.reverse();
+    let decomposed_alpha_powers = (0..<SC::Challenge as AbstractExtensionField<Val<SC>>>::D)
+        .map(|i| {
+            alpha_powers
+                .iter()
+                .map(|alpha_power| alpha_power.as_base_slice()[i])
+                .collect_vec()
+        })
+        .collect_vec();
+
     // assert!(quotient_size >= PackedVal::<SC>::WIDTH);
     // We take PackedVal::<SC>::WIDTH worth of values at a time from a quotient_size slice, so we need to
     // pad with default values in the case where quotient_size is smaller than PackedVal::<SC>::WIDTH.
@@ -58,44 +74,49 @@ where
             let inv_zeroifier = *PackedVal::<SC>::from_slice(&sels.inv_zeroifier[i_range.clone()]);
 
             // TODO: Any way to do it without collect?
-            let preprocessed_local = preprocessed_trace_on_quotient_domain
-                .vertically_packed_row(i_start)
-                .collect_vec();
-            let preprocessed_next = preprocessed_trace_on_quotient_domain
-                .vertically_packed_row(i_start + next_step)
-                .collect_vec();
+            let packed_row = |trace: &Option<Mat>, row| {
+                trace
+                    .as_ref()
+                    .map(|trace| trace.vertically_packed_row(row).collect_vec())
+                    .unwrap_or_default()
+            };
+            let preprocessed_local = packed_row(&preprocessed_trace_on_quotient_domain, i_start);
+            let preprocessed_next =
+                packed_row(&preprocessed_trace_on_quotient_domain, i_start + next_step);
 
-            let main_local = main_trace_on_quotient_domain
-                .vertically_packed_row(i_start)
-                .collect_vec();
-            let main_next = main_trace_on_quotient_domain
-                .vertically_packed_row(i_start + next_step)
-                .collect_vec();
+            let main_local = packed_row(&main_trace_on_quotient_domain, i_start);
+            let main_next = packed_row(&main_trace_on_quotient_domain, i_start + next_step);
 
             // TODO: Use vertically_packed
-            let perm_local = (0..perm_width)
-                .step_by(SC::Challenge::D)
-                .map(|col| {
-                    PackedChallenge::<SC>::from_base_fn(|i| {
-                        PackedVal::<SC>::from_fn(|offset| {
-                            perm_trace_on_quotient_domain.get(wrap(i_start + offset), col + i)
-                        })
+            let packed_ext_row = |row| {
+                perm_trace_on_quotient_domain
+                    .as_ref()
+                    .map(|perm| {
+                        (0..perm.width())
+                            .step_by(SC::Challenge::D)
+                            .map(|col| {
+                                PackedChallenge::<SC>::from_base_fn(|i| {
+                                    PackedVal::<SC>::from_fn(|offset| {
+                                        perm.get(wrap(row + offset), col + i)
+                                    })
+                                })
+                            })
+                            .collect_vec()
                     })
-                })
+                    .unwrap_or_default()
+            };
+            let perm_local = packed_ext_row(i_start);
+            let perm_next = packed_ext_row(i_start + next_step);
+
+            let periodic_values = periodic_values_on_quotient_domain
+                .iter()
+                .map(|col| PackedVal::<SC>::from_fn(|offset| col[(i_start + offset) % col.len()]))
                 .collect_vec();
-            let perm_next = (0..perm_width)
-                .step_by(SC::Challenge::D)
-                .map(|col| {
-                    PackedChallenge::<SC>::from_base_fn(|i| {
-                        PackedVal::<SC>::from_fn(|offset| {
-                            perm_trace_on_quotient_domain
-                                .get(wrap(i_start + next_step + offset), col + i)
-                        })
-                    })
-                })
+            let row_selector_values = row_selectors_on_quotient_domain
+                .iter()
+                .map(|col| PackedVal::<SC>::from_fn(|offset| col[(i_start + offset) % col.len()]))
                 .collect_vec();
 
-            let accumulator = PackedChallenge::<SC>::zero();
             let mut folder = ProverConstraintFolder {
                 preprocessed: VerticalPair::new(
                     RowMajorMatrixView::new_row(&preprocessed_local),
@@ -111,17 +132,27 @@ where
                 ),
                 perm_challenges,
                 public_values,
+                periodic_values: &periodic_values,
+                row_selectors,
+                row_selector_values: &row_selector_values,
                 cumulative_sum,
                 is_first_row,
                 is_last_row,
                 is_transition,
-                alpha,
-                accumulator,
+                alpha_powers: &alpha_powers,
+                decomposed_alpha_powers: &decomposed_alpha_powers,
+                base_accumulators: vec![PackedVal::<SC>::zero(); decomposed_alpha_powers.len()],
+                ext_accumulator: PackedChallenge::<SC>::zero(),
+                constraint_index: 0,
             };
-            air.eval_all(&mut folder);
+            match program {
+                Some(program) => folder.eval_program(program),
+                None => air.eval_all(&mut folder),
+            }
+            debug_assert_eq!(folder.constraint_index, num_constraints);
 
             // quotient(x) = constraints(x) / Z_H(x)
-            let quotient = folder.accumulator * inv_zeroifier;
+            let quotient = folder.accumulator() * inv_zeroifier;
 
             // "Transpose" D packed base coefficients into WIDTH scalar extension coefficients.
             let width = core::cmp::min(PackedVal::<SC>::WIDTH, quotient_size);
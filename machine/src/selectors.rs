@@ -0,0 +1,217 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::iter;
+
+use itertools::Itertools;
+use p3_air_util::RowSelector;
+use p3_commit::PolynomialSpace;
+use p3_field::{batch_multiplicative_inverse, AbstractField, ExtensionField, Field};
+
+use crate::periodic::{periodic_values_at_point, periodic_values_on_coset};
+
+/// Evaluates each row selector at `point` for a trace over `trace_domain`.
+pub fn row_selectors_at_point<D, EF>(
+    selectors: &[RowSelector],
+    trace_domain: D,
+    point: EF,
+) -> Vec<EF>
+where
+    D: PolynomialSpace,
+    EF: ExtensionField<D::Val>,
+{
+    let trace_size = trace_domain.size();
+    let trace_generator = trace_generator(trace_domain);
+    let unshifted_point = point * trace_domain.first_point().inverse();
+    selectors
+        .iter()
+        .map(|selector| match selector {
+            RowSelector::EveryNthRow { period, offset } => {
+                let column = every_nth_row_column(*period, *offset);
+                periodic_values_at_point(&[column], trace_domain, point)[0]
+            }
+            _ => eval_rows_selector(
+                &selector.rows(trace_size).collect_vec(),
+                trace_size,
+                trace_generator,
+                unshifted_point,
+            ),
+        })
+        .collect()
+}
+
+/// Evaluates each row selector over `coset`. As with periodic columns, the value at the `i`th point
+/// of `coset` is at `i % len`, since the evaluations of `EveryNthRow` selectors repeat.
+///
+/// `Row` and `Rows` selectors cost `O(coset size)` field operations each when `coset` is a
+/// disjoint domain of `trace_domain`, whatever the number of rows they select.
+pub fn row_selectors_on_coset<D>(
+    selectors: &[RowSelector],
+    trace_domain: D,
+    coset: D,
+) -> Vec<Vec<D::Val>>
+where
+    D: PolynomialSpace,
+{
+    let trace_size = trace_domain.size();
+    let trace_generator = trace_generator(trace_domain);
+    let coset_generator = coset
+        .next_point(D::Val::one())
+        .expect("coset should have a generator");
+    let unshifted_start = coset.first_point() * trace_domain.first_point().inverse();
+    selectors
+        .iter()
+        .map(|selector| match selector {
+            RowSelector::EveryNthRow { period, offset } => {
+                let column = every_nth_row_column(*period, *offset);
+                periodic_values_on_coset(&[column], trace_domain, coset).remove(0)
+            }
+            _ => {
+                let mut rows = selector.rows(trace_size);
+                let num_rows = rows.len();
+                let Some(start) = rows.next() else {
+                    return vec![D::Val::zero()];
+                };
+                eval_row_range_on_coset(
+                    start,
+                    num_rows,
+                    trace_size,
+                    trace_generator,
+                    unshifted_start,
+                    coset_generator,
+                    coset.size(),
+                )
+                .unwrap_or_else(|| {
+                    let rows = (start..start + num_rows).collect_vec();
+                    coset_generator
+                        .shifted_powers(unshifted_start)
+                        .take(coset.size())
+                        .map(|u| eval_rows_selector(&rows, trace_size, trace_generator, u))
+                        .collect()
+                })
+            }
+        })
+        .collect()
+}
+
+fn trace_generator<D: PolynomialSpace>(trace_domain: D) -> D::Val {
+    trace_domain
+        .next_point(D::Val::one())
+        .expect("trace domain should have a generator")
+}
+
+/// Returns one period of the periodic column which is one on the rows an `EveryNthRow` selector
+/// selects.
+fn every_nth_row_column<F: Field>(period: usize, offset: usize) -> Vec<F> {
+    assert!(
+        offset < period,
+        "row selector offset {} must be smaller than its period {}",
+        offset,
+        period,
+    );
+    let mut column = vec![F::zero(); period];
+    column[offset] = F::one();
+    column
+}
+
+/// Evaluates the sum of the Lagrange basis polynomials of the given rows over the subgroup of size
+/// `n` generated by `generator`, at the point `u`.
+fn eval_rows_selector<F, EF>(rows: &[usize], n: usize, generator: F, u: EF) -> EF
+where
+    F: Field,
+    EF: ExtensionField<F>,
+{
+    if rows.is_empty() {
+        return EF::zero();
+    }
+    let points = rows
+        .iter()
+        .map(|&row| generator.exp_u64(row as u64))
+        .collect_vec();
+    let zerofier = u.exp_u64(n as u64) - EF::one();
+    if zerofier.is_zero() {
+        let selected = points.iter().any(|&point| u == EF::from_base(point));
+        return EF::from_bool(selected);
+    }
+
+    // L_k(u) = g^k (u^n - 1) / (n (u - g^k))
+    let denominators = points
+        .iter()
+        .map(|&point| u - EF::from_base(point))
+        .collect_vec();
+    let inverses = batch_multiplicative_inverse(&denominators);
+    let sum = points
+        .iter()
+        .zip(inverses)
+        .map(|(&point, inverse)| inverse * point)
+        .sum::<EF>();
+    sum * zerofier * F::from_canonical_usize(n).inverse()
+}
+
+/// Evaluates the selector of the `num_rows` rows from `start` of the subgroup of size `n`
+/// generated by `generator` over the coset of `coset_size` points from `start_point` generated by
+/// `coset_generator`. Returns none unless the coset is a disjoint domain whose generator is a root
+/// of `generator`.
+///
+/// Writing the `blowup * q + r`th point of the coset as `c_r g^q`, the selector's value there is
+/// `(c_r^n - 1) / n` times the sum of `g^t / (c_r - g^t)` over the rows shifted back by `q`. These
+/// sums are windows of a cyclic sequence, so they are read off its prefix sums.
+fn eval_row_range_on_coset<F: Field>(
+    start: usize,
+    num_rows: usize,
+    n: usize,
+    generator: F,
+    start_point: F,
+    coset_generator: F,
+    coset_size: usize,
+) -> Option<Vec<F>> {
+    let blowup = coset_size / n;
+    if blowup == 0 || coset_size % n != 0 || coset_generator.exp_u64(blowup as u64) != generator {
+        return None;
+    }
+    let offsets = coset_generator
+        .shifted_powers(start_point)
+        .take(blowup)
+        .collect_vec();
+    let zerofiers = offsets
+        .iter()
+        .map(|offset| offset.exp_u64(n as u64) - F::one())
+        .collect_vec();
+    if zerofiers.iter().any(|zerofier| zerofier.is_zero()) {
+        return None;
+    }
+
+    let powers = generator.powers().take(n).collect_vec();
+    let denominators = offsets
+        .iter()
+        .flat_map(|&offset| powers.iter().map(move |&power| offset - power))
+        .collect_vec();
+    let inverses = batch_multiplicative_inverse(&denominators);
+    let n_inverse = F::from_canonical_usize(n).inverse();
+    let mut values = vec![F::zero(); coset_size];
+    for (r, inverses) in inverses.chunks(n).enumerate() {
+        // prefix_sums[j] is the sum of g^t / (c_r - g^t) over t < j
+        let prefix_sums = iter::once(F::zero())
+            .chain(
+                powers
+                    .iter()
+                    .zip(inverses)
+                    .scan(F::zero(), |sum, (&power, &inverse)| {
+                        *sum += power * inverse;
+                        Some(*sum)
+                    }),
+            )
+            .collect_vec();
+        let scale = zerofiers[r] * n_inverse;
+        for q in 0..n {
+            let low = (start + n - q) % n;
+            let high = low + num_rows;
+            let window = if high <= n {
+                prefix_sums[high] - prefix_sums[low]
+            } else {
+                prefix_sums[n] - prefix_sums[low] + prefix_sums[high - n]
+            };
+            values[q * blowup + r] = window * scale;
+        }
+    }
+    Some(values)
+}
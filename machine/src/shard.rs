@@ -0,0 +1,51 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use p3_field::Field;
+use p3_matrix::{dense::RowMajorMatrix, Matrix};
+
+/// The number of shards a main trace is split into. Missing and empty traces count as one shard.
+pub fn num_shards<F: Field>(
+    trace: &Option<RowMajorMatrix<F>>,
+    max_shard_height: Option<usize>,
+) -> usize {
+    match (trace, max_shard_height) {
+        (Some(trace), Some(max_shard_height)) if trace.height() > max_shard_height => {
+            assert_eq!(
+                trace.height() % max_shard_height,
+                0,
+                "Trace height should be a multiple of the maximum shard height"
+            );
+            trace.height() / max_shard_height
+        }
+        _ => 1,
+    }
+}
+
+/// Splits a main trace into `num_shards` consecutive shards of equal height.
+pub fn shard_trace<F: Field>(
+    trace: Option<RowMajorMatrix<F>>,
+    num_shards: usize,
+) -> Vec<Option<RowMajorMatrix<F>>> {
+    match trace {
+        Some(trace) if num_shards > 1 => {
+            let width = trace.width();
+            let shard_len = trace.values.len() / num_shards;
+            trace
+                .values
+                .chunks(shard_len)
+                .map(|values| Some(RowMajorMatrix::new(values.to_vec(), width)))
+                .collect()
+        }
+        trace => vec![trace],
+    }
+}
+
+/// Repeats each per-chip value once per shard of the chip.
+pub fn repeat_per_shard<T: Clone>(values: &[T], shard_counts: &[usize]) -> Vec<T> {
+    values
+        .iter()
+        .zip(shard_counts)
+        .flat_map(|(value, &count)| core::iter::repeat(value.clone()).take(count))
+        .collect()
+}
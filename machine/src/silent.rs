@@ -0,0 +1,52 @@
+use alloc::boxed::Box;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use std::cell::Cell;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Once;
+use std::thread;
+
+thread_local! {
+    /// Whether the thread is inside `catch_unwind_silently`.
+    static SILENCED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// The number of threads inside `catch_unwind_silently`.
+static NUM_SILENCED: AtomicUsize = AtomicUsize::new(0);
+
+/// Runs `f`, catching a panic without printing its message. The first call installs a panic hook
+/// which stays in place, and which defers to the previous hook unless the panicking thread is
+/// inside this function.
+///
+/// With the `parallel` feature, `f` may run parallel work on rayon's worker threads, which do not
+/// know which thread they are working for. Their panics are silenced too while any thread is
+/// inside this function. Panics of other threads are reported as usual.
+pub(crate) fn catch_unwind_silently<R>(f: impl FnOnce() -> R) -> thread::Result<R> {
+    static INSTALL_HOOK: Once = Once::new();
+    INSTALL_HOOK.call_once(|| {
+        let hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            let silenced = SILENCED.with(Cell::get)
+                || (is_worker_thread() && NUM_SILENCED.load(Ordering::SeqCst) > 0);
+            if !silenced {
+                hook(info);
+            }
+        }));
+    });
+
+    let silenced = SILENCED.with(|silenced| silenced.replace(true));
+    NUM_SILENCED.fetch_add(1, Ordering::SeqCst);
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    NUM_SILENCED.fetch_sub(1, Ordering::SeqCst);
+    SILENCED.with(|s| s.set(silenced));
+    result
+}
+
+#[cfg(feature = "parallel")]
+fn is_worker_thread() -> bool {
+    rayon::current_thread_index().is_some()
+}
+
+#[cfg(not(feature = "parallel"))]
+fn is_worker_thread() -> bool {
+    false
+}
@@ -0,0 +1,541 @@
+#[cfg(feature = "air-logger")]
+use alloc::boxed::Box;
+use alloc::collections::BTreeSet;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::{self, Write as _};
+use core::str::FromStr;
+#[cfg(feature = "air-logger")]
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+
+use itertools::Itertools;
+use p3_air::BaseAir;
+use p3_air_util::debug::rap::{check_constraints, check_cumulative_sums, track_constraints};
+use p3_air_util::folders::rap::{
+    DebugConstraintBuilder, SymbolicAirBuilder, TrackingConstraintBuilder,
+};
+use p3_air_util::folders::EntriesLog;
+use p3_air_util::util::TraceEntry;
+use p3_air_util::{get_constraint_program, get_row_selectors, PeriodicAir};
+use p3_field::{AbstractExtensionField, ExtensionField, Field, PrimeField32};
+use p3_interaction::{generate_permutation_trace, InteractionAir, Rap, NUM_PERM_CHALLENGES};
+use p3_matrix::dense::{RowMajorMatrix, RowMajorMatrixView};
+use p3_matrix::Matrix;
+use p3_uni_stark::{StarkGenericConfig, Val};
+
+use crate::chip::{Chip, ChipId};
+use crate::error::SnapshotError;
+use crate::machine::Machine;
+use crate::trace::MachineTrace;
+
+/// The first line of a snapshot file, identifying its format.
+const HEADER: &str = "p3-trace-snapshot 1";
+
+/// The traces a machine was proven with, from which the prover's debug checks can be replayed
+/// without generating the traces again.
+#[derive(Clone, Debug)]
+pub struct TraceSnapshot<F, EF> {
+    /// The `constraints_hash` of the machine the traces were proven with.
+    pub constraints_hash: u64,
+    /// The traces of each chip, in the order they were proven.
+    pub chip_traces: Vec<ChipTraceSnapshot<F>>,
+    /// The public values of each instance.
+    pub public_values: Vec<Vec<F>>,
+    pub perm_challenges: [EF; NUM_PERM_CHALLENGES],
+}
+
+/// The padded traces of a chip in one instance. Sharded chips have a snapshot per shard.
+#[derive(Clone, Debug)]
+pub struct ChipTraceSnapshot<F> {
+    pub chip: ChipId,
+    pub instance: usize,
+    pub preprocessed: Option<RowMajorMatrix<F>>,
+    pub main: Option<RowMajorMatrix<F>>,
+}
+
+/// Takes a snapshot of the preprocessed and main traces of `trace`, a trace of `machine` whose
+/// permutation traces were generated with `perm_challenges`.
+pub fn snapshot_trace<M, SC>(
+    machine: &M,
+    trace: &MachineTrace<SC, M::Chip>,
+    public_values: &[Vec<Val<SC>>],
+    perm_challenges: [SC::Challenge; NUM_PERM_CHALLENGES],
+) -> TraceSnapshot<Val<SC>, SC::Challenge>
+where
+    M: Machine + ?Sized,
+    SC: StarkGenericConfig,
+    M::Chip: Rap<SymbolicAirBuilder<Val<SC>>> + PeriodicAir<Val<SC>>,
+{
+    let num_public_values = public_values.iter().map(Vec::len).collect_vec();
+    let chip_traces = trace
+        .iter()
+        .map(|chip_trace| ChipTraceSnapshot {
+            chip: chip_trace.id.clone(),
+            instance: chip_trace.instance,
+            preprocessed: chip_trace
+                .preprocessed
+                .as_ref()
+                .map(|preprocessed| preprocessed.trace.value.clone()),
+            main: chip_trace
+                .main
+                .as_ref()
+                .map(|main| main.trace.value.clone()),
+        })
+        .collect();
+    TraceSnapshot {
+        constraints_hash: constraints_hash(machine, &num_public_values),
+        chip_traces,
+        public_values: public_values.to_vec(),
+        perm_challenges,
+    }
+}
+
+/// A hash of the constraints, interactions and periodic columns of the chips of `machine`, given
+/// the number of public values of each instance. The constraints are compiled once for each
+/// distinct number of public values. Snapshots are only replayed against machines with the same
+/// hash. It is computed with FNV-1a, so that it is stable across builds.
+pub fn constraints_hash<M, F>(machine: &M, num_public_values: &[usize]) -> u64
+where
+    M: Machine + ?Sized,
+    F: Field,
+    M::Chip: Rap<SymbolicAirBuilder<F>> + PeriodicAir<F>,
+{
+    let num_public_values: BTreeSet<usize> = num_public_values.iter().copied().collect();
+    let mut hasher = Fnv1a::default();
+    for (id, chip) in machine.chips_by_id() {
+        // Writing to the hasher cannot fail
+        let _ = write!(
+            hasher,
+            "{id}:{}:{}:{:?}:{:?}:{:?}",
+            chip.preprocessed_width(),
+            chip.width(),
+            chip.permutation_width(),
+            chip.periodic_columns(),
+            InteractionAir::<F>::all_interactions(&chip),
+        );
+        for &num_values in num_public_values.iter() {
+            let _ = write!(
+                hasher,
+                ":{:?}:{:?}",
+                get_row_selectors::<F, _>(&chip, num_values),
+                get_constraint_program::<F, _>(&chip, num_values),
+            );
+        }
+    }
+    hasher.0
+}
+
+impl<F, EF> TraceSnapshot<F, EF>
+where
+    F: PrimeField32,
+    EF: ExtensionField<F>,
+{
+    /// Writes the snapshot to a text file. Values are written in canonical form, and extension
+    /// field values by their base field coefficients.
+    pub fn write_to_file(&self, path: &str) -> io::Result<()> {
+        let mut f = BufWriter::new(File::create(path)?);
+        writeln!(f, "{HEADER}")?;
+        writeln!(f, "constraints {:016x}", self.constraints_hash)?;
+        let challenges = self
+            .perm_challenges
+            .iter()
+            .flat_map(|challenge| {
+                AbstractExtensionField::<F>::as_base_slice(challenge)
+                    .iter()
+                    .copied()
+            })
+            .collect_vec();
+        writeln!(f, "challenges {}", values_line(&challenges))?;
+        writeln!(f, "instances {}", self.public_values.len())?;
+        for public_values in self.public_values.iter() {
+            writeln!(f, "public {}", values_line(public_values))?;
+        }
+        for chip_trace in self.chip_traces.iter() {
+            writeln!(f, "chip {} {}", chip_trace.instance, chip_trace.chip)?;
+            write_matrix(&mut f, "preprocessed", chip_trace.preprocessed.as_ref())?;
+            write_matrix(&mut f, "main", chip_trace.main.as_ref())?;
+        }
+        f.flush()
+    }
+
+    /// Reads a snapshot written by `write_to_file`.
+    pub fn read_from_file(path: &str) -> Result<Self, SnapshotError> {
+        let contents = fs::read_to_string(path)?;
+        let mut lines = Lines {
+            lines: contents.lines(),
+            line: 0,
+        };
+        if lines.next_line() != Some(HEADER) {
+            return Err(lines.malformed());
+        }
+
+        let constraints_hash = lines.expect("constraints")?;
+        let constraints_hash =
+            u64::from_str_radix(constraints_hash, 16).map_err(|_| lines.malformed())?;
+
+        let challenges = lines.expect("challenges")?;
+        let challenges = lines.values::<F>(challenges)?;
+        let d = <EF as AbstractExtensionField<F>>::D;
+        if challenges.len() != NUM_PERM_CHALLENGES * d {
+            return Err(lines.malformed());
+        }
+        let perm_challenges = core::array::from_fn(|i| {
+            <EF as AbstractExtensionField<F>>::from_base_slice(&challenges[i * d..(i + 1) * d])
+        });
+
+        let num_instances = lines.expect("instances")?;
+        let num_instances: usize = lines.parse(num_instances)?;
+        let public_values = (0..num_instances)
+            .map(|_| {
+                let public_values = lines.expect("public")?;
+                lines.values(public_values)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut chip_traces = vec![];
+        while !lines.is_empty() {
+            let chip = lines.expect("chip")?;
+            let (instance, chip) = chip.split_once(' ').ok_or_else(|| lines.malformed())?;
+            let instance: usize = lines.parse(instance)?;
+            if instance >= num_instances {
+                return Err(lines.malformed());
+            }
+            let preprocessed = lines.matrix("preprocessed")?;
+            let main = lines.matrix("main")?;
+            chip_traces.push(ChipTraceSnapshot {
+                chip: chip.into(),
+                instance,
+                preprocessed,
+                main,
+            });
+        }
+
+        Ok(Self {
+            constraints_hash,
+            chip_traces,
+            public_values,
+            perm_challenges,
+        })
+    }
+
+    /// The chip of `machine` of each chip trace, after checking that the machine's constraints are
+    /// those the snapshot was taken with.
+    pub fn chips<M>(&self, machine: &M) -> Result<Vec<M::Chip>, SnapshotError>
+    where
+        M: Machine,
+        M::Chip: Rap<SymbolicAirBuilder<F>> + PeriodicAir<F>,
+    {
+        let num_public_values = self.public_values.iter().map(Vec::len).collect_vec();
+        if constraints_hash(machine, &num_public_values) != self.constraints_hash {
+            return Err(SnapshotError::ConstraintsMismatch);
+        }
+        let chips = machine.chips_by_id();
+        self.chip_traces
+            .iter()
+            .map(|chip_trace| {
+                chips
+                    .get(&chip_trace.chip)
+                    .cloned()
+                    .ok_or_else(|| SnapshotError::UnknownChip(chip_trace.chip.clone()))
+            })
+            .collect()
+    }
+
+    /// Replays the prover's debug checks on the snapshot: checks the constraints of every chip
+    /// trace, and that the buses of each instance balance. Panics if they fail, as the prover does.
+    pub fn check_constraints<M>(&self, machine: &M) -> Result<(), SnapshotError>
+    where
+        M: Machine,
+        M::Chip: for<'a> Rap<DebugConstraintBuilder<'a, F, EF>>
+            + Rap<SymbolicAirBuilder<F>>
+            + PeriodicAir<F>,
+    {
+        let chips = self.chips(machine)?;
+        let (preprocessed, main) = self.views();
+        let permutation = self.permutation_traces(&chips);
+        let permutation_views = permutation
+            .iter()
+            .map(|trace| trace.as_ref().map(RowMajorMatrix::as_view))
+            .collect_vec();
+
+        for (i, chip) in chips.iter().enumerate() {
+            check_constraints(
+                chip,
+                &preprocessed[i],
+                &main[i],
+                &permutation_views[i],
+                self.perm_challenges,
+                cumulative_sum(permutation[i].as_ref()),
+                &self.public_values[self.chip_traces[i].instance],
+            );
+        }
+        for instance in 0..self.public_values.len() {
+            let indices = self.instance_indices(instance);
+            check_cumulative_sums::<F, EF, _, M::Bus>(
+                &select(&chips, &indices),
+                &select(&preprocessed, &indices),
+                &select(&main, &indices),
+                &select(&permutation_views, &indices),
+            );
+        }
+        Ok(())
+    }
+
+    /// The entries of each chip trace tracked by `track_constraints`, with the public values of
+    /// its instance.
+    pub fn track_constraints<M>(
+        &self,
+        machine: &M,
+    ) -> Result<Vec<EntriesLog<TraceEntry>>, SnapshotError>
+    where
+        M: Machine,
+        M::Chip: for<'a> Rap<TrackingConstraintBuilder<'a, F, EF>>
+            + Rap<SymbolicAirBuilder<F>>
+            + PeriodicAir<F>,
+    {
+        let chips = self.chips(machine)?;
+        let (preprocessed, main) = self.views();
+        let permutation = self.permutation_traces(&chips);
+
+        Ok(chips
+            .iter()
+            .enumerate()
+            .map(|(i, chip)| {
+                track_constraints(
+                    chip,
+                    &preprocessed[i],
+                    &main[i],
+                    &permutation[i].as_ref().map(RowMajorMatrix::as_view),
+                    self.perm_challenges,
+                    cumulative_sum(permutation[i].as_ref()),
+                    &self.public_values[self.chip_traces[i].instance],
+                )
+            })
+            .collect())
+    }
+
+    /// Writes the traces to an xlsx file, with a worksheet per chip trace, highlighting the failing
+    /// entries of the constraints and of the interactions of each instance.
+    #[cfg(feature = "air-logger")]
+    pub fn write_traces_to_file<M>(&self, machine: &M, path: &str) -> Result<(), Box<dyn Error>>
+    where
+        M: Machine,
+        M::Chip: for<'a> Rap<DebugConstraintBuilder<'a, F, EF>>
+            + for<'a> Rap<TrackingConstraintBuilder<'a, F, EF>>
+            + Rap<SymbolicAirBuilder<F>>
+            + PeriodicAir<F>,
+    {
+        use p3_air_util::debug::rap::track_interactions;
+
+        use crate::trace::{worksheet_names, write_workbook, WorksheetTrace};
+
+        let chips = self.chips(machine)?;
+        let (preprocessed, main) = self.views();
+        let mut entries = self.track_constraints(machine)?;
+        for instance in 0..self.public_values.len() {
+            let indices = self.instance_indices(instance);
+            let interaction_entries = track_interactions::<F, EF, _>(
+                &select(&chips, &indices),
+                &select(&preprocessed, &indices),
+                &select(&main, &indices),
+            );
+            for (&i, interaction_entries) in indices.iter().zip_eq(interaction_entries) {
+                entries[i].extend(&interaction_entries);
+            }
+        }
+
+        let ids = self
+            .chip_traces
+            .iter()
+            .map(|chip_trace| chip_trace.chip.clone())
+            .collect_vec();
+        let traces = chips
+            .iter()
+            .zip_eq(preprocessed)
+            .zip_eq(main)
+            .zip_eq(entries)
+            .zip_eq(worksheet_names(&ids))
+            .map(
+                |((((chip, preprocessed), main), entries), name)| WorksheetTrace {
+                    name,
+                    chip,
+                    preprocessed,
+                    main,
+                    entries,
+                },
+            );
+        write_workbook::<_, EF, _>(path, traces)
+    }
+
+    fn views(
+        &self,
+    ) -> (
+        Vec<Option<RowMajorMatrixView<F>>>,
+        Vec<Option<RowMajorMatrixView<F>>>,
+    ) {
+        self.chip_traces
+            .iter()
+            .map(|chip_trace| {
+                (
+                    chip_trace
+                        .preprocessed
+                        .as_ref()
+                        .map(RowMajorMatrix::as_view),
+                    chip_trace.main.as_ref().map(RowMajorMatrix::as_view),
+                )
+            })
+            .unzip()
+    }
+
+    /// The permutation traces of the chip traces, generated with the snapshot's challenges.
+    fn permutation_traces<C: InteractionAir<F>>(
+        &self,
+        chips: &[C],
+    ) -> Vec<Option<RowMajorMatrix<EF>>> {
+        let (preprocessed, main) = self.views();
+        chips
+            .iter()
+            .zip_eq(preprocessed.iter().zip_eq(main.iter()))
+            .map(|(chip, (preprocessed, main))| {
+                generate_permutation_trace(
+                    preprocessed,
+                    main,
+                    &chip.all_interactions(),
+                    self.perm_challenges,
+                )
+            })
+            .collect()
+    }
+
+    /// The indices of the chip traces of `instance`.
+    fn instance_indices(&self, instance: usize) -> Vec<usize> {
+        self.chip_traces
+            .iter()
+            .positions(|chip_trace| chip_trace.instance == instance)
+            .collect()
+    }
+}
+
+/// The lines of a snapshot file, with the number of the last line read.
+struct Lines<'a> {
+    lines: core::str::Lines<'a>,
+    line: usize,
+}
+
+impl<'a> Lines<'a> {
+    fn next_line(&mut self) -> Option<&'a str> {
+        self.line += 1;
+        self.lines.next()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.lines.clone().next().is_none()
+    }
+
+    fn malformed(&self) -> SnapshotError {
+        SnapshotError::Malformed { line: self.line }
+    }
+
+    /// The rest of the next line, which should start with `keyword`.
+    fn expect(&mut self, keyword: &str) -> Result<&'a str, SnapshotError> {
+        self.next_line()
+            .and_then(|line| line.strip_prefix(keyword))
+            .and_then(|rest| match rest {
+                "" => Some(rest),
+                _ => rest.strip_prefix(' '),
+            })
+            .ok_or_else(|| self.malformed())
+    }
+
+    fn parse<T: FromStr>(&self, field: &str) -> Result<T, SnapshotError> {
+        field.parse().map_err(|_| self.malformed())
+    }
+
+    fn values<F: PrimeField32>(&self, fields: &str) -> Result<Vec<F>, SnapshotError> {
+        fields
+            .split_whitespace()
+            .map(|field| {
+                let value: u32 = self.parse(field)?;
+                if value < F::ORDER_U32 {
+                    Ok(F::from_canonical_u32(value))
+                } else {
+                    Err(self.malformed())
+                }
+            })
+            .collect()
+    }
+
+    /// A matrix written by `write_matrix` under `keyword`.
+    fn matrix<F: PrimeField32>(
+        &mut self,
+        keyword: &str,
+    ) -> Result<Option<RowMajorMatrix<F>>, SnapshotError> {
+        let dimensions = self.expect(keyword)?;
+        if dimensions == "none" {
+            return Ok(None);
+        }
+        let (width, height) = dimensions.split_once(' ').ok_or_else(|| self.malformed())?;
+        let width: usize = self.parse(width)?;
+        let height: usize = self.parse(height)?;
+        let mut values = vec![];
+        for _ in 0..height {
+            let row = self.next_line().ok_or_else(|| self.malformed())?;
+            let row = self.values(row)?;
+            if row.len() != width {
+                return Err(self.malformed());
+            }
+            values.extend(row);
+        }
+        Ok(Some(RowMajorMatrix::new(values, width)))
+    }
+}
+
+/// Writes `matrix` under `keyword`, as its dimensions followed by a line per row.
+fn write_matrix<F: PrimeField32>(
+    f: &mut impl Write,
+    keyword: &str,
+    matrix: Option<&RowMajorMatrix<F>>,
+) -> io::Result<()> {
+    let Some(matrix) = matrix else {
+        return writeln!(f, "{keyword} none");
+    };
+    writeln!(f, "{keyword} {} {}", matrix.width(), matrix.height())?;
+    for r in 0..matrix.height() {
+        writeln!(f, "{}", values_line(&matrix.row_slice(r)))?;
+    }
+    Ok(())
+}
+
+fn values_line<F: PrimeField32>(values: &[F]) -> String {
+    values.iter().map(F::as_canonical_u32).join(" ")
+}
+
+fn cumulative_sum<EF: Field>(permutation: Option<&RowMajorMatrix<EF>>) -> Option<EF> {
+    permutation.map(|perm| *perm.row_slice(perm.height() - 1).last().unwrap())
+}
+
+fn select<T: Clone>(items: &[T], indices: &[usize]) -> Vec<T> {
+    indices.iter().map(|&i| items[i].clone()).collect()
+}
+
+/// The 64-bit FNV-1a hash of the written strings.
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl fmt::Write for Fnv1a {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,353 @@
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use std::any::Any;
+
+use itertools::Itertools;
+use p3_air_util::folders::rap::{SymbolicAirBuilder, VerifierConstraintFolder};
+use p3_air_util::proof::{AdjacentOpenedValues, Commitments, InteractionAirProof};
+use p3_air_util::PeriodicAir;
+use p3_field::{AbstractField, PrimeField32, TwoAdicField};
+use p3_interaction::Rap;
+use p3_uni_stark::{StarkGenericConfig, Val};
+
+use crate::chip::ChipId;
+use crate::error::VerificationError;
+use crate::machine::{verify_instances, Machine};
+use crate::proof::{Com, MachineProof, VerifyingKey};
+use crate::silent::catch_unwind_silently;
+
+type ChipProofs<Challenge> = BTreeMap<ChipId, Vec<Option<InteractionAirProof<Challenge>>>>;
+
+/// A valid proof with some of its commitments or chip proofs tampered with. The opening proof is
+/// left as is.
+pub struct Tampering<SC: StarkGenericConfig> {
+    pub description: String,
+    pub commitments: Commitments<Com<SC>>,
+    pub chip_proofs: ChipProofs<SC::Challenge>,
+    /// The errors the tampered proof may be rejected with.
+    pub expected: Vec<VerificationError>,
+}
+
+/// The structured tamperings of a valid `proof`: flipped opened values, changed degrees, dropped
+/// or altered cumulative sums and commitments, truncated quotient chunks and swapped chip proofs.
+pub fn tamperings<SC: StarkGenericConfig>(proof: &MachineProof<SC>) -> Vec<Tampering<SC>> {
+    let mut tamperings = vec![];
+
+    for (id, shard_proofs) in proof.chip_proofs.iter() {
+        for (shard, chip_proof) in shard_proofs.iter().enumerate() {
+            let Some(chip_proof) = chip_proof else {
+                continue;
+            };
+            let mut tamper =
+                |description: &str,
+                 expected: &[VerificationError],
+                 modify: fn(&mut InteractionAirProof<SC::Challenge>)| {
+                    tamperings.push(tamper_chip_proof(
+                        proof,
+                        id,
+                        shard,
+                        format!("{id}[{shard}]: {description}"),
+                        expected,
+                        modify,
+                    ));
+                };
+            let opened_values = &chip_proof.opened_values;
+
+            if has_values(&opened_values.preprocessed) {
+                tamper(
+                    "flipped a local preprocessed value",
+                    &[VerificationError::InvalidOpeningArgument],
+                    |chip_proof| {
+                        flip(
+                            &mut chip_proof
+                                .opened_values
+                                .preprocessed
+                                .as_mut()
+                                .unwrap()
+                                .local[0],
+                        )
+                    },
+                );
+            }
+            if has_values(&opened_values.main) {
+                tamper(
+                    "flipped a local main value",
+                    &[VerificationError::InvalidOpeningArgument],
+                    |chip_proof| {
+                        flip(&mut chip_proof.opened_values.main.as_mut().unwrap().local[0])
+                    },
+                );
+                tamper(
+                    "flipped a next main value",
+                    &[VerificationError::InvalidOpeningArgument],
+                    |chip_proof| flip(&mut chip_proof.opened_values.main.as_mut().unwrap().next[0]),
+                );
+            }
+            if has_values(&opened_values.permutation) {
+                tamper(
+                    "flipped a local permutation value",
+                    &[VerificationError::InvalidOpeningArgument],
+                    |chip_proof| {
+                        flip(&mut chip_proof.opened_values.permutation.as_mut().unwrap().local[0])
+                    },
+                );
+            }
+            if opened_values.quotient_chunks.is_some() {
+                tamper(
+                    "flipped a quotient chunk value",
+                    &[VerificationError::InvalidOpeningArgument],
+                    |chip_proof| {
+                        flip(&mut chip_proof.opened_values.quotient_chunks.as_mut().unwrap()[0][0])
+                    },
+                );
+                tamper(
+                    "dropped the quotient chunks",
+                    &[VerificationError::InvalidProofShape],
+                    |chip_proof| chip_proof.opened_values.quotient_chunks = None,
+                );
+                tamper(
+                    "truncated the quotient chunks",
+                    &[VerificationError::InvalidProofShape],
+                    |chip_proof| {
+                        chip_proof
+                            .opened_values
+                            .quotient_chunks
+                            .as_mut()
+                            .unwrap()
+                            .pop();
+                    },
+                );
+                tamper(
+                    "truncated a quotient chunk",
+                    &[VerificationError::InvalidProofShape],
+                    |chip_proof| {
+                        chip_proof.opened_values.quotient_chunks.as_mut().unwrap()[0].pop();
+                    },
+                );
+            }
+
+            tamper(
+                "changed the degree to a non power of two",
+                &[VerificationError::InvalidProofShape],
+                |chip_proof| chip_proof.degree *= 3,
+            );
+            // Degrees are not halved to 1, which the opening argument may not support
+            if chip_proof.degree > 2 {
+                tamper(
+                    "halved the degree",
+                    &[
+                        VerificationError::InvalidProofShape,
+                        VerificationError::InvalidOpeningArgument,
+                    ],
+                    |chip_proof| chip_proof.degree /= 2,
+                );
+            }
+            tamper(
+                "inflated the degree by two",
+                &[
+                    VerificationError::InvalidProofShape,
+                    VerificationError::InvalidOpeningArgument,
+                ],
+                |chip_proof| chip_proof.degree *= 2,
+            );
+            tamper(
+                "inflated the degree to a huge power of two",
+                &[VerificationError::InvalidProofShape],
+                |chip_proof| chip_proof.degree = 1 << (usize::BITS - 1),
+            );
+
+            if chip_proof.cumulative_sum.is_some() {
+                tamper(
+                    "dropped the cumulative sum",
+                    &[VerificationError::InvalidProofShape],
+                    |chip_proof| chip_proof.cumulative_sum = None,
+                );
+                tamper(
+                    "altered the cumulative sum",
+                    &[VerificationError::OodEvaluationMismatch],
+                    |chip_proof| flip(chip_proof.cumulative_sum.as_mut().unwrap()),
+                );
+            }
+        }
+    }
+
+    for ((a, a_proofs), (b, b_proofs)) in proof.chip_proofs.iter().tuple_windows() {
+        if a_proofs.iter().chain(b_proofs).all(Option::is_none) {
+            continue;
+        }
+        tamperings.push(tamper(
+            proof,
+            format!("swapped the proofs of {a} and {b}"),
+            &[
+                VerificationError::InvalidProofShape,
+                VerificationError::InvalidOpeningArgument,
+            ],
+            |_, chip_proofs| {
+                let a_proofs = chip_proofs.remove(a).unwrap();
+                let b_proofs = chip_proofs.insert(b.clone(), a_proofs).unwrap();
+                chip_proofs.insert(a.clone(), b_proofs);
+            },
+        ));
+    }
+
+    let commitments = &proof.commitments;
+    let names = (0..commitments.main.len())
+        .map(|group| format!("main commitment {group}"))
+        .chain([
+            "permutation commitment".into(),
+            "quotient commitment".into(),
+        ])
+        .collect_vec();
+    let present = commitments
+        .main
+        .iter()
+        .chain([&commitments.permutation, &commitments.quotient_chunks])
+        .positions(Option::is_some)
+        .collect_vec();
+    for (i, &slot) in present.iter().enumerate() {
+        tamperings.push(tamper(
+            proof,
+            format!("dropped the {}", names[slot]),
+            &[VerificationError::InvalidProofShape],
+            |commitments, _| *commitment_mut(commitments, slot) = None,
+        ));
+        // A commitment is altered by replacing it with another one of the proof's commitments
+        if present.len() > 1 {
+            let other = present[(i + 1) % present.len()];
+            tamperings.push(tamper(
+                proof,
+                format!("replaced the {} with the {}", names[slot], names[other]),
+                &[VerificationError::InvalidOpeningArgument],
+                |commitments, _| {
+                    *commitment_mut(commitments, slot) =
+                        commitment_mut(&mut proof.commitments.clone(), other).clone();
+                },
+            ));
+        }
+    }
+
+    tamperings
+}
+
+/// Verifies each of the `tamperings` of a valid `proof` of `machine`. Panics, listing the failures,
+/// unless the proof is accepted and every tampered proof is rejected with one of its expected
+/// errors without panicking.
+pub fn check_tampered_proofs<M, SC>(
+    machine: &M,
+    config: &SC,
+    challenger: &SC::Challenger,
+    vk: &VerifyingKey<SC>,
+    proof: &MachineProof<SC>,
+    public_values: &[Val<SC>],
+) where
+    M: Machine,
+    SC: StarkGenericConfig,
+    SC::Challenger: Clone,
+    Val<SC>: PrimeField32 + TwoAdicField,
+    M::Chip: for<'b> Rap<VerifierConstraintFolder<'b, SC>>
+        + for<'b> Rap<SymbolicAirBuilder<Val<SC>>>
+        + PeriodicAir<Val<SC>>,
+{
+    let public_values = [public_values.to_vec()];
+    let verify = |commitments: &Commitments<Com<SC>>, chip_proofs: &ChipProofs<SC::Challenge>| {
+        verify_instances(
+            machine,
+            config,
+            &mut challenger.clone(),
+            vk,
+            commitments,
+            &proof.opening_proof,
+            core::slice::from_ref(chip_proofs),
+            &public_values,
+        )
+    };
+    verify(&proof.commitments, &proof.chip_proofs).expect("The untampered proof should verify");
+
+    // Panics are caught and reported, so their messages are silenced while verifying.
+    let failures = tamperings(proof)
+        .into_iter()
+        .filter_map(|tampering| {
+            let result =
+                catch_unwind_silently(|| verify(&tampering.commitments, &tampering.chip_proofs));
+            let outcome = match result {
+                Ok(Err(err)) if tampering.expected.contains(&err) => return None,
+                Ok(Err(err)) => format!("rejected with {err:?}"),
+                Ok(Ok(())) => "accepted".into(),
+                Err(payload) => format!("panicked with \"{}\"", panic_message(&*payload)),
+            };
+            Some(format!(
+                "{}: {outcome}, expected one of {:?}",
+                tampering.description, tampering.expected
+            ))
+        })
+        .collect_vec();
+
+    assert!(
+        failures.is_empty(),
+        "Tampered proofs were not rejected as expected:\n{}",
+        failures.join("\n")
+    );
+}
+
+fn tamper<SC: StarkGenericConfig>(
+    proof: &MachineProof<SC>,
+    description: String,
+    expected: &[VerificationError],
+    modify: impl FnOnce(&mut Commitments<Com<SC>>, &mut ChipProofs<SC::Challenge>),
+) -> Tampering<SC> {
+    let mut commitments = proof.commitments.clone();
+    let mut chip_proofs = proof.chip_proofs.clone();
+    modify(&mut commitments, &mut chip_proofs);
+    Tampering {
+        description,
+        commitments,
+        chip_proofs,
+        expected: expected.to_vec(),
+    }
+}
+
+fn tamper_chip_proof<SC: StarkGenericConfig>(
+    proof: &MachineProof<SC>,
+    id: &ChipId,
+    shard: usize,
+    description: String,
+    expected: &[VerificationError],
+    modify: fn(&mut InteractionAirProof<SC::Challenge>),
+) -> Tampering<SC> {
+    tamper(proof, description, expected, |_, chip_proofs| {
+        modify(chip_proofs.get_mut(id).unwrap()[shard].as_mut().unwrap());
+    })
+}
+
+/// The main commitments of each group, then the permutation and quotient commitments.
+fn commitment_mut<Com>(commitments: &mut Commitments<Com>, slot: usize) -> &mut Option<Com> {
+    let num_groups = commitments.main.len();
+    match slot.checked_sub(num_groups) {
+        None => &mut commitments.main[slot],
+        Some(0) => &mut commitments.permutation,
+        Some(_) => &mut commitments.quotient_chunks,
+    }
+}
+
+fn has_values<T>(opened_values: &Option<AdjacentOpenedValues<T>>) -> bool {
+    opened_values
+        .as_ref()
+        .is_some_and(|values| !values.local.is_empty())
+}
+
+fn flip<T: AbstractField>(value: &mut T) {
+    *value += T::one();
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic"
+    }
+}
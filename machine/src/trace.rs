@@ -1,7 +1,11 @@
 #[cfg(feature = "air-logger")]
 use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
 #[cfg(feature = "air-logger")]
 use alloc::format;
+#[cfg(feature = "air-logger")]
+use alloc::string::String;
+use alloc::sync::Arc;
 use alloc::vec;
 use alloc::vec::Vec;
 #[cfg(feature = "air-logger")]
@@ -17,25 +21,38 @@ use p3_air_util::{
         DebugConstraintBuilder, ProverConstraintFolder, SymbolicAirBuilder,
         VerifierConstraintFolder,
     },
-    get_quotient_degree,
+    get_constraint_program, get_num_constraints, get_quotient_degree, get_row_selectors,
     proof::{AdjacentOpenedValues, InteractionAirProof, OpenedValues},
+    PeriodicAir, RowSelector,
 };
 #[cfg(feature = "air-logger")]
 use p3_air_util::{
     debug::rap::{track_constraints, track_interactions},
+    find_unconstrained_columns,
     util::TraceEntry,
 };
 use p3_commit::{OpenedValuesForRound, Pcs, PolynomialSpace};
 #[cfg(feature = "air-logger")]
 use p3_field::PrimeField32;
 use p3_field::{AbstractExtensionField, AbstractField, ExtensionField, Field};
+#[cfg(feature = "air-logger")]
+use p3_interaction::InteractionAir;
 use p3_interaction::{generate_permutation_trace, Bus, Rap, NUM_PERM_CHALLENGES};
+#[cfg(feature = "air-logger")]
+use p3_matrix::dense::RowMajorMatrixView;
 use p3_matrix::{dense::RowMajorMatrix, Matrix};
+use p3_maybe_rayon::prelude::{IntoParallelIterator, IntoParallelRefMutIterator, ParallelIterator};
 use p3_uni_stark::{Domain, PackedChallenge, StarkGenericConfig, Val};
 
 use crate::{
-    chip::Chip, error::VerificationError, proof::Com, proof::PcsProverData,
-    quotient::quotient_values, verify::verify_constraints,
+    chip::{Chip, ChipId},
+    error::VerificationError,
+    periodic::{periodic_values_at_point, periodic_values_on_coset},
+    proof::Com,
+    proof::PcsProverData,
+    quotient::quotient_values,
+    selectors::{row_selectors_at_point, row_selectors_on_coset},
+    verify::verify_constraints,
 };
 
 #[derive(Clone)]
@@ -64,6 +81,21 @@ where
     }
 }
 
+impl<F, Domain> Trace<F, Domain>
+where
+    F: Field,
+    Domain: PolynomialSpace,
+{
+    /// Moves the values out, leaving an empty matrix of the same width behind. The domain is kept.
+    pub fn take(&mut self) -> Trace<F, Domain> {
+        let width = self.value.width();
+        Trace {
+            value: core::mem::replace(&mut self.value, RowMajorMatrix::new(vec![], width)),
+            domain: self.domain,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct IndexedTrace<F, Domain>
 where
@@ -74,6 +106,23 @@ where
     pub opening_index: usize,
 }
 
+impl<F, Domain> IndexedTrace<F, Domain>
+where
+    F: Field,
+    Domain: PolynomialSpace,
+{
+    /// A copy with an empty matrix of the same width. The domain and opening index are kept.
+    pub fn without_values(&self) -> Self {
+        Self {
+            trace: Trace {
+                value: RowMajorMatrix::new(vec![], self.trace.value.width()),
+                domain: self.trace.domain,
+            },
+            opening_index: self.opening_index,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct QuotientTrace<Domain>
 where
@@ -89,12 +138,20 @@ where
     SC: StarkGenericConfig,
     C: Chip,
 {
+    /// The chip's id within its machine.
+    pub id: ChipId,
     pub chip: C,
 
-    pub preprocessed: Option<IndexedTrace<Val<SC>, Domain<SC>>>,
+    /// The preprocessed trace, shared by the traces of the chip in every instance.
+    pub preprocessed: Option<Arc<IndexedTrace<Val<SC>, Domain<SC>>>>,
     pub main: Option<IndexedTrace<Val<SC>, Domain<SC>>>,
     pub permutation: Option<IndexedTrace<SC::Challenge, Domain<SC>>>,
 
+    /// The main commitment group of the chip. The main opening index is relative to the group.
+    pub main_group: usize,
+    /// The machine instance the trace belongs to, when proving a batch of instances.
+    pub instance: usize,
+
     pub cumulative_sum: Option<SC::Challenge>,
 
     pub quotient_chunks: Option<QuotientTrace<Domain<SC>>>,
@@ -106,12 +163,15 @@ where
     SC: StarkGenericConfig,
     C: Chip,
 {
-    pub fn new(chip: C) -> Self {
+    pub fn new(id: ChipId, chip: C) -> Self {
         Self {
+            id,
             chip,
             preprocessed: None,
             main: None,
             permutation: None,
+            main_group: 0,
+            instance: 0,
             cumulative_sum: None,
             quotient_chunks: None,
             quotient_degree: None,
@@ -144,7 +204,8 @@ where
     SC: StarkGenericConfig,
     C: Chip,
 {
-    fn new(chips: &[C]) -> Self;
+    /// Lays out one chip trace per chip, given with its id.
+    fn new(chips: &[(ChipId, C)]) -> Self;
 }
 
 impl<SC, C> MachineTraceBuilder<SC, C> for MachineTrace<SC, C>
@@ -152,10 +213,10 @@ where
     SC: StarkGenericConfig,
     C: Chip,
 {
-    fn new(chips: &[C]) -> Self {
+    fn new(chips: &[(ChipId, C)]) -> Self {
         chips
             .iter()
-            .map(|chip| ChipTrace::new(chip.clone()))
+            .map(|(id, chip)| ChipTrace::new(id.clone(), chip.clone()))
             .collect_vec()
     }
 }
@@ -166,13 +227,20 @@ where
 {
     fn generate_preprocessed(&mut self, pcs: &'a SC::Pcs);
 
+    /// Loads the preprocessed trace of each chip, keyed by chip id, into every chip trace of the
+    /// chip. Each trace is only stored once, however many instances share it.
     fn load_preprocessed(
         &mut self,
         pcs: &'a SC::Pcs,
-        traces: &'a [Option<RowMajorMatrix<Val<SC>>>],
+        traces: BTreeMap<ChipId, RowMajorMatrix<Val<SC>>>,
     );
 
-    fn load_main(&mut self, pcs: &'a SC::Pcs, traces: Vec<Option<RowMajorMatrix<Val<SC>>>>);
+    fn load_main(
+        &mut self,
+        pcs: &'a SC::Pcs,
+        traces: Vec<Option<RowMajorMatrix<Val<SC>>>>,
+        main_groups: &[usize],
+    );
 
     fn generate_permutation(
         &mut self,
@@ -180,24 +248,33 @@ where
         perm_challenges: [SC::Challenge; NUM_PERM_CHALLENGES],
     );
 
+    /// Evaluates the quotient of each chip, through its compiled constraint program when
+    /// `use_bytecode` is set, and directly otherwise.
     fn generate_quotient(
         &mut self,
         pcs: &'a SC::Pcs,
         preprocessed_data: &'a Option<PcsProverData<SC>>,
-        main_data: &'a Option<PcsProverData<SC>>,
+        main_data: &'a [Option<PcsProverData<SC>>],
         permutation_data: &'a Option<PcsProverData<SC>>,
         perm_challenges: [SC::Challenge; NUM_PERM_CHALLENGES],
         alpha: SC::Challenge,
-        public_values: &[Val<SC>],
+        public_values: &[Vec<Val<SC>>],
+        use_bytecode: bool,
     );
 }
 
 impl<'a, SC, C> MachineTraceLoader<'a, SC> for MachineTrace<SC, C>
 where
     SC: StarkGenericConfig,
+    SC::Pcs: Sync,
+    PcsProverData<SC>: Sync,
+    Domain<SC>: Send + Sync,
     C: Chip
         + for<'b> Rap<ProverConstraintFolder<'b, SC>>
-        + for<'b> Rap<SymbolicAirBuilder<Val<SC>>>,
+        + for<'b> Rap<SymbolicAirBuilder<Val<SC>>>
+        + PeriodicAir<Val<SC>>
+        + Send
+        + Sync,
 {
     fn generate_preprocessed(&mut self, pcs: &'a SC::Pcs) {
         let traces = self
@@ -206,25 +283,45 @@ where
             .collect_vec();
         let traces = load_traces::<SC, _>(pcs, traces);
         for (chip_trace, preprocessed) in self.iter_mut().zip_eq(traces) {
-            chip_trace.preprocessed = preprocessed;
+            chip_trace.preprocessed = preprocessed.map(Arc::new);
         }
     }
 
     fn load_preprocessed(
         &mut self,
         pcs: &'a SC::Pcs,
-        traces: &'a [Option<RowMajorMatrix<Val<SC>>>],
+        traces: BTreeMap<ChipId, RowMajorMatrix<Val<SC>>>,
     ) {
-        let traces = load_traces::<SC, _>(pcs, traces.to_vec());
-        for (chip_trace, preprocessed) in self.iter_mut().zip_eq(traces) {
-            chip_trace.preprocessed = preprocessed;
+        // The traces are laid out in the order of the chip ids, like the committed ones
+        let (ids, traces): (Vec<_>, Vec<_>) = traces
+            .into_iter()
+            .map(|(id, trace)| (id, Some(trace)))
+            .unzip();
+        let traces: BTreeMap<_, _> = ids
+            .into_iter()
+            .zip_eq(load_traces::<SC, _>(pcs, traces))
+            .map(|(id, trace)| (id, trace.map(Arc::new)))
+            .collect();
+        for chip_trace in self.iter_mut() {
+            chip_trace.preprocessed = traces.get(&chip_trace.id).cloned().flatten();
         }
     }
 
-    fn load_main(&mut self, pcs: &'a SC::Pcs, traces: Vec<Option<RowMajorMatrix<Val<SC>>>>) {
+    fn load_main(
+        &mut self,
+        pcs: &'a SC::Pcs,
+        traces: Vec<Option<RowMajorMatrix<Val<SC>>>>,
+        main_groups: &[usize],
+    ) {
         let traces = load_traces::<SC, _>(pcs, traces);
-        for (chip_trace, main) in self.iter_mut().zip_eq(traces) {
+        let mut counts = vec![0; num_groups(main_groups)];
+        for ((chip_trace, mut main), &group) in self.iter_mut().zip_eq(traces).zip_eq(main_groups) {
+            if let Some(main) = main.as_mut() {
+                main.opening_index = counts[group];
+                counts[group] += 1;
+            }
             chip_trace.main = main;
+            chip_trace.main_group = group;
         }
     }
 
@@ -233,7 +330,7 @@ where
         pcs: &'a SC::Pcs,
         perm_challenges: [SC::Challenge; NUM_PERM_CHALLENGES],
     ) {
-        let traces = self
+        let inputs = self
             .iter()
             .map(|trace| {
                 let preprocessed = trace
@@ -242,10 +339,15 @@ where
                     .map(|mt| mt.trace.value.as_view());
                 let main = trace.main.as_ref().map(|mt| mt.trace.value.as_view());
                 let interactions = trace.chip.all_interactions();
-
-                generate_permutation_trace(&preprocessed, &main, &interactions, perm_challenges)
+                (preprocessed, main, interactions)
             })
             .collect_vec();
+        let traces = inputs
+            .into_par_iter()
+            .map(|(preprocessed, main, interactions)| {
+                generate_permutation_trace(&preprocessed, &main, &interactions, perm_challenges)
+            })
+            .collect::<Vec<_>>();
         let cumulative_sums = traces
             .iter()
             .map(|mt| {
@@ -271,63 +373,71 @@ where
         &mut self,
         pcs: &'a SC::Pcs,
         preprocessed_data: &'a Option<PcsProverData<SC>>,
-        main_data: &'a Option<PcsProverData<SC>>,
+        main_data: &'a [Option<PcsProverData<SC>>],
         permutation_data: &'a Option<PcsProverData<SC>>,
         perm_challenges: [SC::Challenge; NUM_PERM_CHALLENGES],
         alpha: SC::Challenge,
-        public_values: &[Val<SC>],
+        public_values: &[Vec<Val<SC>>],
+        use_bytecode: bool,
     ) {
         let perm_challenges = perm_challenges.map(PackedChallenge::<SC>::from_f);
-        let alpha = PackedChallenge::<SC>::from_f(alpha);
 
-        let mut count = 0;
-        for chip_trace in self.iter_mut() {
+        self.par_iter_mut().for_each(|chip_trace| {
+            let public_values = &public_values[chip_trace.instance];
             let quotient_degree =
                 get_quotient_degree::<Val<SC>, _>(&chip_trace.chip, public_values.len());
+            let num_constraints =
+                get_num_constraints::<Val<SC>, _>(&chip_trace.chip, public_values.len());
             let trace_domain = chip_trace.domain();
 
             if let Some(trace_domain) = trace_domain {
                 let quotient_domain =
                     trace_domain.create_disjoint_domain(trace_domain.size() * quotient_degree);
 
-                let preprocessed_trace_on_quotient_domains =
-                    if let Some(preprocessed) = &chip_trace.preprocessed {
-                        pcs.get_evaluations_on_domain(
-                            preprocessed_data.as_ref().unwrap(),
-                            preprocessed.opening_index,
-                            quotient_domain,
-                        )
-                        .to_row_major_matrix()
-                    } else {
-                        RowMajorMatrix::new(vec![], 0)
-                    };
-                let main_trace_on_quotient_domains = if let Some(main) = &chip_trace.main {
-                    pcs.get_evaluations_on_domain(
-                        main_data.as_ref().unwrap(),
-                        main.opening_index,
-                        quotient_domain,
-                    )
-                    .to_row_major_matrix()
-                } else {
-                    RowMajorMatrix::new(vec![], 0)
+                let trace_on_quotient_domain = |data: &'a Option<PcsProverData<SC>>, index| {
+                    pcs.get_evaluations_on_domain(data.as_ref().unwrap(), index, quotient_domain)
                 };
+                let preprocessed_trace_on_quotient_domains =
+                    chip_trace.preprocessed.as_ref().map(|preprocessed| {
+                        trace_on_quotient_domain(preprocessed_data, preprocessed.opening_index)
+                    });
+                let main_trace_on_quotient_domains = chip_trace.main.as_ref().map(|main| {
+                    trace_on_quotient_domain(&main_data[chip_trace.main_group], main.opening_index)
+                });
                 let perm_trace_on_quotient_domains =
-                    if let Some(permutation) = &chip_trace.permutation {
-                        pcs.get_evaluations_on_domain(
-                            permutation_data.as_ref().unwrap(),
-                            permutation.opening_index,
-                            quotient_domain,
-                        )
-                        .to_row_major_matrix()
-                    } else {
-                        RowMajorMatrix::new(vec![], 0)
-                    };
+                    chip_trace.permutation.as_ref().map(|permutation| {
+                        trace_on_quotient_domain(permutation_data, permutation.opening_index)
+                    });
+                // Reading the LDEs in place avoids a copy of each, at the cost of slower row access.
+                #[cfg(not(feature = "low-memory"))]
+                let (
+                    preprocessed_trace_on_quotient_domains,
+                    main_trace_on_quotient_domains,
+                    perm_trace_on_quotient_domains,
+                ) = (
+                    preprocessed_trace_on_quotient_domains.map(|lde| lde.to_row_major_matrix()),
+                    main_trace_on_quotient_domains.map(|lde| lde.to_row_major_matrix()),
+                    perm_trace_on_quotient_domains.map(|lde| lde.to_row_major_matrix()),
+                );
 
                 let cumulative_sum = chip_trace
                     .cumulative_sum
                     .map(PackedChallenge::<SC>::from_f)
                     .unwrap_or_default();
 
+                let periodic_values_on_quotient_domain = periodic_values_on_coset(
+                    &chip_trace.chip.periodic_columns(),
+                    trace_domain,
+                    quotient_domain,
+                );
+                let row_selectors =
+                    get_row_selectors::<Val<SC>, _>(&chip_trace.chip, public_values.len());
+                let row_selectors_on_quotient_domain =
+                    row_selectors_on_coset(&row_selectors, trace_domain, quotient_domain);
+                let program = use_bytecode.then(|| {
+                    get_constraint_program::<Val<SC>, _>(&chip_trace.chip, public_values.len())
+                });
+
                 let quotient_values = quotient_values::<SC, _, _>(
                     &chip_trace.chip,
                     trace_domain,
@@ -335,10 +445,15 @@ where
                     preprocessed_trace_on_quotient_domains,
                     main_trace_on_quotient_domains,
                     perm_trace_on_quotient_domains,
+                    &periodic_values_on_quotient_domain,
+                    &row_selectors,
+                    &row_selectors_on_quotient_domain,
                     perm_challenges,
                     alpha,
+                    num_constraints,
                     cumulative_sum,
                     public_values,
+                    program.as_ref(),
                 );
                 let quotient_flat = RowMajorMatrix::new_col(quotient_values).flatten_to_base();
 
@@ -356,10 +471,18 @@ where
                 chip_trace.quotient_degree = Some(quotient_degree);
                 chip_trace.quotient_chunks = Some(QuotientTrace {
                     traces,
-                    opening_index: count,
+                    opening_index: 0,
                 });
-                count += 1;
             }
+        });
+
+        // Opening indices follow the chip order, independently of which chip finished first.
+        for (index, quotient_chunks) in self
+            .iter_mut()
+            .filter_map(|chip_trace| chip_trace.quotient_chunks.as_mut())
+            .enumerate()
+        {
+            quotient_chunks.opening_index = index;
         }
     }
 }
@@ -368,14 +491,31 @@ pub trait MachineTraceCommiter<'a, SC>
 where
     SC: StarkGenericConfig,
 {
-    fn commit_preprocessed(&self, pcs: &'a SC::Pcs)
-        -> (Option<Com<SC>>, Option<PcsProverData<SC>>);
+    /// In low-memory mode, the preprocessed traces are moved into the PCS instead of being cloned.
+    fn commit_preprocessed(
+        &mut self,
+        pcs: &'a SC::Pcs,
+    ) -> (Option<Com<SC>>, Option<PcsProverData<SC>>);
 
-    fn commit_main(&self, pcs: &'a SC::Pcs) -> (Option<Com<SC>>, Option<PcsProverData<SC>>);
+    /// Commits to the main traces of each main commitment group separately, in group order.
+    ///
+    /// In low-memory mode, the main traces are kept until `release_preprocessed_and_main`, as the
+    /// permutation traces are generated from them.
+    fn commit_main(&self, pcs: &'a SC::Pcs) -> Vec<(Option<Com<SC>>, Option<PcsProverData<SC>>)>;
+
+    /// In low-memory mode, the permutation traces are moved into the PCS instead of being cloned.
+    fn commit_permutation(
+        &mut self,
+        pcs: &'a SC::Pcs,
+    ) -> (Option<Com<SC>>, Option<PcsProverData<SC>>);
 
-    fn commit_permutation(&self, pcs: &'a SC::Pcs) -> (Option<Com<SC>>, Option<PcsProverData<SC>>);
+    /// In low-memory mode, the quotient chunks are moved into the PCS instead of being cloned.
+    fn commit_quotient(&mut self, pcs: &'a SC::Pcs)
+        -> (Option<Com<SC>>, Option<PcsProverData<SC>>);
 
-    fn commit_quotient(&self, pcs: &'a SC::Pcs) -> (Option<Com<SC>>, Option<PcsProverData<SC>>);
+    /// Drops the host copies of the preprocessed and main traces, keeping their domains. They are
+    /// only read again through the committed LDEs.
+    fn release_preprocessed_and_main(&mut self);
 }
 
 impl<'a, SC, C> MachineTraceCommiter<'a, SC> for MachineTrace<SC, C>
@@ -384,73 +524,118 @@ where
     C: Chip,
 {
     fn commit_preprocessed(
-        &self,
+        &mut self,
         pcs: &'a SC::Pcs,
     ) -> (Option<Com<SC>>, Option<PcsProverData<SC>>) {
         let traces = self
-            .iter()
+            .iter_mut()
             .flat_map(|trace| {
-                trace
-                    .preprocessed
-                    .as_ref()
-                    .map(|preprocessed| preprocessed.trace.clone())
+                trace.preprocessed.as_mut().map(|preprocessed| {
+                    if cfg!(feature = "low-memory") {
+                        let released = Arc::new(preprocessed.without_values());
+                        Arc::unwrap_or_clone(core::mem::replace(preprocessed, released)).trace
+                    } else {
+                        preprocessed.trace.clone()
+                    }
+                })
             })
             .collect_vec();
         commit_traces::<SC>(pcs, traces)
     }
 
-    fn commit_main(&self, pcs: &'a SC::Pcs) -> (Option<Com<SC>>, Option<PcsProverData<SC>>) {
-        let traces = self
-            .iter()
-            .flat_map(|trace| trace.main.as_ref().map(|main| main.trace.clone()))
-            .collect_vec();
-        commit_traces::<SC>(pcs, traces)
+    fn commit_main(&self, pcs: &'a SC::Pcs) -> Vec<(Option<Com<SC>>, Option<PcsProverData<SC>>)> {
+        let num_groups = num_groups(&self.iter().map(|trace| trace.main_group).collect_vec());
+        (0..num_groups)
+            .map(|group| {
+                // The main traces are still needed to generate the permutation traces, so they
+                // are cloned even in low-memory mode.
+                let traces = self
+                    .iter()
+                    .filter(|trace| trace.main_group == group)
+                    .flat_map(|trace| trace.main.as_ref().map(|main| main.trace.clone()))
+                    .collect_vec();
+                commit_traces::<SC>(pcs, traces)
+            })
+            .collect()
     }
 
-    fn commit_permutation(&self, pcs: &'a SC::Pcs) -> (Option<Com<SC>>, Option<PcsProverData<SC>>) {
+    fn commit_permutation(
+        &mut self,
+        pcs: &'a SC::Pcs,
+    ) -> (Option<Com<SC>>, Option<PcsProverData<SC>>) {
         let traces = self
-            .iter()
+            .iter_mut()
             .flat_map(|trace| {
-                trace
-                    .permutation
-                    .as_ref()
-                    .map(|permutation| permutation.trace.flatten_to_base())
+                trace.permutation.as_mut().map(|permutation| {
+                    if cfg!(feature = "low-memory") {
+                        permutation.trace.take().flatten_to_base()
+                    } else {
+                        permutation.trace.flatten_to_base()
+                    }
+                })
             })
             .collect_vec();
         commit_traces::<SC>(pcs, traces)
     }
 
-    fn commit_quotient(&self, pcs: &'a SC::Pcs) -> (Option<Com<SC>>, Option<PcsProverData<SC>>) {
+    fn commit_quotient(
+        &mut self,
+        pcs: &'a SC::Pcs,
+    ) -> (Option<Com<SC>>, Option<PcsProverData<SC>>) {
         let traces = self
-            .iter()
+            .iter_mut()
             .flat_map(|trace| {
-                trace
-                    .quotient_chunks
-                    .as_ref()
-                    .map(|quotient| quotient.traces.clone())
+                trace.quotient_chunks.as_mut().map(|quotient| {
+                    if cfg!(feature = "low-memory") {
+                        quotient.traces.iter_mut().map(Trace::take).collect_vec()
+                    } else {
+                        quotient.traces.clone()
+                    }
+                })
             })
             .flatten()
             .collect_vec();
         commit_traces::<SC>(pcs, traces)
     }
+
+    fn release_preprocessed_and_main(&mut self) {
+        for chip_trace in self.iter_mut() {
+            // The shared trace is dropped once every instance has released it
+            if let Some(preprocessed) = chip_trace.preprocessed.as_mut() {
+                *preprocessed = Arc::new(preprocessed.without_values());
+            }
+            if let Some(main) = chip_trace.main.as_mut() {
+                main.trace.take();
+            }
+        }
+    }
 }
 
 pub trait MachineTraceChecker<SC>
 where
     SC: StarkGenericConfig,
 {
-    fn check_constraints<B>(&self, perm_challenges: [SC::Challenge; 2], public_values: &[Val<SC>])
-    where
+    /// Checks the constraints of every chip, and that the buses of each instance balance.
+    fn check_constraints<B>(
+        &self,
+        perm_challenges: [SC::Challenge; 2],
+        public_values: &[Vec<Val<SC>>],
+    ) where
         B: Bus;
 }
 
 impl<SC, C> MachineTraceChecker<SC> for MachineTrace<SC, C>
 where
     SC: StarkGenericConfig,
-    C: Chip + for<'b> Rap<DebugConstraintBuilder<'b, Val<SC>, SC::Challenge>>,
+    C: Chip
+        + for<'b> Rap<DebugConstraintBuilder<'b, Val<SC>, SC::Challenge>>
+        + PeriodicAir<Val<SC>>,
 {
-    fn check_constraints<B>(&self, perm_challenges: [SC::Challenge; 2], public_values: &[Val<SC>])
-    where
+    fn check_constraints<B>(
+        &self,
+        perm_challenges: [SC::Challenge; 2],
+        public_values: &[Vec<Val<SC>>],
+    ) where
         B: Bus,
     {
         for chip_trace in self.iter() {
@@ -473,48 +658,55 @@ where
                 &permutation,
                 perm_challenges,
                 chip_trace.cumulative_sum,
-                public_values,
+                &public_values[chip_trace.instance],
             );
         }
-        let preprocessed_traces = self
-            .iter()
-            .map(|chip_trace| {
-                chip_trace
-                    .preprocessed
-                    .as_ref()
-                    .map(|preprocessed| preprocessed.trace.value.as_view())
-            })
-            .collect_vec();
-        let main_traces = self
-            .iter()
-            .map(|chip_trace| {
-                chip_trace
-                    .main
-                    .as_ref()
-                    .map(|main| main.trace.value.as_view())
-            })
-            .collect_vec();
-        let permutation_traces = self
-            .iter()
-            .map(|chip_trace| {
-                chip_trace
-                    .permutation
-                    .as_ref()
-                    .map(|permutation| permutation.trace.value.as_view())
-            })
-            .collect_vec();
 
-        let airs = self
-            .iter()
-            .map(|chip_trace| chip_trace.chip.clone())
-            .collect_vec();
+        for instance in 0..public_values.len() {
+            let chip_traces = self
+                .iter()
+                .filter(|chip_trace| chip_trace.instance == instance)
+                .collect_vec();
+            let preprocessed_traces = chip_traces
+                .iter()
+                .map(|chip_trace| {
+                    chip_trace
+                        .preprocessed
+                        .as_ref()
+                        .map(|preprocessed| preprocessed.trace.value.as_view())
+                })
+                .collect_vec();
+            let main_traces = chip_traces
+                .iter()
+                .map(|chip_trace| {
+                    chip_trace
+                        .main
+                        .as_ref()
+                        .map(|main| main.trace.value.as_view())
+                })
+                .collect_vec();
+            let permutation_traces = chip_traces
+                .iter()
+                .map(|chip_trace| {
+                    chip_trace
+                        .permutation
+                        .as_ref()
+                        .map(|permutation| permutation.trace.value.as_view())
+                })
+                .collect_vec();
 
-        check_cumulative_sums::<_, _, _, B>(
-            &airs,
-            preprocessed_traces.as_slice(),
-            main_traces.as_slice(),
-            permutation_traces.as_slice(),
-        );
+            let airs = chip_traces
+                .iter()
+                .map(|chip_trace| chip_trace.chip.clone())
+                .collect_vec();
+
+            check_cumulative_sums::<_, _, _, B>(
+                &airs,
+                preprocessed_traces.as_slice(),
+                main_traces.as_slice(),
+                permutation_traces.as_slice(),
+            );
+        }
     }
 }
 
@@ -525,18 +717,32 @@ where
     Val<SC>: PrimeField32,
 {
     // TODO: Move to separate trait
+    /// The entries of each chip trace tracked by `track_constraints`, with the public values of
+    /// its instance.
     fn track_constraints(
         &self,
         perm_challenges: [SC::Challenge; 2],
-        public_values: &[Val<SC>],
+        public_values: &[Vec<Val<SC>>],
     ) -> Vec<EntriesLog<TraceEntry>>;
 
     fn track_interactions(&self) -> Vec<EntriesLog<TraceEntry>>;
 
+    /// The headers of the main columns of each chip trace that are not constrained in any row,
+    /// neither by a constraint nor by an interaction.
+    fn find_unconstrained_columns(
+        &self,
+        perm_challenges: [SC::Challenge; NUM_PERM_CHALLENGES],
+        public_values: &[Vec<Val<SC>>],
+    ) -> Vec<Vec<String>>;
+
+    /// Writes the traces of the given chips, or of every chip, to an xlsx file, with a worksheet
+    /// per chip trace highlighting the failing entries of the constraints and interactions.
     fn write_traces_to_file(
         &self,
         path: &str,
         perm_challenges: [SC::Challenge; NUM_PERM_CHALLENGES],
+        public_values: &[Vec<Val<SC>>],
+        chips: Option<&[ChipId]>,
     ) -> Result<(), Box<dyn Error>>;
 }
 
@@ -547,12 +753,13 @@ where
     Val<SC>: PrimeField32,
     C: Chip
         + for<'b> Rap<DebugConstraintBuilder<'b, Val<SC>, SC::Challenge>>
-        + for<'b> Rap<TrackingConstraintBuilder<'b, Val<SC>, SC::Challenge>>,
+        + for<'b> Rap<TrackingConstraintBuilder<'b, Val<SC>, SC::Challenge>>
+        + PeriodicAir<Val<SC>>,
 {
     fn track_constraints(
         &self,
         perm_challenges: [SC::Challenge; 2],
-        public_values: &[Val<SC>],
+        public_values: &[Vec<Val<SC>>],
     ) -> Vec<EntriesLog<TraceEntry>> {
         let mut chip_indices = Vec::new();
         for chip_trace in self.iter() {
@@ -575,7 +782,7 @@ where
                 &permutation,
                 perm_challenges,
                 chip_trace.cumulative_sum,
-                public_values,
+                &public_values[chip_trace.instance],
             );
             chip_indices.push(indices);
         }
@@ -610,21 +817,59 @@ where
         track_interactions(&airs, &preprocessed_traces, &main_traces)
     }
 
-    fn write_traces_to_file(
+    fn find_unconstrained_columns(
         &self,
-        path: &str,
         perm_challenges: [SC::Challenge; NUM_PERM_CHALLENGES],
-    ) -> Result<(), Box<dyn Error>>
-    where
-        Val<SC>: PrimeField32,
-    {
-        use rust_xlsxwriter::Workbook;
+        public_values: &[Vec<Val<SC>>],
+    ) -> Vec<Vec<String>> {
+        self.iter()
+            .zip_eq(self.track_interactions())
+            .map(|(chip_trace, interaction_entries)| {
+                let Some(main) = chip_trace.main.as_ref() else {
+                    return vec![];
+                };
+                let preprocessed = chip_trace
+                    .preprocessed
+                    .as_ref()
+                    .map(|preprocessed| preprocessed.trace.value.as_view());
+                let permutation = chip_trace
+                    .permutation
+                    .as_ref()
+                    .map(|permutation| permutation.trace.value.as_view());
+                let mut entries = track_constraints(
+                    &chip_trace.chip,
+                    &preprocessed,
+                    &Some(main.trace.value.as_view()),
+                    &permutation,
+                    perm_challenges,
+                    chip_trace.cumulative_sum,
+                    &public_values[chip_trace.instance],
+                );
+                entries.extend(&interaction_entries);
 
-        let mut workbook = Workbook::new();
+                let headers = chip_trace.chip.main_headers();
+                find_unconstrained_columns(&entries, main.trace.value.width())
+                    .into_iter()
+                    .map(|col| {
+                        headers
+                            .get(col)
+                            .cloned()
+                            .unwrap_or_else(|| format!("main[{col}]"))
+                    })
+                    .collect()
+            })
+            .collect()
+    }
 
-        // TODO: Account for public values
+    fn write_traces_to_file(
+        &self,
+        path: &str,
+        perm_challenges: [SC::Challenge; NUM_PERM_CHALLENGES],
+        public_values: &[Vec<Val<SC>>],
+        chips: Option<&[ChipId]>,
+    ) -> Result<(), Box<dyn Error>> {
         let mut entries = vec![EntriesLog::default(); self.len()];
-        self.track_constraints(perm_challenges, &[])
+        self.track_constraints(perm_challenges, public_values)
             .iter()
             .zip(&mut entries)
             .for_each(|(entry, set)| set.extend(entry));
@@ -633,34 +878,89 @@ where
             .zip(&mut entries)
             .for_each(|(entry, set)| set.extend(entry));
 
-        for (chip_trace, chip_entries) in self.iter().zip(entries) {
-            let chip = &chip_trace.chip;
-
-            let worksheet = workbook.add_worksheet();
-            worksheet.set_name(format!("{}", chip))?;
-
-            let preprocessed_trace = chip_trace
-                .preprocessed
-                .as_ref()
-                .map(|preprocessed| preprocessed.trace.value.as_view());
-            let main_trace = chip_trace
-                .main
-                .as_ref()
-                .map(|main| main.trace.value.as_view());
+        let ids = self
+            .iter()
+            .map(|chip_trace| chip_trace.id.clone())
+            .collect_vec();
+        let traces = self
+            .iter()
+            .zip(entries)
+            .zip(ids.iter())
+            .zip(worksheet_names(&ids))
+            .filter(|((_, id), _)| chips.is_none_or(|chips| chips.contains(id)))
+            .map(|(((chip_trace, entries), _), name)| WorksheetTrace {
+                name,
+                chip: &chip_trace.chip,
+                preprocessed: chip_trace
+                    .preprocessed
+                    .as_ref()
+                    .map(|preprocessed| preprocessed.trace.value.as_view()),
+                main: chip_trace
+                    .main
+                    .as_ref()
+                    .map(|main| main.trace.value.as_view()),
+                entries,
+            });
+        write_workbook::<_, SC::Challenge, _>(path, traces)
+    }
+}
 
-            chip.write_traces_to_worksheet::<_, SC::Challenge>(
-                worksheet,
-                &preprocessed_trace,
-                &main_trace,
-                chip.all_interactions(),
-                chip_entries,
-            )?;
-        }
+/// The worksheet name of each chip trace, given the id of its chip. Chips with several traces,
+/// from several shards or instances, get a numbered worksheet for each.
+#[cfg(feature = "air-logger")]
+pub(crate) fn worksheet_names(ids: &[ChipId]) -> Vec<String> {
+    ids.iter()
+        .enumerate()
+        .map(|(i, id)| {
+            let count = |ids: &[ChipId]| ids.iter().filter(|other| *other == id).count();
+            if count(ids) == 1 {
+                id.clone()
+            } else {
+                format!("{id} {}", count(&ids[..i]))
+            }
+        })
+        .collect()
+}
 
-        workbook.save(path)?;
+/// A chip trace written to a worksheet of its own by `write_workbook`.
+#[cfg(feature = "air-logger")]
+pub(crate) struct WorksheetTrace<'a, F, C> {
+    pub name: String,
+    pub chip: &'a C,
+    pub preprocessed: Option<RowMajorMatrixView<'a, F>>,
+    pub main: Option<RowMajorMatrixView<'a, F>>,
+    pub entries: EntriesLog<TraceEntry>,
+}
 
-        Ok(())
+/// Writes each trace to a worksheet, highlighting its failing entries, and saves the workbook to
+/// an xlsx file.
+#[cfg(feature = "air-logger")]
+pub(crate) fn write_workbook<'a, F, EF, C>(
+    path: &str,
+    traces: impl IntoIterator<Item = WorksheetTrace<'a, F, C>>,
+) -> Result<(), Box<dyn Error>>
+where
+    F: PrimeField32,
+    EF: ExtensionField<F>,
+    C: Chip + InteractionAir<F> + 'a,
+{
+    use rust_xlsxwriter::Workbook;
+
+    let mut workbook = Workbook::new();
+    for trace in traces {
+        let worksheet = workbook.add_worksheet();
+        worksheet.set_name(&trace.name)?;
+        trace.chip.write_traces_to_worksheet::<_, EF>(
+            worksheet,
+            &trace.preprocessed,
+            &trace.main,
+            trace.chip.all_interactions(),
+            trace.entries,
+        )?;
     }
+    workbook.save(path)?;
+
+    Ok(())
 }
 
 pub trait MachineTraceOpener<'a, SC>
@@ -671,7 +971,7 @@ where
         &self,
         zeta: SC::Challenge,
         preprocessed_data: &'a Option<PcsProverData<SC>>,
-        main_data: &'a Option<PcsProverData<SC>>,
+        main_data: &'a [Option<PcsProverData<SC>>],
         permutation_data: &'a Option<PcsProverData<SC>>,
         quotient_data: &'a Option<PcsProverData<SC>>,
     ) -> Vec<(&'a PcsProverData<SC>, Vec<Vec<SC::Challenge>>)>;
@@ -680,7 +980,7 @@ where
         &self,
         opening_values: Vec<OpenedValuesForRound<SC::Challenge>>,
         preprocessed_data: &'a Option<PcsProverData<SC>>,
-        main_data: &'a Option<PcsProverData<SC>>,
+        main_data: &'a [Option<PcsProverData<SC>>],
         permutation_data: &'a Option<PcsProverData<SC>>,
         quotient_data: &'a Option<PcsProverData<SC>>,
     ) -> Vec<OpenedValues<SC::Challenge>>;
@@ -700,7 +1000,7 @@ where
         &self,
         zeta: SC::Challenge,
         preprocessed_data: &'a Option<PcsProverData<SC>>,
-        main_data: &'a Option<PcsProverData<SC>>,
+        main_data: &'a [Option<PcsProverData<SC>>],
         permutation_data: &'a Option<PcsProverData<SC>>,
         quotient_data: &'a Option<PcsProverData<SC>>,
     ) -> Vec<(&'a PcsProverData<SC>, Vec<Vec<SC::Challenge>>)> {
@@ -708,6 +1008,7 @@ where
         if let Some(preprocessed_data) = preprocessed_data {
             let opening_points = self
                 .iter()
+                .filter(|chip_trace| chip_trace.instance == 0)
                 .flat_map(|chip_trace| {
                     chip_trace.preprocessed.as_ref().map(|preprocessed| {
                         let domain = preprocessed.trace.domain;
@@ -717,17 +1018,20 @@ where
                 .collect_vec();
             rounds.push((preprocessed_data, opening_points));
         }
-        if let Some(main_data) = main_data {
-            let opening_points = self
-                .iter()
-                .flat_map(|chip_trace| {
-                    chip_trace.main.as_ref().map(|main| {
-                        let domain = main.trace.domain;
-                        vec![zeta, domain.next_point(zeta).unwrap()]
+        for (group, main_data) in main_data.iter().enumerate() {
+            if let Some(main_data) = main_data {
+                let opening_points = self
+                    .iter()
+                    .filter(|chip_trace| chip_trace.main_group == group)
+                    .flat_map(|chip_trace| {
+                        chip_trace.main.as_ref().map(|main| {
+                            let domain = main.trace.domain;
+                            vec![zeta, domain.next_point(zeta).unwrap()]
+                        })
                     })
-                })
-                .collect_vec();
-            rounds.push((main_data, opening_points));
+                    .collect_vec();
+                rounds.push((main_data, opening_points));
+            }
         }
         if let Some(permutation_data) = permutation_data {
             let opening_points = self
@@ -762,7 +1066,7 @@ where
         &self,
         mut opening_values: Vec<OpenedValuesForRound<SC::Challenge>>,
         preprocessed_data: &'a Option<PcsProverData<SC>>,
-        main_data: &'a Option<PcsProverData<SC>>,
+        main_data: &'a [Option<PcsProverData<SC>>],
         permutation_data: &'a Option<PcsProverData<SC>>,
         quotient_data: &'a Option<PcsProverData<SC>>,
     ) -> Vec<OpenedValues<SC::Challenge>> {
@@ -813,24 +1117,32 @@ where
             self.iter().map(|_| None).collect_vec()
         };
 
-        let main_openings = if main_data.is_some() {
-            let openings = opening_values.pop().expect("Opening should be present");
-            self.iter()
-                .map(|chip_trace| {
-                    chip_trace.main.as_ref().map(|main| {
-                        let openings = &openings[main.opening_index];
-                        assert_eq!(openings.len(), 2, "Should have 2 openings");
-                        AdjacentOpenedValues {
-                            local: openings[0].clone(),
-                            next: openings[1].clone(),
-                        }
-                    })
+        // Main rounds are popped last to first.
+        let mut main_group_openings = main_data
+            .iter()
+            .rev()
+            .map(|data| {
+                data.as_ref()
+                    .map(|_| opening_values.pop().expect("Opening should be present"))
+            })
+            .collect_vec();
+        main_group_openings.reverse();
+        let main_openings = self
+            .iter()
+            .map(|chip_trace| {
+                chip_trace.main.as_ref().map(|main| {
+                    let openings = main_group_openings[chip_trace.main_group]
+                        .as_ref()
+                        .expect("Opening should be present");
+                    let openings = &openings[main.opening_index];
+                    assert_eq!(openings.len(), 2, "Should have 2 openings");
+                    AdjacentOpenedValues {
+                        local: openings[0].clone(),
+                        next: openings[1].clone(),
+                    }
                 })
-                .collect_vec()
-        } else {
-            // TODO: Better way
-            self.iter().map(|_| None).collect_vec()
-        };
+            })
+            .collect_vec();
 
         let preprocessed_openings = if preprocessed_data.is_some() {
             let openings = opening_values.pop().expect("Opening should be present");
@@ -889,6 +1201,11 @@ where
     }
 }
 
+/// The number of main commitment groups, given the group of each chip.
+pub(crate) fn num_groups(main_groups: &[usize]) -> usize {
+    main_groups.iter().max().map_or(0, |group| group + 1)
+}
+
 fn load_traces<SC, F>(
     pcs: &SC::Pcs,
     traces: Vec<Option<RowMajorMatrix<F>>>,
@@ -981,12 +1298,17 @@ where
     SC: StarkGenericConfig,
     C: Chip,
 {
+    /// The chip's id within its machine.
+    pub id: ChipId,
     pub chip: C,
 
     pub preprocessed: Option<TraceOpening<SC::Challenge, Domain<SC>>>,
     pub main: Option<TraceOpening<SC::Challenge, Domain<SC>>>,
     pub permutation: Option<TraceOpening<SC::Challenge, Domain<SC>>>,
 
+    pub main_group: usize,
+    pub instance: usize,
+
     pub cumulative_sum: Option<SC::Challenge>,
 
     pub quotient_chunks: Option<QuotientTraceOpening<SC::Challenge, Domain<SC>>>,
@@ -998,12 +1320,15 @@ where
     SC: StarkGenericConfig,
     C: Chip,
 {
-    pub fn new(chip: C) -> Self {
+    pub fn new(id: ChipId, chip: C) -> Self {
         Self {
+            id,
             chip,
             preprocessed: None,
             main: None,
             permutation: None,
+            main_group: 0,
+            instance: 0,
             cumulative_sum: None,
             quotient_chunks: None,
             quotient_degree: None,
@@ -1036,7 +1361,8 @@ where
     SC: StarkGenericConfig,
     C: Chip,
 {
-    fn new(chips: &[C]) -> Self;
+    /// Lays out one chip trace per chip, given with its id.
+    fn new(chips: &[(ChipId, C)]) -> Self;
 }
 
 impl<SC, C> MachineTraceOpeningBuilder<SC, C> for MachineTraceOpening<SC, C>
@@ -1044,10 +1370,10 @@ where
     SC: StarkGenericConfig,
     C: Chip,
 {
-    fn new(chips: &[C]) -> Self {
+    fn new(chips: &[(ChipId, C)]) -> Self {
         chips
             .iter()
-            .map(|chip| ChipTraceOpening::new(chip.clone()))
+            .map(|(id, chip)| ChipTraceOpening::new(id.clone(), chip.clone()))
             .collect_vec()
     }
 }
@@ -1061,27 +1387,33 @@ where
         pcs: &'a SC::Pcs,
         chip_proofs: Vec<Option<InteractionAirProof<SC::Challenge>>>,
         preprocessed_degrees: Vec<usize>,
+        main_groups: &[usize],
+        num_public_values: &[usize],
     );
 
-    fn verify_shapes(&self) -> Result<(), VerificationError>;
+    fn verify_shapes(&self, num_public_values: &[usize]) -> Result<(), VerificationError>;
 }
 
 impl<'a, SC, C> MachineTraceOpeningLoader<'a, SC> for Vec<ChipTraceOpening<SC, C>>
 where
     SC: StarkGenericConfig,
-    C: Chip + for<'b> Rap<SymbolicAirBuilder<Val<SC>>>,
+    C: Chip + for<'b> Rap<SymbolicAirBuilder<Val<SC>>> + PeriodicAir<Val<SC>>,
 {
     fn load_openings(
         &mut self,
         pcs: &'a SC::Pcs,
         chip_proofs: Vec<Option<InteractionAirProof<SC::Challenge>>>,
         preprocessed_degrees: Vec<usize>,
+        main_groups: &[usize],
+        num_public_values: &[usize],
     ) {
-        for ((chip_trace, chip_proof), preprocessed_degree) in self
+        for (((chip_trace, chip_proof), preprocessed_degree), &main_group) in self
             .iter_mut()
             .zip_eq(chip_proofs.into_iter())
             .zip_eq(preprocessed_degrees.into_iter())
+            .zip_eq(main_groups)
         {
+            chip_trace.main_group = main_group;
             if let Some(proof) = chip_proof {
                 chip_trace.preprocessed = proof.opened_values.preprocessed.map(|values| {
                     let domain = pcs.natural_domain_for_degree(preprocessed_degree);
@@ -1099,17 +1431,20 @@ where
                     .map(|values| TraceOpening { values, domain });
                 chip_trace.cumulative_sum = proof.cumulative_sum;
 
-                // TODO: Pub values
-                let quotient_degree = get_quotient_degree::<Val<SC>, _>(&chip_trace.chip, 0);
+                let quotient_degree = get_quotient_degree::<Val<SC>, _>(
+                    &chip_trace.chip,
+                    num_public_values[chip_trace.instance],
+                );
                 chip_trace.quotient_degree = Some(quotient_degree);
 
                 let quotient_domain =
                     domain.create_disjoint_domain(domain.size() * quotient_degree);
                 let quotient_chunks_domains = quotient_domain.split_domains(quotient_degree);
+                // The number of chunks is checked by `verify_shapes`
                 chip_trace.quotient_chunks = proof.opened_values.quotient_chunks.map(|chunks| {
                     let values = chunks
                         .into_iter()
-                        .zip_eq(quotient_chunks_domains.into_iter())
+                        .zip(quotient_chunks_domains.into_iter())
                         .map(|(chunk, domain)| SingleQuotientTraceOpening {
                             values: chunk,
                             domain,
@@ -1121,12 +1456,56 @@ where
         }
     }
 
-    fn verify_shapes(&self) -> Result<(), VerificationError> {
-        // TODO: Add preprocessed and permutation size check
+    fn verify_shapes(&self, num_public_values: &[usize]) -> Result<(), VerificationError> {
         for chip_trace in self.iter() {
+            let num_public_values = num_public_values[chip_trace.instance];
             // TODO: Try to do without the cast
             let main_width = <C as BaseAir<Val<SC>>>::width(&chip_trace.chip);
+            let preprocessed_width =
+                <C as Rap<SymbolicAirBuilder<Val<SC>>>>::preprocessed_width(&chip_trace.chip);
+            let permutation_width =
+                <C as Rap<SymbolicAirBuilder<Val<SC>>>>::permutation_width(&chip_trace.chip)
+                    .map(|width| width * <SC::Challenge as AbstractExtensionField<Val<SC>>>::D);
 
+            if let Some(domain) = chip_trace.domain() {
+                let periodic_columns = chip_trace.chip.periodic_columns();
+                if !periodic_columns
+                    .iter()
+                    .all(|col| col.len().is_power_of_two() && col.len() <= domain.size())
+                {
+                    return Err(VerificationError::InvalidProofShape);
+                }
+                let row_selectors =
+                    get_row_selectors::<Val<SC>, _>(&chip_trace.chip, num_public_values);
+                if !row_selectors.iter().all(|selector| match selector {
+                    RowSelector::EveryNthRow { period, offset } => {
+                        period.is_power_of_two() && *period <= domain.size() && offset < period
+                    }
+                    _ => true,
+                }) {
+                    return Err(VerificationError::InvalidProofShape);
+                }
+                // An opened chip has a main trace unless it has no main columns, a permutation
+                // trace exactly when it has interactions, and quotient chunks
+                if chip_trace.main.is_none() && main_width > 0 {
+                    return Err(VerificationError::InvalidProofShape);
+                }
+                if chip_trace.permutation.is_some() != permutation_width.is_some()
+                    || chip_trace.cumulative_sum.is_some() != permutation_width.is_some()
+                {
+                    return Err(VerificationError::InvalidProofShape);
+                }
+                if chip_trace.quotient_chunks.is_none() {
+                    return Err(VerificationError::InvalidProofShape);
+                }
+            }
+            if let Some(preprocessed) = &chip_trace.preprocessed {
+                if preprocessed.values.local.len() != preprocessed_width
+                    || preprocessed.values.next.len() != preprocessed_width
+                {
+                    return Err(VerificationError::InvalidProofShape);
+                }
+            }
             if let Some(main) = &chip_trace.main {
                 if main.values.local.len() != main_width {
                     return Err(VerificationError::InvalidProofShape);
@@ -1135,9 +1514,16 @@ where
                     return Err(VerificationError::InvalidProofShape);
                 }
             }
+            if let Some(permutation) = &chip_trace.permutation {
+                if Some(permutation.values.local.len()) != permutation_width
+                    || Some(permutation.values.next.len()) != permutation_width
+                {
+                    return Err(VerificationError::InvalidProofShape);
+                }
+            }
             if let Some(quotient_chunks) = &chip_trace.quotient_chunks {
-                // TODO: Pub values
-                let quotient_degree = get_quotient_degree::<Val<SC>, _>(&chip_trace.chip, 0);
+                let quotient_degree =
+                    get_quotient_degree::<Val<SC>, _>(&chip_trace.chip, num_public_values);
                 if quotient_chunks.traces.len() != quotient_degree {
                     return Err(VerificationError::InvalidProofShape);
                 }
@@ -1161,7 +1547,7 @@ where
         &self,
         zeta: SC::Challenge,
         preprocessed_commitment: &Option<Com<SC>>,
-        main_commitment: &Option<Com<SC>>,
+        main_commitments: &[Option<Com<SC>>],
         permutation_commitment: &Option<Com<SC>>,
         quotient_chunks_commitment: &Option<Com<SC>>,
     ) -> Vec<(
@@ -1179,7 +1565,7 @@ where
         &self,
         zeta: SC::Challenge,
         preprocessed_commitment: &Option<Com<SC>>,
-        main_commitment: &Option<Com<SC>>,
+        main_commitments: &[Option<Com<SC>>],
         permutation_commitment: &Option<Com<SC>>,
         quotient_chunks_commitment: &Option<Com<SC>>,
     ) -> Vec<(
@@ -1190,6 +1576,7 @@ where
         if let Some(preprocessed_commitment) = preprocessed_commitment {
             let preprocessed_domains_and_openings = self
                 .iter()
+                .filter(|chip_trace| chip_trace.instance == 0)
                 .filter_map(|chip_trace| {
                     chip_trace.preprocessed.as_ref().map(|trace| {
                         (
@@ -1210,25 +1597,28 @@ where
                 preprocessed_domains_and_openings,
             ));
         }
-        if let Some(main_commitment) = main_commitment {
-            let main_domains_and_openings = self
-                .iter()
-                .filter_map(|chip_trace| {
-                    chip_trace.main.as_ref().map(|trace| {
-                        (
-                            trace.domain,
-                            vec![
-                                (zeta, trace.values.local.clone()),
-                                (
-                                    trace.domain.next_point(zeta).unwrap(),
-                                    trace.values.next.clone(),
-                                ),
-                            ],
-                        )
+        for (group, main_commitment) in main_commitments.iter().enumerate() {
+            if let Some(main_commitment) = main_commitment {
+                let main_domains_and_openings = self
+                    .iter()
+                    .filter(|chip_trace| chip_trace.main_group == group)
+                    .filter_map(|chip_trace| {
+                        chip_trace.main.as_ref().map(|trace| {
+                            (
+                                trace.domain,
+                                vec![
+                                    (zeta, trace.values.local.clone()),
+                                    (
+                                        trace.domain.next_point(zeta).unwrap(),
+                                        trace.values.next.clone(),
+                                    ),
+                                ],
+                            )
+                        })
                     })
-                })
-                .collect_vec();
-            rounds.push((main_commitment.clone(), main_domains_and_openings));
+                    .collect_vec();
+                rounds.push((main_commitment.clone(), main_domains_and_openings));
+            }
         }
         if let Some(permutation_commitment) = permutation_commitment {
             let permutation_domains_and_openings = self
@@ -1285,25 +1675,30 @@ where
         zeta: SC::Challenge,
         alpha: SC::Challenge,
         permutation_challenges: [SC::Challenge; NUM_PERM_CHALLENGES],
-        public_values: &[Val<SC>],
+        public_values: &[Vec<Val<SC>>],
     ) -> Result<(), VerificationError>;
 
+    /// Checks that the cumulative sums of each instance add up to zero.
     fn verify_cumulative_sums(&self) -> Result<(), VerificationError>;
 }
 
 impl<SC, C> MachineTraceConstraintVerifier<SC> for MachineTraceOpening<SC, C>
 where
     SC: StarkGenericConfig,
-    C: Chip + for<'b> Rap<VerifierConstraintFolder<'b, SC>>,
+    C: Chip
+        + for<'b> Rap<SymbolicAirBuilder<Val<SC>>>
+        + for<'b> Rap<VerifierConstraintFolder<'b, SC>>
+        + PeriodicAir<Val<SC>>,
 {
     fn verify_constraints(
         &self,
         zeta: SC::Challenge,
         alpha: SC::Challenge,
         permutation_challenges: [SC::Challenge; NUM_PERM_CHALLENGES],
-        public_values: &[Val<SC>],
+        public_values: &[Vec<Val<SC>>],
     ) -> Result<(), VerificationError> {
         for chip_trace in self.iter() {
+            let public_values = &public_values[chip_trace.instance];
             if let Some(domain) = chip_trace.domain() {
                 let qc_domains = chip_trace
                     .quotient_chunks
@@ -1332,6 +1727,11 @@ where
                             .collect_vec()
                     }),
                 };
+                let periodic_values =
+                    periodic_values_at_point(&chip_trace.chip.periodic_columns(), domain, zeta);
+                let row_selectors =
+                    get_row_selectors::<Val<SC>, _>(&chip_trace.chip, public_values.len());
+                let row_selector_values = row_selectors_at_point(&row_selectors, domain, zeta);
                 verify_constraints::<SC, _>(
                     &chip_trace.chip,
                     &opened_values,
@@ -1342,6 +1742,9 @@ where
                     permutation_challenges,
                     chip_trace.cumulative_sum,
                     public_values,
+                    &periodic_values,
+                    &row_selectors,
+                    &row_selector_values,
                 )?;
             }
         }
@@ -1349,12 +1752,19 @@ where
     }
 
     fn verify_cumulative_sums(&self) -> Result<(), VerificationError> {
-        let sum: SC::Challenge = self
+        let num_instances = self
             .iter()
-            .flat_map(|chip_trace| chip_trace.cumulative_sum)
-            .sum();
+            .map(|chip_trace| chip_trace.instance + 1)
+            .max()
+            .unwrap_or_default();
+        let mut sums = vec![SC::Challenge::zero(); num_instances];
+        for chip_trace in self.iter() {
+            if let Some(cumulative_sum) = chip_trace.cumulative_sum {
+                sums[chip_trace.instance] += cumulative_sum;
+            }
+        }
 
-        if sum != SC::Challenge::zero() {
+        if sums.iter().any(|sum| *sum != SC::Challenge::zero()) {
             return Err(VerificationError::NonZeroCumulativeSum);
         }
         Ok(())
@@ -4,6 +4,7 @@ use alloc::vec::Vec;
 use itertools::Itertools;
 use p3_air_util::folders::rap::VerifierConstraintFolder;
 use p3_air_util::proof::OpenedValues;
+use p3_air_util::RowSelector;
 use p3_commit::PolynomialSpace;
 use p3_field::{AbstractExtensionField, AbstractField, Field};
 use p3_interaction::Rap;
@@ -26,6 +27,9 @@ pub fn verify_constraints<SC, A>(
     permutation_challenges: [SC::Challenge; NUM_PERM_CHALLENGES],
     cumulative_sum: Option<SC::Challenge>,
     public_values: &[Val<SC>],
+    periodic_values: &[SC::Challenge],
+    row_selectors: &[RowSelector],
+    row_selector_values: &[SC::Challenge],
 ) -> Result<(), VerificationError>
 where
     SC: StarkGenericConfig,
@@ -113,6 +117,9 @@ where
         ),
         perm_challenges: permutation_challenges,
         public_values,
+        periodic_values,
+        row_selectors,
+        row_selector_values,
         cumulative_sum: cumulative_sum.unwrap_or_default(),
         is_first_row: sels.is_first_row,
         is_last_row: sels.is_last_row,
@@ -0,0 +1,107 @@
+mod common;
+
+use p3_machine::chip::Chip;
+use p3_machine::error::VerificationError;
+use p3_machine::machine::{Instance, Machine};
+use p3_machine::options::ProverOptions;
+use p3_machine::proof::BatchMachineProof;
+
+use common::counter::{counter_trace, CounterChip, CounterMachine};
+use common::{default_config, MyConfig};
+
+/// A machine with a chip sending its first column on bus 0 and a chip receiving it.
+fn machine() -> CounterMachine {
+    CounterMachine::new(vec![
+        CounterChip {
+            sends: true,
+            ..CounterChip::new(1)
+        },
+        CounterChip {
+            receives: true,
+            ..CounterChip::new(2)
+        },
+    ])
+}
+
+/// Proves one instance per pair of sender and receiver heights, lets `tamper` modify the proof,
+/// and verifies it.
+fn prove_and_verify_batch(
+    heights: &[(usize, usize)],
+    options: &ProverOptions,
+    tamper: impl FnOnce(&mut BatchMachineProof<MyConfig>),
+) -> Result<(), VerificationError> {
+    let machine = machine();
+    let (config, challenger) = default_config();
+    let (pk, vk) = machine.setup(&config);
+    let instances = heights
+        .iter()
+        .map(|&(sender_height, receiver_height)| Instance {
+            main_traces: machine
+                .chips
+                .iter()
+                .zip([sender_height, receiver_height])
+                .map(|(chip, height)| (chip.id(), counter_trace(chip.width, height)))
+                .collect(),
+            public_values: vec![],
+        })
+        .collect();
+
+    let mut proof = machine.prove_batch(&config, &mut challenger.clone(), &pk, instances, options);
+    tamper(&mut proof);
+    let public_values = vec![vec![]; heights.len()];
+    machine.verify_batch(
+        &config,
+        &mut challenger.clone(),
+        &vk,
+        &proof,
+        &public_values,
+    )
+}
+
+#[test]
+fn test_single_instance() {
+    prove_and_verify_batch(&[(8, 8)], &ProverOptions::default(), |_| {}).unwrap();
+}
+
+#[test]
+fn test_multiple_instances() {
+    prove_and_verify_batch(
+        &[(8, 8), (16, 16), (4, 4)],
+        &ProverOptions::default(),
+        |proof| {
+            assert_eq!(proof.chip_proofs.len(), 3);
+        },
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_unbalanced_instances_rejected() {
+    // The buses balance across the batch but not within each instance. The prover's own check of
+    // the cumulative sums is skipped, so that the proof reaches the verifier.
+    let options = ProverOptions {
+        check_constraints: false,
+        ..Default::default()
+    };
+    let result = prove_and_verify_batch(&[(8, 16), (16, 8)], &options, |_| {});
+    assert!(matches!(
+        result,
+        Err(VerificationError::NonZeroCumulativeSum)
+    ));
+}
+
+#[test]
+fn test_swapped_instances_rejected() {
+    let result = prove_and_verify_batch(&[(8, 8), (16, 16)], &ProverOptions::default(), |proof| {
+        proof.chip_proofs.swap(0, 1);
+    });
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_missing_instance_rejected() {
+    let result = prove_and_verify_batch(&[(8, 8), (16, 16)], &ProverOptions::default(), |proof| {
+        proof.chip_proofs.pop();
+    });
+    assert!(matches!(result, Err(VerificationError::InvalidProofShape)));
+}
@@ -0,0 +1,227 @@
+mod common;
+
+use core::fmt::{self, Display, Formatter};
+use std::time::{Duration, Instant};
+
+use itertools::Itertools;
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_air_util::folders::rap::ProverConstraintFolder;
+use p3_air_util::folders::ViewPair;
+use p3_air_util::{get_num_constraints, PeriodicAir};
+use p3_field::{AbstractExtensionField, AbstractField, Field, PackedValue};
+use p3_interaction::{BaseInteractionAir, InteractionAir, InteractionAirBuilder, Rap};
+use p3_matrix::dense::{RowMajorMatrix, RowMajorMatrixView};
+use p3_matrix::stack::VerticalPair;
+use p3_matrix::Matrix;
+use p3_uni_stark::{PackedChallenge, PackedVal};
+use rand::{thread_rng, Rng};
+
+use common::{Challenge, MyConfig, Val};
+
+const WIDTH: usize = 512;
+const LOG_QUOTIENT_SIZE: usize = 14;
+
+/// A wide chip with only base field constraints.
+#[derive(Clone, Debug)]
+struct WideChip;
+
+impl Display for WideChip {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "WideChip")
+    }
+}
+
+impl<F: Field> BaseAir<F> for WideChip {
+    fn width(&self) -> usize {
+        WIDTH
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for WideChip {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let next = main.row_slice(1);
+        for i in 0..WIDTH {
+            let x: AB::Expr = local[i].into();
+            let y: AB::Expr = local[(i + 1) % WIDTH].into();
+            builder
+                .when_first_row()
+                .assert_zero(x.clone() * x.clone() - y.clone());
+            builder
+                .when_transition()
+                .assert_eq(next[i], x.clone() * y + x);
+        }
+    }
+}
+
+impl<F: Field> BaseInteractionAir<F> for WideChip {}
+
+impl<F: Field> InteractionAir<F> for WideChip {}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for WideChip {}
+
+impl<F: Field> PeriodicAir<F> for WideChip {}
+
+/// The previous prover folder, which folds every constraint with a full extension field multiply.
+struct HornerFolder<'a> {
+    main: ViewPair<'a, PackedVal<MyConfig>>,
+    is_first_row: PackedVal<MyConfig>,
+    is_last_row: PackedVal<MyConfig>,
+    is_transition: PackedVal<MyConfig>,
+    alpha: PackedChallenge<MyConfig>,
+    accumulator: PackedChallenge<MyConfig>,
+}
+
+impl<'a> AirBuilder for HornerFolder<'a> {
+    type F = Val;
+    type Expr = PackedVal<MyConfig>;
+    type Var = PackedVal<MyConfig>;
+    type M = ViewPair<'a, PackedVal<MyConfig>>;
+
+    fn main(&self) -> Self::M {
+        self.main
+    }
+
+    fn is_first_row(&self) -> Self::Expr {
+        self.is_first_row
+    }
+
+    fn is_last_row(&self) -> Self::Expr {
+        self.is_last_row
+    }
+
+    fn is_transition_window(&self, size: usize) -> Self::Expr {
+        assert_eq!(size, 2);
+        self.is_transition
+    }
+
+    fn assert_zero<I: Into<Self::Expr>>(&mut self, x: I) {
+        let x: PackedVal<MyConfig> = x.into();
+        self.accumulator *= self.alpha;
+        self.accumulator += x;
+    }
+}
+
+type PackedRow = Vec<PackedVal<MyConfig>>;
+
+fn horner_accumulators(
+    rows: &[(PackedRow, PackedRow)],
+    sels: &[PackedVal<MyConfig>; 3],
+    alpha: Challenge,
+) -> Vec<PackedChallenge<MyConfig>> {
+    rows.iter()
+        .map(|(local, next)| {
+            let mut folder = HornerFolder {
+                main: VerticalPair::new(
+                    RowMajorMatrixView::new_row(local),
+                    RowMajorMatrixView::new_row(next),
+                ),
+                is_first_row: sels[0],
+                is_last_row: sels[1],
+                is_transition: sels[2],
+                alpha: PackedChallenge::<MyConfig>::from_f(alpha),
+                accumulator: PackedChallenge::<MyConfig>::zero(),
+            };
+            WideChip.eval(&mut folder);
+            folder.accumulator
+        })
+        .collect()
+}
+
+fn prover_accumulators(
+    rows: &[(PackedRow, PackedRow)],
+    sels: &[PackedVal<MyConfig>; 3],
+    alpha: Challenge,
+    num_constraints: usize,
+) -> Vec<PackedChallenge<MyConfig>> {
+    let mut alpha_powers = alpha.powers().take(num_constraints).collect_vec();
+    alpha_powers.reverse();
+    let decomposed_alpha_powers = (0..<Challenge as AbstractExtensionField<Val>>::D)
+        .map(|i| {
+            alpha_powers
+                .iter()
+                .map(|alpha_power| alpha_power.as_base_slice()[i])
+                .collect_vec()
+        })
+        .collect_vec();
+
+    let empty: Vec<PackedVal<MyConfig>> = vec![];
+    let empty_ext: Vec<PackedChallenge<MyConfig>> = vec![];
+    rows.iter()
+        .map(|(local, next)| {
+            let mut folder = ProverConstraintFolder::<MyConfig> {
+                preprocessed: VerticalPair::new(
+                    RowMajorMatrixView::new_row(&empty),
+                    RowMajorMatrixView::new_row(&empty),
+                ),
+                main: VerticalPair::new(
+                    RowMajorMatrixView::new_row(local),
+                    RowMajorMatrixView::new_row(next),
+                ),
+                perm: VerticalPair::new(
+                    RowMajorMatrixView::new_row(&empty_ext),
+                    RowMajorMatrixView::new_row(&empty_ext),
+                ),
+                perm_challenges: [PackedChallenge::<MyConfig>::zero(); 2],
+                public_values: &[],
+                periodic_values: &[],
+                row_selectors: &[],
+                row_selector_values: &[],
+                cumulative_sum: PackedChallenge::<MyConfig>::zero(),
+                is_first_row: sels[0],
+                is_last_row: sels[1],
+                is_transition: sels[2],
+                alpha_powers: &alpha_powers,
+                decomposed_alpha_powers: &decomposed_alpha_powers,
+                base_accumulators: vec![
+                    PackedVal::<MyConfig>::zero();
+                    decomposed_alpha_powers.len()
+                ],
+                ext_accumulator: PackedChallenge::<MyConfig>::zero(),
+                constraint_index: 0,
+            };
+            WideChip.eval(&mut folder);
+            folder.accumulator()
+        })
+        .collect()
+}
+
+fn time<T>(f: impl FnOnce() -> T) -> (T, Duration) {
+    let start = Instant::now();
+    let result = f();
+    (result, start.elapsed())
+}
+
+#[test]
+fn bench_quotient_wide_chip() {
+    let mut rng = thread_rng();
+    let quotient_size = 1 << LOG_QUOTIENT_SIZE;
+    let trace = RowMajorMatrix::<Val>::rand(&mut rng, quotient_size, WIDTH);
+    let rows = (0..quotient_size)
+        .step_by(PackedVal::<MyConfig>::WIDTH)
+        .map(|i_start| {
+            let local = trace.vertically_packed_row(i_start).collect_vec();
+            let next = trace
+                .vertically_packed_row((i_start + 2) % quotient_size)
+                .collect_vec();
+            (local, next)
+        })
+        .collect_vec();
+    let sels = [(); 3].map(|_| PackedVal::<MyConfig>::from_fn(|_| rng.gen()));
+    let alpha: Challenge = rng.gen();
+    let num_constraints = get_num_constraints::<Val, _>(&WideChip, 0);
+
+    let (expected, horner_time) = time(|| horner_accumulators(&rows, &sels, alpha));
+    let (actual, prover_time) = time(|| prover_accumulators(&rows, &sels, alpha, num_constraints));
+    assert_eq!(actual, expected);
+
+    tracing::info!(
+        "quotient evaluation of {} constraints over {} rows: horner fold {:?}, split accumulators {:?} ({:.2}x)",
+        num_constraints,
+        quotient_size,
+        horner_time,
+        prover_time,
+        horner_time.as_secs_f64() / prover_time.as_secs_f64(),
+    );
+}
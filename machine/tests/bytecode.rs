@@ -0,0 +1,327 @@
+mod common;
+
+use core::fmt::{self, Display, Formatter};
+
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir, VirtualPairCol};
+use p3_air_util::folders::rap::SymbolicAirBuilder;
+use p3_air_util::{
+    get_constraint_program, get_num_constraints, get_quotient_degree, get_row_selectors,
+    Instruction, PeriodicAir, PeriodicAirBuilder, Register, SelectorAirBuilder,
+};
+use p3_commit::{Pcs, PolynomialSpace};
+use p3_field::{AbstractExtensionField, AbstractField, Field};
+use p3_interaction::{BaseInteractionAir, Interaction, InteractionAir, InteractionAirBuilder, Rap};
+use p3_machine::chip::Chip;
+use p3_machine::machine::Machine;
+use p3_machine::options::ProverOptions;
+use p3_machine::periodic::periodic_values_on_coset;
+use p3_machine::quotient::quotient_values;
+use p3_machine::selectors::row_selectors_on_coset;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use p3_uni_stark::{PackedChallenge, StarkGenericConfig};
+use rand::{thread_rng, Rng};
+
+use common::counter::{counter_trace, CounterChip, CounterMachine};
+use common::{default_config, Challenge, MyConfig, Val};
+
+/// A chip exercising every kind of input a constraint program can read.
+#[derive(Clone, Debug)]
+struct KitchenSinkChip;
+
+impl Display for KitchenSinkChip {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "KitchenSinkChip")
+    }
+}
+
+impl<F: Field> BaseAir<F> for KitchenSinkChip {
+    fn width(&self) -> usize {
+        4
+    }
+}
+
+impl<AB> Air<AB> for KitchenSinkChip
+where
+    AB: AirBuilderWithPublicValues + PeriodicAirBuilder + SelectorAirBuilder,
+{
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let next = main.row_slice(1);
+        let (a, b, c, mult): (AB::Expr, AB::Expr, AB::Expr, AB::Expr) = (
+            local[0].into(),
+            local[1].into(),
+            local[2].into(),
+            local[3].into(),
+        );
+        let public_value: AB::Expr = builder.public_values()[0].into();
+        let periodic_value: AB::Expr = builder.periodic_values()[0].into();
+
+        builder.when_first_row().assert_eq(a.clone(), public_value);
+        builder
+            .when_transition()
+            .assert_eq(next[0], a.clone() * b.clone() + periodic_value);
+        builder.when_row(3).assert_eq(
+            b.clone() * b.clone() * c.clone(),
+            AB::Expr::from_canonical_u32(7),
+        );
+        builder
+            .when_every_nth_row(4, 1)
+            .assert_eq(c.clone(), a.clone());
+        // The same subexpression built twice.
+        builder.when_rows(2..5).assert_eq(
+            (a.clone() * b.clone()) * (a.clone() * b.clone()),
+            (a.clone() * b.clone()) * c.clone(),
+        );
+        // Folds to a constant.
+        builder.assert_zero(a.clone() * AB::Expr::zero() + b.clone() - b.clone());
+        builder.when_last_row().assert_bool(mult);
+    }
+}
+
+impl<F: Field> BaseInteractionAir<F> for KitchenSinkChip {
+    fn receives_from_indices(
+        &self,
+        _preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        vec![Interaction {
+            fields: vec![VirtualPairCol::single_main(main_indices[2])],
+            count: VirtualPairCol::single_main(main_indices[3]),
+            argument_index: 1,
+        }]
+    }
+
+    fn sends_from_indices(
+        &self,
+        _preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        vec![Interaction {
+            fields: vec![
+                VirtualPairCol::single_main(main_indices[0]),
+                VirtualPairCol::single_main(main_indices[1]),
+            ],
+            count: VirtualPairCol::single_main(main_indices[3]),
+            argument_index: 0,
+        }]
+    }
+}
+
+impl<F: Field> InteractionAir<F> for KitchenSinkChip {
+    fn receives(&self) -> Vec<Interaction<F>> {
+        self.receives_from_main_indices(&[0, 1, 2, 3])
+    }
+
+    fn sends(&self) -> Vec<Interaction<F>> {
+        self.sends_from_main_indices(&[0, 1, 2, 3])
+    }
+}
+
+impl<AB> Rap<AB> for KitchenSinkChip where
+    AB: InteractionAirBuilder
+        + AirBuilderWithPublicValues
+        + PeriodicAirBuilder
+        + SelectorAirBuilder
+{
+}
+
+impl<F: Field> PeriodicAir<F> for KitchenSinkChip {
+    fn periodic_columns(&self) -> Vec<Vec<F>> {
+        vec![vec![F::one(), F::two(), F::zero(), F::neg_one()]]
+    }
+}
+
+const NUM_PUBLIC_VALUES: usize = 1;
+
+fn random_matrix(height: usize, width: usize) -> RowMajorMatrix<Val> {
+    RowMajorMatrix::rand(&mut thread_rng(), height, width)
+}
+
+#[test]
+fn test_program_matches_direct_evaluation() {
+    let (config, _) = default_config();
+    let mut rng = thread_rng();
+    let chip = KitchenSinkChip;
+
+    let log_height = 5;
+    let trace_domain = config.pcs().natural_domain_for_degree(1 << log_height);
+    let quotient_degree = get_quotient_degree::<Val, _>(&chip, NUM_PUBLIC_VALUES);
+    let quotient_domain =
+        trace_domain.create_disjoint_domain(trace_domain.size() * quotient_degree);
+    let quotient_size = quotient_domain.size();
+
+    let perm_width = <KitchenSinkChip as Rap<SymbolicAirBuilder<Val>>>::permutation_width(&chip)
+        .unwrap_or_default()
+        * <Challenge as AbstractExtensionField<Val>>::D;
+    let main = random_matrix(quotient_size, 4);
+    let perm = random_matrix(quotient_size, perm_width);
+
+    let periodic_values = periodic_values_on_coset(
+        &PeriodicAir::<Val>::periodic_columns(&chip),
+        trace_domain,
+        quotient_domain,
+    );
+    let row_selectors = get_row_selectors::<Val, _>(&chip, NUM_PUBLIC_VALUES);
+    let row_selector_values = row_selectors_on_coset(&row_selectors, trace_domain, quotient_domain);
+    let perm_challenges = [(); 2].map(|_| PackedChallenge::<MyConfig>::from_f(rng.gen()));
+    let alpha: Challenge = rng.gen();
+    let cumulative_sum = PackedChallenge::<MyConfig>::from_f(rng.gen());
+    let public_values: Vec<Val> = (0..NUM_PUBLIC_VALUES).map(|_| rng.gen()).collect();
+    let num_constraints = get_num_constraints::<Val, _>(&chip, NUM_PUBLIC_VALUES);
+    let program = get_constraint_program::<Val, _>(&chip, NUM_PUBLIC_VALUES);
+    assert_eq!(program.num_constraints, num_constraints);
+
+    let evaluate = |program| {
+        quotient_values::<MyConfig, _, _>(
+            &chip,
+            trace_domain,
+            quotient_domain,
+            None,
+            Some(main.clone()),
+            Some(perm.clone()),
+            &periodic_values,
+            &row_selectors,
+            &row_selector_values,
+            perm_challenges,
+            alpha,
+            num_constraints,
+            cumulative_sum,
+            &public_values,
+            program,
+        )
+    };
+    assert_eq!(evaluate(Some(&program)), evaluate(None));
+}
+
+/// A chip which builds `a * b` twice.
+#[derive(Clone, Debug)]
+struct RepeatedProductChip;
+
+impl Display for RepeatedProductChip {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "RepeatedProductChip")
+    }
+}
+
+impl<F: Field> BaseAir<F> for RepeatedProductChip {
+    fn width(&self) -> usize {
+        2
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for RepeatedProductChip {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        builder.assert_zero((local[0] * local[1]) * (local[0] * local[1]));
+        builder.assert_eq(local[0] * local[1], local[0]);
+    }
+}
+
+impl<F: Field> BaseInteractionAir<F> for RepeatedProductChip {}
+
+impl<F: Field> InteractionAir<F> for RepeatedProductChip {}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for RepeatedProductChip {}
+
+impl<F: Field> PeriodicAir<F> for RepeatedProductChip {}
+
+/// A chip whose constraints are both identically zero.
+#[derive(Clone, Debug)]
+struct TrivialChip;
+
+impl Display for TrivialChip {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "TrivialChip")
+    }
+}
+
+impl<F: Field> BaseAir<F> for TrivialChip {
+    fn width(&self) -> usize {
+        2
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for TrivialChip {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let (a, b): (AB::Expr, AB::Expr) = (local[0].into(), local[1].into());
+        builder.assert_zero(a.clone() * AB::Expr::zero() + b.clone() - b);
+        builder.assert_eq(
+            (AB::Expr::two() + AB::Expr::from_canonical_u32(3)) * a.clone(),
+            a * AB::Expr::from_canonical_u32(5),
+        );
+    }
+}
+
+impl<F: Field> BaseInteractionAir<F> for TrivialChip {}
+
+impl<F: Field> InteractionAir<F> for TrivialChip {}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for TrivialChip {}
+
+impl<F: Field> PeriodicAir<F> for TrivialChip {}
+
+#[test]
+fn test_program_eliminates_common_subexpressions() {
+    let program = get_constraint_program::<Val, _>(&RepeatedProductChip, 0);
+    let count =
+        |f: fn(&Instruction<Val>) -> bool| program.instructions.iter().filter(|i| f(i)).count();
+
+    assert_eq!(count(|i| matches!(i, Instruction::Load { .. })), 2);
+    assert_eq!(count(|i| matches!(i, Instruction::Mul { .. })), 2);
+    assert_eq!(count(|i| matches!(i, Instruction::AssertZero { .. })), 2);
+}
+
+#[test]
+fn test_program_folds_constants() {
+    let program = get_constraint_program::<Val, _>(&TrivialChip, 0);
+    assert_eq!(
+        program.instructions,
+        vec![
+            Instruction::Constant {
+                dst: 0,
+                value: Val::zero(),
+            },
+            Instruction::AssertZero {
+                x: Register::Base(0),
+            },
+            Instruction::AssertZero {
+                x: Register::Base(0),
+            },
+        ]
+    );
+    assert_eq!(program.num_constraints, 2);
+}
+
+#[test]
+fn test_program_proofs_verify() {
+    let sender = CounterChip {
+        sends: true,
+        ..CounterChip::new(1)
+    };
+    let receiver = CounterChip {
+        receives: true,
+        ..CounterChip::new(2)
+    };
+    let machine = CounterMachine::new(vec![sender, receiver]);
+    let (config, challenger) = default_config();
+    let (pk, vk) = machine.setup(&config);
+    let traces = machine
+        .chips
+        .iter()
+        .map(|chip| (chip.id(), counter_trace(chip.width, 16)))
+        .collect();
+    let options = ProverOptions {
+        use_bytecode: true,
+        ..Default::default()
+    };
+
+    let proof = machine.prove(&config, &mut challenger.clone(), &pk, traces, &[], &options);
+    machine
+        .verify(&config, &mut challenger.clone(), &vk, &proof, &[])
+        .unwrap();
+}
@@ -0,0 +1,96 @@
+mod common;
+
+use p3_machine::chip::Chip;
+use p3_machine::error::VerificationError;
+use p3_machine::machine::Machine;
+use p3_machine::options::ProverOptions;
+
+use common::counter::{counter_trace, prove_and_verify, CounterChip, CounterMachine};
+use common::default_config;
+
+fn machine(widths: &[usize]) -> CounterMachine {
+    CounterMachine::new(
+        widths
+            .iter()
+            .map(|&width| CounterChip::new(width))
+            .collect(),
+    )
+}
+
+#[test]
+fn test_reordered_chips_verify() {
+    let machine = machine(&[1, 2, 3]);
+    let reordered = self::machine(&[3, 1, 2]);
+    let (config, challenger) = default_config();
+    let (pk, vk) = machine.setup(&config);
+    let traces = machine
+        .chips
+        .iter()
+        .map(|chip| (chip.id(), counter_trace(chip.width, 8)))
+        .collect();
+
+    let proof = machine.prove(
+        &config,
+        &mut challenger.clone(),
+        &pk,
+        traces,
+        &[],
+        &ProverOptions::default(),
+    );
+    assert!(proof
+        .chip_proofs
+        .keys()
+        .eq(["CounterChip1", "CounterChip2", "CounterChip3"]));
+    reordered
+        .verify(&config, &mut challenger.clone(), &vk, &proof, &[])
+        .unwrap();
+}
+
+#[test]
+fn test_missing_chip_rejected() {
+    let result = prove_and_verify(&machine(&[1, 2]), &[8, 8], |proof| {
+        proof.chip_proofs.remove("CounterChip2");
+    });
+    assert!(matches!(result, Err(VerificationError::InvalidChipIds)));
+}
+
+#[test]
+fn test_mismatched_key_rejected() {
+    let machine = machine(&[1, 2]);
+    let other = self::machine(&[1, 3]);
+    let (config, challenger) = default_config();
+    let (pk, _) = machine.setup(&config);
+    let (_, other_vk) = other.setup(&config);
+    let traces = machine
+        .chips
+        .iter()
+        .map(|chip| (chip.id(), counter_trace(chip.width, 8)))
+        .collect();
+
+    let proof = machine.prove(
+        &config,
+        &mut challenger.clone(),
+        &pk,
+        traces,
+        &[],
+        &ProverOptions::default(),
+    );
+    let result = machine.verify(&config, &mut challenger.clone(), &other_vk, &proof, &[]);
+    assert!(matches!(result, Err(VerificationError::InvalidChipIds)));
+}
+
+#[test]
+fn test_duplicate_names_get_unique_ids() {
+    let machine = machine(&[1, 2, 1]);
+    assert_eq!(
+        machine.chip_ids(),
+        vec!["CounterChip1#0", "CounterChip2", "CounterChip1#1"]
+    );
+    prove_and_verify(&machine, &[8, 16, 32], |proof| {
+        assert!(proof
+            .chip_proofs
+            .keys()
+            .eq(["CounterChip1#0", "CounterChip1#1", "CounterChip2"]));
+    })
+    .unwrap();
+}
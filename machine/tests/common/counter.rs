@@ -0,0 +1,206 @@
+use core::fmt::{self, Display, Formatter};
+use std::collections::BTreeMap;
+
+use p3_air::{Air, AirBuilder, BaseAir, VirtualPairCol};
+use p3_air_util::PeriodicAir;
+use p3_field::{AbstractField, Field};
+use p3_interaction::{
+    BaseInteractionAir, Bus, Interaction, InteractionAir, InteractionAirBuilder, Rap,
+};
+use p3_machine::chip::{unique_ids, Chip, ChipId};
+use p3_machine::error::VerificationError;
+use p3_machine::machine::Machine;
+use p3_machine::options::ProverOptions;
+use p3_machine::proof::MachineProof;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+
+use super::{default_config, MyConfig, Val};
+
+/// A chip whose columns all count up by one on every row. It optionally sends or receives its
+/// first column on bus 0.
+#[derive(Clone, Debug)]
+pub struct CounterChip {
+    pub width: usize,
+    pub sends: bool,
+    pub receives: bool,
+}
+
+impl CounterChip {
+    pub fn new(width: usize) -> Self {
+        Self {
+            width,
+            sends: false,
+            receives: false,
+        }
+    }
+
+    fn interactions<F: Field>(&self, enabled: bool, main_indices: &[usize]) -> Vec<Interaction<F>> {
+        if enabled {
+            vec![Interaction {
+                fields: vec![VirtualPairCol::single_main(main_indices[0])],
+                count: VirtualPairCol::constant(F::one()),
+                argument_index: 0,
+            }]
+        } else {
+            vec![]
+        }
+    }
+}
+
+impl Display for CounterChip {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "CounterChip{}", self.width)
+    }
+}
+
+impl<F: Field> BaseAir<F> for CounterChip {
+    fn width(&self) -> usize {
+        self.width
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for CounterChip {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let next = main.row_slice(1);
+        for i in 0..self.width {
+            builder
+                .when_transition()
+                .assert_eq(next[i], local[i] + AB::Expr::one());
+        }
+    }
+}
+
+impl<F: Field> BaseInteractionAir<F> for CounterChip {
+    fn receives_from_indices(
+        &self,
+        _preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        self.interactions(self.receives, main_indices)
+    }
+
+    fn sends_from_indices(
+        &self,
+        _preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        self.interactions(self.sends, main_indices)
+    }
+}
+
+impl<F: Field> InteractionAir<F> for CounterChip {
+    fn receives(&self) -> Vec<Interaction<F>> {
+        self.receives_from_main_indices(&(0..self.width).collect::<Vec<_>>())
+    }
+
+    fn sends(&self) -> Vec<Interaction<F>> {
+        self.sends_from_main_indices(&(0..self.width).collect::<Vec<_>>())
+    }
+}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for CounterChip {}
+
+impl<F: Field> PeriodicAir<F> for CounterChip {}
+
+impl Chip for CounterChip {}
+
+pub struct CounterBus;
+
+impl From<usize> for CounterBus {
+    fn from(_: usize) -> Self {
+        CounterBus
+    }
+}
+
+impl Display for CounterBus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "CounterBus")
+    }
+}
+
+impl Bus for CounterBus {}
+
+pub struct CounterMachine {
+    pub chips: Vec<CounterChip>,
+    pub main_commitment_groups: BTreeMap<ChipId, usize>,
+    pub max_shard_height: Option<usize>,
+}
+
+impl CounterMachine {
+    pub fn new(chips: Vec<CounterChip>) -> Self {
+        let main_commitment_groups = unique_ids(&chips).into_iter().map(|id| (id, 0)).collect();
+        Self {
+            chips,
+            main_commitment_groups,
+            max_shard_height: None,
+        }
+    }
+
+    /// Assigns the main commitment group of each chip, in the order of `chips`.
+    pub fn with_main_commitment_groups(mut self, groups: &[usize]) -> Self {
+        assert_eq!(groups.len(), self.chips.len());
+        self.main_commitment_groups = self
+            .chip_ids()
+            .into_iter()
+            .zip(groups.iter().copied())
+            .collect();
+        self
+    }
+}
+
+impl Machine for CounterMachine {
+    type Chip = CounterChip;
+
+    type Bus = CounterBus;
+
+    fn chips(&self) -> Vec<Self::Chip> {
+        self.chips.clone()
+    }
+
+    fn main_commitment_groups(&self) -> BTreeMap<ChipId, usize> {
+        self.main_commitment_groups.clone()
+    }
+
+    fn max_shard_height(&self) -> Option<usize> {
+        self.max_shard_height
+    }
+}
+
+pub fn counter_trace(width: usize, height: usize) -> RowMajorMatrix<Val> {
+    let values = (0..height)
+        .flat_map(|row| (0..width).map(move |col| Val::from_canonical_usize(row + col)))
+        .collect();
+    RowMajorMatrix::new(values, width)
+}
+
+/// Proves the machine on counter traces of the given heights, lets `tamper` modify the proof, and
+/// verifies it.
+pub fn prove_and_verify(
+    machine: &CounterMachine,
+    heights: &[usize],
+    tamper: impl FnOnce(&mut MachineProof<MyConfig>),
+) -> Result<(), VerificationError> {
+    let (config, challenger) = default_config();
+    let (pk, vk) = machine.setup(&config);
+    let traces = machine
+        .chip_ids()
+        .into_iter()
+        .zip(&machine.chips)
+        .zip(heights)
+        .map(|((id, chip), &height)| (id, counter_trace(chip.width, height)))
+        .collect();
+
+    let mut proof = machine.prove(
+        &config,
+        &mut challenger.clone(),
+        &pk,
+        traces,
+        &[],
+        &ProverOptions::default(),
+    );
+    tamper(&mut proof);
+    machine.verify(&config, &mut challenger.clone(), &vk, &proof, &[])
+}
@@ -0,0 +1,191 @@
+mod common;
+
+use core::fmt::{self, Display, Formatter};
+use std::collections::BTreeMap;
+
+use p3_air::{Air, AirBuilderWithPublicValues, BaseAir};
+use p3_air_util::PeriodicAir;
+use p3_field::{AbstractField, Field};
+use p3_interaction::{BaseInteractionAir, InteractionAir, InteractionAirBuilder, Rap};
+use p3_machine::chip::Chip;
+use p3_machine::continuation::{ContinuationMachine, Segment};
+use p3_machine::error::VerificationError;
+use p3_machine::machine::Machine;
+use p3_machine::options::ProverOptions;
+use p3_machine::proof::SegmentProof;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+
+use common::counter::CounterBus;
+use common::{default_config, MyConfig, Val};
+
+/// A counter that starts from the first public value, and whose next value is the second one.
+#[derive(Clone, Debug)]
+struct SegmentCounterChip;
+
+impl Display for SegmentCounterChip {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "SegmentCounterChip")
+    }
+}
+
+impl<F: Field> BaseAir<F> for SegmentCounterChip {
+    fn width(&self) -> usize {
+        1
+    }
+}
+
+impl<AB: AirBuilderWithPublicValues> Air<AB> for SegmentCounterChip {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let next = main.row_slice(1);
+        let initial_state: AB::Expr = builder.public_values()[0].into();
+        let final_state: AB::Expr = builder.public_values()[1].into();
+
+        builder.when_first_row().assert_eq(local[0], initial_state);
+        builder
+            .when_transition()
+            .assert_eq(next[0], local[0] + AB::Expr::one());
+        builder
+            .when_last_row()
+            .assert_eq(local[0] + AB::Expr::one(), final_state);
+    }
+}
+
+impl<F: Field> BaseInteractionAir<F> for SegmentCounterChip {}
+
+impl<F: Field> InteractionAir<F> for SegmentCounterChip {}
+
+impl<AB: InteractionAirBuilder + AirBuilderWithPublicValues> Rap<AB> for SegmentCounterChip {}
+
+impl<F: Field> PeriodicAir<F> for SegmentCounterChip {}
+
+impl Chip for SegmentCounterChip {}
+
+struct SegmentCounterMachine;
+
+impl Machine for SegmentCounterMachine {
+    type Chip = SegmentCounterChip;
+
+    type Bus = CounterBus;
+
+    fn chips(&self) -> Vec<Self::Chip> {
+        vec![SegmentCounterChip]
+    }
+}
+
+impl ContinuationMachine for SegmentCounterMachine {
+    fn state_digest_len(&self) -> usize {
+        1
+    }
+}
+
+const SEGMENT_HEIGHT: usize = 8;
+
+/// Splits a count from zero into segments of `SEGMENT_HEIGHT` rows.
+fn segments(num_segments: usize) -> impl Iterator<Item = Segment<Val>> {
+    (0..num_segments).map(|i| {
+        let start = i * SEGMENT_HEIGHT;
+        let end = start + SEGMENT_HEIGHT;
+        let values = (start..end).map(Val::from_canonical_usize).collect();
+        Segment {
+            main_traces: BTreeMap::from([(
+                SegmentCounterChip.id(),
+                RowMajorMatrix::new(values, 1),
+            )]),
+            public_values: vec![
+                Val::from_canonical_usize(start),
+                Val::from_canonical_usize(end),
+            ],
+        }
+    })
+}
+
+fn prove_and_verify(
+    num_segments: usize,
+    tamper: impl FnOnce(&mut Vec<SegmentProof<MyConfig>>),
+    final_state: usize,
+) -> Result<(), VerificationError> {
+    let machine = SegmentCounterMachine;
+    let (config, challenger) = default_config();
+    let (pk, vk) = machine.setup(&config);
+
+    let mut proofs = machine.prove_segments(
+        &config,
+        &challenger,
+        &pk,
+        segments(num_segments),
+        &ProverOptions::default(),
+    );
+    assert_eq!(proofs.len(), num_segments);
+    tamper(&mut proofs);
+    machine.verify_segments(
+        &config,
+        &challenger,
+        &vk,
+        &proofs,
+        &[Val::zero()],
+        &[Val::from_canonical_usize(final_state)],
+    )
+}
+
+#[test]
+fn test_three_segments() {
+    prove_and_verify(3, |_| {}, 3 * SEGMENT_HEIGHT).unwrap();
+}
+
+#[test]
+fn test_wrong_final_state_rejected() {
+    let result = prove_and_verify(3, |_| {}, 3 * SEGMENT_HEIGHT + 1);
+    assert!(matches!(
+        result,
+        Err(VerificationError::InvalidSegmentBoundary)
+    ));
+}
+
+#[test]
+fn test_skipped_segment_rejected() {
+    let result = prove_and_verify(
+        3,
+        |proofs| {
+            proofs.remove(1);
+        },
+        3 * SEGMENT_HEIGHT,
+    );
+    assert!(matches!(
+        result,
+        Err(VerificationError::InvalidSegmentBoundary)
+    ));
+}
+
+#[test]
+fn test_reordered_segments_rejected() {
+    let result = prove_and_verify(3, |proofs| proofs.swap(0, 1), 3 * SEGMENT_HEIGHT);
+    assert!(matches!(
+        result,
+        Err(VerificationError::InvalidSegmentBoundary)
+    ));
+}
+
+#[test]
+fn test_forged_boundary_rejected() {
+    // Claiming a different final state for the middle segment keeps the chain consistent, but the
+    // segment proofs no longer match their public values.
+    let result = prove_and_verify(
+        3,
+        |proofs| {
+            let forged = Val::from_canonical_usize(100);
+            proofs[1].public_values[1] = forged;
+            proofs[2].public_values[0] = forged;
+        },
+        3 * SEGMENT_HEIGHT,
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_no_segments_rejected() {
+    let result = prove_and_verify(0, |_| {}, 0);
+    assert!(matches!(result, Err(VerificationError::InvalidProofShape)));
+}
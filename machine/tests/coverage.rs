@@ -0,0 +1,187 @@
+mod common;
+
+use core::borrow::Borrow;
+
+use p3_air::{Air, AirBuilder, BaseAir, VirtualPairCol};
+use p3_air_util::debug::air::track_constraints;
+use p3_air_util::{
+    find_unconstrained_columns, find_underconstrained_columns, ColumnIssue, PeriodicAir,
+    TraceBuilder, UnderconstrainedColumn,
+};
+use p3_derive::Columnar;
+use p3_field::{AbstractField, Field};
+use p3_interaction::{BaseInteractionAir, Interaction, InteractionAir, InteractionAirBuilder, Rap};
+use p3_matrix::Matrix;
+
+use common::Val;
+
+const VALUE_BUS: usize = 0;
+const RANGE_BUS: usize = 1;
+
+#[repr(C)]
+#[derive(Columnar, Default)]
+struct AuditCols<T> {
+    a: T,
+    b: T,
+    sum: T,
+    product: T,
+    checked: T,
+    sent: T,
+    is_on: T,
+    x: T,
+    y: T,
+    unused: T,
+}
+
+/// A chip with a column for each kind of coverage. `sum` and `checked` are only constrained
+/// linearly, but `checked` is range checked. `sent` is only sent on a bus, `x` and `y` are only
+/// constrained when `is_on` is set, and `unused` is never referenced.
+#[derive(Clone, Debug)]
+struct AuditChip;
+
+impl<F: Field> BaseAir<F> for AuditChip {
+    fn width(&self) -> usize {
+        AuditCols::<F>::num_cols()
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for AuditChip {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let local: &AuditCols<AB::Var> = (*local).borrow();
+
+        builder.assert_eq(local.sum, local.a + local.b);
+        builder.assert_eq(local.product, local.a * local.b);
+        builder.assert_eq(local.checked, local.sum + AB::Expr::one());
+        builder.assert_bool(local.is_on);
+        builder.when(local.is_on).assert_eq(local.x, local.y);
+    }
+}
+
+impl<F: Field> BaseInteractionAir<F> for AuditChip {
+    fn sends_from_indices(
+        &self,
+        _preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        let col_map = AuditCols::from_slice(main_indices);
+        vec![
+            Interaction {
+                fields: vec![VirtualPairCol::single_main(col_map.sent)],
+                count: VirtualPairCol::constant(F::one()),
+                argument_index: VALUE_BUS,
+            },
+            Interaction {
+                fields: vec![VirtualPairCol::single_main(col_map.checked)],
+                count: VirtualPairCol::constant(F::one()),
+                argument_index: RANGE_BUS,
+            },
+        ]
+    }
+}
+
+impl<F: Field> InteractionAir<F> for AuditChip {
+    fn sends(&self) -> Vec<Interaction<F>> {
+        let col_map = AuditCols::<F>::col_map();
+        self.sends_from_main_indices(col_map.as_slice())
+    }
+}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for AuditChip {}
+
+impl<F: Field> PeriodicAir<F> for AuditChip {}
+
+#[test]
+fn test_symbolic_coverage() {
+    let col_map = AuditCols::<usize>::col_map();
+    let columns = find_underconstrained_columns::<Val, _>(&AuditChip, 0, &[RANGE_BUS]);
+    assert_eq!(
+        columns,
+        vec![
+            UnderconstrainedColumn {
+                col: col_map.sum,
+                issue: ColumnIssue::OnlyLinear,
+            },
+            UnderconstrainedColumn {
+                col: col_map.unused,
+                issue: ColumnIssue::Unreferenced,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_symbolic_coverage_without_range_checks() {
+    let col_map = AuditCols::<usize>::col_map();
+    let columns = find_underconstrained_columns::<Val, _>(&AuditChip, 0, &[]);
+    let only_linear = columns
+        .iter()
+        .filter(|column| column.issue == ColumnIssue::OnlyLinear)
+        .map(|column| column.col)
+        .collect::<Vec<_>>();
+    assert_eq!(only_linear, vec![col_map.sum, col_map.checked]);
+}
+
+#[test]
+fn test_trace_coverage() {
+    let trace = TraceBuilder::<Val, AuditCols<Val>>::from_rows(4, |i, row| {
+        row.a = Val::from_canonical_usize(i + 1);
+        row.b = Val::from_canonical_usize(2);
+        row.sum = row.a + row.b;
+        row.product = row.a * row.b;
+        row.checked = row.sum + Val::one();
+        row.sent = Val::from_canonical_usize(i);
+        row.x = Val::from_canonical_usize(3);
+        row.y = Val::from_canonical_usize(5);
+    })
+    .build();
+    assert_eq!(trace.width(), AuditCols::<Val>::num_cols());
+
+    // The interactions are not tracked here, so `sent` is unconstrained too. The gate of `x` and
+    // `y` is never set, so they are not constrained in any row.
+    let entries = track_constraints(&AuditChip, &None, &Some(trace.as_view()), &[]);
+    let col_map = AuditCols::<usize>::col_map();
+    assert_eq!(
+        find_unconstrained_columns(&entries, trace.width()),
+        vec![col_map.sent, col_map.x, col_map.y, col_map.unused]
+    );
+}
+
+/// A chip whose only column is constrained to start at zero, and nowhere else.
+#[derive(Clone, Debug)]
+struct StartChip;
+
+impl<F: Field> BaseAir<F> for StartChip {
+    fn width(&self) -> usize {
+        1
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for StartChip {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        builder.when_first_row().assert_zero(local[0]);
+    }
+}
+
+impl<F: Field> BaseInteractionAir<F> for StartChip {}
+
+impl<F: Field> InteractionAir<F> for StartChip {}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for StartChip {}
+
+impl<F: Field> PeriodicAir<F> for StartChip {}
+
+#[test]
+fn test_row_selectors_not_counted_in_degree() {
+    let columns = find_underconstrained_columns::<Val, _>(&StartChip, 0, &[]);
+    assert_eq!(
+        columns,
+        vec![UnderconstrainedColumn {
+            col: 0,
+            issue: ColumnIssue::OnlyLinear,
+        }]
+    );
+}
@@ -0,0 +1,90 @@
+#![cfg(feature = "air-logger")]
+
+mod common;
+
+use core::fmt::{self, Display, Formatter};
+use std::collections::BTreeMap;
+
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_air_util::{AirLogger, ColumnIssue, PeriodicAir};
+use p3_field::Field;
+use p3_interaction::{BaseInteractionAir, InteractionAir, InteractionAirBuilder, Rap};
+use p3_machine::chip::Chip;
+use p3_machine::machine::Machine;
+
+use common::counter::CounterBus;
+use common::Val;
+
+/// A chip with a column constrained only on the first row, a squared column, and an unused
+/// column. Only its first column has a header.
+#[derive(Clone, Debug)]
+struct HeaderChip;
+
+impl Display for HeaderChip {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "HeaderChip")
+    }
+}
+
+impl<F: Field> BaseAir<F> for HeaderChip {
+    fn width(&self) -> usize {
+        3
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for HeaderChip {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        builder.when_first_row().assert_zero(local[0]);
+        builder.assert_bool(local[1]);
+    }
+}
+
+impl<F: Field> BaseInteractionAir<F> for HeaderChip {}
+
+impl<F: Field> InteractionAir<F> for HeaderChip {}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for HeaderChip {}
+
+impl<F: Field> PeriodicAir<F> for HeaderChip {}
+
+impl AirLogger for HeaderChip {
+    fn main_headers(&self) -> Vec<String> {
+        vec!["start".to_string()]
+    }
+
+    #[cfg(feature = "schema")]
+    fn main_headers_and_types(&self) -> Vec<(String, String, core::ops::Range<usize>)> {
+        vec![("start".to_string(), "Field".to_string(), 0..1)]
+    }
+}
+
+impl Chip for HeaderChip {}
+
+struct HeaderMachine;
+
+impl Machine for HeaderMachine {
+    type Chip = HeaderChip;
+
+    type Bus = CounterBus;
+
+    fn chips(&self) -> Vec<Self::Chip> {
+        vec![HeaderChip]
+    }
+}
+
+#[test]
+fn test_underconstrained_columns_by_header() {
+    let columns = HeaderMachine.find_underconstrained_columns::<Val>(0, &[]);
+    assert_eq!(
+        columns,
+        BTreeMap::from([(
+            HeaderChip.id(),
+            vec![
+                ("start".to_string(), ColumnIssue::OnlyLinear),
+                ("main[2]".to_string(), ColumnIssue::Unreferenced),
+            ]
+        )])
+    );
+}
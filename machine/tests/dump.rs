@@ -0,0 +1,155 @@
+#![cfg(feature = "air-logger")]
+
+mod common;
+
+use core::fmt::{self, Display, Formatter};
+use std::collections::BTreeMap;
+use std::fs;
+
+use p3_air::{Air, AirBuilderWithPublicValues, BaseAir};
+use p3_air_util::{AirLogger, PeriodicAir};
+use p3_field::{AbstractField, Field};
+use p3_interaction::{BaseInteractionAir, InteractionAir, InteractionAirBuilder, Rap};
+use p3_machine::chip::Chip;
+use p3_machine::machine::{Instance, Machine};
+use p3_machine::options::{ProverOptions, TraceDump, TraceFormat};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+
+use common::counter::CounterBus;
+use common::{default_config, Val};
+
+/// A chip whose column starts at the first public value and counts up by one on every row.
+#[derive(Clone, Debug)]
+struct PublicStartChip;
+
+impl Display for PublicStartChip {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "PublicStartChip")
+    }
+}
+
+impl<F: Field> BaseAir<F> for PublicStartChip {
+    fn width(&self) -> usize {
+        1
+    }
+}
+
+impl<AB: AirBuilderWithPublicValues> Air<AB> for PublicStartChip {
+    fn eval(&self, builder: &mut AB) {
+        let start: AB::Expr = builder.public_values()[0].into();
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let next = main.row_slice(1);
+        builder.when_first_row().assert_eq(local[0], start);
+        builder
+            .when_transition()
+            .assert_eq(next[0], local[0] + AB::Expr::one());
+    }
+}
+
+impl<F: Field> BaseInteractionAir<F> for PublicStartChip {}
+
+impl<F: Field> InteractionAir<F> for PublicStartChip {}
+
+impl<AB: InteractionAirBuilder + AirBuilderWithPublicValues> Rap<AB> for PublicStartChip {}
+
+impl<F: Field> PeriodicAir<F> for PublicStartChip {}
+
+impl AirLogger for PublicStartChip {
+    fn main_headers(&self) -> Vec<String> {
+        vec!["counter".to_string()]
+    }
+
+    #[cfg(feature = "schema")]
+    fn main_headers_and_types(&self) -> Vec<(String, String, core::ops::Range<usize>)> {
+        vec![("counter".to_string(), "Field".to_string(), 0..1)]
+    }
+}
+
+impl Chip for PublicStartChip {}
+
+struct PublicStartMachine;
+
+impl Machine for PublicStartMachine {
+    type Chip = PublicStartChip;
+
+    type Bus = CounterBus;
+
+    fn chips(&self) -> Vec<Self::Chip> {
+        vec![PublicStartChip]
+    }
+}
+
+fn temp_path(name: &str) -> String {
+    let path = std::env::temp_dir().join(format!("{name}-{}.xlsx", std::process::id()));
+    path.to_str().unwrap().into()
+}
+
+/// Proves an instance per start value with the constraints checked and the traces dumped to
+/// `path` as an xlsx workbook, and verifies the proof.
+fn prove_with_xlsx_dump(starts: &[u32], path: &str) {
+    let machine = PublicStartMachine;
+    let (config, challenger) = default_config();
+    let (pk, vk) = machine.setup(&config);
+    let public_values = starts
+        .iter()
+        .map(|&start| vec![Val::from_canonical_u32(start)])
+        .collect::<Vec<_>>();
+    let instances = public_values
+        .iter()
+        .map(|public_values| {
+            let start = public_values[0];
+            let values = (0..8).map(|i| start + Val::from_canonical_u32(i)).collect();
+            Instance {
+                main_traces: BTreeMap::from([(
+                    PublicStartChip.id(),
+                    RowMajorMatrix::new(values, 1),
+                )]),
+                public_values: public_values.clone(),
+            }
+        })
+        .collect();
+    let options = ProverOptions {
+        check_constraints: true,
+        dump: Some(TraceDump::new(path, TraceFormat::Xlsx)),
+        ..Default::default()
+    };
+    let proof = machine.prove_batch(&config, &mut challenger.clone(), &pk, instances, &options);
+    machine
+        .verify_batch(
+            &config,
+            &mut challenger.clone(),
+            &vk,
+            &proof,
+            &public_values,
+        )
+        .unwrap();
+}
+
+#[test]
+fn test_xlsx_dump_with_public_values() {
+    let path = temp_path("public-values");
+    prove_with_xlsx_dump(&[3, 5], &path);
+    let workbook = fs::read(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    // The names of the workbook's files are stored uncompressed, so each instance's worksheet can
+    // be found without unzipping it
+    let contains = |name: &str| {
+        workbook
+            .windows(name.len())
+            .any(|window| window == name.as_bytes())
+    };
+    assert!(contains("xl/worksheets/sheet1.xml"));
+    assert!(contains("xl/worksheets/sheet2.xml"));
+    assert!(!contains("xl/worksheets/sheet3.xml"));
+}
+
+#[test]
+#[should_panic(expected = "Failed to dump the traces to")]
+fn test_failed_xlsx_dump_panics() {
+    let dir = std::env::temp_dir().join(format!("missing-dir-{}", std::process::id()));
+    let path = dir.join("trace.xlsx");
+    prove_with_xlsx_dump(&[3, 5], path.to_str().unwrap());
+}
@@ -0,0 +1,379 @@
+extern crate alloc;
+
+mod common;
+
+use core::borrow::Borrow;
+use core::fmt::{self, Display, Formatter};
+use std::collections::BTreeMap;
+
+use p3_air::{Air, AirBuilder, BaseAir, VirtualPairCol};
+use p3_air_util::debug::air::check_constraints;
+use p3_air_util::{
+    periodic_values_on_row, Embedded, PeriodicAir, PeriodicAirBuilder, SelectorAirBuilder,
+    TraceBuilder,
+};
+use p3_derive::Columnar;
+use p3_field::{AbstractField, Field};
+use p3_interaction::{BaseInteractionAir, Interaction, InteractionAir, InteractionAirBuilder, Rap};
+use p3_machine::chip::Chip;
+use p3_machine::machine::Machine;
+use p3_machine::options::ProverOptions;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+
+use common::counter::CounterBus;
+use common::{default_config, Val};
+
+#[repr(C)]
+#[derive(Columnar, Default)]
+struct IncrementCols<T> {
+    a: T,
+    b: T,
+}
+
+/// A chip whose second column is its first plus one, and which sends both on bus 0.
+#[derive(Clone, Debug)]
+struct IncrementChip;
+
+impl<F: Field> BaseAir<F> for IncrementChip {
+    fn width(&self) -> usize {
+        IncrementCols::<F>::num_cols()
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for IncrementChip {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let local: &IncrementCols<AB::Var> = (*local).borrow();
+        builder.assert_eq(local.b, local.a + AB::Expr::one());
+    }
+}
+
+impl<F: Field> BaseInteractionAir<F> for IncrementChip {
+    fn sends_from_indices(
+        &self,
+        _preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        let col_map = IncrementCols::from_slice(main_indices);
+        vec![Interaction {
+            fields: vec![
+                VirtualPairCol::single_main(col_map.a),
+                VirtualPairCol::single_main(col_map.b),
+            ],
+            count: VirtualPairCol::constant(F::one()),
+            argument_index: 0,
+        }]
+    }
+}
+
+#[repr(C)]
+#[derive(Columnar, Default)]
+struct ParentCols<T> {
+    x: T,
+    increment: IncrementCols<T>,
+}
+
+/// Either a parent embedding an `IncrementChip` after its own column, which it constrains to be
+/// the embedded `a`, or a chip receiving the pairs on bus 0.
+#[derive(Clone, Debug)]
+enum EmbeddingChip {
+    Parent,
+    Receiver,
+}
+
+fn embedded_increment() -> Embedded<IncrementChip> {
+    Embedded::new_main(
+        IncrementChip,
+        ParentCols::<usize>::col_map().increment.as_range(),
+    )
+}
+
+impl Display for EmbeddingChip {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            EmbeddingChip::Parent => write!(f, "Parent"),
+            EmbeddingChip::Receiver => write!(f, "Receiver"),
+        }
+    }
+}
+
+impl<F: Field> BaseAir<F> for EmbeddingChip {
+    fn width(&self) -> usize {
+        match self {
+            EmbeddingChip::Parent => ParentCols::<F>::num_cols(),
+            EmbeddingChip::Receiver => 2,
+        }
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for EmbeddingChip {
+    fn eval(&self, builder: &mut AB) {
+        match self {
+            EmbeddingChip::Parent => {
+                let main = builder.main();
+                let local = main.row_slice(0);
+                let local: &ParentCols<AB::Var> = (*local).borrow();
+                builder.assert_eq(local.x, local.increment.a);
+
+                embedded_increment().eval(builder);
+            }
+            EmbeddingChip::Receiver => {}
+        }
+    }
+}
+
+impl<F: Field> BaseInteractionAir<F> for EmbeddingChip {
+    fn receives_from_indices(
+        &self,
+        _preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        match self {
+            EmbeddingChip::Parent => vec![],
+            EmbeddingChip::Receiver => vec![Interaction {
+                fields: vec![
+                    VirtualPairCol::single_main(main_indices[0]),
+                    VirtualPairCol::single_main(main_indices[1]),
+                ],
+                count: VirtualPairCol::constant(F::one()),
+                argument_index: 0,
+            }],
+        }
+    }
+
+    fn sends_from_indices(
+        &self,
+        preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        match self {
+            EmbeddingChip::Parent => {
+                embedded_increment().sends_from_indices(preprocessed_indices, main_indices)
+            }
+            EmbeddingChip::Receiver => vec![],
+        }
+    }
+}
+
+impl<F: Field> InteractionAir<F> for EmbeddingChip {
+    fn receives(&self) -> Vec<Interaction<F>> {
+        self.receives_from_indices(&[], &[0, 1])
+    }
+
+    fn sends(&self) -> Vec<Interaction<F>> {
+        match self {
+            EmbeddingChip::Parent => embedded_increment().sends(),
+            EmbeddingChip::Receiver => vec![],
+        }
+    }
+}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for EmbeddingChip {}
+
+impl<F: Field> PeriodicAir<F> for EmbeddingChip {}
+
+impl Chip for EmbeddingChip {}
+
+struct EmbeddingMachine;
+
+impl Machine for EmbeddingMachine {
+    type Chip = EmbeddingChip;
+
+    type Bus = CounterBus;
+
+    fn chips(&self) -> Vec<Self::Chip> {
+        vec![EmbeddingChip::Parent, EmbeddingChip::Receiver]
+    }
+}
+
+const HEIGHT: usize = 8;
+
+fn parent_trace() -> RowMajorMatrix<Val> {
+    TraceBuilder::<Val, ParentCols<Val>>::from_rows(HEIGHT, |i, row| {
+        row.x = Val::from_canonical_usize(i);
+        row.increment.a = row.x;
+        row.increment.b = row.x + Val::one();
+    })
+    .build()
+}
+
+#[test]
+fn test_interactions_remapped_to_parent_columns() {
+    let sends = InteractionAir::<Val>::sends(&EmbeddingChip::Parent);
+    assert_eq!(sends.len(), 1);
+
+    let row = [3, 4, 5].map(Val::from_canonical_u32);
+    let fields = sends[0]
+        .fields
+        .iter()
+        .map(|field| field.apply::<Val, Val>(&[], &row))
+        .collect::<Vec<_>>();
+    assert_eq!(fields, [4, 5].map(Val::from_canonical_u32));
+
+    let from_indices = EmbeddingChip::Parent.sends_from_indices(&[], &[7, 8, 9]);
+    let fields = from_indices[0]
+        .fields
+        .iter()
+        .map(|field| {
+            field.apply::<Val, Val>(
+                &[],
+                &[0, 0, 0, 0, 0, 0, 0, 1, 2, 3].map(Val::from_canonical_u32),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(fields, [2, 3].map(Val::from_canonical_u32));
+}
+
+#[test]
+fn test_embedded_chip_prove_and_verify() {
+    let machine = EmbeddingMachine;
+    let receiver_values = (0..HEIGHT)
+        .flat_map(|i| [i, i + 1].map(Val::from_canonical_usize))
+        .collect();
+    let traces = BTreeMap::from([
+        (EmbeddingChip::Parent.id(), parent_trace()),
+        (
+            EmbeddingChip::Receiver.id(),
+            RowMajorMatrix::new(receiver_values, 2),
+        ),
+    ]);
+
+    let (config, challenger) = default_config();
+    let (pk, vk) = machine.setup(&config);
+    let proof = machine.prove(
+        &config,
+        &mut challenger.clone(),
+        &pk,
+        traces,
+        &[],
+        &ProverOptions::default(),
+    );
+    machine
+        .verify(&config, &mut challenger.clone(), &vk, &proof, &[])
+        .unwrap();
+}
+
+#[test]
+#[should_panic(expected = "values didn't match on row 0")]
+fn test_embedded_constraints_checked() {
+    let mut trace = parent_trace();
+    // Break the embedded constraint while keeping the parent's own.
+    trace.values[2] = Val::from_canonical_u32(7);
+    check_constraints(&EmbeddingChip::Parent, &None, &Some(trace.as_view()), &[]);
+}
+
+/// A chip whose column starts at zero and adds its period-4 step on every row.
+#[derive(Clone, Debug)]
+struct StepChip;
+
+impl<F: Field> BaseAir<F> for StepChip {
+    fn width(&self) -> usize {
+        1
+    }
+}
+
+impl<AB: PeriodicAirBuilder + SelectorAirBuilder> Air<AB> for StepChip {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let next = main.row_slice(1);
+        let step: AB::Expr = builder.periodic_values()[0].into();
+        builder.when_row(0).assert_zero(local[0]);
+        builder
+            .when_transition()
+            .assert_eq(next[0], local[0] + step);
+    }
+}
+
+impl<F: Field> PeriodicAir<F> for StepChip {
+    fn periodic_columns(&self) -> Vec<Vec<F>> {
+        vec![[1, 2, 3, 4].map(F::from_canonical_u32).to_vec()]
+    }
+}
+
+/// A parent with a periodic column of its own, which its first column must equal, embedding a
+/// `StepChip` in its second column. The child's periodic column follows the parent's.
+#[derive(Clone, Debug)]
+struct StepParent;
+
+fn embedded_step() -> Embedded<StepChip> {
+    Embedded::new_main(StepChip, 1..2).with_periodic_range(1..2)
+}
+
+impl Display for StepParent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "StepParent")
+    }
+}
+
+impl<F: Field> BaseAir<F> for StepParent {
+    fn width(&self) -> usize {
+        2
+    }
+}
+
+impl<AB: PeriodicAirBuilder + SelectorAirBuilder> Air<AB> for StepParent {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let own: AB::Expr = builder.periodic_values()[0].into();
+        builder.assert_eq(local[0], own);
+
+        embedded_step().eval(builder);
+    }
+}
+
+impl<F: Field> BaseInteractionAir<F> for StepParent {}
+
+impl<F: Field> InteractionAir<F> for StepParent {}
+
+impl<AB: InteractionAirBuilder + PeriodicAirBuilder + SelectorAirBuilder> Rap<AB> for StepParent {}
+
+impl<F: Field> PeriodicAir<F> for StepParent {
+    fn periodic_columns(&self) -> Vec<Vec<F>> {
+        let mut columns = vec![vec![F::from_canonical_u32(5), F::from_canonical_u32(6)]];
+        columns.extend(embedded_step().periodic_columns());
+        columns
+    }
+}
+
+impl Chip for StepParent {}
+
+struct StepMachine;
+
+impl Machine for StepMachine {
+    type Chip = StepParent;
+
+    type Bus = CounterBus;
+
+    fn chips(&self) -> Vec<Self::Chip> {
+        vec![StepParent]
+    }
+}
+
+#[test]
+fn test_embedded_periodic_values_offset() {
+    let columns = PeriodicAir::<Val>::periodic_columns(&StepParent);
+    let mut values = vec![];
+    let mut step_sum = Val::zero();
+    for row in 0..HEIGHT {
+        let periodic_values = periodic_values_on_row(&columns, row);
+        values.extend([periodic_values[0], step_sum]);
+        step_sum += periodic_values[1];
+    }
+    let traces = BTreeMap::from([(StepParent.id(), RowMajorMatrix::new(values, 2))]);
+
+    let machine = StepMachine;
+    let (config, challenger) = default_config();
+    let (pk, vk) = machine.setup(&config);
+    let options = ProverOptions {
+        check_constraints: true,
+        ..Default::default()
+    };
+    let proof = machine.prove(&config, &mut challenger.clone(), &pk, traces, &[], &options);
+    machine
+        .verify(&config, &mut challenger.clone(), &vk, &proof, &[])
+        .unwrap();
+}
@@ -0,0 +1,52 @@
+#![cfg(feature = "low-memory")]
+
+mod common;
+
+use std::collections::BTreeMap;
+
+use p3_machine::machine::Machine;
+use p3_machine::options::ProverOptions;
+
+use common::counter::{counter_trace, CounterChip, CounterMachine};
+use common::default_config;
+
+/// The main traces are kept until the permutation traces are generated and checked, and are only
+/// read through their LDEs once released.
+#[test]
+fn test_released_main_traces_prove() {
+    let machine = CounterMachine::new(vec![
+        CounterChip {
+            sends: true,
+            ..CounterChip::new(2)
+        },
+        CounterChip {
+            receives: true,
+            ..CounterChip::new(3)
+        },
+    ])
+    .with_main_commitment_groups(&[0, 1]);
+    let (config, challenger) = default_config();
+    let (pk, vk) = machine.setup(&config);
+    for height in [8, 64, 1 << 10] {
+        let traces: BTreeMap<_, _> = machine
+            .chip_ids()
+            .into_iter()
+            .zip(&machine.chips)
+            .map(|(id, chip)| (id, counter_trace(chip.width, height)))
+            .collect();
+        let proof = machine.prove(
+            &config,
+            &mut challenger.clone(),
+            &pk,
+            traces,
+            &[],
+            &ProverOptions {
+                check_constraints: true,
+                ..Default::default()
+            },
+        );
+        machine
+            .verify(&config, &mut challenger.clone(), &vk, &proof, &[])
+            .unwrap();
+    }
+}
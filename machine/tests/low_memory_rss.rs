@@ -0,0 +1,43 @@
+#![cfg(feature = "low-memory")]
+
+mod common;
+
+use p3_machine::chip::Chip;
+use p3_machine::memory::MemoryUsage;
+use p3_machine::trace::{
+    MachineTrace, MachineTraceBuilder, MachineTraceCommiter, MachineTraceLoader,
+};
+use p3_uni_stark::StarkGenericConfig;
+
+use common::counter::{counter_trace, CounterChip};
+use common::{default_config, MyConfig, Val};
+
+/// Releasing the committed main traces gives their memory back, while their LDEs are kept. This
+/// test is the only one in its binary, so that no other test allocates concurrently.
+#[test]
+fn test_released_main_traces_lower_rss() {
+    // The resident set size can only be read on platforms with procfs
+    if MemoryUsage::current().is_none() {
+        return;
+    }
+    let chip = CounterChip::new(4);
+    let height = 1 << 20;
+    let (config, _) = default_config();
+    let pcs = config.pcs();
+
+    let mut trace: MachineTrace<MyConfig, _> = MachineTraceBuilder::new(&[(chip.id(), chip)]);
+    trace.load_main(pcs, vec![Some(counter_trace(4, height))], &[0]);
+    let _main_data = trace.commit_main(pcs);
+
+    let before = MemoryUsage::current().unwrap();
+    trace.release_preprocessed_and_main();
+    let after = MemoryUsage::current().unwrap();
+
+    let trace_kib = (4 * height * core::mem::size_of::<Val>() / 1024) as u64;
+    let released_kib = before.rss_kib.saturating_sub(after.rss_kib);
+    assert!(
+        released_kib >= trace_kib / 2,
+        "Releasing a {trace_kib} KiB main trace only lowered the rss by {released_kib} KiB"
+    );
+    assert_eq!(trace[0].main.as_ref().unwrap().trace.value.values.len(), 0);
+}
@@ -0,0 +1,57 @@
+mod common;
+
+use p3_machine::error::VerificationError;
+
+use common::counter::{prove_and_verify, CounterChip, CounterMachine};
+
+fn machine(widths: &[usize], main_commitment_groups: &[usize]) -> CounterMachine {
+    CounterMachine::new(
+        widths
+            .iter()
+            .map(|&width| CounterChip::new(width))
+            .collect(),
+    )
+    .with_main_commitment_groups(main_commitment_groups)
+}
+
+#[test]
+fn test_single_group() {
+    let machine = machine(&[1, 2, 3], &[0, 0, 0]);
+    prove_and_verify(&machine, &[8, 16, 32], |_| {}).unwrap();
+}
+
+#[test]
+fn test_multiple_groups() {
+    let machine = machine(&[1, 2, 3], &[1, 0, 1]);
+    prove_and_verify(&machine, &[8, 16, 32], |proof| {
+        assert_eq!(proof.commitments.main.len(), 2);
+    })
+    .unwrap();
+}
+
+#[test]
+fn test_empty_group() {
+    let machine = machine(&[1, 2], &[0, 2]);
+    prove_and_verify(&machine, &[8, 16], |proof| {
+        assert!(proof.commitments.main[1].is_none());
+    })
+    .unwrap();
+}
+
+#[test]
+fn test_swapped_groups_rejected() {
+    let machine = machine(&[1, 2], &[0, 1]);
+    let result = prove_and_verify(&machine, &[8, 16], |proof| {
+        proof.commitments.main.swap(0, 1)
+    });
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_missing_group_rejected() {
+    let machine = machine(&[1, 2], &[0, 1]);
+    let result = prove_and_verify(&machine, &[8, 16], |proof| {
+        proof.commitments.main.pop();
+    });
+    assert!(matches!(result, Err(VerificationError::InvalidProofShape)));
+}
@@ -0,0 +1,158 @@
+mod common;
+
+use core::fmt::{self, Display, Formatter};
+use std::collections::BTreeMap;
+
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_air_util::debug::rap::track_constraints;
+use p3_air_util::folders::rap::TrackingConstraintBuilder;
+use p3_air_util::folders::EntriesLog;
+use p3_air_util::util::TraceEntry;
+use p3_air_util::{pad_to_power_of_two, PeriodicAir};
+use p3_field::{AbstractField, Field};
+use p3_interaction::{BaseInteractionAir, InteractionAir, InteractionAirBuilder, Rap};
+use p3_machine::chip::Chip;
+use p3_machine::machine::Machine;
+use p3_machine::minimize::minimize_failing_trace;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+
+use common::counter::{counter_trace, CounterBus, CounterChip, CounterMachine};
+use common::{Challenge, Val};
+
+/// A chip whose columns are all boolean, padded with zeros.
+#[derive(Clone, Debug)]
+struct BoolChip;
+
+impl Display for BoolChip {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "BoolChip")
+    }
+}
+
+impl<F: Field> BaseAir<F> for BoolChip {
+    fn width(&self) -> usize {
+        2
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for BoolChip {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        for &value in local.iter() {
+            builder.assert_bool(value);
+        }
+    }
+}
+
+impl<F: Field> BaseInteractionAir<F> for BoolChip {}
+
+impl<F: Field> InteractionAir<F> for BoolChip {}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for BoolChip {}
+
+impl<F: Field> PeriodicAir<F> for BoolChip {}
+
+impl Chip for BoolChip {
+    fn padding_row<F: Field>(&self) -> Option<Vec<F>> {
+        Some(vec![F::zero(); 2])
+    }
+}
+
+struct BoolMachine;
+
+impl Machine for BoolMachine {
+    type Chip = BoolChip;
+
+    type Bus = CounterBus;
+
+    fn chips(&self) -> Vec<Self::Chip> {
+        vec![BoolChip]
+    }
+}
+
+fn perm_challenges() -> [Challenge; 2] {
+    [Challenge::zero(); 2]
+}
+
+/// The failing-constraint report of a chip without interactions.
+fn report<C>(chip: &C, main: &RowMajorMatrix<Val>) -> EntriesLog<TraceEntry>
+where
+    C: for<'a> Rap<TrackingConstraintBuilder<'a, Val, Challenge>> + PeriodicAir<Val>,
+{
+    track_constraints(
+        chip,
+        &None,
+        &Some(main.as_view()),
+        &None,
+        perm_challenges(),
+        None,
+        &[],
+    )
+}
+
+#[test]
+fn test_transition_failure_minimized_to_its_window() {
+    let machine = CounterMachine::new(vec![CounterChip::new(2), CounterChip::new(1)]);
+    let target = machine.chips[0].id();
+    let mut traces: BTreeMap<_, _> = machine
+        .chips
+        .iter()
+        .zip([16, 8])
+        .map(|(chip, height)| (chip.id(), counter_trace(chip.width, height)))
+        .collect();
+    let main = traces.get_mut(&target).unwrap();
+    main.values[9 * 2 + 1] += Val::from_canonical_u32(5);
+    let report = report(&machine.chips[0], main);
+
+    let minimized =
+        minimize_failing_trace(&machine, &target, &traces, &report, &[], perm_challenges());
+    assert_eq!(minimized.rows[&target], vec![8, 9]);
+    let main = &minimized.main_traces[&target];
+    assert_eq!(main.height(), 2);
+    assert_eq!(main.values, traces[&target].values[8 * 2..10 * 2]);
+    // The other chip does not fail without its trace
+    assert_eq!(minimized.main_traces.len(), 1);
+}
+
+#[test]
+fn test_padded_trace_minimized_to_failing_row() {
+    let machine = BoolMachine;
+    let values = (0..20)
+        .map(|i| match i {
+            12 => Val::two(),
+            _ => Val::from_canonical_usize(i % 2),
+        })
+        .collect();
+    let trace = RowMajorMatrix::new(values, 2);
+    let mut padded = trace.clone();
+    pad_to_power_of_two(&mut padded, &[Val::zero(); 2]);
+    let report = report(&BoolChip, &padded);
+    let target = BoolChip.id();
+    let traces = BTreeMap::from([(target.clone(), trace)]);
+
+    let minimized =
+        minimize_failing_trace(&machine, &target, &traces, &report, &[], perm_challenges());
+    assert_eq!(minimized.rows[&target], vec![6]);
+    assert_eq!(
+        minimized.main_traces[&target].values,
+        vec![Val::two(), Val::one()]
+    );
+}
+
+#[test]
+#[should_panic(expected = "The target should fail as reported")]
+fn test_mismatched_report() {
+    let machine = BoolMachine;
+    let mut values = vec![Val::zero(); 8];
+    values[2] = Val::two();
+    let trace = RowMajorMatrix::new(values, 2);
+    let mut other = trace.clone();
+    other.values.swap(2, 3);
+    let report = report(&BoolChip, &other);
+    let target = BoolChip.id();
+    let traces = BTreeMap::from([(target.clone(), trace)]);
+
+    minimize_failing_trace(&machine, &target, &traces, &report, &[], perm_challenges());
+}
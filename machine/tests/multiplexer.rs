@@ -0,0 +1,480 @@
+extern crate alloc;
+
+mod common;
+
+use core::fmt::{self, Display, Formatter};
+use std::collections::BTreeMap;
+
+use p3_air::{Air, AirBuilder, BaseAir, VirtualPairCol};
+use p3_air_util::debug::air::check_constraints;
+use p3_air_util::PeriodicAir;
+use p3_derive::{Columnar, EnumDispatch};
+use p3_field::{AbstractField, Field};
+use p3_interaction::{BaseInteractionAir, Interaction, InteractionAir, InteractionAirBuilder, Rap};
+use p3_machine::chip::{Chip, ChipId};
+use p3_machine::machine::Machine;
+use p3_machine::multiplexer::{LocalAir, MultiplexedChip};
+use p3_machine::options::ProverOptions;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+
+use common::counter::CounterBus;
+use common::{default_config, Val};
+
+const ADD: u32 = 0;
+const MUL: u32 = 1;
+const SQUARE: u32 = 2;
+
+/// Sends `(op, a, b, c)` on bus 0 once per row.
+fn send_op<F: Field>(op: u32, a: usize, b: usize, c: usize) -> Interaction<F> {
+    Interaction {
+        fields: vec![
+            VirtualPairCol::constant(F::from_canonical_u32(op)),
+            VirtualPairCol::single_main(a),
+            VirtualPairCol::single_main(b),
+            VirtualPairCol::single_main(c),
+        ],
+        count: VirtualPairCol::constant(F::one()),
+        argument_index: 0,
+    }
+}
+
+/// Constrains `c = a + b`.
+#[derive(Clone, Debug)]
+struct AddChip;
+
+impl Display for AddChip {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "AddChip")
+    }
+}
+
+impl<F: Field> BaseAir<F> for AddChip {
+    fn width(&self) -> usize {
+        3
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for AddChip {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        builder.assert_eq(local[2], local[0] + local[1]);
+    }
+}
+
+impl<F: Field> BaseInteractionAir<F> for AddChip {
+    fn sends_from_indices(
+        &self,
+        _preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        vec![send_op(
+            ADD,
+            main_indices[0],
+            main_indices[1],
+            main_indices[2],
+        )]
+    }
+}
+
+/// Constrains `c = a * b`.
+#[derive(Clone, Debug)]
+struct MulChip;
+
+impl Display for MulChip {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "MulChip")
+    }
+}
+
+impl<F: Field> BaseAir<F> for MulChip {
+    fn width(&self) -> usize {
+        3
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for MulChip {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        builder.assert_eq(local[2], local[0] * local[1]);
+    }
+}
+
+impl<F: Field> BaseInteractionAir<F> for MulChip {
+    fn sends_from_indices(
+        &self,
+        _preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        vec![send_op(
+            MUL,
+            main_indices[0],
+            main_indices[1],
+            main_indices[2],
+        )]
+    }
+}
+
+/// Constrains `b = a * a`, and sends it as `(SQUARE, a, a, b)`.
+#[derive(Clone, Debug)]
+struct SquareChip;
+
+impl Display for SquareChip {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "SquareChip")
+    }
+}
+
+impl<F: Field> BaseAir<F> for SquareChip {
+    fn width(&self) -> usize {
+        2
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for SquareChip {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        builder.assert_eq(local[1], local[0] * local[0]);
+    }
+}
+
+impl<F: Field> BaseInteractionAir<F> for SquareChip {
+    fn sends_from_indices(
+        &self,
+        _preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        vec![send_op(
+            SQUARE,
+            main_indices[0],
+            main_indices[0],
+            main_indices[1],
+        )]
+    }
+}
+
+macro_rules! impl_op_chip {
+    ($chip:ident, $width:expr) => {
+        impl<F: Field> InteractionAir<F> for $chip {
+            fn sends(&self) -> Vec<Interaction<F>> {
+                self.sends_from_main_indices(&(0..$width).collect::<Vec<_>>())
+            }
+        }
+
+        impl<AB: InteractionAirBuilder> Rap<AB> for $chip {}
+
+        impl<F: Field> PeriodicAir<F> for $chip {}
+
+        impl Chip for $chip {}
+
+        impl LocalAir for $chip {}
+    };
+}
+
+impl_op_chip!(AddChip, 3);
+impl_op_chip!(MulChip, 3);
+impl_op_chip!(SquareChip, 2);
+
+#[repr(C)]
+#[derive(Columnar, Default)]
+struct CpuCols<T> {
+    op: T,
+    a: T,
+    b: T,
+    c: T,
+}
+
+/// Receives every operation on bus 0.
+#[derive(Clone, Debug)]
+struct CpuChip;
+
+impl Display for CpuChip {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "CpuChip")
+    }
+}
+
+impl<F: Field> BaseAir<F> for CpuChip {
+    fn width(&self) -> usize {
+        CpuCols::<F>::num_cols()
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for CpuChip {
+    fn eval(&self, _builder: &mut AB) {}
+}
+
+impl<F: Field> BaseInteractionAir<F> for CpuChip {
+    fn receives_from_indices(
+        &self,
+        _preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        let col_map = CpuCols::from_slice(main_indices);
+        vec![Interaction {
+            fields: vec![
+                VirtualPairCol::single_main(col_map.op),
+                VirtualPairCol::single_main(col_map.a),
+                VirtualPairCol::single_main(col_map.b),
+                VirtualPairCol::single_main(col_map.c),
+            ],
+            count: VirtualPairCol::constant(F::one()),
+            argument_index: 0,
+        }]
+    }
+}
+
+impl<F: Field> InteractionAir<F> for CpuChip {
+    fn receives(&self) -> Vec<Interaction<F>> {
+        let col_map = CpuCols::<F>::col_map();
+        self.receives_from_main_indices(col_map.as_slice())
+    }
+}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for CpuChip {}
+
+impl<F: Field> PeriodicAir<F> for CpuChip {}
+
+impl Chip for CpuChip {}
+
+#[derive(Clone, Debug, EnumDispatch)]
+enum OpChip {
+    Add(AddChip),
+    Mul(MulChip),
+    Square(SquareChip),
+}
+
+impl LocalAir for OpChip {}
+
+#[derive(Clone, Debug, EnumDispatch)]
+enum UnmergedChip {
+    Add(AddChip),
+    Mul(MulChip),
+    Square(SquareChip),
+    Cpu(CpuChip),
+}
+
+#[derive(Clone, Debug, EnumDispatch)]
+enum MergedChip {
+    Ops(MultiplexedChip<OpChip>),
+    Cpu(CpuChip),
+}
+
+struct UnmergedMachine;
+
+impl Machine for UnmergedMachine {
+    type Chip = UnmergedChip;
+
+    type Bus = CounterBus;
+
+    fn chips(&self) -> Vec<Self::Chip> {
+        vec![
+            UnmergedChip::Add(AddChip),
+            UnmergedChip::Mul(MulChip),
+            UnmergedChip::Square(SquareChip),
+            UnmergedChip::Cpu(CpuChip),
+        ]
+    }
+}
+
+struct MergedMachine;
+
+fn ops_chip() -> MultiplexedChip<OpChip> {
+    MultiplexedChip::new::<Val>(vec![
+        OpChip::Add(AddChip),
+        OpChip::Mul(MulChip),
+        OpChip::Square(SquareChip),
+    ])
+}
+
+impl Machine for MergedMachine {
+    type Chip = MergedChip;
+
+    type Bus = CounterBus;
+
+    fn chips(&self) -> Vec<Self::Chip> {
+        vec![MergedChip::Ops(ops_chip()), MergedChip::Cpu(CpuChip)]
+    }
+}
+
+fn matrix(rows: &[[u32; 3]], width: usize) -> RowMajorMatrix<Val> {
+    let values = rows
+        .iter()
+        .flat_map(|row| row[..width].iter().copied().map(Val::from_canonical_u32))
+        .collect();
+    RowMajorMatrix::new(values, width)
+}
+
+/// The traces of each operation chip, and the CPU trace receiving all of them.
+fn op_traces() -> ([RowMajorMatrix<Val>; 3], RowMajorMatrix<Val>) {
+    let adds = [[1, 2, 3], [4, 5, 9], [0, 0, 0], [7, 7, 14]];
+    let muls = [[3, 4, 12], [5, 6, 30]];
+    let squares = [[3, 9, 0], [11, 121, 0]];
+
+    let mut cpu = vec![];
+    cpu.extend(adds.iter().map(|&[a, b, c]| [ADD, a, b, c]));
+    cpu.extend(muls.iter().map(|&[a, b, c]| [MUL, a, b, c]));
+    cpu.extend(squares.iter().map(|&[a, b, _]| [SQUARE, a, a, b]));
+    let cpu_values = cpu
+        .into_iter()
+        .flatten()
+        .map(Val::from_canonical_u32)
+        .collect();
+
+    (
+        [matrix(&adds, 3), matrix(&muls, 3), matrix(&squares, 2)],
+        RowMajorMatrix::new(cpu_values, CpuCols::<Val>::num_cols()),
+    )
+}
+
+fn merged_traces() -> BTreeMap<ChipId, RowMajorMatrix<Val>> {
+    let ([adds, muls, squares], cpu) = op_traces();
+    let ops = ops_chip().merge_traces(&[Some(adds), Some(muls), Some(squares)]);
+    BTreeMap::from([
+        (MergedChip::Ops(ops_chip()).id(), ops),
+        (MergedChip::Cpu(CpuChip).id(), cpu),
+    ])
+}
+
+#[test]
+fn test_unmerged_machine() {
+    let ([adds, muls, squares], cpu) = op_traces();
+    let traces = BTreeMap::from([
+        (UnmergedChip::Add(AddChip).id(), adds),
+        (UnmergedChip::Mul(MulChip).id(), muls),
+        (UnmergedChip::Square(SquareChip).id(), squares),
+        (UnmergedChip::Cpu(CpuChip).id(), cpu),
+    ]);
+
+    let machine = UnmergedMachine;
+    let (config, challenger) = default_config();
+    let (pk, vk) = machine.setup(&config);
+    let proof = machine.prove(
+        &config,
+        &mut challenger.clone(),
+        &pk,
+        traces,
+        &[],
+        &ProverOptions::default(),
+    );
+    machine
+        .verify(&config, &mut challenger.clone(), &vk, &proof, &[])
+        .unwrap();
+}
+
+#[test]
+fn test_merged_machine() {
+    let machine = MergedMachine;
+    let (config, challenger) = default_config();
+    let (pk, vk) = machine.setup(&config);
+    let proof = machine.prove(
+        &config,
+        &mut challenger.clone(),
+        &pk,
+        merged_traces(),
+        &[],
+        &ProverOptions::default(),
+    );
+    assert_eq!(proof.chip_proofs.len(), 2);
+    machine
+        .verify(&config, &mut challenger.clone(), &vk, &proof, &[])
+        .unwrap();
+}
+
+#[test]
+fn test_merged_layout() {
+    let ops = ops_chip();
+    // Three selectors, three shared columns and a count column per sub-chip.
+    assert_eq!(BaseAir::<Val>::width(&ops), 9);
+    assert_eq!(InteractionAir::<Val>::sends(&ops).len(), 3);
+
+    let trace = &merged_traces()[&MergedChip::Ops(ops_chip()).id()];
+    assert_eq!(trace.height(), 8);
+    // The first square row, with its selector, shared columns and count.
+    let row = [0, 0, 1, 3, 9, 0, 0, 0, 1].map(Val::from_canonical_u32);
+    assert_eq!(trace.row_slice(6).to_vec(), row);
+}
+
+#[test]
+fn test_padding_rows_satisfy_constraints() {
+    let ops = ops_chip();
+    let trace = RowMajorMatrix::new(
+        ops.padding_row::<Val>().unwrap(),
+        BaseAir::<Val>::width(&ops),
+    );
+    check_constraints(&ops, &None, &Some(trace.as_view()), &[]);
+}
+
+#[test]
+#[should_panic(expected = "constraints had nonzero value on row 4")]
+fn test_wrong_sub_chip_row_rejected() {
+    let ops = ops_chip();
+    let mut trace = merged_traces()
+        .remove(&MergedChip::Ops(ops_chip()).id())
+        .unwrap();
+    // A wrong product in the first multiplication row.
+    let width = trace.width();
+    trace.values[4 * width + 5] = Val::from_canonical_u32(13);
+    check_constraints(&ops, &None, &Some(trace.as_view()), &[]);
+}
+
+#[test]
+#[should_panic(expected = "values didn't match on row 0")]
+fn test_ungated_count_rejected() {
+    let ops = ops_chip();
+    let mut trace = merged_traces()
+        .remove(&MergedChip::Ops(ops_chip()).id())
+        .unwrap();
+    // Claim the first addition row also sends a multiplication.
+    let width = trace.width();
+    trace.values[7] = Val::one();
+    check_constraints(&ops, &None, &Some(trace.as_view()), &[]);
+}
+
+/// A sub-chip with a preprocessed or a periodic column, which can't be multiplexed.
+#[derive(Clone, Debug)]
+enum FixedColumnChip {
+    Preprocessed,
+    Periodic,
+}
+
+impl<F: Field> BaseAir<F> for FixedColumnChip {
+    fn width(&self) -> usize {
+        1
+    }
+
+    fn preprocessed_trace(&self) -> Option<RowMajorMatrix<F>> {
+        match self {
+            FixedColumnChip::Preprocessed => Some(RowMajorMatrix::new_col(vec![F::one()])),
+            FixedColumnChip::Periodic => None,
+        }
+    }
+}
+
+impl<F: Field> BaseInteractionAir<F> for FixedColumnChip {}
+
+impl<F: Field> PeriodicAir<F> for FixedColumnChip {
+    fn periodic_columns(&self) -> Vec<Vec<F>> {
+        match self {
+            FixedColumnChip::Preprocessed => vec![],
+            FixedColumnChip::Periodic => vec![vec![F::one(), F::two()]],
+        }
+    }
+}
+
+impl LocalAir for FixedColumnChip {}
+
+#[test]
+#[should_panic(expected = "Sub-chip 0 should have no preprocessed trace")]
+fn test_preprocessed_sub_chip_rejected() {
+    MultiplexedChip::new::<Val>(vec![FixedColumnChip::Preprocessed]);
+}
+
+#[test]
+#[should_panic(expected = "Sub-chip 0 should have no periodic columns")]
+fn test_periodic_sub_chip_rejected() {
+    MultiplexedChip::new::<Val>(vec![FixedColumnChip::Periodic]);
+}
@@ -0,0 +1,227 @@
+mod common;
+
+use core::fmt::{self, Display, Formatter};
+use std::collections::BTreeMap;
+
+use p3_air::{Air, AirBuilder, BaseAir, VirtualPairCol};
+use p3_air_util::PeriodicAir;
+use p3_field::{AbstractField, Field};
+use p3_interaction::{BaseInteractionAir, Interaction, InteractionAir, InteractionAirBuilder, Rap};
+use p3_machine::chip::{Chip, ChipId};
+use p3_machine::error::LookupError;
+use p3_machine::lookup::generate_multiplicities;
+use p3_machine::machine::Machine;
+use p3_machine::options::ProverOptions;
+use p3_matrix::dense::RowMajorMatrix;
+
+use common::counter::CounterBus;
+use common::{default_config, Val};
+
+const TABLE_SIZE: usize = 8;
+
+/// Either a preprocessed table of `0..TABLE_SIZE` receiving on bus 0 with a multiplicity column,
+/// or a chip sending its first column on bus 0 with its second column as the count. The
+/// `MainTable` variant is a malformed table, keying its receive by its main column.
+#[derive(Clone, Debug)]
+enum TableChip {
+    Table,
+    MainTable,
+    Sender,
+}
+
+impl Display for TableChip {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TableChip::Table => write!(f, "Table"),
+            TableChip::MainTable => write!(f, "MainTable"),
+            TableChip::Sender => write!(f, "Sender"),
+        }
+    }
+}
+
+impl<F: Field> BaseAir<F> for TableChip {
+    fn width(&self) -> usize {
+        match self {
+            TableChip::Table | TableChip::MainTable => 1,
+            TableChip::Sender => 2,
+        }
+    }
+
+    fn preprocessed_trace(&self) -> Option<RowMajorMatrix<F>> {
+        match self {
+            TableChip::Table | TableChip::MainTable => Some(RowMajorMatrix::new_col(
+                (0..TABLE_SIZE).map(F::from_canonical_usize).collect(),
+            )),
+            TableChip::Sender => None,
+        }
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for TableChip {
+    fn eval(&self, _builder: &mut AB) {}
+}
+
+impl<F: Field> BaseInteractionAir<F> for TableChip {
+    fn receives_from_indices(
+        &self,
+        preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        match self {
+            TableChip::Table => vec![Interaction {
+                fields: vec![VirtualPairCol::single_preprocessed(preprocessed_indices[0])],
+                count: VirtualPairCol::single_main(main_indices[0]),
+                argument_index: 0,
+            }],
+            TableChip::MainTable => vec![Interaction {
+                fields: vec![VirtualPairCol::single_main(main_indices[0])],
+                count: VirtualPairCol::single_main(main_indices[0]),
+                argument_index: 0,
+            }],
+            TableChip::Sender => vec![],
+        }
+    }
+
+    fn sends_from_indices(
+        &self,
+        _preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        match self {
+            TableChip::Table | TableChip::MainTable => vec![],
+            TableChip::Sender => vec![Interaction {
+                fields: vec![VirtualPairCol::single_main(main_indices[0])],
+                count: VirtualPairCol::single_main(main_indices[1]),
+                argument_index: 0,
+            }],
+        }
+    }
+}
+
+impl<F: Field> InteractionAir<F> for TableChip {
+    fn receives(&self) -> Vec<Interaction<F>> {
+        self.receives_from_indices(&[0], &[0])
+    }
+
+    fn sends(&self) -> Vec<Interaction<F>> {
+        self.sends_from_indices(&[], &[0, 1])
+    }
+}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for TableChip {
+    fn preprocessed_width(&self) -> usize {
+        match self {
+            TableChip::Table | TableChip::MainTable => 1,
+            TableChip::Sender => 0,
+        }
+    }
+}
+
+impl<F: Field> PeriodicAir<F> for TableChip {}
+
+impl Chip for TableChip {}
+
+struct TableMachine;
+
+impl Machine for TableMachine {
+    type Chip = TableChip;
+
+    type Bus = CounterBus;
+
+    fn chips(&self) -> Vec<Self::Chip> {
+        vec![TableChip::Table, TableChip::Sender]
+    }
+}
+
+/// A sender trace with the given values and counts.
+fn sender_trace(rows: &[(u32, u32)]) -> BTreeMap<ChipId, RowMajorMatrix<Val>> {
+    let values = rows
+        .iter()
+        .flat_map(|&(value, count)| [value, count].map(Val::from_canonical_u32))
+        .collect();
+    BTreeMap::from([(TableChip::Sender.id(), RowMajorMatrix::new(values, 2))])
+}
+
+#[test]
+fn test_multiplicities_weighted_by_count() {
+    let traces = sender_trace(&[(3, 1), (1, 2), (3, 1), (7, 0)]);
+    let multiplicities =
+        generate_multiplicities(&TableChip::Table, 0, &TableMachine.chips_by_id(), &traces)
+            .unwrap();
+
+    let expected = [0, 2, 0, 2, 0, 0, 0, 0].map(Val::from_canonical_u32);
+    assert_eq!(multiplicities, expected);
+}
+
+#[test]
+fn test_prove_with_multiplicities() {
+    let machine = TableMachine;
+    let mut traces = sender_trace(&[
+        (3, 1),
+        (1, 2),
+        (3, 1),
+        (7, 0),
+        (0, 1),
+        (5, 3),
+        (6, 1),
+        (2, 1),
+    ]);
+    let multiplicities =
+        generate_multiplicities(&TableChip::Table, 0, &machine.chips_by_id(), &traces).unwrap();
+    traces.insert(
+        TableChip::Table.id(),
+        RowMajorMatrix::new_col(multiplicities),
+    );
+
+    let (config, challenger) = default_config();
+    let (pk, vk) = machine.setup(&config);
+    let proof = machine.prove(
+        &config,
+        &mut challenger.clone(),
+        &pk,
+        traces,
+        &[],
+        &ProverOptions::default(),
+    );
+    machine
+        .verify(&config, &mut challenger.clone(), &vk, &proof, &[])
+        .unwrap();
+}
+
+#[test]
+fn test_message_not_in_table() {
+    let traces = sender_trace(&[(3, 1), (8, 1)]);
+    let result =
+        generate_multiplicities(&TableChip::Table, 0, &TableMachine.chips_by_id(), &traces);
+
+    match result {
+        Err(LookupError::MessageNotInTable { chip, row, message }) => {
+            assert_eq!(chip, "Sender");
+            assert_eq!(row, 1);
+            assert_eq!(message, vec![Val::from_canonical_u32(8)]);
+        }
+        _ => panic!("Expected a message not in the table"),
+    }
+}
+
+#[test]
+fn test_invalid_table() {
+    let result = generate_multiplicities(
+        &TableChip::Sender,
+        0,
+        &TableMachine.chips_by_id(),
+        &sender_trace(&[]),
+    );
+    assert!(matches!(result, Err(LookupError::InvalidTable(_))));
+}
+
+#[test]
+fn test_table_keyed_by_main_column_rejected() {
+    let result = generate_multiplicities(
+        &TableChip::MainTable,
+        0,
+        &TableMachine.chips_by_id(),
+        &sender_trace(&[(3, 1)]),
+    );
+    assert!(matches!(result, Err(LookupError::InvalidTable(id)) if id == "MainTable"));
+}
@@ -0,0 +1,186 @@
+mod common;
+
+use core::fmt::{self, Display, Formatter};
+use std::collections::BTreeMap;
+
+use p3_air::{Air, BaseAir};
+use p3_air_util::{periodic_values_on_row, PeriodicAir, PeriodicAirBuilder};
+use p3_commit::{Pcs, PolynomialSpace};
+use p3_field::{AbstractExtensionField, AbstractField, Field};
+use p3_interaction::{BaseInteractionAir, InteractionAir, InteractionAirBuilder, Rap};
+use p3_machine::chip::Chip;
+use p3_machine::error::VerificationError;
+use p3_machine::machine::Machine;
+use p3_machine::options::ProverOptions;
+use p3_machine::periodic::{periodic_values_at_point, periodic_values_on_coset};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use p3_uni_stark::StarkGenericConfig;
+use rand::{thread_rng, Rng};
+
+use common::counter::CounterBus;
+use common::{default_config, Challenge, Val};
+
+/// A chip adding a round constant of period 4 and another of period 8 to its column on every
+/// row.
+#[derive(Clone, Debug)]
+struct RoundChip {
+    round_constants: [u32; 4],
+}
+
+impl Display for RoundChip {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "RoundChip")
+    }
+}
+
+impl<F: Field> BaseAir<F> for RoundChip {
+    fn width(&self) -> usize {
+        1
+    }
+}
+
+impl<AB: PeriodicAirBuilder> Air<AB> for RoundChip {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let next = main.row_slice(1);
+        let periodic_values = builder.periodic_values();
+        let (short, long): (AB::Expr, AB::Expr) =
+            (periodic_values[0].into(), periodic_values[1].into());
+        builder
+            .when_transition()
+            .assert_eq(next[0], local[0] + short + long);
+    }
+}
+
+impl<F: Field> BaseInteractionAir<F> for RoundChip {}
+
+impl<F: Field> InteractionAir<F> for RoundChip {}
+
+impl<AB: InteractionAirBuilder + PeriodicAirBuilder> Rap<AB> for RoundChip {}
+
+impl<F: Field> PeriodicAir<F> for RoundChip {
+    fn periodic_columns(&self) -> Vec<Vec<F>> {
+        vec![
+            self.round_constants
+                .iter()
+                .map(|&constant| F::from_canonical_u32(constant))
+                .collect(),
+            (1..=8).map(F::from_canonical_u32).collect(),
+        ]
+    }
+}
+
+impl Chip for RoundChip {}
+
+struct RoundMachine {
+    chip: RoundChip,
+}
+
+impl Machine for RoundMachine {
+    type Chip = RoundChip;
+
+    type Bus = CounterBus;
+
+    fn chips(&self) -> Vec<Self::Chip> {
+        vec![self.chip.clone()]
+    }
+}
+
+fn machine(round_constants: [u32; 4]) -> RoundMachine {
+    RoundMachine {
+        chip: RoundChip { round_constants },
+    }
+}
+
+fn round_trace(chip: &RoundChip, height: usize) -> RowMajorMatrix<Val> {
+    let columns = PeriodicAir::<Val>::periodic_columns(chip);
+    let mut values = vec![Val::zero()];
+    for row in 0..height - 1 {
+        let constants = periodic_values_on_row(&columns, row);
+        values.push(values[row] + constants[0] + constants[1]);
+    }
+    RowMajorMatrix::new_col(values)
+}
+
+/// Proves the trace of `prover` and verifies the proof against the constraints of `verifier`.
+fn prove_and_verify(
+    prover: &RoundMachine,
+    verifier: &RoundMachine,
+    height: usize,
+) -> Result<(), VerificationError> {
+    let (config, challenger) = default_config();
+    let (pk, vk) = prover.setup(&config);
+    let traces = BTreeMap::from([(prover.chip.id(), round_trace(&prover.chip, height))]);
+    let proof = prover.prove(
+        &config,
+        &mut challenger.clone(),
+        &pk,
+        traces,
+        &[],
+        &ProverOptions::default(),
+    );
+    verifier.verify(&config, &mut challenger.clone(), &vk, &proof, &[])
+}
+
+#[test]
+fn test_periodic_columns_prove() {
+    let machine = machine([3, 1, 4, 1]);
+    for height in [8, 16, 64] {
+        prove_and_verify(&machine, &machine, height).unwrap();
+    }
+}
+
+#[test]
+fn test_changed_round_constants_rejected() {
+    let result = prove_and_verify(&machine([3, 1, 4, 1]), &machine([3, 1, 5, 1]), 16);
+    assert!(matches!(
+        result,
+        Err(VerificationError::OodEvaluationMismatch)
+    ));
+}
+
+#[test]
+fn test_interpolation_at_point_matches_coset() {
+    let (config, _) = default_config();
+    let columns = PeriodicAir::<Val>::periodic_columns(&RoundChip {
+        round_constants: [3, 1, 4, 1],
+    });
+    let trace_domain = config.pcs().natural_domain_for_degree(16);
+    let coset = trace_domain.create_disjoint_domain(64);
+    let coset_values = periodic_values_on_coset(&columns, trace_domain, coset);
+
+    let mut point = coset.first_point();
+    for i in 0..coset.size() {
+        let values = periodic_values_at_point::<_, Challenge>(&columns, trace_domain, point.into());
+        for (values_on_coset, value) in coset_values.iter().zip(values) {
+            assert_eq!(
+                value,
+                Challenge::from_base(values_on_coset[i % values_on_coset.len()]),
+                "point {i}"
+            );
+        }
+        point = coset.next_point(point).unwrap();
+    }
+
+    // On the trace domain, the interpolation takes the column's values
+    let mut point = trace_domain.first_point();
+    for row in 0..trace_domain.size() {
+        let values = periodic_values_at_point::<_, Challenge>(&columns, trace_domain, point.into());
+        let expected = periodic_values_on_row(&columns, row)
+            .into_iter()
+            .map(Challenge::from_base)
+            .collect::<Vec<_>>();
+        assert_eq!(values, expected);
+        point = trace_domain.next_point(point).unwrap();
+    }
+
+    // Off both domains, shifting the point by a period of the columns keeps their values
+    let zeta: Challenge = thread_rng().gen();
+    let period_shift = trace_domain.next_point(Val::one()).unwrap().exp_u64(8);
+    assert_eq!(
+        periodic_values_at_point(&columns, trace_domain, zeta),
+        periodic_values_at_point(&columns, trace_domain, zeta * period_shift)
+    );
+}
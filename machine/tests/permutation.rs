@@ -0,0 +1,103 @@
+mod common;
+
+use p3_air::VirtualPairCol;
+use p3_field::{AbstractField, Field};
+use p3_interaction::{
+    generate_permutation_trace, generate_rlc_elements, reduce_row, Interaction, InteractionType,
+};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use rand::{thread_rng, Rng};
+
+use common::{Challenge, Val};
+
+fn interactions() -> Vec<(Interaction<Val>, InteractionType)> {
+    vec![
+        (
+            Interaction {
+                fields: vec![
+                    VirtualPairCol::single_main(0),
+                    VirtualPairCol::single_main(1),
+                ],
+                count: VirtualPairCol::single_main(2),
+                argument_index: 0,
+            },
+            InteractionType::Send,
+        ),
+        (
+            Interaction {
+                fields: vec![VirtualPairCol::single_main(1)],
+                count: VirtualPairCol::constant(Val::one()),
+                argument_index: 1,
+            },
+            InteractionType::Receive,
+        ),
+    ]
+}
+
+/// The permutation trace computed one row at a time, with a running sum.
+fn sequential_permutation_trace(
+    main: &RowMajorMatrix<Val>,
+    interactions: &[(Interaction<Val>, InteractionType)],
+    random_elements: [Challenge; 2],
+) -> RowMajorMatrix<Challenge> {
+    let alphas = generate_rlc_elements(interactions, random_elements[0]);
+    let mut values = Vec::new();
+    let mut phi = Challenge::zero();
+    for n in 0..main.height() {
+        let main_row = main.row_slice(n);
+        for (interaction, interaction_type) in interactions {
+            let reciprocal = reduce_row::<Val, Val, Val, Challenge>(
+                &[],
+                &*main_row,
+                &interaction.fields,
+                alphas[interaction.argument_index],
+                random_elements[1].powers(),
+            )
+            .try_inverse()
+            .unwrap_or_default();
+            let mult = interaction.count.apply::<Val, Val>(&[], &*main_row);
+            match interaction_type {
+                InteractionType::Send => phi += reciprocal * mult,
+                InteractionType::Receive => phi -= reciprocal * mult,
+            }
+            values.push(reciprocal);
+        }
+        values.push(phi);
+    }
+    RowMajorMatrix::new(values, interactions.len() + 1)
+}
+
+/// The chunked batch inversion and prefix sum match the sequential computation exactly, for
+/// heights within one chunk, on chunk boundaries and across several chunks.
+#[test]
+fn test_parallel_permutation_trace_matches_sequential() {
+    let mut rng = thread_rng();
+    let interactions = interactions();
+    for height in [
+        1,
+        7,
+        1365,
+        1366,
+        2048,
+        4095,
+        4096,
+        4097,
+        3 * 4096 + 5,
+        1 << 14,
+    ] {
+        let main = RowMajorMatrix::<Val>::rand(&mut rng, height, 3);
+        let random_elements: [Challenge; 2] = rng.gen();
+
+        let perm = generate_permutation_trace(
+            &None,
+            &Some(main.as_view()),
+            &interactions,
+            random_elements,
+        )
+        .unwrap();
+        let expected = sequential_permutation_trace(&main, &interactions, random_elements);
+        assert_eq!(perm.width(), expected.width());
+        assert_eq!(perm.values, expected.values, "height {height}");
+    }
+}
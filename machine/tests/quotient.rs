@@ -0,0 +1,183 @@
+mod common;
+
+use core::fmt::{self, Display, Formatter};
+
+use p3_air::{Air, AirBuilder, BaseAir, VirtualPairCol};
+use p3_air_util::folders::rap::{SymbolicAirBuilder, VerifierConstraintFolder};
+use p3_air_util::{get_num_constraints, get_quotient_degree, PeriodicAir};
+use p3_commit::{Pcs, PolynomialSpace};
+use p3_field::{AbstractExtensionField, AbstractField, Field};
+use p3_interaction::{BaseInteractionAir, Interaction, InteractionAir, InteractionAirBuilder, Rap};
+use p3_machine::quotient::quotient_values;
+use p3_matrix::dense::{RowMajorMatrix, RowMajorMatrixView};
+use p3_matrix::stack::VerticalPair;
+use p3_matrix::Matrix;
+use p3_uni_stark::{PackedChallenge, StarkGenericConfig};
+use rand::{thread_rng, Rng};
+
+use common::{default_config, Challenge, MyConfig, Val};
+
+/// A chip with base field constraints, and extension field constraints from the interaction it
+/// sends.
+#[derive(Clone, Debug)]
+struct MixedChip;
+
+impl Display for MixedChip {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "MixedChip")
+    }
+}
+
+impl<F: Field> BaseAir<F> for MixedChip {
+    fn width(&self) -> usize {
+        3
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for MixedChip {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let next = main.row_slice(1);
+        builder
+            .when_transition()
+            .assert_eq(next[0], local[0] * local[1]);
+        builder.when_first_row().assert_zero(local[2]);
+        builder.assert_bool(local[2]);
+    }
+}
+
+impl<F: Field> BaseInteractionAir<F> for MixedChip {
+    fn sends_from_indices(
+        &self,
+        _preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        vec![Interaction {
+            fields: vec![
+                VirtualPairCol::single_main(main_indices[0]),
+                VirtualPairCol::single_main(main_indices[1]),
+            ],
+            count: VirtualPairCol::single_main(main_indices[2]),
+            argument_index: 0,
+        }]
+    }
+}
+
+impl<F: Field> InteractionAir<F> for MixedChip {
+    fn sends(&self) -> Vec<Interaction<F>> {
+        self.sends_from_main_indices(&[0, 1, 2])
+    }
+}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for MixedChip {}
+
+impl<F: Field> PeriodicAir<F> for MixedChip {}
+
+/// Checks that the quotient of random traces of the given height matches the verifier's Horner
+/// fold of the constraints, evaluated sequentially at every point of the quotient domain.
+fn check_quotient_matches_verifier_fold(height: usize) {
+    let (config, _) = default_config();
+    let mut rng = thread_rng();
+    let chip = MixedChip;
+
+    let trace_domain = config.pcs().natural_domain_for_degree(height);
+    let quotient_degree = get_quotient_degree::<Val, _>(&chip, 0);
+    let quotient_domain =
+        trace_domain.create_disjoint_domain(trace_domain.size() * quotient_degree);
+    let quotient_size = quotient_domain.size();
+    let next_step = quotient_size / trace_domain.size();
+
+    let perm_width =
+        <MixedChip as Rap<SymbolicAirBuilder<Val>>>::permutation_width(&chip).unwrap_or_default();
+    let main = RowMajorMatrix::<Val>::rand(&mut rng, quotient_size, 3);
+    let perm = RowMajorMatrix::<Val>::rand(
+        &mut rng,
+        quotient_size,
+        perm_width * <Challenge as AbstractExtensionField<Val>>::D,
+    );
+    let perm_challenges: [Challenge; 2] = rng.gen();
+    let cumulative_sum: Challenge = rng.gen();
+    let alpha: Challenge = rng.gen();
+    let num_constraints = get_num_constraints::<Val, _>(&chip, 0);
+
+    let quotient = quotient_values::<MyConfig, _, _>(
+        &chip,
+        trace_domain,
+        quotient_domain,
+        None,
+        Some(main.clone()),
+        Some(perm.clone()),
+        &[],
+        &[],
+        &[],
+        perm_challenges.map(PackedChallenge::<MyConfig>::from_f),
+        alpha,
+        num_constraints,
+        PackedChallenge::<MyConfig>::from_f(cumulative_sum),
+        &[],
+        None,
+    );
+    assert_eq!(quotient.len(), quotient_size);
+
+    let main_row = |row: usize| {
+        main.row_slice(row % quotient_size)
+            .iter()
+            .map(|&value| Challenge::from_base(value))
+            .collect::<Vec<_>>()
+    };
+    let perm_row = |row: usize| {
+        perm.row_slice(row % quotient_size)
+            .chunks_exact(<Challenge as AbstractExtensionField<Val>>::D)
+            .map(<Challenge as AbstractExtensionField<Val>>::from_base_slice)
+            .collect::<Vec<_>>()
+    };
+    let mut point = quotient_domain.first_point();
+    for (i, &value) in quotient.iter().enumerate() {
+        let sels = trace_domain.selectors_at_point(Challenge::from_base(point));
+        let (main_local, main_next) = (main_row(i), main_row(i + next_step));
+        let (perm_local, perm_next) = (perm_row(i), perm_row(i + next_step));
+        let mut folder: VerifierConstraintFolder<'_, MyConfig> = VerifierConstraintFolder {
+            preprocessed: VerticalPair::new(
+                RowMajorMatrixView::new_row(&[]),
+                RowMajorMatrixView::new_row(&[]),
+            ),
+            main: VerticalPair::new(
+                RowMajorMatrixView::new_row(&main_local),
+                RowMajorMatrixView::new_row(&main_next),
+            ),
+            perm: VerticalPair::new(
+                RowMajorMatrixView::new_row(&perm_local),
+                RowMajorMatrixView::new_row(&perm_next),
+            ),
+            perm_challenges,
+            public_values: &[],
+            periodic_values: &[],
+            row_selectors: &[],
+            row_selector_values: &[],
+            cumulative_sum,
+            is_first_row: sels.is_first_row,
+            is_last_row: sels.is_last_row,
+            is_transition: sels.is_transition,
+            alpha,
+            accumulator: Challenge::zero(),
+        };
+        chip.eval_all(&mut folder);
+        assert_eq!(value, folder.accumulator * sels.inv_zeroifier, "point {i}");
+        point = quotient_domain.next_point(point).unwrap();
+    }
+}
+
+/// The split base and extension accumulators of the prover give the same quotient as the
+/// verifier's Horner fold of the constraints.
+#[test]
+fn test_quotient_matches_verifier_fold() {
+    check_quotient_matches_verifier_fold(8);
+}
+
+/// The quotient evaluated in parallel over packed rows matches the sequential evaluation, over a
+/// quotient domain spanning many parallel chunks.
+#[test]
+fn test_parallel_quotient_matches_sequential_fold() {
+    check_quotient_matches_verifier_fold(1 << 13);
+}
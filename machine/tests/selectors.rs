@@ -0,0 +1,250 @@
+mod common;
+
+use core::fmt::{self, Display, Formatter};
+use std::collections::BTreeMap;
+
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_air_util::debug::rap::{check_constraints, track_constraints};
+use p3_air_util::util::TraceEntry;
+use p3_air_util::{PeriodicAir, RowSelector, SelectorAirBuilder};
+use p3_commit::{Pcs, PolynomialSpace};
+use p3_field::{AbstractExtensionField, AbstractField, Field};
+use p3_interaction::{BaseInteractionAir, InteractionAir, InteractionAirBuilder, Rap};
+use p3_machine::chip::Chip;
+use p3_machine::error::VerificationError;
+use p3_machine::machine::Machine;
+use p3_machine::options::ProverOptions;
+use p3_machine::selectors::{row_selectors_at_point, row_selectors_on_coset};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use p3_uni_stark::StarkGenericConfig;
+
+use common::counter::CounterBus;
+use common::{default_config, Challenge, Val};
+
+/// A chip whose first column is 7 on row 1 and 5 on the given row, and whose second column is
+/// zero on rows 4 to 6 and one on every fourth row from row 3.
+#[derive(Clone, Debug)]
+struct ScheduleChip {
+    row: usize,
+}
+
+impl Display for ScheduleChip {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "ScheduleChip")
+    }
+}
+
+impl<F: Field> BaseAir<F> for ScheduleChip {
+    fn width(&self) -> usize {
+        2
+    }
+}
+
+impl<AB: SelectorAirBuilder> Air<AB> for ScheduleChip {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let (a, b): (AB::Expr, AB::Expr) = (local[0].into(), local[1].into());
+
+        let is_row_one = builder.is_row(1);
+        builder.assert_zero(is_row_one * (a.clone() - AB::Expr::from_canonical_u32(7)));
+        builder
+            .when_row(self.row)
+            .assert_eq(a, AB::Expr::from_canonical_u32(5));
+        builder.when_rows(4..7).assert_zero(b.clone());
+        builder.when_every_nth_row(4, 3).assert_one(b);
+    }
+}
+
+impl<F: Field> BaseInteractionAir<F> for ScheduleChip {}
+
+impl<F: Field> InteractionAir<F> for ScheduleChip {}
+
+impl<AB: InteractionAirBuilder + SelectorAirBuilder> Rap<AB> for ScheduleChip {}
+
+impl<F: Field> PeriodicAir<F> for ScheduleChip {}
+
+impl Chip for ScheduleChip {}
+
+struct ScheduleMachine {
+    chip: ScheduleChip,
+}
+
+impl Machine for ScheduleMachine {
+    type Chip = ScheduleChip;
+
+    type Bus = CounterBus;
+
+    fn chips(&self) -> Vec<Self::Chip> {
+        vec![self.chip.clone()]
+    }
+}
+
+fn machine(row: usize) -> ScheduleMachine {
+    ScheduleMachine {
+        chip: ScheduleChip { row },
+    }
+}
+
+fn schedule_trace(row: usize, height: usize) -> RowMajorMatrix<Val> {
+    let values = (0..height)
+        .flat_map(|i| {
+            let a = match i {
+                1 => 7,
+                _ if i == row => 5,
+                _ => i as u32 + 10,
+            };
+            let b = match i {
+                4..=6 => 0,
+                _ if i % 4 == 3 => 1,
+                _ => 2,
+            };
+            [a, b].map(Val::from_canonical_u32)
+        })
+        .collect();
+    RowMajorMatrix::new(values, 2)
+}
+
+/// Proves the trace of `prover` and verifies the proof against the constraints of `verifier`.
+fn prove_and_verify(
+    prover: &ScheduleMachine,
+    verifier: &ScheduleMachine,
+    height: usize,
+) -> Result<(), VerificationError> {
+    let (config, challenger) = default_config();
+    let (pk, vk) = prover.setup(&config);
+    let traces = BTreeMap::from([(prover.chip.id(), schedule_trace(prover.chip.row, height))]);
+    let proof = prover.prove(
+        &config,
+        &mut challenger.clone(),
+        &pk,
+        traces,
+        &[],
+        &ProverOptions::default(),
+    );
+    verifier.verify(&config, &mut challenger.clone(), &vk, &proof, &[])
+}
+
+#[test]
+fn test_row_selectors_prove() {
+    for height in [8, 16, 64] {
+        prove_and_verify(&machine(2), &machine(2), height).unwrap();
+    }
+}
+
+#[test]
+fn test_changed_row_rejected() {
+    let result = prove_and_verify(&machine(2), &machine(3), 16);
+    assert!(matches!(
+        result,
+        Err(VerificationError::OodEvaluationMismatch)
+    ));
+}
+
+fn perm_challenges() -> [Challenge; 2] {
+    [Challenge::zero(); 2]
+}
+
+#[test]
+fn test_debug_builders_select_rows() {
+    let chip = ScheduleChip { row: 2 };
+    let trace = schedule_trace(2, 16);
+    check_constraints(
+        &chip,
+        &None,
+        &Some(trace.as_view()),
+        &None,
+        perm_challenges(),
+        None,
+        &[],
+    );
+    let entries = track_constraints(
+        &chip,
+        &None,
+        &Some(trace.as_view()),
+        &None,
+        perm_challenges(),
+        None,
+        &[],
+    );
+    assert!(entries.failing.is_empty());
+
+    // Each selector only constrains its own rows
+    let mut trace = trace;
+    for (row, col) in [(2, 0), (5, 1), (11, 1)] {
+        trace.values[row * 2 + col] += Val::one();
+    }
+    trace.values[8 * 2 + 1] = Val::zero();
+    let entries = track_constraints(
+        &chip,
+        &None,
+        &Some(trace.as_view()),
+        &None,
+        perm_challenges(),
+        None,
+        &[],
+    );
+    assert_eq!(
+        entries.failing.into_iter().collect::<Vec<_>>(),
+        vec![
+            TraceEntry::Main { row: 2, col: 0 },
+            TraceEntry::Main { row: 5, col: 1 },
+            TraceEntry::Main { row: 11, col: 1 },
+        ]
+    );
+}
+
+#[test]
+#[should_panic(expected = "constraints had nonzero value on row 5")]
+fn test_debug_builder_rejects_selected_row() {
+    let mut trace = schedule_trace(2, 16);
+    trace.values[5 * 2 + 1] = Val::one();
+    check_constraints(
+        &ScheduleChip { row: 2 },
+        &None,
+        &Some(trace.as_view()),
+        &None,
+        perm_challenges(),
+        None,
+        &[],
+    );
+}
+
+#[test]
+fn test_row_selectors_at_point_match_coset() {
+    let (config, _) = default_config();
+    let selectors = [
+        RowSelector::Row(2),
+        RowSelector::Row(20),
+        RowSelector::Rows(4..7),
+        RowSelector::Rows(13..20),
+        RowSelector::Rows(0..16),
+        RowSelector::Rows(5..5),
+        RowSelector::EveryNthRow {
+            period: 4,
+            offset: 3,
+        },
+    ];
+    let trace_domain = config.pcs().natural_domain_for_degree(16);
+    for coset_size in [16, 64] {
+        let coset = trace_domain.create_disjoint_domain(coset_size);
+        let coset_values = row_selectors_on_coset(&selectors, trace_domain, coset);
+
+        let mut point = coset.first_point();
+        for i in 0..coset.size() {
+            let values =
+                row_selectors_at_point::<_, Challenge>(&selectors, trace_domain, point.into());
+            for ((selector, values_on_coset), value) in
+                selectors.iter().zip(&coset_values).zip(values)
+            {
+                assert_eq!(
+                    value,
+                    Challenge::from_base(values_on_coset[i % values_on_coset.len()]),
+                    "{selector:?} at point {i} of a coset of size {coset_size}"
+                );
+            }
+            point = coset.next_point(point).unwrap();
+        }
+    }
+}
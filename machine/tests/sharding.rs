@@ -0,0 +1,75 @@
+mod common;
+
+use p3_machine::error::VerificationError;
+use p3_machine::proof::MachineProof;
+
+use common::counter::{prove_and_verify, CounterChip, CounterMachine};
+use common::MyConfig;
+
+/// A machine where one counter sends its values to another on the same bus.
+fn machine(max_shard_height: Option<usize>) -> CounterMachine {
+    let sender = CounterChip {
+        sends: true,
+        ..CounterChip::new(1)
+    };
+    let receiver = CounterChip {
+        receives: true,
+        ..CounterChip::new(2)
+    };
+    CounterMachine {
+        max_shard_height,
+        ..CounterMachine::new(vec![sender, receiver])
+    }
+}
+
+fn shard_counts(proof: &MachineProof<MyConfig>) -> Vec<usize> {
+    proof.chip_proofs.values().map(Vec::len).collect()
+}
+
+#[test]
+fn test_unsharded() {
+    prove_and_verify(&machine(None), &[16, 16], |proof| {
+        assert_eq!(shard_counts(proof), vec![1, 1]);
+    })
+    .unwrap();
+}
+
+#[test]
+fn test_sharded_buses_balance() {
+    prove_and_verify(&machine(Some(4)), &[16, 16], |proof| {
+        assert_eq!(shard_counts(proof), vec![4, 4]);
+    })
+    .unwrap();
+}
+
+#[test]
+fn test_only_oversized_chips_are_sharded() {
+    prove_and_verify(&machine(Some(8)), &[16, 16], |proof| {
+        assert_eq!(shard_counts(proof), vec![2, 2]);
+    })
+    .unwrap();
+    prove_and_verify(&machine(Some(16)), &[16, 16], |proof| {
+        assert_eq!(shard_counts(proof), vec![1, 1]);
+    })
+    .unwrap();
+}
+
+#[test]
+fn test_dropped_shard_rejected() {
+    let result = prove_and_verify(&machine(Some(4)), &[16, 16], |proof| {
+        proof.chip_proofs.get_mut("CounterChip2").unwrap().pop();
+    });
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_shard_count_mismatch_rejected() {
+    let result = prove_and_verify(&machine(Some(4)), &[16, 16], |proof| {
+        proof
+            .chip_proofs
+            .get_mut("CounterChip1")
+            .unwrap()
+            .push(None);
+    });
+    assert!(matches!(result, Err(VerificationError::InvalidProofShape)));
+}
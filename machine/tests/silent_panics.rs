@@ -0,0 +1,68 @@
+mod common;
+
+use std::collections::BTreeMap;
+use std::panic;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use p3_machine::machine::Machine;
+use p3_machine::mutation::{find_surviving_mutations, MutationOptions, MutationStrategy};
+use p3_machine::options::ProverOptions;
+use p3_machine::tamper::check_tampered_proofs;
+
+use common::counter::{counter_trace, CounterChip, CounterMachine};
+use common::{default_config, Challenge, Val};
+
+/// The harnesses' failing checks panic on whichever thread runs them, which are rayon's worker
+/// threads with the `parallel` feature. None of their panics reach the panic hook. This test is
+/// the only one in its binary, as it replaces the panic hook.
+#[test]
+fn test_harness_panics_are_silenced() {
+    let reported = Arc::new(AtomicUsize::new(0));
+    let hook_reported = reported.clone();
+    panic::set_hook(Box::new(move |_| {
+        hook_reported.fetch_add(1, Ordering::SeqCst);
+    }));
+
+    let machine = CounterMachine::new(vec![
+        CounterChip {
+            sends: true,
+            ..CounterChip::new(2)
+        },
+        CounterChip {
+            receives: true,
+            ..CounterChip::new(1)
+        },
+    ]);
+    // Tall enough traces for the rows to be checked on several threads
+    let traces: BTreeMap<_, _> = machine
+        .chip_ids()
+        .into_iter()
+        .zip(&machine.chips)
+        .map(|(id, chip)| (id, counter_trace(chip.width, 1 << 10)))
+        .collect();
+
+    let target = machine.chip_ids()[0].clone();
+    let options = MutationOptions {
+        strategy: MutationStrategy::Random { num_mutations: 64 },
+        ..Default::default()
+    };
+    let mutations =
+        find_surviving_mutations::<_, Val, Challenge>(&machine, &target, &traces, &[], &options);
+    assert!(mutations.is_empty());
+
+    let (config, challenger) = default_config();
+    let (pk, vk) = machine.setup(&config);
+    let proof = machine.prove(
+        &config,
+        &mut challenger.clone(),
+        &pk,
+        traces,
+        &[],
+        &ProverOptions::default(),
+    );
+    check_tampered_proofs(&machine, &config, &challenger, &vk, &proof, &[]);
+
+    let _ = panic::take_hook();
+    assert_eq!(reported.load(Ordering::SeqCst), 0);
+}
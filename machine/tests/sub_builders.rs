@@ -0,0 +1,497 @@
+mod common;
+
+use core::fmt::{self, Display, Formatter};
+use std::collections::BTreeMap;
+
+use p3_air::{
+    Air, AirBuilder, AirBuilderWithPublicValues, BaseAir, PairBuilder, PermutationAirBuilder,
+    VirtualPairCol,
+};
+use p3_air_util::builders::{SubAirBuilder, SubRangeAirBuilder};
+use p3_air_util::debug::{air, rap};
+use p3_air_util::{Embedded, PeriodicAir};
+use p3_field::{AbstractExtensionField, AbstractField, Field};
+use p3_interaction::{BaseInteractionAir, Interaction, InteractionAir, InteractionAirBuilder, Rap};
+use p3_machine::chip::Chip;
+use p3_machine::machine::Machine;
+use p3_machine::options::ProverOptions;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+
+use common::counter::CounterBus;
+use common::{default_config, Challenge, Val};
+
+/// Asserts that its main column equals its preprocessed column.
+struct PreprocessedChild;
+
+impl<F> BaseAir<F> for PreprocessedChild {
+    fn width(&self) -> usize {
+        1
+    }
+}
+
+impl<AB: PairBuilder> Air<AB> for PreprocessedChild {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let preprocessed = builder.preprocessed();
+        let (main_local, preprocessed_local) = (main.row_slice(0), preprocessed.row_slice(0));
+        builder.assert_eq(main_local[0], preprocessed_local[0]);
+    }
+}
+
+/// Evaluates a `PreprocessedChild` over the second preprocessed and main columns, through either
+/// sub-builder.
+enum PreprocessedParent {
+    Range,
+    Indices,
+}
+
+impl<F> BaseAir<F> for PreprocessedParent {
+    fn width(&self) -> usize {
+        2
+    }
+}
+
+impl<AB: PairBuilder> Air<AB> for PreprocessedParent {
+    fn eval(&self, builder: &mut AB) {
+        match self {
+            PreprocessedParent::Range => {
+                PreprocessedChild.eval(&mut SubRangeAirBuilder::new(builder, 1..2, 1..2))
+            }
+            PreprocessedParent::Indices => {
+                PreprocessedChild.eval(&mut SubAirBuilder::new(builder, vec![1], vec![1]))
+            }
+        }
+    }
+}
+
+fn check_preprocessed(parent: PreprocessedParent, main: &[u32]) {
+    let preprocessed = RowMajorMatrix::new([1, 2, 3, 4].map(Val::from_canonical_u32).to_vec(), 2);
+    let main = RowMajorMatrix::new(
+        main.iter().copied().map(Val::from_canonical_u32).collect(),
+        2,
+    );
+    air::check_constraints(
+        &parent,
+        &Some(preprocessed.as_view()),
+        &Some(main.as_view()),
+        &[],
+    );
+}
+
+#[test]
+fn test_sub_range_builder_preprocessed() {
+    check_preprocessed(PreprocessedParent::Range, &[0, 2, 0, 4]);
+}
+
+#[test]
+fn test_sub_builder_preprocessed() {
+    check_preprocessed(PreprocessedParent::Indices, &[0, 2, 0, 4]);
+}
+
+#[test]
+#[should_panic(expected = "values didn't match on row 0")]
+fn test_sub_range_builder_preprocessed_not_main() {
+    check_preprocessed(PreprocessedParent::Range, &[1, 5, 3, 6]);
+}
+
+#[test]
+#[should_panic(expected = "values didn't match on row 0")]
+fn test_sub_builder_preprocessed_not_main() {
+    check_preprocessed(PreprocessedParent::Indices, &[1, 5, 3, 6]);
+}
+
+/// Asserts that its main column equals its first public value.
+struct PublicValuesChild;
+
+impl<F> BaseAir<F> for PublicValuesChild {
+    fn width(&self) -> usize {
+        1
+    }
+}
+
+impl<AB: AirBuilderWithPublicValues> Air<AB> for PublicValuesChild {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let public_value: AB::Expr = builder.public_values()[0].into();
+        builder.assert_eq(local[0], public_value);
+    }
+}
+
+/// Evaluates a `PublicValuesChild` with only the second public value.
+struct PublicValuesParent;
+
+impl<F> BaseAir<F> for PublicValuesParent {
+    fn width(&self) -> usize {
+        1
+    }
+}
+
+impl<AB: AirBuilderWithPublicValues> Air<AB> for PublicValuesParent {
+    fn eval(&self, builder: &mut AB) {
+        PublicValuesChild
+            .eval(&mut SubRangeAirBuilder::new_main(builder, 0..1).with_public_values_range(1..2));
+    }
+}
+
+fn check_public_values(value: u32) {
+    let main = RowMajorMatrix::new_col(vec![Val::from_canonical_u32(value); 2]);
+    let public_values = [5, 7].map(Val::from_canonical_u32);
+    air::check_constraints(
+        &PublicValuesParent,
+        &None,
+        &Some(main.as_view()),
+        &public_values,
+    );
+}
+
+#[test]
+fn test_public_values_range() {
+    check_public_values(7);
+}
+
+#[test]
+#[should_panic(expected = "values didn't match on row 0")]
+fn test_public_values_range_excludes_others() {
+    check_public_values(5);
+}
+
+/// Asserts that its main column equals its first permutation column.
+struct PermutationChild;
+
+impl<F> BaseAir<F> for PermutationChild {
+    fn width(&self) -> usize {
+        1
+    }
+}
+
+impl<AB: PermutationAirBuilder> Air<AB> for PermutationChild {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let permutation = builder.permutation();
+        let (main_local, permutation_local) = (main.row_slice(0), permutation.row_slice(0));
+        let value: AB::Expr = main_local[0].into();
+        builder.assert_eq_ext(permutation_local[0], AB::ExprEF::from_base(value));
+    }
+}
+
+/// Evaluates a `PermutationChild` with only the second permutation column.
+#[derive(Clone, Debug)]
+struct PermutationParent;
+
+impl<F> BaseAir<F> for PermutationParent {
+    fn width(&self) -> usize {
+        1
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for PermutationParent {
+    fn eval(&self, _builder: &mut AB) {}
+}
+
+impl<F: Field> BaseInteractionAir<F> for PermutationParent {}
+
+impl<F: Field> InteractionAir<F> for PermutationParent {}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for PermutationParent {
+    fn eval_all(&self, builder: &mut AB) {
+        PermutationChild
+            .eval(&mut SubAirBuilder::new_main(builder, vec![0]).with_permutation_indices(vec![1]));
+    }
+}
+
+impl<F: Field> PeriodicAir<F> for PermutationParent {}
+
+fn check_permutation(permutation: &[u32]) {
+    let main = RowMajorMatrix::new_col([3, 4].map(Val::from_canonical_u32).to_vec());
+    let permutation = RowMajorMatrix::new(
+        permutation
+            .iter()
+            .copied()
+            .map(Challenge::from_canonical_u32)
+            .collect(),
+        2,
+    );
+    rap::check_constraints(
+        &PermutationParent,
+        &None,
+        &Some(main.as_view()),
+        &Some(permutation.as_view()),
+        [Challenge::zero(); 2],
+        None,
+        &[],
+    );
+}
+
+#[test]
+fn test_permutation_indices() {
+    check_permutation(&[0, 3, 0, 4]);
+}
+
+#[test]
+#[should_panic(expected = "values didn't match on row 0")]
+fn test_permutation_indices_exclude_others() {
+    check_permutation(&[3, 0, 4, 0]);
+}
+
+/// Asserts that its first permutation column ends at its cumulative sum.
+struct CumulativeSumChild;
+
+impl<F> BaseAir<F> for CumulativeSumChild {
+    fn width(&self) -> usize {
+        1
+    }
+}
+
+impl<AB: InteractionAirBuilder> Air<AB> for CumulativeSumChild {
+    fn eval(&self, builder: &mut AB) {
+        let permutation = builder.permutation();
+        let local = permutation.row_slice(0);
+        let cumulative_sum = builder.cumulative_sum();
+        builder
+            .when_last_row()
+            .assert_eq_ext(local[0], cumulative_sum);
+    }
+}
+
+/// Evaluates a `CumulativeSumChild` with only the second permutation column, through either
+/// sub-builder.
+#[derive(Clone, Debug)]
+enum CumulativeSumParent {
+    Range,
+    Indices,
+}
+
+impl<F> BaseAir<F> for CumulativeSumParent {
+    fn width(&self) -> usize {
+        1
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for CumulativeSumParent {
+    fn eval(&self, _builder: &mut AB) {}
+}
+
+impl<F: Field> BaseInteractionAir<F> for CumulativeSumParent {}
+
+impl<F: Field> InteractionAir<F> for CumulativeSumParent {}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for CumulativeSumParent {
+    fn eval_all(&self, builder: &mut AB) {
+        match self {
+            CumulativeSumParent::Range => CumulativeSumChild.eval(
+                &mut SubRangeAirBuilder::new_main(builder, 0..1).with_permutation_range(1..2),
+            ),
+            CumulativeSumParent::Indices => CumulativeSumChild.eval(
+                &mut SubAirBuilder::new_main(builder, vec![0]).with_permutation_indices(vec![1]),
+            ),
+        }
+    }
+}
+
+impl<F: Field> PeriodicAir<F> for CumulativeSumParent {}
+
+fn check_cumulative_sum(parent: CumulativeSumParent) {
+    let main = RowMajorMatrix::new_col(vec![Val::zero(); 2]);
+    let permutation = RowMajorMatrix::new(vec![Challenge::one(); 4], 2);
+    rap::check_constraints(
+        &parent,
+        &None,
+        &Some(main.as_view()),
+        &Some(permutation.as_view()),
+        [Challenge::zero(); 2],
+        Some(Challenge::one()),
+        &[],
+    );
+}
+
+#[test]
+#[should_panic(expected = "a sub-RAP with a restricted permutation matrix has no cumulative sum")]
+fn test_sub_range_builder_restricted_cumulative_sum_rejected() {
+    check_cumulative_sum(CumulativeSumParent::Range);
+}
+
+#[test]
+#[should_panic(expected = "a sub-RAP with a restricted permutation matrix has no cumulative sum")]
+fn test_sub_builder_restricted_cumulative_sum_rejected() {
+    check_cumulative_sum(CumulativeSumParent::Indices);
+}
+
+/// A chip whose second column is its first plus one, and which sends both on bus 0.
+#[derive(Clone, Debug)]
+struct IncrementChip;
+
+impl<F> BaseAir<F> for IncrementChip {
+    fn width(&self) -> usize {
+        2
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for IncrementChip {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        builder.assert_eq(local[1], local[0] + AB::Expr::one());
+    }
+}
+
+impl<F: Field> BaseInteractionAir<F> for IncrementChip {
+    fn sends_from_indices(
+        &self,
+        _preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        vec![Interaction {
+            fields: vec![
+                VirtualPairCol::single_main(main_indices[0]),
+                VirtualPairCol::single_main(main_indices[1]),
+            ],
+            count: VirtualPairCol::constant(F::one()),
+            argument_index: 0,
+        }]
+    }
+}
+
+impl<F: Field> InteractionAir<F> for IncrementChip {
+    fn sends(&self) -> Vec<Interaction<F>> {
+        self.sends_from_indices(&[], &[0, 1])
+    }
+}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for IncrementChip {}
+
+/// Either a parent evaluating an `IncrementChip`, with its permutation constraints, after its own
+/// column, or a chip receiving the pairs on bus 0.
+#[derive(Clone, Debug)]
+enum InteractionChip {
+    Parent,
+    Receiver,
+}
+
+const INCREMENT_RANGE: core::ops::Range<usize> = 1..3;
+
+impl Display for InteractionChip {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            InteractionChip::Parent => write!(f, "Parent"),
+            InteractionChip::Receiver => write!(f, "Receiver"),
+        }
+    }
+}
+
+impl<F: Field> BaseAir<F> for InteractionChip {
+    fn width(&self) -> usize {
+        match self {
+            InteractionChip::Parent => 3,
+            InteractionChip::Receiver => 2,
+        }
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for InteractionChip {
+    fn eval(&self, builder: &mut AB) {
+        match self {
+            InteractionChip::Parent => {
+                let main = builder.main();
+                let local = main.row_slice(0);
+                builder.assert_eq(local[0], local[1]);
+            }
+            InteractionChip::Receiver => {}
+        }
+    }
+}
+
+impl<F: Field> BaseInteractionAir<F> for InteractionChip {}
+
+impl<F: Field> InteractionAir<F> for InteractionChip {
+    fn receives(&self) -> Vec<Interaction<F>> {
+        match self {
+            InteractionChip::Parent => vec![],
+            InteractionChip::Receiver => vec![Interaction {
+                fields: vec![
+                    VirtualPairCol::single_main(0),
+                    VirtualPairCol::single_main(1),
+                ],
+                count: VirtualPairCol::constant(F::one()),
+                argument_index: 0,
+            }],
+        }
+    }
+
+    fn sends(&self) -> Vec<Interaction<F>> {
+        match self {
+            InteractionChip::Parent => Embedded::new_main(IncrementChip, INCREMENT_RANGE).sends(),
+            InteractionChip::Receiver => vec![],
+        }
+    }
+}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for InteractionChip {
+    fn eval_all(&self, builder: &mut AB) {
+        match self {
+            // The parent's interactions are exactly the child's, so the child's permutation
+            // constraints are evaluated over the whole permutation trace.
+            InteractionChip::Parent => {
+                self.eval(builder);
+                IncrementChip.eval_all(&mut SubRangeAirBuilder::new_main(builder, INCREMENT_RANGE));
+            }
+            InteractionChip::Receiver => {
+                self.eval(builder);
+                self.eval_permutation_constraints(builder);
+            }
+        }
+    }
+}
+
+impl<F: Field> PeriodicAir<F> for InteractionChip {}
+
+impl Chip for InteractionChip {}
+
+struct InteractionMachine;
+
+impl Machine for InteractionMachine {
+    type Chip = InteractionChip;
+
+    type Bus = CounterBus;
+
+    fn chips(&self) -> Vec<Self::Chip> {
+        vec![InteractionChip::Parent, InteractionChip::Receiver]
+    }
+}
+
+#[test]
+fn test_sub_rap_with_interactions() {
+    const HEIGHT: usize = 8;
+    let machine = InteractionMachine;
+    let parent_values = (0..HEIGHT)
+        .flat_map(|i| [i, i, i + 1].map(Val::from_canonical_usize))
+        .collect();
+    let receiver_values = (0..HEIGHT)
+        .flat_map(|i| [i, i + 1].map(Val::from_canonical_usize))
+        .collect();
+    let traces = BTreeMap::from([
+        (
+            InteractionChip::Parent.id(),
+            RowMajorMatrix::new(parent_values, 3),
+        ),
+        (
+            InteractionChip::Receiver.id(),
+            RowMajorMatrix::new(receiver_values, 2),
+        ),
+    ]);
+
+    let (config, challenger) = default_config();
+    let (pk, vk) = machine.setup(&config);
+    let proof = machine.prove(
+        &config,
+        &mut challenger.clone(),
+        &pk,
+        traces,
+        &[],
+        &ProverOptions::default(),
+    );
+    machine
+        .verify(&config, &mut challenger.clone(), &vk, &proof, &[])
+        .unwrap();
+}
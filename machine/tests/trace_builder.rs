@@ -0,0 +1,137 @@
+extern crate alloc;
+
+mod common;
+
+use core::borrow::Borrow;
+use core::fmt::{self, Display, Formatter};
+use std::collections::BTreeMap;
+
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_air_util::{PeriodicAir, TraceBuilder};
+use p3_derive::Columnar;
+use p3_field::{AbstractField, Field};
+use p3_interaction::{BaseInteractionAir, InteractionAir, InteractionAirBuilder, Rap};
+use p3_machine::chip::Chip;
+use p3_machine::machine::Machine;
+use p3_machine::options::ProverOptions;
+use p3_matrix::Matrix;
+
+use common::counter::CounterBus;
+use common::{default_config, Val};
+
+#[repr(C)]
+#[derive(Columnar, Default)]
+struct SquareCols<T> {
+    x: T,
+    x_squared: T,
+}
+
+/// A chip whose second column is the square of its first. Its trace is padded with zero rows.
+#[derive(Clone, Debug)]
+struct SquareChip;
+
+impl Display for SquareChip {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "SquareChip")
+    }
+}
+
+impl<F: Field> BaseAir<F> for SquareChip {
+    fn width(&self) -> usize {
+        SquareCols::<F>::num_cols()
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for SquareChip {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let local: &SquareCols<AB::Var> = (*local).borrow();
+        builder.assert_eq(local.x_squared, local.x * local.x);
+    }
+}
+
+impl<F: Field> BaseInteractionAir<F> for SquareChip {}
+
+impl<F: Field> InteractionAir<F> for SquareChip {}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for SquareChip {}
+
+impl<F: Field> PeriodicAir<F> for SquareChip {}
+
+impl Chip for SquareChip {
+    fn padding_row<F: Field>(&self) -> Option<Vec<F>> {
+        Some(vec![F::zero(); SquareCols::<F>::num_cols()])
+    }
+}
+
+struct SquareMachine;
+
+impl Machine for SquareMachine {
+    type Chip = SquareChip;
+
+    type Bus = CounterBus;
+
+    fn chips(&self) -> Vec<Self::Chip> {
+        vec![SquareChip]
+    }
+}
+
+fn square(i: usize, row: &mut SquareCols<Val>) {
+    row.x = Val::from_canonical_usize(i);
+    row.x_squared = row.x * row.x;
+}
+
+#[test]
+fn test_push_rows_matches_parallel_fill() {
+    let mut builder = TraceBuilder::<Val, SquareCols<Val>>::new();
+    for i in 0..5 {
+        builder.push_row(|row| square(i, row));
+    }
+    assert_eq!(builder.height(), 5);
+    let pushed = builder.build();
+    let filled = TraceBuilder::from_rows(5, square).build();
+
+    assert_eq!(pushed.width(), SquareCols::<Val>::num_cols());
+    assert_eq!(pushed.values, filled.values);
+}
+
+#[test]
+fn test_build_padded() {
+    let trace = TraceBuilder::<Val, SquareCols<Val>>::from_rows(5, square)
+        .build_padded(&[Val::zero(), Val::zero()]);
+
+    assert_eq!(trace.height(), 8);
+    assert_eq!(
+        trace.row_slice(4).to_vec(),
+        vec![Val::from_canonical_usize(4), Val::from_canonical_usize(16)]
+    );
+    assert!(trace.values[10..].iter().all(|value| *value == Val::zero()));
+}
+
+#[test]
+fn test_machine_pads_main_traces() {
+    let machine = SquareMachine;
+    let (config, challenger) = default_config();
+    let (pk, vk) = machine.setup(&config);
+    let traces = BTreeMap::from([(
+        SquareChip.id(),
+        TraceBuilder::<Val, SquareCols<Val>>::from_rows(5, square).build(),
+    )]);
+
+    let proof = machine.prove(
+        &config,
+        &mut challenger.clone(),
+        &pk,
+        traces,
+        &[],
+        &ProverOptions::default(),
+    );
+    assert_eq!(
+        proof.chip_proofs["SquareChip"][0].as_ref().unwrap().degree,
+        8
+    );
+    machine
+        .verify(&config, &mut challenger.clone(), &vk, &proof, &[])
+        .unwrap();
+}
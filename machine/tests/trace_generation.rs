@@ -0,0 +1,272 @@
+mod common;
+
+use core::fmt::{self, Display, Formatter};
+use std::collections::BTreeMap;
+
+use p3_air::{Air, AirBuilder, BaseAir, VirtualPairCol};
+use p3_air_util::PeriodicAir;
+use p3_field::{AbstractField, Field, PrimeField32};
+use p3_interaction::{BaseInteractionAir, Interaction, InteractionAir, InteractionAirBuilder, Rap};
+use p3_machine::chip::{Chip, ChipId};
+use p3_machine::generation::{generate_traces, TraceGenerator};
+use p3_machine::machine::Machine;
+use p3_machine::options::ProverOptions;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+
+use common::counter::CounterBus;
+use common::{default_config, Val};
+
+const RANGE: usize = 8;
+
+/// Values to range check.
+struct Record {
+    values: Vec<u32>,
+}
+
+/// Either a chip sending the recorded values on bus 0, or a table of `0..RANGE` receiving them
+/// with a multiplicity column.
+#[derive(Clone, Debug)]
+enum LookupChip {
+    Values,
+    Range,
+}
+
+impl Display for LookupChip {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            LookupChip::Values => write!(f, "ValuesChip"),
+            LookupChip::Range => write!(f, "RangeChip"),
+        }
+    }
+}
+
+impl<F: Field> BaseAir<F> for LookupChip {
+    fn width(&self) -> usize {
+        match self {
+            LookupChip::Values => 1,
+            LookupChip::Range => 2,
+        }
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for LookupChip {
+    fn eval(&self, builder: &mut AB) {
+        if let LookupChip::Range = self {
+            let main = builder.main();
+            let local = main.row_slice(0);
+            let next = main.row_slice(1);
+            builder.when_first_row().assert_zero(local[0]);
+            builder
+                .when_transition()
+                .assert_eq(next[0], local[0] + AB::Expr::one());
+        }
+    }
+}
+
+impl<F: Field> BaseInteractionAir<F> for LookupChip {
+    fn receives_from_indices(
+        &self,
+        _preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        match self {
+            LookupChip::Values => vec![],
+            LookupChip::Range => vec![Interaction {
+                fields: vec![VirtualPairCol::single_main(main_indices[0])],
+                count: VirtualPairCol::single_main(main_indices[1]),
+                argument_index: 0,
+            }],
+        }
+    }
+
+    fn sends_from_indices(
+        &self,
+        _preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        match self {
+            LookupChip::Values => vec![Interaction {
+                fields: vec![VirtualPairCol::single_main(main_indices[0])],
+                count: VirtualPairCol::constant(F::one()),
+                argument_index: 0,
+            }],
+            LookupChip::Range => vec![],
+        }
+    }
+}
+
+impl<F: Field> InteractionAir<F> for LookupChip {
+    fn receives(&self) -> Vec<Interaction<F>> {
+        let width = <Self as BaseAir<F>>::width(self);
+        self.receives_from_main_indices(&(0..width).collect::<Vec<_>>())
+    }
+
+    fn sends(&self) -> Vec<Interaction<F>> {
+        let width = <Self as BaseAir<F>>::width(self);
+        self.sends_from_main_indices(&(0..width).collect::<Vec<_>>())
+    }
+}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for LookupChip {}
+
+impl<F: Field> PeriodicAir<F> for LookupChip {}
+
+impl Chip for LookupChip {}
+
+impl<F: PrimeField32> TraceGenerator<F, Record> for LookupChip {
+    fn dependencies(&self) -> Vec<ChipId> {
+        match self {
+            LookupChip::Values => vec![],
+            LookupChip::Range => vec![LookupChip::Values.id()],
+        }
+    }
+
+    fn generate_trace(
+        &self,
+        record: &Record,
+        traces: &BTreeMap<ChipId, RowMajorMatrix<F>>,
+    ) -> Option<RowMajorMatrix<F>> {
+        match self {
+            LookupChip::Values => Some(RowMajorMatrix::new_col(
+                record
+                    .values
+                    .iter()
+                    .map(|&v| F::from_canonical_u32(v))
+                    .collect(),
+            )),
+            LookupChip::Range => {
+                // Count the values sent by the values chip
+                let mut multiplicities = vec![0; RANGE];
+                for value in traces[&LookupChip::Values.id()].values.iter() {
+                    multiplicities[value.as_canonical_u32() as usize] += 1;
+                }
+                let values = multiplicities
+                    .into_iter()
+                    .enumerate()
+                    .flat_map(|(value, multiplicity)| {
+                        [
+                            F::from_canonical_usize(value),
+                            F::from_canonical_usize(multiplicity),
+                        ]
+                    })
+                    .collect();
+                Some(RowMajorMatrix::new(values, 2))
+            }
+        }
+    }
+}
+
+struct LookupMachine;
+
+impl Machine for LookupMachine {
+    type Chip = LookupChip;
+
+    type Bus = CounterBus;
+
+    fn chips(&self) -> Vec<Self::Chip> {
+        // The table comes first, although it is generated last
+        vec![LookupChip::Range, LookupChip::Values]
+    }
+}
+
+fn record() -> Record {
+    Record {
+        values: vec![3, 1, 4, 1, 5, 1, 2, 6],
+    }
+}
+
+#[test]
+fn test_multiplicities_from_dependencies() {
+    let traces: BTreeMap<_, RowMajorMatrix<Val>> = LookupMachine.generate_traces(&record());
+    let range = &traces["RangeChip"];
+
+    let multiplicities = (0..RANGE).map(|row| range.get(row, 1)).collect::<Vec<_>>();
+    let expected = [0, 3, 1, 1, 1, 1, 1, 0].map(Val::from_canonical_usize);
+    assert_eq!(multiplicities, expected);
+}
+
+#[test]
+fn test_prove_record() {
+    let machine = LookupMachine;
+    let (config, challenger) = default_config();
+    let (pk, vk) = machine.setup(&config);
+
+    let proof = machine.prove_record(
+        &config,
+        &mut challenger.clone(),
+        &pk,
+        &record(),
+        &[],
+        &ProverOptions::default(),
+    );
+    machine
+        .verify(&config, &mut challenger.clone(), &vk, &proof, &[])
+        .unwrap();
+}
+
+/// A generator that only records its dependencies.
+#[derive(Clone, Debug)]
+struct Node {
+    name: &'static str,
+    dependencies: Vec<&'static str>,
+}
+
+impl Display for Node {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+impl Chip for Node {}
+
+impl TraceGenerator<Val, ()> for Node {
+    fn dependencies(&self) -> Vec<ChipId> {
+        self.dependencies
+            .iter()
+            .map(|name| name.to_string())
+            .collect()
+    }
+
+    fn generate_trace(
+        &self,
+        _record: &(),
+        traces: &BTreeMap<ChipId, RowMajorMatrix<Val>>,
+    ) -> Option<RowMajorMatrix<Val>> {
+        for dependency in self.dependencies.iter() {
+            assert!(traces.contains_key(*dependency));
+        }
+        Some(RowMajorMatrix::new_col(vec![Val::zero()]))
+    }
+}
+
+fn node(name: &'static str, dependencies: &[&'static str]) -> Node {
+    Node {
+        name,
+        dependencies: dependencies.to_vec(),
+    }
+}
+
+#[test]
+fn test_dependency_order() {
+    let nodes = [
+        node("d", &["b", "c"]),
+        node("c", &["a"]),
+        node("b", &["a"]),
+        node("a", &[]),
+    ];
+    let traces = generate_traces::<Val, _, _>(&nodes, &());
+    assert_eq!(traces.len(), 4);
+}
+
+#[test]
+#[should_panic(expected = "Chip dependencies should not be cyclic")]
+fn test_cyclic_dependencies() {
+    generate_traces::<Val, _, _>(&[node("a", &["b"]), node("b", &["a"])], &());
+}
+
+#[test]
+#[should_panic(expected = "depends on unknown chip")]
+fn test_unknown_dependency() {
+    generate_traces::<Val, _, _>(&[node("a", &["b"])], &());
+}